@@ -1,6 +1,12 @@
+use std::collections::BTreeSet;
 use std::fmt;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::level1::Level1Engine;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum CollectionLevel {
     Level0,
     Level1,
@@ -17,6 +23,12 @@ impl CollectionLevel {
     ];
 }
 
+impl Serialize for CollectionLevel {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
 impl fmt::Display for CollectionLevel {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let label = match self {
@@ -46,23 +58,98 @@ impl Default for CollectionPolicy {
     }
 }
 
-#[derive(Debug, Clone, Default)]
-pub struct CapabilityProbe {
-    pub has_mysql_status_access: bool,
-    pub has_mysql_variables_access: bool,
-    pub has_information_schema_access: bool,
-    pub has_replication_status_access: bool,
-    pub has_os_metrics_access: bool,
-    pub can_enable_slow_log_hot_switch: bool,
-    pub can_read_slow_log: bool,
-    pub can_read_error_log: bool,
-    pub performance_schema_enabled: bool,
-    pub has_performance_schema_access: bool,
-    pub has_sys_schema_access: bool,
-    pub can_capture_tcpdump_short_window: bool,
-    pub can_capture_perf_short_window: bool,
-    pub can_capture_strace_short_window: bool,
-    pub can_sample_innodb_status_high_frequency: bool,
+/// Engine-neutral capability keys. Each engine declares which of them a
+/// level requires and how to explain a missing one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    StatusCounters,
+    Settings,
+    SchemaMetadata,
+    ReplicationStatus,
+    OsMetrics,
+    StatementLogHotSwitch,
+    StatementLog,
+    ErrorLog,
+    StatementStatsEnabled,
+    StatementStatsAccess,
+    DiagnosticViews,
+    TcpdumpShortWindow,
+    PerfShortWindow,
+    StraceShortWindow,
+    EngineStatusSampling,
+}
+
+/// Capabilities confirmed by the probe.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct CapabilityProbe(BTreeSet<Capability>);
+
+impl CapabilityProbe {
+    pub fn has(&self, capability: Capability) -> bool {
+        self.0.contains(&capability)
+    }
+
+    /// Records the probe result and returns it.
+    pub fn set(&mut self, capability: Capability, granted: bool) -> bool {
+        if granted {
+            self.0.insert(capability);
+        } else {
+            self.0.remove(&capability);
+        }
+        granted
+    }
+}
+
+impl FromIterator<Capability> for CapabilityProbe {
+    fn from_iter<I: IntoIterator<Item = Capability>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CapabilityRequirement {
+    pub level: CollectionLevel,
+    /// Satisfied when any of these capabilities is present.
+    pub any_of: &'static [Capability],
+    /// Stable identifier for automation; never reworded.
+    pub code: &'static str,
+    pub reason: &'static str,
+    /// `{grantee}` is replaced by the probed account.
+    pub remediation: &'static str,
+}
+
+/// Replaces a requirement's remediation on servers older than
+/// `before_version_num`, where the usual privilege does not exist yet.
+#[derive(Debug, Clone, Copy)]
+pub struct LegacyRemediation {
+    pub code: &'static str,
+    /// Same encoding as PostgreSQL's `server_version_num`.
+    pub before_version_num: u32,
+    pub remediation: &'static str,
+}
+
+/// Why a level was not selected, and how to unblock it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DowngradeReason {
+    pub code: &'static str,
+    /// Any one of these would satisfy the requirement; empty for policy.
+    pub missing_capabilities: Vec<Capability>,
+    pub blocked_level: CollectionLevel,
+    pub message: &'static str,
+    pub remediation: String,
+}
+
+impl DowngradeReason {
+    fn from_requirement(requirement: &CapabilityRequirement) -> Self {
+        Self {
+            code: requirement.code,
+            missing_capabilities: requirement.any_of.to_vec(),
+            blocked_level: requirement.level,
+            message: requirement.reason,
+            remediation: requirement.remediation.to_string(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -71,26 +158,81 @@ pub struct CollectionTask {
     pub name: &'static str,
     pub source: &'static str,
     pub purpose: &'static str,
+    /// Optional helper capability; the task is dropped when it is missing.
+    pub requires: Option<Capability>,
+}
+
+/// What an engine needs for each level and which tasks it schedules.
+#[derive(Debug, Clone, Copy)]
+pub struct EngineProfile {
+    pub engine: Level1Engine,
+    /// Used in remediation hints when the probe could not name the account.
+    pub grantee_placeholder: &'static str,
+    pub legacy_remediations: &'static [LegacyRemediation],
+    pub requirements: &'static [CapabilityRequirement],
+    pub tasks: &'static [CollectionTask],
+}
+
+pub fn engine_profile(engine: Level1Engine) -> &'static EngineProfile {
+    match engine {
+        Level1Engine::Mysql => &MYSQL_PROFILE,
+        Level1Engine::Postgres => &POSTGRES_PROFILE,
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct LevelEvaluation {
     pub level: CollectionLevel,
     pub ok: bool,
-    pub reasons: Vec<&'static str>,
+    pub reasons: Vec<DowngradeReason>,
 }
 
 #[derive(Debug, Clone)]
 pub struct NegotiationResult {
+    pub engine: Level1Engine,
     pub selected_level: CollectionLevel,
     pub evaluations: Vec<LevelEvaluation>,
     pub tasks: Vec<CollectionTask>,
 }
 
+impl NegotiationResult {
+    /// Reasons blocking the highest evaluated level, one per unmet
+    /// requirement, with `{grantee}` filled in. Remediation for an older
+    /// `server_version_num` uses the profile's legacy hints; an unknown
+    /// version gets the current ones.
+    pub fn downgrade_reasons(
+        &self,
+        grantee: Option<&str>,
+        server_version_num: Option<u32>,
+    ) -> Vec<DowngradeReason> {
+        let profile = engine_profile(self.engine);
+        let grantee = grantee.unwrap_or(profile.grantee_placeholder);
+        self.evaluations
+            .first()
+            .filter(|it| !it.ok)
+            .map(|it| it.reasons.clone())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|mut reason| {
+                if let Some(legacy) = profile.legacy_remediations.iter().find(|it| {
+                    it.code == reason.code
+                        && server_version_num.is_some_and(|version| version < it.before_version_num)
+                }) {
+                    reason.remediation = legacy.remediation.to_string();
+                }
+                reason.remediation = reason.remediation.replace("{grantee}", grantee);
+                reason
+            })
+            .collect()
+    }
+}
+
 pub fn negotiate_collection_level(
+    engine: Level1Engine,
     policy: &CollectionPolicy,
     probe: &CapabilityProbe,
 ) -> NegotiationResult {
+    let profile = engine_profile(engine);
     let target = policy.preferred_level.min(policy.max_accepted_level);
     let mut evaluations = Vec::with_capacity(CollectionLevel::ALL_DESC.len());
     let mut selected = CollectionLevel::Level0;
@@ -99,7 +241,7 @@ pub fn negotiate_collection_level(
         if level > target {
             continue;
         }
-        let evaluation = evaluate_level(level, policy, probe);
+        let evaluation = evaluate_level(profile, level, policy, probe);
         if selected == CollectionLevel::Level0 && evaluation.ok {
             selected = level;
         }
@@ -113,75 +255,36 @@ pub fn negotiate_collection_level(
         selected = CollectionLevel::Level0;
     }
 
-    let tasks = tasks_for_level(selected, probe);
+    let tasks = tasks_for_level(engine, selected, probe);
     NegotiationResult {
+        engine,
         selected_level: selected,
         evaluations,
         tasks,
     }
 }
 
+/// A level needs every requirement declared at or below it.
 fn evaluate_level(
+    profile: &EngineProfile,
     level: CollectionLevel,
     policy: &CollectionPolicy,
     probe: &CapabilityProbe,
 ) -> LevelEvaluation {
-    let mut reasons = Vec::new();
-
-    match level {
-        CollectionLevel::Level0 => {
-            if !probe.has_mysql_status_access {
-                reasons.push("missing SHOW GLOBAL STATUS access");
-            }
-            if !probe.has_mysql_variables_access {
-                reasons.push("missing SHOW VARIABLES access");
-            }
-            if !probe.has_information_schema_access {
-                reasons.push("missing INFORMATION_SCHEMA access");
-            }
-            if !probe.has_replication_status_access {
-                reasons.push("missing replication status access");
-            }
-            if !probe.has_os_metrics_access {
-                reasons.push("missing OS-level metrics access (/proc, vmstat, iostat, sar)");
-            }
-        }
-        CollectionLevel::Level1 => {
-            reasons.extend(evaluate_level(CollectionLevel::Level0, policy, probe).reasons);
-            if !probe.can_enable_slow_log_hot_switch {
-                reasons.push("cannot hot-enable slow log");
-            }
-            if !probe.can_read_slow_log {
-                reasons.push("cannot collect slow log for digest aggregation");
-            }
-            if !probe.can_read_error_log {
-                reasons.push("cannot collect error log");
-            }
-        }
-        CollectionLevel::Level2 => {
-            reasons.extend(evaluate_level(CollectionLevel::Level1, policy, probe).reasons);
-            if !probe.performance_schema_enabled {
-                reasons.push("performance_schema is disabled");
-            }
-            if !probe.has_performance_schema_access {
-                reasons.push("missing performance_schema read access");
-            }
-        }
-        CollectionLevel::Level3 => {
-            reasons.extend(evaluate_level(CollectionLevel::Level2, policy, probe).reasons);
-            if !policy.expert_mode_enabled {
-                reasons.push("expert mode is disabled by policy");
-            }
-            if !probe.can_sample_innodb_status_high_frequency {
-                reasons.push("cannot sample InnoDB status at high frequency");
-            }
-            let has_deep_sampler = probe.can_capture_tcpdump_short_window
-                || probe.can_capture_perf_short_window
-                || probe.can_capture_strace_short_window;
-            if !has_deep_sampler {
-                reasons.push("no short-window deep sampler available (tcpdump/perf/strace)");
-            }
-        }
+    let mut reasons = profile
+        .requirements
+        .iter()
+        .filter(|it| it.level <= level && !it.any_of.iter().any(|cap| probe.has(*cap)))
+        .map(DowngradeReason::from_requirement)
+        .collect::<Vec<_>>();
+    if level == CollectionLevel::Level3 && !policy.expert_mode_enabled {
+        reasons.push(DowngradeReason {
+            code: "expert_mode_disabled",
+            missing_capabilities: Vec::new(),
+            blocked_level: CollectionLevel::Level3,
+            message: "expert mode is disabled by policy",
+            remediation: "rerun with --expert-mode (or EXPERT_MODE_ENABLED=true)".to_string(),
+        });
     }
 
     LevelEvaluation {
@@ -191,197 +294,620 @@ fn evaluate_level(
     }
 }
 
-pub fn tasks_for_level(level: CollectionLevel, probe: &CapabilityProbe) -> Vec<CollectionTask> {
-    let mut tasks = Vec::new();
-    tasks.extend(level0_tasks());
+pub fn tasks_for_level(
+    engine: Level1Engine,
+    level: CollectionLevel,
+    probe: &CapabilityProbe,
+) -> Vec<CollectionTask> {
+    engine_profile(engine)
+        .tasks
+        .iter()
+        .filter(|it| it.level <= level && it.requires.is_none_or(|cap| probe.has(cap)))
+        .cloned()
+        .collect()
+}
 
-    if level >= CollectionLevel::Level1 {
-        tasks.extend(level1_tasks());
-    }
-    if level >= CollectionLevel::Level2 {
-        tasks.extend(level2_tasks(probe));
-    }
-    if level >= CollectionLevel::Level3 {
-        tasks.extend(level3_tasks());
-    }
-    tasks
+/// Every task the engine can schedule, in level order.
+pub fn all_tasks(engine: Level1Engine) -> Vec<CollectionTask> {
+    engine_profile(engine).tasks.to_vec()
 }
 
-fn level0_tasks() -> Vec<CollectionTask> {
-    vec![
+const DEEP_SAMPLERS: &[Capability] = &[
+    Capability::TcpdumpShortWindow,
+    Capability::PerfShortWindow,
+    Capability::StraceShortWindow,
+];
+const OS_METRICS: CapabilityRequirement = CapabilityRequirement {
+    level: CollectionLevel::Level0,
+    any_of: &[Capability::OsMetrics],
+    code: "os_metrics_unavailable",
+    reason: "missing OS-level metrics access (/proc, vmstat, iostat, sar)",
+    remediation: "run the collector on the database host with read access to /proc",
+};
+const DEEP_SAMPLER: CapabilityRequirement = CapabilityRequirement {
+    level: CollectionLevel::Level3,
+    any_of: DEEP_SAMPLERS,
+    code: "deep_sampler_unavailable",
+    reason: "no short-window deep sampler available (tcpdump/perf/strace)",
+    remediation: "install tcpdump, perf or strace and run the collector as root \
+                  (or with CAP_NET_RAW, CAP_PERFMON or CAP_SYS_PTRACE)",
+};
+
+static MYSQL_PROFILE: EngineProfile = EngineProfile {
+    engine: Level1Engine::Mysql,
+    grantee_placeholder: "'<user>'@'<host>'",
+    // SYSTEM_VARIABLES_ADMIN was added in MySQL 8.0.
+    legacy_remediations: &[LegacyRemediation {
+        code: "mysql_slow_log_hot_switch_denied",
+        before_version_num: 80000,
+        remediation: "GRANT SUPER ON *.* TO {grantee};",
+    }],
+    requirements: &[
+        CapabilityRequirement {
+            level: CollectionLevel::Level0,
+            any_of: &[Capability::StatusCounters],
+            code: "mysql_global_status_denied",
+            reason: "missing SHOW GLOBAL STATUS access",
+            remediation: "GRANT SELECT ON performance_schema.* TO {grantee};",
+        },
+        CapabilityRequirement {
+            level: CollectionLevel::Level0,
+            any_of: &[Capability::Settings],
+            code: "mysql_variables_denied",
+            reason: "missing SHOW VARIABLES access",
+            remediation: "GRANT SELECT ON performance_schema.* TO {grantee};",
+        },
+        CapabilityRequirement {
+            level: CollectionLevel::Level0,
+            any_of: &[Capability::SchemaMetadata],
+            code: "mysql_information_schema_denied",
+            reason: "missing INFORMATION_SCHEMA access",
+            remediation: "GRANT SELECT ON *.* TO {grantee};",
+        },
+        CapabilityRequirement {
+            level: CollectionLevel::Level0,
+            any_of: &[Capability::ReplicationStatus],
+            code: "mysql_replication_status_denied",
+            reason: "missing replication status access",
+            remediation: "GRANT REPLICATION CLIENT ON *.* TO {grantee};",
+        },
+        OS_METRICS,
+        CapabilityRequirement {
+            level: CollectionLevel::Level1,
+            any_of: &[Capability::StatementLogHotSwitch],
+            code: "mysql_slow_log_hot_switch_denied",
+            reason: "cannot hot-enable slow log",
+            remediation: "GRANT SYSTEM_VARIABLES_ADMIN ON *.* TO {grantee};",
+        },
+        CapabilityRequirement {
+            level: CollectionLevel::Level1,
+            any_of: &[Capability::StatementLog],
+            code: "mysql_slow_log_unreadable",
+            reason: "cannot collect slow log for digest aggregation",
+            remediation: "make slow_query_log_file readable by the collector, or pass --slow-log-path",
+        },
+        CapabilityRequirement {
+            level: CollectionLevel::Level1,
+            any_of: &[Capability::ErrorLog],
+            code: "mysql_error_log_unreadable",
+            reason: "cannot collect error log",
+            remediation: "make log_error readable by the collector, or pass --error-log-path",
+        },
+        CapabilityRequirement {
+            level: CollectionLevel::Level2,
+            any_of: &[Capability::StatementStatsEnabled],
+            code: "mysql_performance_schema_disabled",
+            reason: "performance_schema is disabled",
+            remediation: "set performance_schema=ON in my.cnf and restart mysqld",
+        },
+        CapabilityRequirement {
+            level: CollectionLevel::Level2,
+            any_of: &[Capability::StatementStatsAccess],
+            code: "mysql_performance_schema_denied",
+            reason: "missing performance_schema read access",
+            remediation: "GRANT SELECT ON performance_schema.* TO {grantee};",
+        },
+        CapabilityRequirement {
+            level: CollectionLevel::Level3,
+            any_of: &[Capability::EngineStatusSampling],
+            code: "mysql_innodb_status_denied",
+            reason: "cannot sample InnoDB status at high frequency",
+            remediation: "GRANT PROCESS ON *.* TO {grantee};",
+        },
+        DEEP_SAMPLER,
+    ],
+    tasks: &[
         CollectionTask {
             level: CollectionLevel::Level0,
             name: "global_status",
             source: "SHOW GLOBAL STATUS",
             purpose: "Capture throughput/latency/error counters",
+            requires: None,
         },
         CollectionTask {
             level: CollectionLevel::Level0,
             name: "global_variables",
             source: "SHOW VARIABLES",
             purpose: "Capture runtime settings and limits",
+            requires: None,
         },
         CollectionTask {
             level: CollectionLevel::Level0,
             name: "schema_storage",
             source: "INFORMATION_SCHEMA.TABLES/STATISTICS",
             purpose: "Collect table size and index layout",
+            requires: None,
         },
         CollectionTask {
             level: CollectionLevel::Level0,
             name: "replication_status",
             source: "SHOW REPLICA/SLAVE STATUS",
             purpose: "Observe replication health and lag",
+            requires: None,
         },
         CollectionTask {
             level: CollectionLevel::Level0,
             name: "os_basic_metrics",
             source: "/proc + vmstat/iostat/sar",
             purpose: "Capture CPU, memory, IO pressure",
+            requires: None,
         },
-    ]
-}
-
-fn level1_tasks() -> Vec<CollectionTask> {
-    vec![
         CollectionTask {
             level: CollectionLevel::Level1,
             name: "slow_log_window",
             source: "slow_query_log",
             purpose: "Collect slow SQL with threshold and time window",
+            requires: None,
         },
         CollectionTask {
             level: CollectionLevel::Level1,
             name: "slow_log_digest",
             source: "slow_query_log digest",
             purpose: "Aggregate similar SQL fingerprints",
+            requires: None,
         },
         CollectionTask {
             level: CollectionLevel::Level1,
             name: "error_log_alerts",
             source: "error log",
             purpose: "Detect deadlock/crash recovery/purge/replication alerts",
+            requires: None,
         },
-    ]
-}
-
-fn level2_tasks(probe: &CapabilityProbe) -> Vec<CollectionTask> {
-    let mut tasks = vec![
         CollectionTask {
             level: CollectionLevel::Level2,
             name: "statement_summary",
             source: "performance_schema.events_statements_summary_by_digest",
             purpose: "Track SQL latency and digest-level hotspots",
+            requires: None,
         },
         CollectionTask {
             level: CollectionLevel::Level2,
             name: "wait_events",
             source: "performance_schema wait event tables",
             purpose: "Attribute CPU/IO/lock wait bottlenecks",
+            requires: None,
         },
         CollectionTask {
             level: CollectionLevel::Level2,
             name: "metadata_locks",
             source: "performance_schema.metadata_locks",
             purpose: "Find metadata lock contention",
+            requires: None,
         },
         CollectionTask {
             level: CollectionLevel::Level2,
             name: "transaction_lock_views",
             source: "performance_schema",
             purpose: "Correlate transaction and lock wait chains",
+            requires: None,
         },
-    ];
-
-    if probe.has_sys_schema_access {
-        tasks.push(CollectionTask {
+        CollectionTask {
             level: CollectionLevel::Level2,
             name: "sys_schema_helpers",
             source: "sys schema",
             purpose: "Use pre-joined lock and statement diagnostic views",
-        });
-    }
-    tasks
-}
-
-fn level3_tasks() -> Vec<CollectionTask> {
-    vec![
+            requires: Some(Capability::DiagnosticViews),
+        },
         CollectionTask {
             level: CollectionLevel::Level3,
             name: "tcpdump_short_window",
             source: "tcpdump",
             purpose: "Capture packet-level anomalies in short windows",
+            requires: None,
         },
         CollectionTask {
             level: CollectionLevel::Level3,
             name: "perf_or_strace_short_window",
             source: "perf/strace",
             purpose: "Capture syscall and CPU hotspots in short windows",
+            requires: None,
         },
         CollectionTask {
             level: CollectionLevel::Level3,
             name: "innodb_status_hf",
             source: "SHOW ENGINE INNODB STATUS",
             purpose: "High-frequency sampling for expert diagnostics",
+            requires: None,
         },
-    ]
-}
+    ],
+};
+
+static POSTGRES_PROFILE: EngineProfile = EngineProfile {
+    engine: Level1Engine::Postgres,
+    grantee_placeholder: "<role>",
+    // ALTER SYSTEM is superuser-only before PostgreSQL 15.
+    legacy_remediations: &[LegacyRemediation {
+        code: "postgres_statement_log_hot_switch_denied",
+        before_version_num: 150000,
+        remediation: "ALTER ROLE {grantee} SUPERUSER;",
+    }],
+    requirements: &[
+        CapabilityRequirement {
+            level: CollectionLevel::Level0,
+            any_of: &[Capability::StatusCounters],
+            code: "postgres_stat_database_denied",
+            reason: "missing pg_stat_database access",
+            remediation: "GRANT pg_read_all_stats TO {grantee};",
+        },
+        CapabilityRequirement {
+            level: CollectionLevel::Level0,
+            any_of: &[Capability::Settings],
+            code: "postgres_settings_denied",
+            reason: "missing pg_settings access",
+            remediation: "GRANT pg_read_all_settings TO {grantee};",
+        },
+        CapabilityRequirement {
+            level: CollectionLevel::Level0,
+            any_of: &[Capability::SchemaMetadata],
+            code: "postgres_catalog_denied",
+            reason: "missing relation/index metadata access",
+            remediation: "GRANT SELECT ON pg_catalog.pg_class, pg_catalog.pg_indexes TO {grantee};",
+        },
+        CapabilityRequirement {
+            level: CollectionLevel::Level0,
+            any_of: &[Capability::ReplicationStatus],
+            code: "postgres_replication_status_denied",
+            reason: "missing pg_stat_replication/pg_stat_wal_receiver access",
+            remediation: "GRANT pg_monitor TO {grantee};",
+        },
+        OS_METRICS,
+        CapabilityRequirement {
+            level: CollectionLevel::Level1,
+            any_of: &[Capability::StatementLogHotSwitch],
+            code: "postgres_statement_log_hot_switch_denied",
+            reason: "cannot hot-enable log_min_duration_statement (ALTER SYSTEM + reload)",
+            remediation: "GRANT ALTER SYSTEM ON PARAMETER log_min_duration_statement TO {grantee}; GRANT EXECUTE ON FUNCTION pg_reload_conf() TO {grantee};",
+        },
+        CapabilityRequirement {
+            level: CollectionLevel::Level1,
+            any_of: &[Capability::StatementLog],
+            code: "postgres_statement_log_unreadable",
+            reason: "cannot collect statement log for digest aggregation",
+            remediation: "set logging_collector=on and make log_directory readable by the collector, or pass --slow-log-path",
+        },
+        CapabilityRequirement {
+            level: CollectionLevel::Level1,
+            any_of: &[Capability::ErrorLog],
+            code: "postgres_error_log_unreadable",
+            reason: "cannot collect PostgreSQL log alerts",
+            remediation: "set logging_collector=on and make the server log readable by the collector, or pass --error-log-path",
+        },
+        CapabilityRequirement {
+            level: CollectionLevel::Level2,
+            any_of: &[Capability::StatementStatsEnabled],
+            code: "postgres_pg_stat_statements_missing",
+            reason: "pg_stat_statements is not installed",
+            remediation: "add pg_stat_statements to shared_preload_libraries, restart, then CREATE EXTENSION pg_stat_statements;",
+        },
+        CapabilityRequirement {
+            level: CollectionLevel::Level2,
+            any_of: &[Capability::StatementStatsAccess],
+            code: "postgres_pg_stat_statements_denied",
+            reason: "missing pg_stat_statements read access",
+            remediation: "GRANT pg_read_all_stats TO {grantee};",
+        },
+        DEEP_SAMPLER,
+    ],
+    tasks: &[
+        CollectionTask {
+            level: CollectionLevel::Level0,
+            name: "global_status",
+            source: "pg_stat_database",
+            purpose: "Capture throughput/latency/error counters",
+            requires: None,
+        },
+        CollectionTask {
+            level: CollectionLevel::Level0,
+            name: "global_variables",
+            source: "pg_settings",
+            purpose: "Capture runtime settings and limits",
+            requires: None,
+        },
+        CollectionTask {
+            level: CollectionLevel::Level0,
+            name: "schema_storage",
+            source: "pg_class/pg_indexes",
+            purpose: "Collect table size and index layout",
+            requires: None,
+        },
+        CollectionTask {
+            level: CollectionLevel::Level0,
+            name: "replication_status",
+            source: "pg_stat_replication/pg_stat_wal_receiver",
+            purpose: "Observe replication health and lag",
+            requires: None,
+        },
+        CollectionTask {
+            level: CollectionLevel::Level0,
+            name: "os_basic_metrics",
+            source: "/proc + vmstat/iostat/sar",
+            purpose: "Capture CPU, memory, IO pressure",
+            requires: None,
+        },
+        CollectionTask {
+            level: CollectionLevel::Level1,
+            name: "slow_log_window",
+            source: "log_min_duration_statement",
+            purpose: "Collect slow SQL with threshold and time window",
+            requires: None,
+        },
+        CollectionTask {
+            level: CollectionLevel::Level1,
+            name: "slow_log_digest",
+            source: "statement log digest",
+            purpose: "Aggregate similar SQL fingerprints",
+            requires: None,
+        },
+        CollectionTask {
+            level: CollectionLevel::Level1,
+            name: "error_log_alerts",
+            source: "server log",
+            purpose: "Detect deadlock/crash recovery/checkpoint/replication alerts",
+            requires: None,
+        },
+        CollectionTask {
+            level: CollectionLevel::Level2,
+            name: "statement_summary",
+            source: "pg_stat_statements",
+            purpose: "Track SQL latency and digest-level hotspots",
+            requires: None,
+        },
+        CollectionTask {
+            level: CollectionLevel::Level2,
+            name: "wait_events",
+            source: "pg_stat_activity.wait_event",
+            purpose: "Attribute CPU/IO/lock wait bottlenecks",
+            requires: None,
+        },
+        CollectionTask {
+            level: CollectionLevel::Level2,
+            name: "transaction_lock_views",
+            source: "pg_locks + pg_blocking_pids()",
+            purpose: "Correlate transaction and lock wait chains",
+            requires: None,
+        },
+        CollectionTask {
+            level: CollectionLevel::Level3,
+            name: "tcpdump_short_window",
+            source: "tcpdump",
+            purpose: "Capture packet-level anomalies in short windows",
+            requires: None,
+        },
+        CollectionTask {
+            level: CollectionLevel::Level3,
+            name: "perf_or_strace_short_window",
+            source: "perf/strace",
+            purpose: "Capture syscall and CPU hotspots in short windows",
+            requires: None,
+        },
+    ],
+};
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn should_downgrade_to_level1_when_performance_schema_unavailable() {
-        let policy = CollectionPolicy {
+    const LEVEL1_CAPABILITIES: [Capability; 8] = [
+        Capability::StatusCounters,
+        Capability::Settings,
+        Capability::SchemaMetadata,
+        Capability::ReplicationStatus,
+        Capability::OsMetrics,
+        Capability::StatementLogHotSwitch,
+        Capability::StatementLog,
+        Capability::ErrorLog,
+    ];
+
+    fn expert_policy() -> CollectionPolicy {
+        CollectionPolicy {
             preferred_level: CollectionLevel::Level3,
             max_accepted_level: CollectionLevel::Level3,
             expert_mode_enabled: true,
-        };
-        let probe = CapabilityProbe {
-            has_mysql_status_access: true,
-            has_mysql_variables_access: true,
-            has_information_schema_access: true,
-            has_replication_status_access: true,
-            has_os_metrics_access: true,
-            can_enable_slow_log_hot_switch: true,
-            can_read_slow_log: true,
-            can_read_error_log: true,
-            performance_schema_enabled: false,
-            has_performance_schema_access: false,
-            has_sys_schema_access: false,
-            can_capture_tcpdump_short_window: false,
-            can_capture_perf_short_window: false,
-            can_capture_strace_short_window: false,
-            can_sample_innodb_status_high_frequency: false,
-        };
+        }
+    }
 
-        let result = negotiate_collection_level(&policy, &probe);
+    #[test]
+    fn should_downgrade_to_level1_when_performance_schema_unavailable() {
+        let probe = LEVEL1_CAPABILITIES.into_iter().collect::<CapabilityProbe>();
+
+        let result = negotiate_collection_level(Level1Engine::Mysql, &expert_policy(), &probe);
         assert_eq!(result.selected_level, CollectionLevel::Level1);
+        let level2 = &result.evaluations[1];
+        assert_eq!(level2.level, CollectionLevel::Level2);
+        assert_eq!(
+            level2.reasons.iter().map(|it| it.code).collect::<Vec<_>>(),
+            vec![
+                "mysql_performance_schema_disabled",
+                "mysql_performance_schema_denied"
+            ]
+        );
     }
 
     #[test]
     fn should_select_level2_when_customer_caps_at_level2() {
-        let policy = CollectionPolicy::default();
-        let probe = CapabilityProbe {
-            has_mysql_status_access: true,
-            has_mysql_variables_access: true,
-            has_information_schema_access: true,
-            has_replication_status_access: true,
-            has_os_metrics_access: true,
-            can_enable_slow_log_hot_switch: true,
-            can_read_slow_log: true,
-            can_read_error_log: true,
-            performance_schema_enabled: true,
-            has_performance_schema_access: true,
-            has_sys_schema_access: true,
-            can_capture_tcpdump_short_window: true,
-            can_capture_perf_short_window: true,
-            can_capture_strace_short_window: false,
-            can_sample_innodb_status_high_frequency: true,
-        };
+        let probe = LEVEL1_CAPABILITIES
+            .into_iter()
+            .chain([
+                Capability::StatementStatsEnabled,
+                Capability::StatementStatsAccess,
+                Capability::DiagnosticViews,
+                Capability::TcpdumpShortWindow,
+                Capability::PerfShortWindow,
+                Capability::EngineStatusSampling,
+            ])
+            .collect::<CapabilityProbe>();
 
-        let result = negotiate_collection_level(&policy, &probe);
+        let result =
+            negotiate_collection_level(Level1Engine::Mysql, &CollectionPolicy::default(), &probe);
         assert_eq!(result.selected_level, CollectionLevel::Level2);
+        assert!(
+            result
+                .tasks
+                .iter()
+                .any(|it| it.name == "sys_schema_helpers")
+        );
+    }
+
+    #[test]
+    fn should_negotiate_postgres_with_its_own_requirements_and_reasons() {
+        let mut probe = LEVEL1_CAPABILITIES
+            .into_iter()
+            .chain([Capability::StraceShortWindow])
+            .collect::<CapabilityProbe>();
+
+        let result = negotiate_collection_level(Level1Engine::Postgres, &expert_policy(), &probe);
+        assert_eq!(result.selected_level, CollectionLevel::Level1);
+        assert_eq!(
+            result.evaluations[0]
+                .reasons
+                .iter()
+                .map(|it| it.message)
+                .collect::<Vec<_>>(),
+            vec![
+                "pg_stat_statements is not installed",
+                "missing pg_stat_statements read access"
+            ]
+        );
+
+        probe.set(Capability::StatementLogHotSwitch, false);
+        let result = negotiate_collection_level(Level1Engine::Postgres, &expert_policy(), &probe);
+        assert_eq!(
+            result.evaluations[2].reasons[0].message,
+            "cannot hot-enable log_min_duration_statement (ALTER SYSTEM + reload)"
+        );
+
+        // No InnoDB requirement: PostgreSQL reaches Level 3 with any deep sampler.
+        probe.set(Capability::StatementLogHotSwitch, true);
+        probe.set(Capability::StatementStatsEnabled, true);
+        probe.set(Capability::StatementStatsAccess, true);
+        let result = negotiate_collection_level(Level1Engine::Postgres, &expert_policy(), &probe);
+        assert_eq!(result.selected_level, CollectionLevel::Level3);
+        let names = result.tasks.iter().map(|it| it.name).collect::<Vec<_>>();
+        assert!(names.contains(&"perf_or_strace_short_window"));
+        assert!(!names.contains(&"innodb_status_hf"));
+        assert!(!names.contains(&"sys_schema_helpers"));
+    }
+
+    #[test]
+    fn should_report_structured_downgrade_reasons_with_grantee() {
+        let probe = [
+            Capability::StatusCounters,
+            Capability::Settings,
+            Capability::SchemaMetadata,
+            Capability::OsMetrics,
+        ]
+        .into_iter()
+        .collect::<CapabilityProbe>();
+        let policy = CollectionPolicy {
+            preferred_level: CollectionLevel::Level1,
+            max_accepted_level: CollectionLevel::Level1,
+            expert_mode_enabled: false,
+        };
+
+        let result = negotiate_collection_level(Level1Engine::Mysql, &policy, &probe);
+        let reasons = result.downgrade_reasons(Some("'monitor'@'%'"), Some(80036));
+        assert_eq!(
+            reasons.iter().map(|it| it.code).collect::<Vec<_>>(),
+            vec![
+                "mysql_replication_status_denied",
+                "mysql_slow_log_hot_switch_denied",
+                "mysql_slow_log_unreadable",
+                "mysql_error_log_unreadable"
+            ]
+        );
+        assert_eq!(
+            reasons[0],
+            DowngradeReason {
+                code: "mysql_replication_status_denied",
+                missing_capabilities: vec![Capability::ReplicationStatus],
+                blocked_level: CollectionLevel::Level0,
+                message: "missing replication status access",
+                remediation: "GRANT REPLICATION CLIENT ON *.* TO 'monitor'@'%';".to_string(),
+            }
+        );
+        assert_eq!(reasons[1].blocked_level, CollectionLevel::Level1);
+
+        let policy = CollectionPolicy {
+            expert_mode_enabled: false,
+            ..expert_policy()
+        };
+        let postgres = negotiate_collection_level(Level1Engine::Postgres, &policy, &probe);
+        let reasons = postgres.downgrade_reasons(None, None);
+        assert_eq!(
+            reasons
+                .iter()
+                .find(|it| it.code == "postgres_replication_status_denied")
+                .map(|it| it.remediation.as_str()),
+            Some("GRANT pg_monitor TO <role>;")
+        );
+        assert_eq!(
+            reasons.last().map(|it| it.code),
+            Some("expert_mode_disabled")
+        );
+        assert!(reasons.last().unwrap().missing_capabilities.is_empty());
+    }
+
+    #[test]
+    fn should_suggest_legacy_remediation_for_old_servers() {
+        let probe = [
+            Capability::StatusCounters,
+            Capability::Settings,
+            Capability::SchemaMetadata,
+            Capability::ReplicationStatus,
+            Capability::OsMetrics,
+            Capability::StatementLog,
+            Capability::ErrorLog,
+        ]
+        .into_iter()
+        .collect::<CapabilityProbe>();
+        let policy = CollectionPolicy {
+            preferred_level: CollectionLevel::Level1,
+            max_accepted_level: CollectionLevel::Level1,
+            expert_mode_enabled: false,
+        };
+        let hot_switch = |engine, grantee, version| {
+            negotiate_collection_level(engine, &policy, &probe)
+                .downgrade_reasons(Some(grantee), version)
+                .into_iter()
+                .find(|it| it.code.ends_with("_hot_switch_denied"))
+                .map(|it| it.remediation)
+                .expect("hot switch reason")
+        };
+
+        assert_eq!(
+            hot_switch(Level1Engine::Mysql, "'monitor'@'%'", Some(50744)),
+            "GRANT SUPER ON *.* TO 'monitor'@'%';"
+        );
+        assert_eq!(
+            hot_switch(Level1Engine::Mysql, "'monitor'@'%'", Some(80036)),
+            "GRANT SYSTEM_VARIABLES_ADMIN ON *.* TO 'monitor'@'%';"
+        );
+        assert_eq!(
+            hot_switch(Level1Engine::Postgres, "monitor", Some(140011)),
+            "ALTER ROLE monitor SUPERUSER;"
+        );
+        assert!(
+            hot_switch(Level1Engine::Postgres, "monitor", None).starts_with("GRANT ALTER SYSTEM")
+        );
     }
 }
@@ -0,0 +1,450 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::collection::CollectionLevel;
+use crate::level1::Level1Engine;
+use crate::pipeline::RunMode;
+use crate::secrets::{mask_url, redact};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("failed reading config {path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("failed parsing config {path}: {message}")]
+    Parse { path: String, message: String },
+    #[error("unknown config profile `{profile}` (known: {known})")]
+    UnknownProfile { profile: String, known: String },
+}
+
+/// Config file layout: a `[defaults]` layer, named `[profiles.<name>]`
+/// layers applied on top of it, and an optional `default_profile`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    pub default_profile: Option<String>,
+    #[serde(default)]
+    pub defaults: ConfigLayer,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, ConfigLayer>,
+}
+
+/// One layer of settings. Unset keys fall through to lower layers; the merged
+/// result sits below environment variables and CLI flags.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigLayer {
+    #[serde(default)]
+    pub connection: ConnectionSection,
+    #[serde(default)]
+    pub collection: CollectionSection,
+    #[serde(default)]
+    pub scheduler: SchedulerSection,
+    #[serde(default)]
+    pub limits: LimitsSection,
+    #[serde(default)]
+    pub level1: Level1Section,
+    #[serde(default)]
+    pub rules: RulesSection,
+    #[serde(default)]
+    pub output: OutputSection,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConnectionSection {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub engine: Option<Level1Engine>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mysql_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub postgres_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password_file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub targets_file: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CollectionSection {
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_level"
+    )]
+    pub collect_level: Option<CollectionLevel>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expert_mode: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_tasks: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclude_tasks: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SchedulerSection {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub run_mode: Option<RunMode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interval_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jitter_pct: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_times: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_backoff_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_cycles: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_workers: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LimitsSection {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub table_limit: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index_limit: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_slow_log_bytes: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_error_log_bytes: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_error_log_lines: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Level1Section {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slow_log_window_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slow_log_long_query_time_secs: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slow_log_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_log_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slow_log_hot_switch: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub restore_slow_log_settings: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RulesSection {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_alert_rules: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub long_transaction_secs: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idle_in_transaction_secs: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OutputSection {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<OutputFormat>,
+    /// Appends records to this file instead of writing them to stdout.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    Json,
+    PrettyJson,
+}
+
+impl ConfigFile {
+    /// Loads a TOML config file.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let display = path.display().to_string();
+        let text = fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: display.clone(),
+            source,
+        })?;
+        let file: ConfigFile = toml::from_str(&text).map_err(|err| ConfigError::Parse {
+            path: display.clone(),
+            message: err.to_string(),
+        })?;
+        if let Some(profile) = &file.default_profile {
+            file.profile_layer(profile)?;
+        }
+        Ok(file)
+    }
+
+    /// Merges `[defaults]` with the named profile, or with `default_profile`
+    /// when no name is given.
+    pub fn resolve(&self, profile: Option<&str>) -> Result<ConfigLayer, ConfigError> {
+        match profile.or(self.default_profile.as_deref()) {
+            Some(name) => Ok(self
+                .defaults
+                .clone()
+                .merge(self.profile_layer(name)?.clone())),
+            None => Ok(self.defaults.clone()),
+        }
+    }
+
+    fn profile_layer(&self, name: &str) -> Result<&ConfigLayer, ConfigError> {
+        self.profiles
+            .get(name)
+            .ok_or_else(|| ConfigError::UnknownProfile {
+                profile: name.to_string(),
+                known: self
+                    .profiles
+                    .keys()
+                    .map(String::as_str)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            })
+    }
+}
+
+impl ConfigLayer {
+    /// Returns `self` with every key set in `overlay` replaced.
+    pub fn merge(self, overlay: ConfigLayer) -> ConfigLayer {
+        ConfigLayer {
+            connection: ConnectionSection {
+                engine: overlay.connection.engine.or(self.connection.engine),
+                mysql_url: overlay.connection.mysql_url.or(self.connection.mysql_url),
+                postgres_url: overlay
+                    .connection
+                    .postgres_url
+                    .or(self.connection.postgres_url),
+                password_file: overlay
+                    .connection
+                    .password_file
+                    .or(self.connection.password_file),
+                targets_file: overlay
+                    .connection
+                    .targets_file
+                    .or(self.connection.targets_file),
+            },
+            collection: CollectionSection {
+                collect_level: overlay
+                    .collection
+                    .collect_level
+                    .or(self.collection.collect_level),
+                expert_mode: overlay
+                    .collection
+                    .expert_mode
+                    .or(self.collection.expert_mode),
+                include_tasks: overlay
+                    .collection
+                    .include_tasks
+                    .or(self.collection.include_tasks),
+                exclude_tasks: overlay
+                    .collection
+                    .exclude_tasks
+                    .or(self.collection.exclude_tasks),
+            },
+            scheduler: SchedulerSection {
+                run_mode: overlay.scheduler.run_mode.or(self.scheduler.run_mode),
+                interval_secs: overlay
+                    .scheduler
+                    .interval_secs
+                    .or(self.scheduler.interval_secs),
+                jitter_pct: overlay.scheduler.jitter_pct.or(self.scheduler.jitter_pct),
+                timeout_secs: overlay
+                    .scheduler
+                    .timeout_secs
+                    .or(self.scheduler.timeout_secs),
+                retry_times: overlay.scheduler.retry_times.or(self.scheduler.retry_times),
+                retry_backoff_ms: overlay
+                    .scheduler
+                    .retry_backoff_ms
+                    .or(self.scheduler.retry_backoff_ms),
+                max_cycles: overlay.scheduler.max_cycles.or(self.scheduler.max_cycles),
+                max_workers: overlay.scheduler.max_workers.or(self.scheduler.max_workers),
+            },
+            limits: LimitsSection {
+                table_limit: overlay.limits.table_limit.or(self.limits.table_limit),
+                index_limit: overlay.limits.index_limit.or(self.limits.index_limit),
+                max_slow_log_bytes: overlay
+                    .limits
+                    .max_slow_log_bytes
+                    .or(self.limits.max_slow_log_bytes),
+                max_error_log_bytes: overlay
+                    .limits
+                    .max_error_log_bytes
+                    .or(self.limits.max_error_log_bytes),
+                max_error_log_lines: overlay
+                    .limits
+                    .max_error_log_lines
+                    .or(self.limits.max_error_log_lines),
+            },
+            level1: Level1Section {
+                slow_log_window_secs: overlay
+                    .level1
+                    .slow_log_window_secs
+                    .or(self.level1.slow_log_window_secs),
+                slow_log_long_query_time_secs: overlay
+                    .level1
+                    .slow_log_long_query_time_secs
+                    .or(self.level1.slow_log_long_query_time_secs),
+                slow_log_path: overlay.level1.slow_log_path.or(self.level1.slow_log_path),
+                error_log_path: overlay.level1.error_log_path.or(self.level1.error_log_path),
+                slow_log_hot_switch: overlay
+                    .level1
+                    .slow_log_hot_switch
+                    .or(self.level1.slow_log_hot_switch),
+                restore_slow_log_settings: overlay
+                    .level1
+                    .restore_slow_log_settings
+                    .or(self.level1.restore_slow_log_settings),
+            },
+            rules: RulesSection {
+                error_alert_rules: overlay
+                    .rules
+                    .error_alert_rules
+                    .or(self.rules.error_alert_rules),
+                long_transaction_secs: overlay
+                    .rules
+                    .long_transaction_secs
+                    .or(self.rules.long_transaction_secs),
+                idle_in_transaction_secs: overlay
+                    .rules
+                    .idle_in_transaction_secs
+                    .or(self.rules.idle_in_transaction_secs),
+            },
+            output: OutputSection {
+                format: overlay.output.format.or(self.output.format),
+                file: overlay.output.file.or(self.output.file),
+            },
+        }
+    }
+
+    /// Renders the layer as TOML with connection URL passwords masked and
+    /// every other value passed through [`redact`].
+    pub fn to_masked_toml(&self) -> Result<String, toml::ser::Error> {
+        let mut unmasked = self.clone();
+        let mysql_url = unmasked.connection.mysql_url.take();
+        let postgres_url = unmasked.connection.postgres_url.take();
+        let mut table = toml::Table::try_from(&unmasked)?;
+        table.iter_mut().for_each(|(_, it)| redact_toml(it));
+        if let Some(toml::Value::Table(connection)) = table.get_mut("connection") {
+            for (key, url) in [("mysql_url", mysql_url), ("postgres_url", postgres_url)] {
+                if let Some(url) = url {
+                    connection.insert(key.to_string(), mask_url(&url).into());
+                }
+            }
+        }
+        toml::to_string_pretty(&table)
+    }
+}
+
+fn redact_toml(value: &mut toml::Value) {
+    match value {
+        toml::Value::String(text) => {
+            if let Cow::Owned(redacted) = redact(text) {
+                *text = redacted;
+            }
+        }
+        toml::Value::Array(items) => items.iter_mut().for_each(redact_toml),
+        toml::Value::Table(fields) => fields.iter_mut().for_each(|(_, it)| redact_toml(it)),
+        _ => {}
+    }
+}
+
+fn serialize_level<S: Serializer>(
+    level: &Option<CollectionLevel>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let name = match level {
+        Some(CollectionLevel::Level0) => "level0",
+        Some(CollectionLevel::Level1) => "level1",
+        Some(CollectionLevel::Level2) => "level2",
+        Some(CollectionLevel::Level3) => "level3",
+        None => return serializer.serialize_none(),
+    };
+    serializer.serialize_str(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> ConfigFile {
+        ConfigFile::load(
+            &Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/config/sql-insight.toml"),
+        )
+        .expect("config fixture")
+    }
+
+    #[test]
+    fn should_layer_profile_over_defaults() {
+        let file = fixture();
+
+        let light = file.resolve(None).unwrap();
+        assert_eq!(light.scheduler.run_mode, Some(RunMode::Daemon));
+        assert_eq!(light.scheduler.interval_secs, Some(300));
+        assert_eq!(light.scheduler.timeout_secs, Some(60));
+        assert_eq!(
+            light.collection.collect_level,
+            Some(CollectionLevel::Level0)
+        );
+        assert_eq!(light.limits.table_limit, Some(50));
+
+        let deep = file.resolve(Some("incident-deep")).unwrap();
+        assert_eq!(deep.scheduler.interval_secs, Some(30));
+        assert_eq!(deep.scheduler.timeout_secs, Some(60));
+        assert_eq!(deep.collection.collect_level, Some(CollectionLevel::Level3));
+        assert_eq!(deep.collection.expert_mode, Some(true));
+        assert_eq!(deep.level1.slow_log_window_secs, Some(120));
+        assert_eq!(deep.rules.long_transaction_secs, Some(0.5));
+        assert_eq!(deep.output.format, Some(OutputFormat::Json));
+        assert_eq!(deep.limits.table_limit, None);
+    }
+
+    #[test]
+    fn should_reject_unknown_profiles_and_keys() {
+        let err = fixture().resolve(Some("prod")).unwrap_err().to_string();
+        assert!(err.contains("unknown config profile `prod`"), "{err}");
+        assert!(err.contains("incident-deep, prod-light"), "{err}");
+
+        assert!(toml::from_str::<ConfigFile>("[defaults.scheduler]\ninterval = 5").is_err());
+        let file: ConfigFile = toml::from_str("default_profile = \"x\"").unwrap();
+        assert!(file.resolve(None).is_err());
+    }
+
+    #[test]
+    fn should_mask_url_passwords() {
+        let mut layer = ConfigLayer::default();
+        layer.connection.mysql_url = Some("mysql://monitor:hunter2@db:3306".to_string());
+        layer.connection.postgres_url = Some("host=db user=app password=hunter2".to_string());
+        layer.connection.password_file = Some("/run/pw?password=hunter2".to_string());
+        layer.collection.collect_level = Some(CollectionLevel::Level1);
+        let rendered = layer.to_masked_toml().unwrap();
+        assert!(!rendered.contains("hunter2"), "{rendered}");
+        assert!(
+            rendered.contains("mysql_url = \"mysql://monitor:****@db:3306\""),
+            "{rendered}"
+        );
+        assert!(
+            rendered.contains("postgres_url = \"host=db user=app password=****\""),
+            "{rendered}"
+        );
+        assert!(
+            rendered.contains("collect_level = \"level1\""),
+            "{rendered}"
+        );
+        let reparsed: ConfigLayer = toml::from_str(&rendered).unwrap();
+        assert_eq!(
+            reparsed.collection.collect_level,
+            Some(CollectionLevel::Level1)
+        );
+    }
+}
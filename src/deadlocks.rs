@@ -0,0 +1,713 @@
+use std::collections::BTreeSet;
+
+use serde::Serialize;
+
+use crate::error_log::{parse_mysql_error_line, parse_postgres_error_line};
+use crate::fingerprint::{SqlDialect, digest_hash, fingerprinter_for, referenced_tables};
+
+pub const INNODB_ERROR_LOG_SOURCE: &str = "innodb_error_log";
+pub const INNODB_STATUS_SOURCE: &str = "innodb_status";
+pub const POSTGRES_LOG_SOURCE: &str = "postgres_log";
+
+const MAX_DEADLOCK_GROUPS: usize = 20;
+
+/// `lock_type` is `record`/`table` for InnoDB and `transaction`, `tuple`,
+/// `relation`, ... for PostgreSQL. `object` keeps the raw lock target.
+#[derive(Debug, Clone, Serialize, Default, PartialEq, Eq)]
+pub struct DeadlockLock {
+    pub lock_type: String,
+    pub mode: String,
+    pub table: Option<String>,
+    pub index: Option<String>,
+    pub object: Option<String>,
+}
+
+/// `label` is the InnoDB transaction number (`1`, `2`) or the PostgreSQL PID.
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct DeadlockParticipant {
+    pub label: String,
+    pub transaction_id: Option<String>,
+    pub thread_id: Option<u64>,
+    pub active_secs: Option<u64>,
+    pub lock_structs: Option<u64>,
+    pub row_locks: Option<u64>,
+    pub statement: Option<String>,
+    pub statement_fingerprint: Option<String>,
+    pub holds: Vec<DeadlockLock>,
+    pub waits_for: Vec<DeadlockLock>,
+    pub rolled_back: bool,
+}
+
+/// `deadlock_id` hashes the participants' statement fingerprints and awaited
+/// locks, so the same pattern gets the same id regardless of row values,
+/// transaction ids or which side was rolled back.
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct DeadlockRecord {
+    pub source: String,
+    pub detected_at: Option<String>,
+    pub deadlock_id: String,
+    pub participants: Vec<DeadlockParticipant>,
+    pub victim: Option<String>,
+    pub tables: Vec<String>,
+    pub indexes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct DeadlockGroup {
+    pub deadlock_id: String,
+    pub count: u64,
+    pub first_detected_at: Option<String>,
+    pub last_detected_at: Option<String>,
+    pub tables: Vec<String>,
+    pub statements: Vec<String>,
+    pub latest: DeadlockRecord,
+    /// Occurrence keys already counted; see [`occurrence_key`].
+    #[serde(skip)]
+    seen: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct DeadlockReport {
+    pub parsed: usize,
+    pub groups: Vec<DeadlockGroup>,
+}
+
+impl DeadlockReport {
+    /// Adds records to their pattern group. A record with the same occurrence
+    /// key as one already in its group is the same deadlock seen through
+    /// another source and is not counted again.
+    pub fn add(&mut self, records: Vec<DeadlockRecord>) {
+        for record in records {
+            let key = occurrence_key(&record);
+            let position = self
+                .groups
+                .iter()
+                .position(|it| it.deadlock_id == record.deadlock_id);
+            let Some(position) = position else {
+                self.parsed += 1;
+                self.groups.push(DeadlockGroup {
+                    deadlock_id: record.deadlock_id.clone(),
+                    count: 1,
+                    first_detected_at: record.detected_at.clone(),
+                    last_detected_at: record.detected_at.clone(),
+                    tables: record.tables.clone(),
+                    statements: record_statements(&record),
+                    seen: key.into_iter().collect(),
+                    latest: record,
+                });
+                continue;
+            };
+            let group = &mut self.groups[position];
+            if let Some(key) = key
+                && !group.seen.insert(key)
+            {
+                continue;
+            }
+            self.parsed += 1;
+            group.count += 1;
+            if record.detected_at.is_some()
+                && (group.first_detected_at.is_none()
+                    || record.detected_at < group.first_detected_at)
+            {
+                group.first_detected_at = record.detected_at.clone();
+            }
+            if record.detected_at >= group.last_detected_at {
+                group.last_detected_at = record.detected_at.clone();
+                group.latest = record;
+            }
+        }
+        self.groups.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| b.last_detected_at.cmp(&a.last_detected_at))
+                .then_with(|| a.deadlock_id.cmp(&b.deadlock_id))
+        });
+        self.groups.truncate(MAX_DEADLOCK_GROUPS);
+    }
+}
+
+/// Identifies one deadlock occurrence across sources. InnoDB transaction ids
+/// are unique per occurrence, while the error log and `SHOW ENGINE INNODB
+/// STATUS` may print the detection time in different zones, so the time is
+/// only used when no transaction ids were parsed.
+fn occurrence_key(record: &DeadlockRecord) -> Option<String> {
+    let mut ids = record
+        .participants
+        .iter()
+        .filter_map(|it| {
+            let transaction_id = it.transaction_id.as_deref()?;
+            Some(format!(
+                "{transaction_id}:{}",
+                it.thread_id.map(|id| id.to_string()).unwrap_or_default()
+            ))
+        })
+        .collect::<Vec<_>>();
+    if ids.is_empty() {
+        return record.detected_at.as_ref().map(|it| format!("at {it}"));
+    }
+    ids.sort();
+    Some(format!(
+        "{}|{}",
+        ids.join(","),
+        record.victim.as_deref().unwrap_or_default()
+    ))
+}
+
+fn record_statements(record: &DeadlockRecord) -> Vec<String> {
+    record
+        .participants
+        .iter()
+        .filter_map(|it| it.statement_fingerprint.clone())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+/// Parses InnoDB deadlock sections, either `LATEST DETECTED DEADLOCK` from
+/// `SHOW ENGINE INNODB STATUS` or the dumps written to the error log with
+/// `innodb_print_all_deadlocks`. Error log header prefixes are stripped.
+pub fn parse_innodb_deadlocks<S: AsRef<str>>(lines: &[S], source: &str) -> Vec<DeadlockRecord> {
+    let mut records = Vec::new();
+    let mut current: Option<InnodbDeadlock> = None;
+    let mut pending_detected_at: Option<String> = None;
+    let mut awaiting_timestamp = false;
+
+    for raw in lines {
+        let raw = raw.as_ref();
+        let (timestamp, text) = match parse_mysql_error_line(raw) {
+            Some(record) if record.timestamp.is_some() => (record.timestamp, record.message),
+            _ => (None, raw.trim().to_string()),
+        };
+        let text = text.trim();
+
+        if text == "LATEST DETECTED DEADLOCK" || text.starts_with("Transactions deadlock detected")
+        {
+            if let Some(done) = current.take() {
+                records.push(done.finish(source));
+            }
+            pending_detected_at = timestamp.as_deref().map(normalize_timestamp);
+            awaiting_timestamp = pending_detected_at.is_none();
+            continue;
+        }
+        if awaiting_timestamp
+            && text.len() >= 19
+            && text.as_bytes()[..4].iter().all(u8::is_ascii_digit)
+            && text.as_bytes()[4] == b'-'
+        {
+            pending_detected_at = Some(normalize_timestamp(text));
+            awaiting_timestamp = false;
+            continue;
+        }
+
+        if let Some(label) = text
+            .strip_prefix("*** WE ROLL BACK TRANSACTION (")
+            .and_then(|it| it.split(')').next())
+        {
+            if let Some(mut done) = current.take() {
+                done.victim = Some(label.to_string());
+                records.push(done.finish(source));
+            }
+            continue;
+        }
+        if let Some(rest) = text.strip_prefix("*** (") {
+            let Some((label, heading)) = rest.split_once(')') else {
+                continue;
+            };
+            let heading = heading.trim();
+            if heading.starts_with("TRANSACTION") {
+                let deadlock = current.get_or_insert_with(|| InnodbDeadlock {
+                    detected_at: pending_detected_at.take(),
+                    ..InnodbDeadlock::default()
+                });
+                awaiting_timestamp = false;
+                deadlock.participants.push(DeadlockParticipant {
+                    label: label.to_string(),
+                    ..DeadlockParticipant::default()
+                });
+                deadlock.section = Section::Transaction(deadlock.participants.len() - 1);
+            } else if let Some(deadlock) = current.as_mut() {
+                let index = deadlock.participant_index(label);
+                deadlock.section = if heading.starts_with("HOLDS") {
+                    Section::Holds(index)
+                } else if heading.starts_with("WAITING") {
+                    Section::Waits(index)
+                } else {
+                    Section::None
+                };
+            }
+            continue;
+        }
+
+        if let Some(deadlock) = current.as_mut() {
+            deadlock.push_line(text);
+        }
+    }
+    if let Some(done) = current {
+        records.push(done.finish(source));
+    }
+    // A dump cut by the tail window starts mid-way and lists one transaction.
+    records.retain(|it| it.participants.len() >= 2);
+    records
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+enum Section {
+    #[default]
+    None,
+    Transaction(usize),
+    Holds(usize),
+    Waits(usize),
+}
+
+#[derive(Debug, Default)]
+struct InnodbDeadlock {
+    detected_at: Option<String>,
+    participants: Vec<DeadlockParticipant>,
+    victim: Option<String>,
+    section: Section,
+    statement_started: bool,
+    statement_lines: Vec<Vec<String>>,
+}
+
+impl InnodbDeadlock {
+    fn participant_index(&mut self, label: &str) -> usize {
+        if let Some(index) = self.participants.iter().position(|it| it.label == label) {
+            return index;
+        }
+        self.participants.push(DeadlockParticipant {
+            label: label.to_string(),
+            ..DeadlockParticipant::default()
+        });
+        self.participants.len() - 1
+    }
+
+    fn push_line(&mut self, text: &str) {
+        match self.section {
+            Section::None => {}
+            Section::Transaction(index) => {
+                if self.statement_lines.len() <= index {
+                    self.statement_lines.resize(index + 1, Vec::new());
+                    self.statement_started = false;
+                }
+                let participant = &mut self.participants[index];
+                if let Some(rest) = text.strip_prefix("TRANSACTION ") {
+                    let mut parts = rest.split(", ");
+                    participant.transaction_id = parts.next().map(str::to_string);
+                    participant.active_secs = parts
+                        .next()
+                        .and_then(|it| it.strip_prefix("ACTIVE "))
+                        .and_then(|it| it.split_whitespace().next())
+                        .and_then(|it| it.parse().ok());
+                } else if let Some(rest) = text.strip_prefix("MySQL thread id ") {
+                    participant.thread_id =
+                        rest.split(',').next().and_then(|it| it.trim().parse().ok());
+                    self.statement_started = true;
+                } else if text.contains("lock struct(s)") {
+                    participant.lock_structs = leading_number(text, "lock struct(s)");
+                    participant.row_locks = leading_number(text, "row lock(s)");
+                } else if self.statement_started && !text.is_empty() {
+                    self.statement_lines[index].push(text.to_string());
+                }
+            }
+            Section::Holds(index) => {
+                if let Some(lock) = parse_innodb_lock(text) {
+                    self.participants[index].holds.push(lock);
+                }
+            }
+            Section::Waits(index) => {
+                if let Some(lock) = parse_innodb_lock(text) {
+                    self.participants[index].waits_for.push(lock);
+                }
+            }
+        }
+    }
+
+    fn finish(mut self, source: &str) -> DeadlockRecord {
+        for (index, lines) in self.statement_lines.into_iter().enumerate() {
+            if !lines.is_empty() {
+                self.participants[index].statement = Some(lines.join(" "));
+            }
+        }
+        build_record(
+            source,
+            SqlDialect::Mysql,
+            self.detected_at,
+            self.participants,
+            self.victim,
+        )
+    }
+}
+
+/// The number right before `unit`, e.g. `3` in `LOCK WAIT 3 lock struct(s)`.
+fn leading_number(text: &str, unit: &str) -> Option<u64> {
+    let idx = text.find(unit)?;
+    text[..idx].split_whitespace().last()?.parse().ok()
+}
+
+/// `RECORD LOCKS space id 2 page no 4 n bits 72 index PRIMARY of table
+/// `shop`.`accounts` trx id 1 lock_mode X locks rec but not gap waiting` or
+/// `TABLE LOCK table `shop`.`accounts` trx id 1 lock mode IX`.
+fn parse_innodb_lock(text: &str) -> Option<DeadlockLock> {
+    let (lock_type, table_start) = if text.starts_with("RECORD LOCKS ") {
+        ("record", text.find(" of table ")? + " of table ".len())
+    } else if let Some(rest) = text.strip_prefix("TABLE LOCK table ") {
+        ("table", text.len() - rest.len())
+    } else {
+        return None;
+    };
+    let index = (lock_type == "record")
+        .then(|| {
+            let start = text.find(" index ")? + " index ".len();
+            let name = text[start..].split_whitespace().next()?;
+            Some(name.trim_matches('`').to_string())
+        })
+        .flatten();
+    let table_text = &text[table_start..];
+    let table_end = table_text.find(" trx id").unwrap_or(table_text.len());
+    let table = table_text[..table_end]
+        .split(" /*")
+        .next()
+        .unwrap_or_default()
+        .replace('`', "");
+    let mode = ["lock_mode ", "lock mode "]
+        .iter()
+        .find_map(|marker| text.find(marker).map(|it| &text[(it + marker.len())..]))
+        .unwrap_or_default()
+        .trim()
+        .trim_end_matches("waiting")
+        .trim();
+    Some(DeadlockLock {
+        lock_type: lock_type.to_string(),
+        mode: mode.to_string(),
+        table: (!table.is_empty()).then_some(table),
+        index,
+        object: None,
+    })
+}
+
+/// Parses `deadlock detected` errors with their `DETAIL`, `CONTEXT` and
+/// `STATEMENT` lines. The reporting backend is the one that was rolled back.
+pub fn parse_postgres_deadlocks<S: AsRef<str>>(lines: &[S]) -> Vec<DeadlockRecord> {
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    enum Field {
+        Detail,
+        Context,
+        Statement,
+        Other,
+    }
+
+    let mut records = Vec::new();
+    let mut current: Option<PostgresDeadlock> = None;
+    let mut field = Field::Other;
+    for raw in lines {
+        let raw = raw.as_ref();
+        if let Some(record) = parse_postgres_error_line(raw) {
+            if let Some(done) = current.take() {
+                records.push(done.finish());
+            }
+            if record.message.contains("deadlock detected") {
+                current = Some(PostgresDeadlock {
+                    detected_at: record.timestamp.as_deref().map(normalize_timestamp),
+                    victim: record.thread_id,
+                    ..PostgresDeadlock::default()
+                });
+                field = Field::Other;
+            }
+            continue;
+        }
+        let Some(deadlock) = current.as_mut() else {
+            continue;
+        };
+
+        let mut text = raw.trim();
+        for (marker, next) in [
+            ("DETAIL:", Field::Detail),
+            ("CONTEXT:", Field::Context),
+            ("STATEMENT:", Field::Statement),
+            ("HINT:", Field::Other),
+        ] {
+            if let Some(idx) = text.find(marker) {
+                field = next;
+                text = text[(idx + marker.len())..].trim();
+                break;
+            }
+        }
+        match field {
+            Field::Detail => deadlock.push_detail(text),
+            Field::Context => {
+                if deadlock.context_table.is_none() {
+                    deadlock.context_table = text
+                        .split_once("in relation \"")
+                        .and_then(|(_, rest)| rest.split('"').next())
+                        .map(str::to_string);
+                }
+            }
+            Field::Statement => deadlock.victim_statement.push(text.to_string()),
+            Field::Other => {}
+        }
+    }
+    if let Some(done) = current {
+        records.push(done.finish());
+    }
+    records
+}
+
+#[derive(Debug, Default)]
+struct PostgresDeadlock {
+    detected_at: Option<String>,
+    victim: Option<u64>,
+    participants: Vec<DeadlockParticipant>,
+    context_table: Option<String>,
+    victim_statement: Vec<String>,
+}
+
+impl PostgresDeadlock {
+    fn participant(&mut self, pid: &str) -> &mut DeadlockParticipant {
+        let index = match self.participants.iter().position(|it| it.label == pid) {
+            Some(index) => index,
+            None => {
+                self.participants.push(DeadlockParticipant {
+                    label: pid.to_string(),
+                    thread_id: pid.parse().ok(),
+                    ..DeadlockParticipant::default()
+                });
+                self.participants.len() - 1
+            }
+        };
+        &mut self.participants[index]
+    }
+
+    /// `Process 77 waits for ShareLock on transaction 1235; blocked by process
+    /// 78.` or `Process 77: UPDATE ...`.
+    fn push_detail(&mut self, text: &str) {
+        let Some(rest) = text.strip_prefix("Process ") else {
+            return;
+        };
+        let pid_end = rest
+            .find(|it: char| !it.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let pid = &rest[..pid_end];
+        if pid.is_empty() {
+            return;
+        }
+        let rest = &rest[pid_end..];
+        if let Some(statement) = rest.strip_prefix(": ") {
+            self.participant(pid).statement = Some(statement.trim().to_string());
+            return;
+        }
+        let Some(wait) = rest.trim().strip_prefix("waits for ") else {
+            return;
+        };
+        let (lock_text, blocker) = match wait.split_once("; blocked by process ") {
+            Some((lock, blocker)) => (lock, Some(blocker.trim_end_matches('.').trim())),
+            None => (wait.trim_end_matches('.'), None),
+        };
+        let Some((mode, object)) = lock_text.split_once(" on ") else {
+            return;
+        };
+        let lock = DeadlockLock {
+            lock_type: object
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+            mode: mode.to_string(),
+            table: None,
+            index: None,
+            object: Some(object.to_string()),
+        };
+        self.participant(pid).waits_for.push(lock.clone());
+        if let Some(blocker) = blocker {
+            self.participant(blocker).holds.push(lock);
+        }
+    }
+
+    fn finish(mut self) -> DeadlockRecord {
+        let victim = self.victim.map(|it| it.to_string());
+        if let Some(victim) = victim.as_deref() {
+            let statement =
+                (!self.victim_statement.is_empty()).then(|| self.victim_statement.join(" "));
+            let context_table = self.context_table.take();
+            let participant = self.participant(victim);
+            if participant.statement.is_none() {
+                participant.statement = statement;
+            }
+            for lock in &mut participant.waits_for {
+                if lock.table.is_none() && lock.lock_type != "transaction" {
+                    lock.table = context_table.clone();
+                }
+            }
+        }
+        build_record(
+            POSTGRES_LOG_SOURCE,
+            SqlDialect::Postgres,
+            self.detected_at,
+            self.participants,
+            victim,
+        )
+    }
+}
+
+fn build_record(
+    source: &str,
+    dialect: SqlDialect,
+    detected_at: Option<String>,
+    mut participants: Vec<DeadlockParticipant>,
+    victim: Option<String>,
+) -> DeadlockRecord {
+    let fingerprinter = fingerprinter_for(dialect);
+    let mut tables = BTreeSet::new();
+    let mut indexes = BTreeSet::new();
+    let mut signatures = Vec::new();
+    for participant in &mut participants {
+        participant.rolled_back = victim.as_deref() == Some(participant.label.as_str());
+        if let Some(statement) = participant.statement.as_deref() {
+            participant.statement_fingerprint = Some(fingerprinter.fingerprint(statement).text);
+            tables.extend(referenced_tables(statement, dialect));
+        }
+        for lock in participant.holds.iter().chain(&participant.waits_for) {
+            tables.extend(lock.table.clone());
+            indexes.extend(lock.index.clone());
+        }
+        let waits = participant
+            .waits_for
+            .iter()
+            .map(|it| {
+                format!(
+                    "{} {} {} {}",
+                    it.lock_type,
+                    it.mode,
+                    it.table.as_deref().unwrap_or_default(),
+                    it.index.as_deref().unwrap_or_default()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        signatures.push(format!(
+            "{}|{waits}",
+            participant
+                .statement_fingerprint
+                .as_deref()
+                .unwrap_or_default()
+        ));
+    }
+    signatures.sort();
+    // Lock tables are schema-qualified; drop the bare names statements add.
+    let qualified = tables
+        .iter()
+        .filter_map(|it| it.rsplit_once('.').map(|(_, name)| name.to_string()))
+        .collect::<BTreeSet<_>>();
+    tables.retain(|it| it.contains('.') || !qualified.contains(it));
+
+    DeadlockRecord {
+        source: source.to_string(),
+        detected_at,
+        deadlock_id: digest_hash(&signatures.join("\n")),
+        participants,
+        victim,
+        tables: tables.into_iter().collect(),
+        indexes: indexes.into_iter().collect(),
+    }
+}
+
+/// `2026-02-07T12:00:00.000000Z` and `2026-02-07 12:00:00 0x7f..` both become
+/// `2026-02-07 12:00:00`; the zone is dropped.
+fn normalize_timestamp(text: &str) -> String {
+    text.chars()
+        .take(19)
+        .map(|it| if it == 'T' { ' ' } else { it })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_lines(relative: &str) -> Vec<String> {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(relative);
+        std::fs::read_to_string(path)
+            .expect("failed reading fixture")
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn should_parse_innodb_deadlock_from_status_and_error_log() {
+        let status = parse_innodb_deadlocks(
+            &read_lines("tests/fixtures/logs/innodb_status.txt"),
+            INNODB_STATUS_SOURCE,
+        );
+        assert_eq!(status.len(), 1);
+        let record = &status[0];
+        assert_eq!(record.detected_at.as_deref(), Some("2026-02-07 12:00:00"));
+        assert_eq!(record.victim.as_deref(), Some("2"));
+        assert_eq!(record.tables, vec!["shop.accounts"]);
+        assert_eq!(record.indexes, vec!["PRIMARY"]);
+
+        let first = &record.participants[0];
+        assert_eq!(first.transaction_id.as_deref(), Some("1854"));
+        assert_eq!(first.active_secs, Some(9));
+        assert_eq!(first.thread_id, Some(11));
+        assert_eq!(first.lock_structs, Some(3));
+        assert_eq!(first.row_locks, Some(2));
+        assert_eq!(
+            first.statement_fingerprint.as_deref(),
+            Some("update accounts set balance = balance - ? where id = ?")
+        );
+        assert_eq!(first.holds[0].mode, "X locks rec but not gap");
+        assert_eq!(first.waits_for[0].table.as_deref(), Some("shop.accounts"));
+        assert!(!first.rolled_back && record.participants[1].rolled_back);
+
+        let logged = parse_innodb_deadlocks(
+            &read_lines("tests/fixtures/logs/mysql_deadlock_error.log"),
+            INNODB_ERROR_LOG_SOURCE,
+        );
+        assert_eq!(logged.len(), 2);
+        assert!(logged.iter().all(|it| it.deadlock_id == record.deadlock_id));
+
+        // The status output prints server local time, the error log UTC.
+        let mut status = status;
+        status[0].detected_at = Some("2026-02-07 14:00:00".to_string());
+        let mut report = DeadlockReport::default();
+        report.add(logged);
+        report.add(status);
+        assert_eq!(report.parsed, 2);
+        assert_eq!(report.groups.len(), 1);
+        assert_eq!(report.groups[0].count, 2);
+        assert_eq!(
+            report.groups[0].first_detected_at.as_deref(),
+            Some("2026-02-07 11:00:00")
+        );
+    }
+
+    #[test]
+    fn should_parse_postgres_deadlock_detail() {
+        let records = parse_postgres_deadlocks(&[
+            "2026-02-07 16:00:03 UTC [77] ERROR:  deadlock detected",
+            "2026-02-07 16:00:03 UTC [77] DETAIL:  Process 77 waits for ShareLock on transaction 1235; blocked by process 78.",
+            "\tProcess 78 waits for ShareLock on transaction 1234; blocked by process 77.",
+            "\tProcess 77: UPDATE accounts SET balance = 0 WHERE id = 2",
+            "\tProcess 78: UPDATE accounts SET balance = 0 WHERE id = 1",
+            "2026-02-07 16:00:03 UTC [77] HINT:  See server log for query details.",
+            "2026-02-07 16:00:03 UTC [77] CONTEXT:  while updating tuple (0,2) in relation \"accounts\"",
+            "2026-02-07 16:00:03 UTC [77] STATEMENT:  UPDATE accounts SET balance = 0 WHERE id = 2",
+            "2026-02-07 16:00:04 UTC [80] LOG:  checkpoint starting: time",
+        ]);
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+        assert_eq!(record.victim.as_deref(), Some("77"));
+        assert_eq!(record.tables, vec!["accounts"]);
+        assert_eq!(record.participants.len(), 2);
+
+        let victim = &record.participants[0];
+        assert!(victim.rolled_back);
+        assert_eq!(victim.waits_for[0].lock_type, "transaction");
+        assert_eq!(victim.waits_for[0].mode, "ShareLock");
+        assert_eq!(victim.holds[0].object.as_deref(), Some("transaction 1234"));
+        assert_eq!(
+            record.participants[1].statement_fingerprint.as_deref(),
+            Some("update accounts set balance = ? where id = ?")
+        );
+    }
+}
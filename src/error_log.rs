@@ -0,0 +1,896 @@
+use std::fs;
+use std::path::Path;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::level1::Level1Engine;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorSeverity {
+    Debug,
+    Info,
+    Note,
+    Notice,
+    Log,
+    System,
+    Warning,
+    Error,
+    Fatal,
+    Panic,
+}
+
+/// One header line of a server error log. MySQL codes look like `MY-010584`;
+/// PostgreSQL codes are SQLSTATEs such as `40P01`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorLogRecord {
+    pub timestamp: Option<String>,
+    pub thread_id: Option<u64>,
+    pub severity: ErrorSeverity,
+    pub code: Option<String>,
+    pub subsystem: Option<String>,
+    pub message: String,
+}
+
+pub fn parse_error_line(engine: Level1Engine, line: &str) -> Option<ErrorLogRecord> {
+    match engine {
+        Level1Engine::Mysql => parse_mysql_error_line(line),
+        Level1Engine::Postgres => parse_postgres_error_line(line),
+    }
+}
+
+/// `2026-02-07T12:00:00.000000Z 0 [Warning] [MY-010584] [Repl] message` (8.0)
+/// or `... 0 [Warning] InnoDB: message` (5.7).
+pub fn parse_mysql_error_line(line: &str) -> Option<ErrorLogRecord> {
+    let open = line.find('[')?;
+    let close = open + line[open..].find(']')?;
+    let severity = match line[(open + 1)..close].to_ascii_lowercase().as_str() {
+        "note" => ErrorSeverity::Note,
+        "system" => ErrorSeverity::System,
+        "warning" => ErrorSeverity::Warning,
+        "error" => ErrorSeverity::Error,
+        _ => return None,
+    };
+
+    let mut header = line[..open].split_whitespace().collect::<Vec<_>>();
+    let thread_id = header
+        .last()
+        .and_then(|it| it.parse::<u64>().ok())
+        .inspect(|_| {
+            header.pop();
+        });
+    let timestamp = (!header.is_empty()).then(|| header.join(" "));
+
+    let mut rest = line[(close + 1)..].trim_start();
+    let mut code = None;
+    let mut subsystem = None;
+    while let Some(tag) = rest.strip_prefix('[') {
+        let Some(end) = tag.find(']') else {
+            break;
+        };
+        let value = &tag[..end];
+        if value.starts_with("MY-") && code.is_none() {
+            code = Some(value.to_string());
+        } else if subsystem.is_none() {
+            subsystem = Some(value.to_string());
+        } else {
+            break;
+        }
+        rest = tag[(end + 1)..].trim_start();
+    }
+    if subsystem.is_none()
+        && let Some((prefix, message)) = rest.split_once(": ")
+        && !prefix.is_empty()
+        && prefix
+            .chars()
+            .all(|it| it.is_ascii_alphanumeric() || it == '_')
+    {
+        subsystem = Some(prefix.to_string());
+        rest = message;
+    }
+
+    Some(ErrorLogRecord {
+        timestamp,
+        thread_id,
+        severity,
+        code,
+        subsystem,
+        message: rest.trim().to_string(),
+    })
+}
+
+/// `2026-02-07 16:00:03 UTC [77] ERROR:  40P01: deadlock detected`; the
+/// SQLSTATE is taken from verbose messages or from a `%e` prefix field.
+/// Continuation lines (`DETAIL`, `STATEMENT`, ...) are not records.
+pub fn parse_postgres_error_line(line: &str) -> Option<ErrorLogRecord> {
+    const SEVERITIES: &[(&str, ErrorSeverity)] = &[
+        ("PANIC:", ErrorSeverity::Panic),
+        ("FATAL:", ErrorSeverity::Fatal),
+        ("ERROR:", ErrorSeverity::Error),
+        ("WARNING:", ErrorSeverity::Warning),
+        ("LOG:", ErrorSeverity::Log),
+        ("NOTICE:", ErrorSeverity::Notice),
+        ("INFO:", ErrorSeverity::Info),
+        ("DEBUG:", ErrorSeverity::Debug),
+        ("DEBUG1:", ErrorSeverity::Debug),
+        ("DEBUG2:", ErrorSeverity::Debug),
+        ("DEBUG3:", ErrorSeverity::Debug),
+        ("DEBUG4:", ErrorSeverity::Debug),
+        ("DEBUG5:", ErrorSeverity::Debug),
+    ];
+
+    let mut position = 0;
+    let (prefix, severity, message) = loop {
+        let token_start = position + line[position..].find(|it: char| !it.is_whitespace())?;
+        let token_end = line[token_start..]
+            .find(char::is_whitespace)
+            .map_or(line.len(), |it| token_start + it);
+        let token = &line[token_start..token_end];
+        if let Some((_, severity)) = SEVERITIES.iter().find(|(marker, _)| *marker == token) {
+            break (&line[..token_start], *severity, line[token_end..].trim());
+        }
+        position = token_end;
+    };
+
+    let is_sqlstate = |it: &str| {
+        it.len() == 5
+            && it
+                .chars()
+                .all(|ch| ch.is_ascii_digit() || ch.is_ascii_uppercase())
+            && it.chars().any(|ch| ch.is_ascii_digit())
+    };
+    // `%e` is unbracketed, unlike `[%p]`. An all-digit code such as 53300
+    // is only taken from the last prefix field, right before the severity.
+    let fields = prefix.split_whitespace().collect::<Vec<_>>();
+    let mut code = fields
+        .iter()
+        .enumerate()
+        .filter(|(_, it)| !it.starts_with('['))
+        .map(|(index, it)| (index, it.trim_end_matches(':')))
+        .rfind(|(index, it)| {
+            is_sqlstate(it) && (index + 1 == fields.len() || it.parse::<u64>().is_err())
+        })
+        .map(|(_, it)| it.to_string());
+    let mut message = message;
+    if let Some((head, tail)) = message.split_once(": ")
+        && is_sqlstate(head)
+    {
+        code.get_or_insert_with(|| head.to_string());
+        message = tail.trim();
+    }
+
+    let timestamp = prefix
+        .split_whitespace()
+        .take_while(|it| !it.starts_with('['))
+        .take(3)
+        .collect::<Vec<_>>();
+    let thread_id = prefix.find('[').and_then(|start| {
+        let end = start + prefix[start..].find(']')?;
+        prefix[(start + 1)..end].parse::<u64>().ok()
+    });
+
+    Some(ErrorLogRecord {
+        timestamp: (!timestamp.is_empty()).then(|| timestamp.join(" ")),
+        thread_id,
+        severity,
+        code,
+        subsystem: None,
+        message: message.to_string(),
+    })
+}
+
+/// Built-in catalog entry; see [`ErrorAlertRule`] for the matching semantics.
+struct BuiltinRule {
+    category: &'static str,
+    engine: Level1Engine,
+    codes: &'static [&'static str],
+    subsystems: &'static [&'static str],
+    min_severity: ErrorSeverity,
+    keywords: &'static [&'static str],
+}
+
+const BUILTIN_MAX_SAMPLES: usize = 3;
+const MAX_RULE_SAMPLES: usize = 50;
+
+const BUILTIN_RULES: &[BuiltinRule] = &[
+    BuiltinRule {
+        category: "deadlock",
+        engine: Level1Engine::Mysql,
+        codes: &["MY-012468"],
+        subsystems: &[],
+        min_severity: ErrorSeverity::Note,
+        keywords: &["deadlock"],
+    },
+    BuiltinRule {
+        category: "crash_recovery",
+        engine: Level1Engine::Mysql,
+        codes: &[],
+        subsystems: &[],
+        min_severity: ErrorSeverity::Note,
+        keywords: &["crash recovery", "was not shutdown normally"],
+    },
+    BuiltinRule {
+        category: "purge",
+        engine: Level1Engine::Mysql,
+        codes: &[],
+        subsystems: &["InnoDB"],
+        min_severity: ErrorSeverity::Warning,
+        keywords: &["purge", "history list"],
+    },
+    // Replica SQL/IO thread errors, including 1236 from the source; bare
+    // "replica"/"binlog" wording is too common in routine notes to alert on.
+    BuiltinRule {
+        category: "replication",
+        engine: Level1Engine::Mysql,
+        codes: &["MY-010584", "MY-010586", "MY-013114", "MY-013117"],
+        subsystems: &["Repl"],
+        min_severity: ErrorSeverity::Warning,
+        keywords: &[],
+    },
+    BuiltinRule {
+        category: "deadlock",
+        engine: Level1Engine::Postgres,
+        codes: &["40P01"],
+        subsystems: &[],
+        min_severity: ErrorSeverity::Error,
+        keywords: &["deadlock detected"],
+    },
+    BuiltinRule {
+        category: "crash_recovery",
+        engine: Level1Engine::Postgres,
+        codes: &[],
+        subsystems: &[],
+        min_severity: ErrorSeverity::Log,
+        keywords: &[
+            "database system was interrupted",
+            "was not properly shut down",
+            "automatic recovery in progress",
+            "redo starts at",
+            "redo done at",
+        ],
+    },
+    BuiltinRule {
+        category: "purge",
+        engine: Level1Engine::Postgres,
+        codes: &[],
+        subsystems: &[],
+        min_severity: ErrorSeverity::Warning,
+        keywords: &["autovacuum", "xmin"],
+    },
+    BuiltinRule {
+        category: "replication",
+        engine: Level1Engine::Postgres,
+        codes: &[],
+        subsystems: &[],
+        min_severity: ErrorSeverity::Warning,
+        keywords: &["replication", "wal receiver", "wal sender", "wal stream"],
+    },
+    BuiltinRule {
+        category: "replication",
+        engine: Level1Engine::Postgres,
+        codes: &[],
+        subsystems: &[],
+        min_severity: ErrorSeverity::Log,
+        keywords: &[
+            "wal receiver process terminated",
+            "replication terminated by primary",
+        ],
+    },
+    BuiltinRule {
+        category: "out_of_memory",
+        engine: Level1Engine::Mysql,
+        codes: &[],
+        subsystems: &[],
+        min_severity: ErrorSeverity::Warning,
+        keywords: &[
+            "out of memory",
+            "cannot allocate memory",
+            "failed to allocate",
+        ],
+    },
+    BuiltinRule {
+        category: "disk_full",
+        engine: Level1Engine::Mysql,
+        codes: &["MY-001114"],
+        subsystems: &[],
+        min_severity: ErrorSeverity::Warning,
+        keywords: &[
+            "no space left on device",
+            "disk is full",
+            "disk full",
+            "error number 28",
+            "errno: 28",
+        ],
+    },
+    BuiltinRule {
+        category: "too_many_connections",
+        engine: Level1Engine::Mysql,
+        codes: &["MY-001040"],
+        subsystems: &[],
+        min_severity: ErrorSeverity::Note,
+        keywords: &["too many connections"],
+    },
+    BuiltinRule {
+        category: "aborted_connection",
+        engine: Level1Engine::Mysql,
+        codes: &["MY-010914"],
+        subsystems: &[],
+        min_severity: ErrorSeverity::Note,
+        keywords: &[
+            "aborted connection",
+            "got an error reading communication packets",
+            "got timeout reading communication packets",
+        ],
+    },
+    BuiltinRule {
+        category: "authentication_failure",
+        engine: Level1Engine::Mysql,
+        codes: &["MY-001045"],
+        subsystems: &[],
+        min_severity: ErrorSeverity::Note,
+        keywords: &["access denied for user"],
+    },
+    BuiltinRule {
+        category: "corruption",
+        engine: Level1Engine::Mysql,
+        codes: &[],
+        subsystems: &[],
+        min_severity: ErrorSeverity::Warning,
+        keywords: &[
+            "corrupt",
+            "checksum mismatch",
+            "page checksum",
+            "is marked as crashed",
+        ],
+    },
+    BuiltinRule {
+        category: "semaphore_wait",
+        engine: Level1Engine::Mysql,
+        codes: &[],
+        subsystems: &["InnoDB"],
+        min_severity: ErrorSeverity::Warning,
+        keywords: &["semaphore wait", "the semaphore"],
+    },
+    BuiltinRule {
+        category: "checkpoint_pressure",
+        engine: Level1Engine::Mysql,
+        codes: &[],
+        subsystems: &["InnoDB"],
+        min_severity: ErrorSeverity::Warning,
+        keywords: &[
+            "age of the last checkpoint",
+            "exceeds the log group capacity",
+        ],
+    },
+    BuiltinRule {
+        category: "out_of_memory",
+        engine: Level1Engine::Postgres,
+        codes: &["53200"],
+        subsystems: &[],
+        min_severity: ErrorSeverity::Error,
+        keywords: &["out of memory"],
+    },
+    BuiltinRule {
+        category: "out_of_memory",
+        engine: Level1Engine::Postgres,
+        codes: &[],
+        subsystems: &[],
+        min_severity: ErrorSeverity::Log,
+        keywords: &["was terminated by signal 9"],
+    },
+    BuiltinRule {
+        category: "disk_full",
+        engine: Level1Engine::Postgres,
+        codes: &["53100"],
+        subsystems: &[],
+        min_severity: ErrorSeverity::Warning,
+        keywords: &["no space left on device", "could not extend file"],
+    },
+    BuiltinRule {
+        category: "too_many_connections",
+        engine: Level1Engine::Postgres,
+        codes: &["53300"],
+        subsystems: &[],
+        min_severity: ErrorSeverity::Warning,
+        keywords: &[
+            "too many clients",
+            "remaining connection slots are reserved",
+        ],
+    },
+    BuiltinRule {
+        category: "aborted_connection",
+        engine: Level1Engine::Postgres,
+        codes: &[],
+        subsystems: &[],
+        min_severity: ErrorSeverity::Log,
+        keywords: &[
+            "could not receive data from client",
+            "unexpected eof on client connection",
+            "connection reset by peer",
+        ],
+    },
+    BuiltinRule {
+        category: "authentication_failure",
+        engine: Level1Engine::Postgres,
+        codes: &["28P01", "28000"],
+        subsystems: &[],
+        min_severity: ErrorSeverity::Fatal,
+        keywords: &["password authentication failed", "no pg_hba.conf entry"],
+    },
+    BuiltinRule {
+        category: "corruption",
+        engine: Level1Engine::Postgres,
+        codes: &["XX001", "XX002"],
+        subsystems: &[],
+        min_severity: ErrorSeverity::Warning,
+        keywords: &[
+            "invalid page in block",
+            "checksum verification failed",
+            "index contains unexpected zero page",
+        ],
+    },
+    BuiltinRule {
+        category: "checkpoint_pressure",
+        engine: Level1Engine::Postgres,
+        codes: &[],
+        subsystems: &[],
+        min_severity: ErrorSeverity::Log,
+        keywords: &["checkpoints are occurring too frequently"],
+    },
+    BuiltinRule {
+        category: "wraparound",
+        engine: Level1Engine::Postgres,
+        codes: &[],
+        subsystems: &[],
+        min_severity: ErrorSeverity::Warning,
+        keywords: &["wraparound", "must be vacuumed within"],
+    },
+    BuiltinRule {
+        category: "wal_retention",
+        engine: Level1Engine::Postgres,
+        codes: &[],
+        subsystems: &[],
+        min_severity: ErrorSeverity::Log,
+        keywords: &[
+            "has already been removed",
+            "invalidating obsolete replication slot",
+            "max_slot_wal_keep_size",
+        ],
+    },
+];
+
+/// A record matches when its code is listed, or when its subsystem (if the
+/// rule names any), severity, message keywords (any of, if set) and `pattern`
+/// (if set) all match. Keywords are lowercase substrings; `pattern` is a
+/// regex over the original message.
+#[derive(Debug, Clone)]
+pub struct ErrorAlertRule {
+    pub category: String,
+    /// Empty means every engine.
+    pub engines: Vec<Level1Engine>,
+    pub codes: Vec<String>,
+    pub subsystems: Vec<String>,
+    pub min_severity: ErrorSeverity,
+    pub keywords: Vec<String>,
+    pub pattern: Option<Regex>,
+    pub max_samples: usize,
+}
+
+impl ErrorAlertRule {
+    fn matches(&self, record: &ErrorLogRecord, lower_message: &str) -> bool {
+        if record
+            .code
+            .as_deref()
+            .is_some_and(|code| self.codes.iter().any(|it| it == code))
+        {
+            return true;
+        }
+        let subsystem_ok = self.subsystems.is_empty()
+            || record.subsystem.as_deref().is_some_and(|subsystem| {
+                self.subsystems
+                    .iter()
+                    .any(|it| it.eq_ignore_ascii_case(subsystem))
+            });
+        let keywords_ok = self.keywords.is_empty()
+            || self
+                .keywords
+                .iter()
+                .any(|it| lower_message.contains(it.as_str()));
+        let pattern_ok = self
+            .pattern
+            .as_ref()
+            .is_none_or(|it| it.is_match(&record.message));
+        subsystem_ok && keywords_ok && pattern_ok && record.severity >= self.min_severity
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AlertRuleError {
+    #[error("failed reading alert rules {path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("failed parsing alert rules {path}: {message}")]
+    Parse { path: String, message: String },
+    #[error("invalid alert rule #{index} ({category}): {reason}")]
+    Invalid {
+        index: usize,
+        category: String,
+        reason: String,
+    },
+}
+
+/// Rules file layout, as TOML (`[[rules]]`) or YAML (`rules:`).
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AlertRuleFile {
+    #[serde(default)]
+    replace_builtin: bool,
+    #[serde(default)]
+    rules: Vec<AlertRuleSpec>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AlertRuleSpec {
+    category: String,
+    #[serde(default)]
+    engines: Vec<Level1Engine>,
+    #[serde(default)]
+    codes: Vec<String>,
+    #[serde(default)]
+    subsystems: Vec<String>,
+    #[serde(default = "default_rule_severity")]
+    min_severity: ErrorSeverity,
+    #[serde(default)]
+    keywords: Vec<String>,
+    pattern: Option<String>,
+    #[serde(default = "default_rule_samples")]
+    max_samples: usize,
+}
+
+fn default_rule_severity() -> ErrorSeverity {
+    ErrorSeverity::Warning
+}
+
+fn default_rule_samples() -> usize {
+    BUILTIN_MAX_SAMPLES
+}
+
+/// The alert catalog: built-in rules, optionally extended or replaced by a
+/// rules file.
+#[derive(Debug, Clone)]
+pub struct ErrorAlertRules {
+    rules: Vec<ErrorAlertRule>,
+}
+
+impl Default for ErrorAlertRules {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl ErrorAlertRules {
+    pub fn builtin() -> Self {
+        let rules = BUILTIN_RULES
+            .iter()
+            .map(|it| ErrorAlertRule {
+                category: it.category.to_string(),
+                engines: vec![it.engine],
+                codes: it.codes.iter().map(|it| it.to_string()).collect(),
+                subsystems: it.subsystems.iter().map(|it| it.to_string()).collect(),
+                min_severity: it.min_severity,
+                keywords: it.keywords.iter().map(|it| it.to_string()).collect(),
+                pattern: None,
+                max_samples: BUILTIN_MAX_SAMPLES,
+            })
+            .collect();
+        Self { rules }
+    }
+
+    /// Loads a `.toml`, `.yaml` or `.yml` rules file. File rules are checked
+    /// after the built-in ones unless `replace_builtin = true`.
+    pub fn load(path: &Path) -> Result<Self, AlertRuleError> {
+        let display = path.display().to_string();
+        let text = fs::read_to_string(path).map_err(|source| AlertRuleError::Io {
+            path: display.clone(),
+            source,
+        })?;
+        let is_yaml = path
+            .extension()
+            .and_then(|it| it.to_str())
+            .is_some_and(|it| it.eq_ignore_ascii_case("yaml") || it.eq_ignore_ascii_case("yml"));
+        let file: AlertRuleFile = if is_yaml {
+            serde_norway::from_str(&text).map_err(|err| err.to_string())
+        } else {
+            toml::from_str(&text).map_err(|err| err.to_string())
+        }
+        .map_err(|message| AlertRuleError::Parse {
+            path: display,
+            message,
+        })?;
+        Self::from_specs(file)
+    }
+
+    fn from_specs(file: AlertRuleFile) -> Result<Self, AlertRuleError> {
+        let mut result = if file.replace_builtin {
+            Self { rules: Vec::new() }
+        } else {
+            Self::builtin()
+        };
+        for (index, spec) in file.rules.into_iter().enumerate() {
+            let invalid = |reason: String| AlertRuleError::Invalid {
+                index,
+                category: spec.category.clone(),
+                reason,
+            };
+            if spec.category.is_empty()
+                || !spec
+                    .category
+                    .chars()
+                    .all(|it| it.is_ascii_lowercase() || it.is_ascii_digit() || it == '_')
+            {
+                return Err(invalid("category must be non-empty snake_case".to_string()));
+            }
+            if spec.codes.is_empty() && spec.keywords.is_empty() && spec.pattern.is_none() {
+                return Err(invalid(
+                    "rule needs at least one of codes, keywords or pattern".to_string(),
+                ));
+            }
+            if !(1..=MAX_RULE_SAMPLES).contains(&spec.max_samples) {
+                return Err(invalid(format!(
+                    "max_samples must be between 1 and {MAX_RULE_SAMPLES}"
+                )));
+            }
+            let pattern = spec
+                .pattern
+                .as_deref()
+                .map(Regex::new)
+                .transpose()
+                .map_err(|err| invalid(format!("invalid pattern: {err}")))?;
+            result.rules.push(ErrorAlertRule {
+                category: spec.category,
+                engines: spec.engines,
+                codes: spec.codes,
+                subsystems: spec.subsystems,
+                min_severity: spec.min_severity,
+                keywords: spec
+                    .keywords
+                    .iter()
+                    .map(|it| it.to_ascii_lowercase())
+                    .collect(),
+                pattern,
+                max_samples: spec.max_samples,
+            });
+        }
+        Ok(result)
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Matching rules for a parsed record, first rule per category only.
+    pub fn categorize(
+        &self,
+        engine: Level1Engine,
+        record: &ErrorLogRecord,
+    ) -> Vec<&ErrorAlertRule> {
+        let lower = record.message.to_ascii_lowercase();
+        let mut matched: Vec<&ErrorAlertRule> = Vec::new();
+        for rule in self
+            .rules
+            .iter()
+            .filter(|it| it.engines.is_empty() || it.engines.contains(&engine))
+        {
+            if !matched.iter().any(|it| it.category == rule.category)
+                && rule.matches(record, &lower)
+            {
+                matched.push(rule);
+            }
+        }
+        matched
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_mysql_8_and_5_7_lines() {
+        let record = parse_mysql_error_line(
+            "2026-02-07T12:00:00.123456Z 14 [ERROR] [MY-010584] [Repl] Replica SQL for channel '': Error 'x'",
+        )
+        .expect("mysql 8 line");
+        assert_eq!(
+            record.timestamp.as_deref(),
+            Some("2026-02-07T12:00:00.123456Z")
+        );
+        assert_eq!(record.thread_id, Some(14));
+        assert_eq!(record.severity, ErrorSeverity::Error);
+        assert_eq!(record.code.as_deref(), Some("MY-010584"));
+        assert_eq!(record.subsystem.as_deref(), Some("Repl"));
+        assert_eq!(record.message, "Replica SQL for channel '': Error 'x'");
+
+        let legacy = parse_mysql_error_line(
+            "2026-02-07T12:00:03.000000Z 0 [Warning] InnoDB: purge lag increased",
+        )
+        .expect("mysql 5.7 line");
+        assert_eq!(legacy.code, None);
+        assert_eq!(legacy.subsystem.as_deref(), Some("InnoDB"));
+        assert_eq!(legacy.message, "purge lag increased");
+
+        assert_eq!(parse_mysql_error_line("---TRANSACTION 1234, ACTIVE"), None);
+    }
+
+    #[test]
+    fn should_parse_postgres_severity_and_sqlstate() {
+        let verbose = parse_postgres_error_line(
+            "2026-02-07 16:00:03.120 UTC [77] ERROR:  40P01: deadlock detected",
+        )
+        .expect("verbose line");
+        assert_eq!(
+            verbose.timestamp.as_deref(),
+            Some("2026-02-07 16:00:03.120 UTC")
+        );
+        assert_eq!(verbose.thread_id, Some(77));
+        assert_eq!(verbose.severity, ErrorSeverity::Error);
+        assert_eq!(verbose.code.as_deref(), Some("40P01"));
+        assert_eq!(verbose.message, "deadlock detected");
+
+        let prefixed = parse_postgres_error_line(
+            "2026-02-07 16:00:04 UTC [78] 57P01 FATAL:  terminating connection",
+        )
+        .expect("prefixed sqlstate");
+        assert_eq!(prefixed.code.as_deref(), Some("57P01"));
+        assert_eq!(prefixed.severity, ErrorSeverity::Fatal);
+
+        let numeric = parse_postgres_error_line(
+            "2026-02-07 16:00:05 UTC [12345] 53300 FATAL:  sorry, too many clients already",
+        )
+        .expect("numeric sqlstate");
+        assert_eq!(numeric.code.as_deref(), Some("53300"));
+        assert_eq!(numeric.thread_id, Some(12345));
+        assert_eq!(
+            ErrorAlertRules::builtin()
+                .categorize(Level1Engine::Postgres, &numeric)
+                .into_iter()
+                .map(|it| it.category.clone())
+                .collect::<Vec<_>>(),
+            vec!["too_many_connections"]
+        );
+        let pid_only =
+            parse_postgres_error_line("2026-02-07 16:00:05 UTC [53300] LOG:  checkpoint")
+                .expect("bracketed pid");
+        assert_eq!(pid_only.code, None);
+
+        assert_eq!(
+            parse_postgres_error_line("2026-02-07 16:00:03 UTC [77] DETAIL:  Process 77 waits"),
+            None
+        );
+    }
+
+    #[test]
+    fn should_not_alert_on_routine_postgres_messages() {
+        let categories = |line| {
+            let record = parse_postgres_error_line(line).expect("postgres line");
+            ErrorAlertRules::builtin()
+                .categorize(Level1Engine::Postgres, &record)
+                .into_iter()
+                .map(|it| it.category.clone())
+                .collect::<Vec<_>>()
+        };
+        assert!(
+            categories(
+                "2026-02-07 16:00:06 UTC [77] LOG:  automatic vacuum of table \"public.orders\": index scans: 1"
+            )
+            .is_empty()
+        );
+        assert!(
+            categories("2026-02-07 16:00:07 UTC [77] LOG:  replication slot \"a\" is active")
+                .is_empty()
+        );
+        assert_eq!(
+            categories(
+                "2026-02-07 16:00:08 UTC [77] WARNING:  database \"app\" must be vacuumed within 1000 transactions"
+            ),
+            vec!["wraparound"]
+        );
+    }
+
+    #[test]
+    fn should_load_custom_rules_from_toml_and_yaml() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/alert_rules");
+        let categories = |rules: &ErrorAlertRules, engine, line: &str| {
+            let record = parse_error_line(engine, line).expect("error line");
+            rules
+                .categorize(engine, &record)
+                .into_iter()
+                .map(|it| (it.category.clone(), it.max_samples))
+                .collect::<Vec<_>>()
+        };
+
+        let toml_rules = ErrorAlertRules::load(&root.join("custom.toml")).expect("toml rules");
+        assert_eq!(toml_rules.len(), ErrorAlertRules::builtin().len() + 2);
+        assert_eq!(
+            categories(
+                &toml_rules,
+                Level1Engine::Postgres,
+                "2026-02-07 16:00:01 UTC [80] LOG:  temporary file: path \"base/pgsql_tmp/pgsql_tmp80.0\", size 52428800",
+            ),
+            vec![("temp_file_spill".to_string(), 3)]
+        );
+        assert_eq!(
+            categories(
+                &toml_rules,
+                Level1Engine::Mysql,
+                "2026-02-07T12:00:00.000000Z 31 [Note] [MY-001205] [Server] Lock wait timeout exceeded; try restarting transaction",
+            ),
+            vec![("lock_wait_timeout".to_string(), 5)]
+        );
+        assert_eq!(
+            categories(
+                &toml_rules,
+                Level1Engine::Postgres,
+                "2026-02-07 16:00:03 UTC [77] ERROR:  40P01: deadlock detected",
+            ),
+            vec![("deadlock".to_string(), 3)]
+        );
+
+        let yaml_rules = ErrorAlertRules::load(&root.join("custom.yaml")).expect("yaml rules");
+        assert_eq!(yaml_rules.len(), 1);
+        assert_eq!(
+            categories(
+                &yaml_rules,
+                Level1Engine::Mysql,
+                "2026-02-07T12:00:00.000000Z 0 [ERROR] [MY-012592] [InnoDB] Operating system error number 28: No space left on device",
+            ),
+            vec![("disk_full".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn should_reject_invalid_rules() {
+        let parse = |text: &str| {
+            let file: AlertRuleFile = toml::from_str(text).expect("rules toml");
+            ErrorAlertRules::from_specs(file).map(|it| it.len())
+        };
+        let message =
+            |result: Result<usize, AlertRuleError>| result.expect_err("invalid rule").to_string();
+
+        assert!(
+            message(parse(
+                "[[rules]]\ncategory = \"Disk Full\"\nkeywords = [\"disk\"]"
+            ))
+            .contains("snake_case")
+        );
+        assert!(message(parse("[[rules]]\ncategory = \"empty\"")).contains("at least one"));
+        assert!(
+            message(parse(
+                "[[rules]]\ncategory = \"bad\"\npattern = \"(unclosed\""
+            ))
+            .contains("invalid pattern")
+        );
+        assert!(
+            message(parse(
+                "[[rules]]\ncategory = \"many\"\nkeywords = [\"x\"]\nmax_samples = 0"
+            ))
+            .contains("max_samples")
+        );
+        assert!(
+            toml::from_str::<AlertRuleFile>("[[rules]]\ncategory = \"x\"\nengine = [\"mysql\"]")
+                .is_err()
+        );
+        assert!(matches!(
+            ErrorAlertRules::load(Path::new("/nonexistent/rules.toml")),
+            Err(AlertRuleError::Io { .. })
+        ));
+    }
+}
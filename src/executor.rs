@@ -0,0 +1,449 @@
+use std::any::Any;
+use std::collections::BTreeSet;
+use std::panic::{self, AssertUnwindSafe};
+use std::time::Instant;
+
+use serde::Serialize;
+
+use crate::collection::{CollectionTask, all_tasks};
+use crate::level1::Level1Engine;
+
+/// How a collector run ended when it did not fail outright.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CollectorOutcome {
+    Completed,
+    /// Ran, but some sources reported errors.
+    Partial(Vec<String>),
+    Skipped(String),
+}
+
+impl CollectorOutcome {
+    pub fn from_report(skipped_reason: Option<&str>, warnings: &[String]) -> Self {
+        if let Some(reason) = skipped_reason {
+            CollectorOutcome::Skipped(reason.to_string())
+        } else if warnings.is_empty() {
+            CollectorOutcome::Completed
+        } else {
+            CollectorOutcome::Partial(warnings.to_vec())
+        }
+    }
+}
+
+/// A data source that serves one or more negotiated tasks and writes its
+/// report into the shared state `S`. `tasks` holds the subset of
+/// [`Collector::tasks`] this run allows; sources behind any other task must
+/// not be touched.
+pub trait Collector<S> {
+    fn name(&self) -> &'static str;
+    fn tasks(&self) -> &[&'static str];
+    fn collect(&self, tasks: &[&'static str], state: &mut S) -> Result<CollectorOutcome, String>;
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskExecution {
+    pub task: String,
+    pub level: String,
+    pub collectors: Vec<String>,
+    pub status: String,
+    pub duration_ms: u128,
+    pub skipped_reason: Option<String>,
+    pub errors: Vec<String>,
+}
+
+/// Include/exclude task names from the CLI; an empty include list keeps all.
+#[derive(Debug, Clone, Default)]
+pub struct TaskFilter {
+    include: BTreeSet<String>,
+    exclude: BTreeSet<String>,
+}
+
+impl TaskFilter {
+    pub fn new(
+        engine: Level1Engine,
+        include: &[String],
+        exclude: &[String],
+    ) -> Result<Self, String> {
+        let known = all_tasks(engine)
+            .iter()
+            .map(|it| it.name)
+            .collect::<Vec<_>>();
+        for name in include.iter().chain(exclude) {
+            if !known.contains(&name.as_str()) {
+                return Err(format!(
+                    "unknown task `{name}` in task filter (known: {})",
+                    known.join(", ")
+                ));
+            }
+        }
+        Ok(Self {
+            include: include.iter().cloned().collect(),
+            exclude: exclude.iter().cloned().collect(),
+        })
+    }
+
+    pub fn allows(&self, task: &str) -> bool {
+        (self.include.is_empty() || self.include.contains(task)) && !self.exclude.contains(task)
+    }
+}
+
+struct CollectorRun {
+    duration_ms: u128,
+    outcome: Result<CollectorOutcome, String>,
+}
+
+pub struct CollectorRegistry<S> {
+    collectors: Vec<Box<dyn Collector<S>>>,
+}
+
+impl<S> Default for CollectorRegistry<S> {
+    fn default() -> Self {
+        Self {
+            collectors: Vec::new(),
+        }
+    }
+}
+
+impl<S> CollectorRegistry<S> {
+    pub fn register<C>(&mut self, collector: C) -> &mut Self
+    where
+        C: Collector<S> + 'static,
+    {
+        self.collectors.push(Box::new(collector));
+        self
+    }
+
+    /// Runs the collectors serving each allowed task, in task order. A
+    /// collector serving several tasks runs once, limited to the ones that
+    /// are negotiated and pass the filter, and its result is reported for
+    /// each of them; a failing or panicking collector does not stop the
+    /// remaining tasks.
+    pub fn execute(
+        &self,
+        tasks: &[CollectionTask],
+        filter: &TaskFilter,
+        state: &mut S,
+    ) -> Vec<TaskExecution> {
+        let mut runs: Vec<Option<CollectorRun>> = self.collectors.iter().map(|_| None).collect();
+        let mut executions = Vec::with_capacity(tasks.len());
+
+        for task in tasks {
+            let mut execution = TaskExecution {
+                task: task.name.to_string(),
+                level: task.level.to_string(),
+                collectors: Vec::new(),
+                status: "skipped".to_string(),
+                duration_ms: 0,
+                skipped_reason: None,
+                errors: Vec::new(),
+            };
+            if !filter.allows(task.name) {
+                execution.skipped_reason = Some("excluded by task filter".to_string());
+                executions.push(execution);
+                continue;
+            }
+
+            let serving = self
+                .collectors
+                .iter()
+                .enumerate()
+                .filter(|(_, it)| it.tasks().contains(&task.name))
+                .map(|(index, _)| index)
+                .collect::<Vec<_>>();
+            if serving.is_empty() {
+                execution.skipped_reason = Some("no collector registered for task".to_string());
+                executions.push(execution);
+                continue;
+            }
+
+            let mut failed = false;
+            let mut skipped = Vec::new();
+            for index in serving {
+                let collector = &self.collectors[index];
+                let run = runs[index].get_or_insert_with(|| {
+                    let allowed = collector
+                        .tasks()
+                        .iter()
+                        .copied()
+                        .filter(|name| {
+                            filter.allows(name) && tasks.iter().any(|it| it.name == *name)
+                        })
+                        .collect::<Vec<_>>();
+                    run_collector(collector.as_ref(), &allowed, state)
+                });
+                execution.collectors.push(collector.name().to_string());
+                execution.duration_ms += run.duration_ms;
+                match &run.outcome {
+                    Ok(CollectorOutcome::Completed) => {}
+                    Ok(CollectorOutcome::Partial(errors)) => {
+                        execution.errors.extend(errors.iter().cloned())
+                    }
+                    Ok(CollectorOutcome::Skipped(reason)) => {
+                        skipped.push(format!("{}: {reason}", collector.name()))
+                    }
+                    Err(err) => {
+                        failed = true;
+                        execution
+                            .errors
+                            .push(format!("{}: {err}", collector.name()));
+                    }
+                }
+            }
+
+            execution.status = if failed {
+                "failed"
+            } else if skipped.len() == execution.collectors.len() {
+                "skipped"
+            } else if !skipped.is_empty() || !execution.errors.is_empty() {
+                "partial"
+            } else {
+                "ok"
+            }
+            .to_string();
+            if !skipped.is_empty() {
+                execution.skipped_reason = Some(skipped.join("; "));
+            }
+            executions.push(execution);
+        }
+        executions
+    }
+}
+
+fn run_collector<S>(
+    collector: &dyn Collector<S>,
+    tasks: &[&'static str],
+    state: &mut S,
+) -> CollectorRun {
+    let started = Instant::now();
+    let outcome = panic::catch_unwind(AssertUnwindSafe(|| collector.collect(tasks, state)))
+        .unwrap_or_else(|payload| Err(format!("collector panicked: {}", panic_message(&payload))));
+    CollectorRun {
+        duration_ms: started.elapsed().as_millis(),
+        outcome,
+    }
+}
+
+fn panic_message(payload: &Box<dyn Any + Send>) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|it| it.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collection::{CapabilityProbe, CollectionLevel, tasks_for_level};
+
+    #[derive(Default)]
+    struct State {
+        calls: Vec<&'static str>,
+        /// Side effects, one per task a collector actually performed.
+        performed: Vec<&'static str>,
+    }
+
+    struct FakeCollector {
+        name: &'static str,
+        tasks: &'static [&'static str],
+        outcome: Result<CollectorOutcome, String>,
+    }
+
+    impl Collector<State> for FakeCollector {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn tasks(&self) -> &[&'static str] {
+            self.tasks
+        }
+
+        fn collect(
+            &self,
+            tasks: &[&'static str],
+            state: &mut State,
+        ) -> Result<CollectorOutcome, String> {
+            state.calls.push(self.name);
+            state.performed.extend(tasks);
+            if self.name == "panicking" {
+                panic!("boom");
+            }
+            self.outcome.clone()
+        }
+    }
+
+    fn execution<'a>(executions: &'a [TaskExecution], task: &str) -> &'a TaskExecution {
+        executions
+            .iter()
+            .find(|it| it.task == task)
+            .expect("task execution")
+    }
+
+    #[test]
+    fn should_run_shared_collector_once_and_report_every_task() {
+        let mut registry = CollectorRegistry::default();
+        registry
+            .register(FakeCollector {
+                name: "level0",
+                tasks: &["global_status", "global_variables", "schema_storage"],
+                outcome: Ok(CollectorOutcome::Completed),
+            })
+            .register(FakeCollector {
+                name: "os",
+                tasks: &["os_basic_metrics"],
+                outcome: Ok(CollectorOutcome::Partial(vec![
+                    "vmstat missing".to_string(),
+                ])),
+            })
+            .register(FakeCollector {
+                name: "replication",
+                tasks: &["replication_status"],
+                outcome: Err("connection refused".to_string()),
+            });
+        let tasks = tasks_for_level(
+            Level1Engine::Mysql,
+            CollectionLevel::Level1,
+            &CapabilityProbe::default(),
+        );
+        let filter =
+            TaskFilter::new(Level1Engine::Mysql, &[], &["error_log_alerts".to_string()]).unwrap();
+        let mut state = State::default();
+
+        let executions = registry.execute(&tasks, &filter, &mut state);
+
+        assert_eq!(state.calls, vec!["level0", "replication", "os"]);
+        assert_eq!(executions.len(), tasks.len());
+        assert_eq!(execution(&executions, "global_status").status, "ok");
+        assert_eq!(
+            execution(&executions, "schema_storage").collectors,
+            vec!["level0"]
+        );
+        let os = execution(&executions, "os_basic_metrics");
+        assert_eq!(os.status, "partial");
+        assert_eq!(os.errors, vec!["vmstat missing"]);
+        let replication = execution(&executions, "replication_status");
+        assert_eq!(replication.status, "failed");
+        assert_eq!(replication.errors, vec!["replication: connection refused"]);
+        let slow_log = execution(&executions, "slow_log_window");
+        assert_eq!(slow_log.status, "skipped");
+        assert_eq!(
+            slow_log.skipped_reason.as_deref(),
+            Some("no collector registered for task")
+        );
+        assert_eq!(
+            execution(&executions, "error_log_alerts")
+                .skipped_reason
+                .as_deref(),
+            Some("excluded by task filter")
+        );
+    }
+
+    #[test]
+    fn should_combine_collectors_on_one_task_and_isolate_panics() {
+        let mut registry = CollectorRegistry::default();
+        registry
+            .register(FakeCollector {
+                name: "perf",
+                tasks: &["perf_or_strace_short_window"],
+                outcome: Ok(CollectorOutcome::Skipped("perf not found".to_string())),
+            })
+            .register(FakeCollector {
+                name: "strace",
+                tasks: &["perf_or_strace_short_window"],
+                outcome: Ok(CollectorOutcome::Completed),
+            })
+            .register(FakeCollector {
+                name: "panicking",
+                tasks: &["innodb_status_hf"],
+                outcome: Ok(CollectorOutcome::Completed),
+            })
+            .register(FakeCollector {
+                name: "tcpdump",
+                tasks: &["tcpdump_short_window"],
+                outcome: Ok(CollectorOutcome::Completed),
+            });
+        let tasks = tasks_for_level(
+            Level1Engine::Mysql,
+            CollectionLevel::Level3,
+            &CapabilityProbe::default(),
+        )
+        .into_iter()
+        .filter(|it| it.level == CollectionLevel::Level3)
+        .collect::<Vec<_>>();
+        let filter = TaskFilter::new(
+            Level1Engine::Mysql,
+            &[
+                "perf_or_strace_short_window".to_string(),
+                "innodb_status_hf".to_string(),
+            ],
+            &[],
+        )
+        .unwrap();
+        let mut state = State::default();
+
+        let executions = registry.execute(&tasks, &filter, &mut state);
+
+        assert_eq!(state.calls, vec!["perf", "strace", "panicking"]);
+        let short_window = execution(&executions, "perf_or_strace_short_window");
+        assert_eq!(short_window.status, "partial");
+        assert_eq!(short_window.collectors, vec!["perf", "strace"]);
+        assert_eq!(
+            short_window.skipped_reason.as_deref(),
+            Some("perf: perf not found")
+        );
+        let hf = execution(&executions, "innodb_status_hf");
+        assert_eq!(hf.status, "failed");
+        assert_eq!(hf.errors, vec!["panicking: collector panicked: boom"]);
+        assert_eq!(
+            execution(&executions, "tcpdump_short_window").status,
+            "skipped"
+        );
+    }
+
+    #[test]
+    fn should_not_perform_excluded_or_unnegotiated_tasks() {
+        let mut registry = CollectorRegistry::default();
+        registry.register(FakeCollector {
+            name: "level1",
+            tasks: &["slow_log_window", "slow_log_digest", "error_log_alerts"],
+            outcome: Ok(CollectorOutcome::Completed),
+        });
+        let tasks = tasks_for_level(
+            Level1Engine::Mysql,
+            CollectionLevel::Level1,
+            &CapabilityProbe::default(),
+        )
+        .into_iter()
+        .filter(|it| it.name != "error_log_alerts")
+        .collect::<Vec<_>>();
+        let filter =
+            TaskFilter::new(Level1Engine::Mysql, &[], &["slow_log_window".to_string()]).unwrap();
+        let mut state = State::default();
+
+        let executions = registry.execute(&tasks, &filter, &mut state);
+
+        assert_eq!(state.calls, vec!["level1"]);
+        assert_eq!(state.performed, vec!["slow_log_digest"]);
+        assert_eq!(
+            execution(&executions, "slow_log_window")
+                .skipped_reason
+                .as_deref(),
+            Some("excluded by task filter")
+        );
+        assert_eq!(execution(&executions, "slow_log_digest").status, "ok");
+    }
+
+    #[test]
+    fn should_reject_unknown_task_names_in_filter() {
+        let err = TaskFilter::new(Level1Engine::Mysql, &["slow_log".to_string()], &[]).unwrap_err();
+        assert!(err.contains("unknown task `slow_log`"));
+        assert!(TaskFilter::new(Level1Engine::Mysql, &[], &["wait_events".to_string()]).is_ok());
+        assert!(
+            TaskFilter::new(
+                Level1Engine::Postgres,
+                &["innodb_status_hf".to_string()],
+                &[]
+            )
+            .is_err()
+        );
+    }
+}
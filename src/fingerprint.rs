@@ -0,0 +1,839 @@
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SqlDialect {
+    Mysql,
+    Postgres,
+}
+
+/// `text` is the readable normalized statement; `hash` is computed over a
+/// canonical form (uniform token spacing, MySQL identifier quotes dropped) so
+/// that differently rendered copies of a query, such as performance_schema
+/// `DIGEST_TEXT` or `pg_stat_statements.query`, hash to the same value.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Fingerprint {
+    pub text: String,
+    pub hash: String,
+}
+
+/// Turns a statement into its normalized text and a stable hash of that text.
+pub trait Fingerprinter: Send + Sync {
+    fn dialect(&self) -> SqlDialect;
+    fn fingerprint(&self, sql: &str) -> Fingerprint;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MysqlFingerprinter;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PostgresFingerprinter;
+
+impl Fingerprinter for MysqlFingerprinter {
+    fn dialect(&self) -> SqlDialect {
+        SqlDialect::Mysql
+    }
+
+    fn fingerprint(&self, sql: &str) -> Fingerprint {
+        fingerprint_sql(sql, SqlDialect::Mysql)
+    }
+}
+
+impl Fingerprinter for PostgresFingerprinter {
+    fn dialect(&self) -> SqlDialect {
+        SqlDialect::Postgres
+    }
+
+    fn fingerprint(&self, sql: &str) -> Fingerprint {
+        fingerprint_sql(sql, SqlDialect::Postgres)
+    }
+}
+
+pub fn fingerprinter_for(dialect: SqlDialect) -> &'static dyn Fingerprinter {
+    match dialect {
+        SqlDialect::Mysql => &MysqlFingerprinter,
+        SqlDialect::Postgres => &PostgresFingerprinter,
+    }
+}
+
+/// 64-bit FNV-1a over the normalized text, rendered as 16 hex digits. The
+/// value only depends on the text, so it is stable across runs and hosts.
+pub fn digest_hash(text: &str) -> String {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    let hash = text.bytes().fold(OFFSET_BASIS, |acc, byte| {
+        (acc ^ u64::from(byte)).wrapping_mul(PRIME)
+    });
+    format!("{hash:016x}")
+}
+
+fn fingerprint_sql(sql: &str, dialect: SqlDialect) -> Fingerprint {
+    let tokens = collapse_value_lists(tokenize(sql, dialect));
+    let text = render(&tokens);
+    let hash = digest_hash(&render_canonical(&tokens, dialect));
+    Fingerprint { text, hash }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum StatementKind {
+    Select,
+    Dml,
+    Ddl,
+    Transaction,
+    Utility,
+    #[default]
+    Other,
+}
+
+/// Classifies a statement by its leading verb; `WITH` queries take the verb
+/// of the main statement after the CTE list.
+pub fn classify_statement(sql: &str, dialect: SqlDialect) -> StatementKind {
+    let tokens = tokenize(sql, dialect);
+    let mut depth = 0usize;
+    let mut verb = None;
+    for token in &tokens {
+        if token.is_punct("(") {
+            depth += 1;
+            continue;
+        }
+        if token.is_punct(")") {
+            depth = depth.saturating_sub(1);
+            continue;
+        }
+        if token.kind != TokenKind::Word {
+            continue;
+        }
+        match verb {
+            None => verb = Some(token.text.as_str()),
+            Some("with") => {
+                if depth == 0
+                    && matches!(
+                        token.text.as_str(),
+                        "select" | "insert" | "update" | "delete" | "merge"
+                    )
+                {
+                    verb = Some(token.text.as_str());
+                    break;
+                }
+            }
+            Some(_) => break,
+        }
+    }
+
+    match verb.unwrap_or_default() {
+        "select" | "values" | "table" => StatementKind::Select,
+        "insert" | "update" | "delete" | "replace" | "merge" | "load" => StatementKind::Dml,
+        "create" | "alter" | "drop" | "truncate" | "rename" | "comment" => StatementKind::Ddl,
+        "begin" | "start" | "commit" | "rollback" | "savepoint" | "release" | "xa" | "end"
+        | "abort" => StatementKind::Transaction,
+        "set" | "show" | "use" | "explain" | "describe" | "desc" | "analyze" | "optimize"
+        | "vacuum" | "reindex" | "cluster" | "checkpoint" | "grant" | "revoke" | "flush"
+        | "kill" | "lock" | "unlock" | "prepare" | "execute" | "deallocate" | "call" | "do"
+        | "copy" | "discard" | "listen" | "notify" | "reset" | "handler" => StatementKind::Utility,
+        _ => StatementKind::Other,
+    }
+}
+
+/// Tables named after `FROM`, `JOIN`, `UPDATE`, `INTO` and DDL targets, as
+/// `schema.table` when qualified, in first-seen order. CTE names, derived tables and table
+/// functions are not reported.
+pub fn referenced_tables(sql: &str, dialect: SqlDialect) -> Vec<String> {
+    let tokens = tokenize(sql, dialect);
+    let cte_names = tokens
+        .windows(3)
+        .filter(|it| it[0].kind == TokenKind::Word && it[1].is_word("as") && it[2].is_punct("("))
+        .map(|it| it[0].text.clone())
+        .collect::<Vec<_>>();
+
+    let creates_index = tokens.first().is_some_and(|it| it.is_word("create"))
+        && tokens.iter().any(|it| it.is_word("index"));
+
+    let mut tables = Vec::new();
+    // Whether each open parenthesis belongs to a function whose arguments use
+    // `FROM` as a separator, e.g. `EXTRACT(YEAR FROM ts)`.
+    let mut parens = Vec::new();
+    let mut idx = 0;
+    while idx < tokens.len() {
+        let token = &tokens[idx];
+        idx += 1;
+        if token.is_punct("(") {
+            let opener = idx.checked_sub(2).map(|prev| &tokens[prev]);
+            parens.push(opener.is_some_and(|it| {
+                ["extract", "trim", "substring", "overlay", "position"]
+                    .iter()
+                    .any(|word| it.is_word(word))
+            }));
+            continue;
+        }
+        if token.is_punct(")") {
+            parens.pop();
+            continue;
+        }
+        if parens.last() == Some(&true) {
+            continue;
+        }
+
+        // DDL: `ALTER TABLE t`, `DROP TABLE IF EXISTS a, b`, `TRUNCATE t` and
+        // `CREATE INDEX i ON t (...)`.
+        let is_ddl_target = token.is_word("table")
+            || (token.is_word("truncate") && idx == 1)
+            || (token.is_word("on") && creates_index);
+        // `ON DUPLICATE KEY UPDATE col = ...` names columns, not tables.
+        let is_upsert =
+            idx >= 3 && tokens[idx - 3].is_word("duplicate") && tokens[idx - 2].is_word("key");
+        let column_list_allowed = token.is_word("into") || is_ddl_target;
+        let list_allowed =
+            token.is_word("from") || (token.is_word("update") && !is_upsert) || is_ddl_target;
+        if !(list_allowed || column_list_allowed || token.is_word("join")) {
+            continue;
+        }
+        loop {
+            while tokens.get(idx).is_some_and(|it| {
+                ["only", "if", "not", "exists"]
+                    .iter()
+                    .any(|word| it.is_word(word))
+            }) {
+                idx += 1;
+            }
+            let Some((name, next)) = read_table_name(&tokens, idx) else {
+                break;
+            };
+            idx = next;
+            let is_call = tokens.get(idx).is_some_and(|it| it.is_punct("("));
+            if (column_list_allowed || !is_call)
+                && name != "dual"
+                && !cte_names.contains(&name)
+                && !tables.contains(&name)
+            {
+                tables.push(name);
+            }
+            if !list_allowed {
+                break;
+            }
+            if tokens.get(idx).is_some_and(|it| it.is_word("as")) {
+                idx += 1;
+            }
+            if tokens
+                .get(idx)
+                .is_some_and(|it| it.kind == TokenKind::Word && !is_clause_keyword(&it.text))
+            {
+                idx += 1;
+            }
+            if !tokens.get(idx).is_some_and(|it| it.is_punct(",")) {
+                break;
+            }
+            idx += 1;
+        }
+    }
+    tables
+}
+
+fn read_table_name(tokens: &[Token], mut idx: usize) -> Option<(String, usize)> {
+    let mut parts = Vec::new();
+    loop {
+        let token = tokens.get(idx)?;
+        match token.kind {
+            TokenKind::Word if parts.is_empty() && is_clause_keyword(&token.text) => return None,
+            TokenKind::Word => parts.push(token.text.clone()),
+            TokenKind::QuotedIdent => parts.push(unquote_ident(&token.text)),
+            _ => return None,
+        }
+        idx += 1;
+        if !tokens.get(idx).is_some_and(|it| it.is_punct(".")) {
+            return Some((parts.join("."), idx));
+        }
+        idx += 1;
+    }
+}
+
+fn unquote_ident(text: &str) -> String {
+    match text.chars().next() {
+        Some('`') => text.trim_matches('`').replace("``", "`").to_lowercase(),
+        Some('"') => text.trim_matches('"').replace("\"\"", "\""),
+        _ => text.to_string(),
+    }
+}
+
+fn is_clause_keyword(word: &str) -> bool {
+    matches!(
+        word,
+        "select"
+            | "where"
+            | "join"
+            | "inner"
+            | "left"
+            | "right"
+            | "full"
+            | "outer"
+            | "cross"
+            | "natural"
+            | "straight_join"
+            | "lateral"
+            | "on"
+            | "using"
+            | "set"
+            | "values"
+            | "value"
+            | "group"
+            | "order"
+            | "having"
+            | "limit"
+            | "offset"
+            | "union"
+            | "window"
+            | "for"
+            | "returning"
+            | "use"
+            | "force"
+            | "ignore"
+            | "partition"
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenKind {
+    Word,
+    QuotedIdent,
+    Placeholder,
+    Punct,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Token {
+    kind: TokenKind,
+    text: String,
+    space_before: bool,
+}
+
+impl Token {
+    fn is_word(&self, word: &str) -> bool {
+        self.kind == TokenKind::Word && self.text == word
+    }
+
+    fn is_punct(&self, punct: &str) -> bool {
+        self.kind == TokenKind::Punct && self.text == punct
+    }
+}
+
+struct Tokenizer {
+    chars: Vec<char>,
+    pos: usize,
+    dialect: SqlDialect,
+    tokens: Vec<Token>,
+    pending_space: bool,
+}
+
+fn tokenize(sql: &str, dialect: SqlDialect) -> Vec<Token> {
+    let mut tokenizer = Tokenizer {
+        chars: sql.chars().collect(),
+        pos: 0,
+        dialect,
+        tokens: Vec::new(),
+        pending_space: false,
+    };
+    tokenizer.run();
+    tokenizer.tokens
+}
+
+impl Tokenizer {
+    fn peek(&self, ahead: usize) -> Option<char> {
+        self.chars.get(self.pos + ahead).copied()
+    }
+
+    fn push(&mut self, kind: TokenKind, text: String) {
+        self.tokens.push(Token {
+            kind,
+            text,
+            space_before: self.pending_space && !self.tokens.is_empty(),
+        });
+        self.pending_space = false;
+    }
+
+    fn run(&mut self) {
+        while let Some(ch) = self.peek(0) {
+            let next = self.peek(1);
+            match ch {
+                _ if ch.is_whitespace() => {
+                    self.pos += 1;
+                    self.pending_space = true;
+                }
+                '-' if next == Some('-') && self.starts_line_comment() => self.skip_line(),
+                '#' if self.dialect == SqlDialect::Mysql => self.skip_line(),
+                '/' if next == Some('*') => self.skip_block_comment(),
+                '\'' => {
+                    let backslash = self.dialect == SqlDialect::Mysql;
+                    self.skip_quoted('\'', backslash);
+                    self.push(TokenKind::Placeholder, "?".to_string());
+                }
+                '"' if self.dialect == SqlDialect::Mysql => {
+                    self.skip_quoted('"', true);
+                    self.push(TokenKind::Placeholder, "?".to_string());
+                }
+                '"' => {
+                    let text = self.read_quoted_ident('"');
+                    self.push(TokenKind::QuotedIdent, text);
+                }
+                '`' if self.dialect == SqlDialect::Mysql => {
+                    let text = self.read_quoted_ident('`');
+                    self.push(TokenKind::QuotedIdent, text);
+                }
+                '$' if self.dialect == SqlDialect::Postgres => self.read_dollar(),
+                '?' => {
+                    self.pos += 1;
+                    self.push(TokenKind::Placeholder, "?".to_string());
+                }
+                // performance_schema abbreviates collapsed lists as `(...)`.
+                '.' if next == Some('.') && self.peek(2) == Some('.') => {
+                    self.pos += 3;
+                    self.push(TokenKind::Placeholder, "?".to_string());
+                }
+                _ if ch.is_ascii_digit()
+                    || (ch == '.' && next.is_some_and(|it| it.is_ascii_digit())) =>
+                {
+                    self.skip_number();
+                    self.push(TokenKind::Placeholder, "?".to_string());
+                }
+                _ if ch.is_alphabetic() || ch == '_' => self.read_word(),
+                _ => {
+                    self.pos += 1;
+                    self.push(TokenKind::Punct, ch.to_string());
+                }
+            }
+        }
+    }
+
+    /// MySQL only treats `--` as a comment when followed by whitespace.
+    fn starts_line_comment(&self) -> bool {
+        match self.dialect {
+            SqlDialect::Mysql => self.peek(2).is_none_or(char::is_whitespace),
+            SqlDialect::Postgres => true,
+        }
+    }
+
+    fn skip_line(&mut self) {
+        while let Some(ch) = self.peek(0) {
+            self.pos += 1;
+            if ch == '\n' {
+                break;
+            }
+        }
+        self.pending_space = true;
+    }
+
+    /// PostgreSQL block comments nest; MySQL ones end at the first `*/`.
+    fn skip_block_comment(&mut self) {
+        self.pos += 2;
+        let mut depth = 1;
+        while depth > 0 {
+            match (self.peek(0), self.peek(1)) {
+                (None, _) => break,
+                (Some('*'), Some('/')) => {
+                    depth -= 1;
+                    self.pos += 2;
+                }
+                (Some('/'), Some('*')) if self.dialect == SqlDialect::Postgres => {
+                    depth += 1;
+                    self.pos += 2;
+                }
+                _ => self.pos += 1,
+            }
+        }
+        // Only whitespace after the comment counts, so a trailing tag comment
+        // does not leave a gap before `;`; adjacent words still stay apart.
+        let glued = self
+            .tokens
+            .last()
+            .is_some_and(|it| it.kind == TokenKind::Word)
+            && self
+                .peek(0)
+                .is_some_and(|it| it.is_alphanumeric() || it == '_');
+        self.pending_space = glued;
+    }
+
+    /// Skips a quoted literal, honouring doubled quotes and, when enabled,
+    /// backslash escapes.
+    fn skip_quoted(&mut self, quote: char, backslash_escapes: bool) {
+        self.pos += 1;
+        while let Some(ch) = self.peek(0) {
+            self.pos += 1;
+            if backslash_escapes && ch == '\\' {
+                self.pos += 1;
+            } else if ch == quote {
+                if self.peek(0) == Some(quote) {
+                    self.pos += 1;
+                } else {
+                    return;
+                }
+            }
+        }
+    }
+
+    fn read_quoted_ident(&mut self, quote: char) -> String {
+        let start = self.pos;
+        self.skip_quoted(quote, false);
+        self.chars[start..self.pos.min(self.chars.len())]
+            .iter()
+            .collect()
+    }
+
+    fn skip_number(&mut self) {
+        let hex_or_binary = self.peek(0) == Some('0')
+            && matches!(self.peek(1), Some('x' | 'X' | 'b' | 'B'))
+            && self.peek(2).is_some_and(|it| it.is_ascii_hexdigit());
+        if hex_or_binary {
+            self.pos += 2;
+            while self.peek(0).is_some_and(|it| it.is_ascii_hexdigit()) {
+                self.pos += 1;
+            }
+            return;
+        }
+
+        while self
+            .peek(0)
+            .is_some_and(|it| it.is_ascii_digit() || it == '.')
+        {
+            self.pos += 1;
+        }
+        if matches!(self.peek(0), Some('e' | 'E')) {
+            let sign = usize::from(matches!(self.peek(1), Some('+' | '-')));
+            if self.peek(1 + sign).is_some_and(|it| it.is_ascii_digit()) {
+                self.pos += 1 + sign;
+                while self.peek(0).is_some_and(|it| it.is_ascii_digit()) {
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+
+    fn read_word(&mut self) {
+        let start = self.pos;
+        while self
+            .peek(0)
+            .is_some_and(|it| it.is_alphanumeric() || it == '_' || it == '$')
+        {
+            self.pos += 1;
+        }
+        let word = self.chars[start..self.pos]
+            .iter()
+            .collect::<String>()
+            .to_lowercase();
+
+        // Prefixed literals: E'..', X'..', B'..', N'..' and MySQL `_charset'..'`.
+        if self.peek(0) == Some('\'') {
+            let escaped = match (self.dialect, word.as_str()) {
+                (SqlDialect::Postgres, "e") => Some(true),
+                (SqlDialect::Mysql, "x" | "b" | "n") | (SqlDialect::Postgres, "x" | "b") => {
+                    Some(self.dialect == SqlDialect::Mysql)
+                }
+                (SqlDialect::Mysql, it) if it.starts_with('_') => Some(true),
+                _ => None,
+            };
+            if let Some(backslash) = escaped {
+                self.skip_quoted('\'', backslash);
+                self.push(TokenKind::Placeholder, "?".to_string());
+                return;
+            }
+        }
+        self.push(TokenKind::Word, word);
+    }
+
+    /// `$1` parameters become placeholders; `$tag$...$tag$` bodies are literals.
+    fn read_dollar(&mut self) {
+        if self.peek(1).is_some_and(|it| it.is_ascii_digit()) {
+            self.pos += 1;
+            while self.peek(0).is_some_and(|it| it.is_ascii_digit()) {
+                self.pos += 1;
+            }
+            self.push(TokenKind::Placeholder, "?".to_string());
+            return;
+        }
+
+        let mut end = self.pos + 1;
+        while self
+            .chars
+            .get(end)
+            .is_some_and(|it| it.is_alphanumeric() || *it == '_')
+        {
+            end += 1;
+        }
+        if self.chars.get(end) != Some(&'$') {
+            self.pos += 1;
+            self.push(TokenKind::Punct, "$".to_string());
+            return;
+        }
+
+        let tag = self.chars[self.pos..=end].to_vec();
+        self.pos = end + 1;
+        while self.pos < self.chars.len() {
+            if self.chars[self.pos..].starts_with(&tag) {
+                self.pos += tag.len();
+                break;
+            }
+            self.pos += 1;
+        }
+        self.push(TokenKind::Placeholder, "?".to_string());
+    }
+}
+
+/// Index just past a `( ? [, ?]* )` tuple starting at `start`, if any.
+fn placeholder_tuple_end(tokens: &[Token], start: usize) -> Option<usize> {
+    if !tokens.get(start)?.is_punct("(") {
+        return None;
+    }
+    let mut idx = start + 1;
+    loop {
+        if tokens.get(idx)?.kind != TokenKind::Placeholder {
+            return None;
+        }
+        idx += 1;
+        let token = tokens.get(idx)?;
+        if token.is_punct(")") {
+            return Some(idx + 1);
+        }
+        if !token.is_punct(",") {
+            return None;
+        }
+        idx += 1;
+    }
+}
+
+/// Rewrites `IN (?, ?, ...)` and multi-row `VALUES (...), (...)` to `(?)` so
+/// statements differing only in list length share one fingerprint.
+fn collapse_value_lists(tokens: Vec<Token>) -> Vec<Token> {
+    let mut out = Vec::with_capacity(tokens.len());
+    let mut idx = 0;
+    while idx < tokens.len() {
+        let token = &tokens[idx];
+        out.push(token.clone());
+        idx += 1;
+
+        let is_list_head = token.is_word("in") || token.is_word("values") || token.is_word("value");
+        if !is_list_head {
+            continue;
+        }
+        let Some(mut end) = placeholder_tuple_end(&tokens, idx) else {
+            continue;
+        };
+        if !token.is_word("in") {
+            while tokens.get(end).is_some_and(|it| it.is_punct(","))
+                && let Some(next) = placeholder_tuple_end(&tokens, end + 1)
+            {
+                end = next;
+            }
+        }
+
+        let open = &tokens[idx];
+        out.push(open.clone());
+        out.push(Token {
+            kind: TokenKind::Placeholder,
+            text: "?".to_string(),
+            space_before: false,
+        });
+        out.push(Token {
+            kind: TokenKind::Punct,
+            text: ")".to_string(),
+            space_before: false,
+        });
+        idx = end;
+    }
+    out
+}
+
+fn render(tokens: &[Token]) -> String {
+    let mut out = String::new();
+    for token in tokens {
+        if token.space_before {
+            out.push(' ');
+        }
+        out.push_str(&token.text);
+    }
+    out
+}
+
+fn render_canonical(tokens: &[Token], dialect: SqlDialect) -> String {
+    let end = tokens
+        .iter()
+        .rposition(|it| !it.is_punct(";"))
+        .map_or(0, |it| it + 1);
+    tokens[..end]
+        .iter()
+        .map(|token| match (token.kind, dialect) {
+            (TokenKind::QuotedIdent, SqlDialect::Mysql) => token
+                .text
+                .trim_matches('`')
+                .replace("``", "`")
+                .to_lowercase(),
+            _ => token.text.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mysql(sql: &str) -> String {
+        MysqlFingerprinter.fingerprint(sql).text
+    }
+
+    fn postgres(sql: &str) -> String {
+        PostgresFingerprinter.fingerprint(sql).text
+    }
+
+    #[test]
+    fn should_normalize_sql_fingerprint() {
+        let sql = r#"SELECT * FROM t WHERE user_id = 123 AND name = "alice" AND score > 1.5"#;
+        assert_eq!(
+            mysql(sql),
+            "select * from t where user_id = ? and name = ? and score > ?"
+        );
+    }
+
+    #[test]
+    fn should_preserve_identifiers_with_digits() {
+        assert_eq!(
+            mysql("SELECT c1 FROM orders_2024 t1 WHERE t1.id = 7"),
+            "select c1 from orders_2024 t1 where t1.id = ?"
+        );
+        assert_eq!(
+            postgres(r#"SELECT "Total2" FROM "Orders" WHERE id = $1"#),
+            r#"select "Total2" from "Orders" where id = ?"#
+        );
+        assert_eq!(
+            mysql("SELECT `col 1` FROM `db`.`t2`"),
+            "select `col 1` from `db`.`t2`"
+        );
+    }
+
+    #[test]
+    fn should_classify_statements() {
+        let kind = |sql| classify_statement(sql, SqlDialect::Mysql);
+        assert_eq!(kind("/* app='x' */ SELECT 1"), StatementKind::Select);
+        assert_eq!(kind("(SELECT 1) UNION (SELECT 2)"), StatementKind::Select);
+        assert_eq!(kind("REPLACE INTO t VALUES (1)"), StatementKind::Dml);
+        assert_eq!(
+            kind("ALTER TABLE orders ADD INDEX idx_user (user_id)"),
+            StatementKind::Ddl
+        );
+        assert_eq!(kind("CREATE INDEX idx_a ON t (a)"), StatementKind::Ddl);
+        assert_eq!(kind("START TRANSACTION"), StatementKind::Transaction);
+        assert_eq!(kind("SHOW PROCESSLIST"), StatementKind::Utility);
+        assert_eq!(
+            classify_statement(
+                "WITH moved AS (DELETE FROM queue RETURNING *) INSERT INTO done SELECT * FROM moved",
+                SqlDialect::Postgres
+            ),
+            StatementKind::Dml
+        );
+        assert_eq!(
+            classify_statement("WITH x AS (SELECT 1) SELECT * FROM x", SqlDialect::Postgres),
+            StatementKind::Select
+        );
+    }
+
+    #[test]
+    fn should_list_referenced_tables() {
+        let tables = |sql| referenced_tables(sql, SqlDialect::Mysql);
+        assert_eq!(
+            tables(
+                "SELECT o.id FROM `shop`.`orders` o, items AS i JOIN users u ON u.id = o.user_id \
+                 WHERE EXTRACT(YEAR FROM o.created_at) = 2024"
+            ),
+            vec!["shop.orders", "items", "users"]
+        );
+        assert_eq!(
+            tables("INSERT INTO audit (id, note) SELECT id, 'x' FROM dual"),
+            vec!["audit"]
+        );
+        assert_eq!(tables("UPDATE carts SET qty = 1"), vec!["carts"]);
+        assert_eq!(
+            tables(
+                "INSERT INTO stock (id, qty) VALUES (1, 2) ON DUPLICATE KEY UPDATE qty = qty + 1"
+            ),
+            vec!["stock"]
+        );
+        assert_eq!(tables("DELETE FROM carts WHERE id = 1"), vec!["carts"]);
+        assert_eq!(
+            tables("ALTER TABLE shop.orders ADD COLUMN note TEXT"),
+            vec!["shop.orders"]
+        );
+        assert_eq!(tables("DROP TABLE IF EXISTS a, b"), vec!["a", "b"]);
+        assert_eq!(tables("CREATE UNIQUE INDEX idx_a ON t (a)"), vec!["t"]);
+        assert_eq!(tables("CREATE TABLE t (id INT)"), vec!["t"]);
+        assert_eq!(tables("TRUNCATE carts"), vec!["carts"]);
+        assert_eq!(
+            referenced_tables(
+                "WITH recent AS (SELECT * FROM public.\"Events\") \
+                 SELECT * FROM recent, generate_series(1, 3) g JOIN (SELECT 1) s ON true",
+                SqlDialect::Postgres
+            ),
+            vec!["public.Events"]
+        );
+    }
+
+    #[test]
+    fn should_strip_comments() {
+        assert_eq!(
+            mysql("/* app=checkout */ SELECT 1 # trailing\n FROM dual -- note"),
+            "select ? from dual"
+        );
+        assert_eq!(
+            postgres("SELECT /* outer /* nested */ still */ 1"),
+            "select ?"
+        );
+        assert_eq!(mysql("SELECT 5--1"), "select ?--?");
+        assert_eq!(mysql("SELECT 1 /*app='checkout'*/;"), "select ?;");
+    }
+
+    #[test]
+    fn should_replace_dialect_literals() {
+        assert_eq!(
+            mysql(r"SELECT 0xFF, X'0A', b'101', 0b11, 'it\'s', 'a''b', _utf8mb4'x'"),
+            "select ?, ?, ?, ?, ?, ?, ?"
+        );
+        assert_eq!(
+            postgres(r"SELECT E'it\'s', 'c:\', $fn$ body ' $fn$, $$x$$, 1e-3"),
+            "select ?, ?, ?, ?, ?"
+        );
+    }
+
+    #[test]
+    fn should_collapse_in_lists_and_multi_row_values() {
+        assert_eq!(
+            mysql("SELECT * FROM t WHERE id IN (1, 2, 3)"),
+            mysql("select * from t where id in (4)")
+        );
+        assert_eq!(
+            mysql("INSERT INTO t (a, b) VALUES (1, 'x'), (2, 'y'), (3, 'z')"),
+            "insert into t (a, b) values (?)"
+        );
+        assert_eq!(
+            postgres("SELECT * FROM t WHERE id IN (SELECT id FROM u)"),
+            "select * from t where id in (select id from u)"
+        );
+    }
+
+    #[test]
+    fn should_produce_stable_hash() {
+        let a = MysqlFingerprinter.fingerprint("SELECT * FROM t WHERE id = 1");
+        let b = MysqlFingerprinter.fingerprint("select *   from t where id = 99");
+        assert_eq!(a, b);
+        assert_eq!(a.hash.len(), 16);
+        assert_eq!(digest_hash(""), "cbf29ce484222325");
+    }
+
+    #[test]
+    fn should_hash_native_digest_text_like_raw_statement() {
+        let raw = MysqlFingerprinter.fingerprint("SELECT COUNT(*) FROM orders WHERE id IN (1,2)");
+        let performance_schema =
+            MysqlFingerprinter.fingerprint("SELECT COUNT ( * ) FROM `orders` WHERE `id` IN (...)");
+        assert_eq!(raw.hash, performance_schema.hash);
+
+        let raw = PostgresFingerprinter.fingerprint("SELECT * FROM orders WHERE user_id = 1;");
+        let pg_stat_statements =
+            PostgresFingerprinter.fingerprint("SELECT * FROM orders WHERE user_id = $1");
+        assert_eq!(raw.hash, pg_stat_statements.hash);
+    }
+}
@@ -0,0 +1,539 @@
+use mysql::PooledConn;
+use mysql::prelude::Queryable;
+use serde::Serialize;
+
+use crate::deadlocks::{DeadlockRecord, INNODB_STATUS_SOURCE, parse_innodb_deadlocks};
+
+const MAX_REPORTED_TRANSACTIONS: usize = 50;
+const MAX_SEMAPHORE_WAITS: usize = 20;
+const MAX_FOREIGN_KEY_ERROR_LINES: usize = 20;
+
+/// Structured `SHOW ENGINE INNODB STATUS`. Sections missing from the output
+/// (older versions, truncated text) keep their defaults.
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct InnodbStatus {
+    pub captured_at: Option<String>,
+    pub semaphores: InnodbSemaphores,
+    pub latest_foreign_key_error: Option<Vec<String>>,
+    pub latest_deadlock: Option<DeadlockRecord>,
+    pub transactions: InnodbTransactions,
+    pub file_io: InnodbFileIo,
+    pub insert_buffer: InnodbInsertBuffer,
+    pub log: InnodbLog,
+    pub buffer_pool: InnodbBufferPool,
+    pub row_operations: InnodbRowOperations,
+}
+
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct InnodbSemaphores {
+    pub reservation_count: Option<u64>,
+    pub signal_count: Option<u64>,
+    pub wait_count: usize,
+    pub waits: Vec<InnodbSemaphoreWait>,
+}
+
+/// `--Thread 139901 has waited at btr0sea.ic line 90 for 241 seconds the semaphore:`
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct InnodbSemaphoreWait {
+    pub thread_id: Option<u64>,
+    pub location: String,
+    pub waited_secs: f64,
+    pub latch: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct InnodbTransactions {
+    pub trx_id_counter: Option<u64>,
+    pub history_list_length: Option<u64>,
+    pub active_count: usize,
+    pub lock_wait_count: usize,
+    pub max_active_secs: Option<u64>,
+    /// Active transactions, longest running first.
+    pub active: Vec<InnodbTransaction>,
+}
+
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct InnodbTransaction {
+    pub trx_id: String,
+    pub active_secs: u64,
+    pub state: Option<String>,
+    pub thread_id: Option<u64>,
+    pub lock_structs: Option<u64>,
+    pub row_locks: Option<u64>,
+    pub undo_log_entries: Option<u64>,
+    pub lock_wait_secs: Option<u64>,
+    pub statement: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct InnodbFileIo {
+    pub pending_aio_reads: Option<u64>,
+    pub pending_aio_writes: Option<u64>,
+    pub pending_log_fsyncs: Option<u64>,
+    pub pending_buffer_pool_fsyncs: Option<u64>,
+    pub os_file_reads: Option<u64>,
+    pub os_file_writes: Option<u64>,
+    pub os_fsyncs: Option<u64>,
+    pub reads_per_sec: Option<f64>,
+    pub writes_per_sec: Option<f64>,
+    pub fsyncs_per_sec: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct InnodbInsertBuffer {
+    pub size: Option<u64>,
+    pub free_list_len: Option<u64>,
+    pub seg_size: Option<u64>,
+    pub merges: Option<u64>,
+    pub hash_searches_per_sec: Option<f64>,
+    pub non_hash_searches_per_sec: Option<f64>,
+}
+
+/// `checkpoint_age` is `log_sequence_number - last_checkpoint_at` in bytes.
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct InnodbLog {
+    pub log_sequence_number: Option<u64>,
+    pub log_flushed_up_to: Option<u64>,
+    pub pages_flushed_up_to: Option<u64>,
+    pub last_checkpoint_at: Option<u64>,
+    pub checkpoint_age: Option<u64>,
+}
+
+/// Sizes are in pages except `total_large_memory_allocated` (bytes).
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct InnodbBufferPool {
+    pub total_large_memory_allocated: Option<u64>,
+    pub pool_size_pages: Option<u64>,
+    pub free_buffers: Option<u64>,
+    pub database_pages: Option<u64>,
+    pub modified_db_pages: Option<u64>,
+    pub pending_reads: Option<u64>,
+    pub pending_writes_lru: Option<u64>,
+    pub pending_writes_flush_list: Option<u64>,
+    pub pages_read: Option<u64>,
+    pub pages_created: Option<u64>,
+    pub pages_written: Option<u64>,
+    pub hit_rate_per_mille: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct InnodbRowOperations {
+    pub queries_inside: Option<u64>,
+    pub queries_in_queue: Option<u64>,
+    pub read_views_open: Option<u64>,
+    pub rows_inserted: Option<u64>,
+    pub rows_updated: Option<u64>,
+    pub rows_deleted: Option<u64>,
+    pub rows_read: Option<u64>,
+    pub inserts_per_sec: Option<f64>,
+    pub updates_per_sec: Option<f64>,
+    pub deletes_per_sec: Option<f64>,
+    pub reads_per_sec: Option<f64>,
+}
+
+/// Returns the `Status` column; needs the `PROCESS` privilege.
+pub fn fetch_innodb_status_text(conn: &mut PooledConn) -> Result<Option<String>, mysql::Error> {
+    let row: Option<(String, String, String)> = conn.query_first("SHOW ENGINE INNODB STATUS")?;
+    Ok(row.map(|(_, _, status)| status))
+}
+
+pub fn parse_innodb_status(text: &str) -> InnodbStatus {
+    let mut status = InnodbStatus::default();
+    for (title, body) in split_sections(text) {
+        match title {
+            "SEMAPHORES" => status.semaphores = parse_semaphores(&body),
+            "LATEST FOREIGN KEY ERROR" => {
+                status.latest_foreign_key_error = Some(
+                    body.iter()
+                        .filter(|it| !it.trim().is_empty())
+                        .take(MAX_FOREIGN_KEY_ERROR_LINES)
+                        .map(|it| it.to_string())
+                        .collect(),
+                );
+            }
+            "LATEST DETECTED DEADLOCK" => {
+                let mut lines = vec![title];
+                lines.extend(&body);
+                status.latest_deadlock = parse_innodb_deadlocks(&lines, INNODB_STATUS_SOURCE)
+                    .into_iter()
+                    .next();
+            }
+            "TRANSACTIONS" => status.transactions = parse_transactions(&body),
+            "FILE I/O" => status.file_io = parse_file_io(&body),
+            "INSERT BUFFER AND ADAPTIVE HASH INDEX" => {
+                status.insert_buffer = parse_insert_buffer(&body)
+            }
+            "LOG" => status.log = parse_log(&body),
+            "BUFFER POOL AND MEMORY" => status.buffer_pool = parse_buffer_pool(&body),
+            "ROW OPERATIONS" => status.row_operations = parse_row_operations(&body),
+            _ => {}
+        }
+    }
+    status.captured_at = text
+        .lines()
+        .find(|it| it.contains("INNODB MONITOR OUTPUT"))
+        .and_then(|it| it.get(..19))
+        .map(str::to_string);
+    status
+}
+
+/// Sections are a title between two dashed rules; the body runs to the next
+/// title.
+fn split_sections(text: &str) -> Vec<(&str, Vec<&str>)> {
+    let lines = text.lines().collect::<Vec<_>>();
+    let is_rule = |it: &str| it.len() >= 3 && it.trim_end().chars().all(|ch| ch == '-');
+    let mut sections: Vec<(&str, Vec<&str>)> = Vec::new();
+    let mut idx = 0;
+    while idx < lines.len() {
+        if is_rule(lines[idx]) && idx + 2 < lines.len() && is_rule(lines[idx + 2]) {
+            sections.push((lines[idx + 1].trim(), Vec::new()));
+            idx += 3;
+            continue;
+        }
+        if let Some((_, body)) = sections.last_mut() {
+            body.push(lines[idx]);
+        }
+        idx += 1;
+    }
+    sections
+}
+
+/// First integer after `key`, ignoring separators such as `,` and `;`.
+fn number_after(line: &str, key: &str) -> Option<u64> {
+    let idx = line.find(key)?;
+    line[(idx + key.len())..]
+        .split_whitespace()
+        .next()?
+        .trim_matches(|it: char| !it.is_ascii_digit())
+        .parse()
+        .ok()
+}
+
+/// Number right before `unit`, e.g. `5.20` in `5.20 writes/s`.
+fn number_before(line: &str, unit: &str) -> Option<f64> {
+    let idx = line.find(unit)?;
+    line[..idx]
+        .split_whitespace()
+        .last()?
+        .trim_matches(',')
+        .parse()
+        .ok()
+}
+
+fn parse_semaphores(body: &[&str]) -> InnodbSemaphores {
+    let mut result = InnodbSemaphores::default();
+    for (idx, line) in body.iter().enumerate() {
+        if line.starts_with("OS WAIT ARRAY INFO: reservation count") {
+            result.reservation_count = number_after(line, "reservation count");
+        } else if line.starts_with("OS WAIT ARRAY INFO: signal count") {
+            result.signal_count = number_after(line, "signal count");
+        } else if let Some(rest) = line.strip_prefix("--Thread ") {
+            result.wait_count += 1;
+            if result.waits.len() >= MAX_SEMAPHORE_WAITS {
+                continue;
+            }
+            let location = rest
+                .split_once(" has waited at ")
+                .and_then(|(_, it)| it.split(" for ").next())
+                .unwrap_or_default();
+            result.waits.push(InnodbSemaphoreWait {
+                thread_id: rest
+                    .split_whitespace()
+                    .next()
+                    .and_then(|it| it.parse().ok()),
+                location: location.to_string(),
+                waited_secs: number_before(line, "seconds the semaphore").unwrap_or_default(),
+                latch: body.get(idx + 1).map(|it| it.trim().to_string()),
+            });
+        }
+    }
+    result
+}
+
+fn parse_transactions(body: &[&str]) -> InnodbTransactions {
+    let mut result = InnodbTransactions::default();
+    let mut current: Option<InnodbTransaction> = None;
+    let mut expect_statement = false;
+    let finish = |trx: Option<InnodbTransaction>, result: &mut InnodbTransactions| {
+        if let Some(trx) = trx {
+            result.active_count += 1;
+            if trx.lock_wait_secs.is_some() {
+                result.lock_wait_count += 1;
+            }
+            result.active.push(trx);
+        }
+    };
+
+    for line in body {
+        let line = line.trim_end();
+        if let Some(rest) = line.strip_prefix("Trx id counter ") {
+            result.trx_id_counter = rest.trim().parse().ok();
+        } else if line.starts_with("History list length") {
+            result.history_list_length = number_after(line, "History list length");
+        } else if let Some(rest) = line.strip_prefix("---TRANSACTION ") {
+            finish(current.take(), &mut result);
+            expect_statement = false;
+            let mut parts = rest.splitn(3, ", ");
+            let trx_id = parts.next().unwrap_or_default().to_string();
+            let Some(active) = parts.next().and_then(|it| it.strip_prefix("ACTIVE ")) else {
+                continue;
+            };
+            let mut words = active.split_whitespace();
+            let active_secs = words.next().and_then(|it| it.parse().ok()).unwrap_or(0);
+            let state = active
+                .split_once(" sec")
+                .map(|(_, it)| it.trim())
+                .filter(|it| !it.is_empty())
+                .map(str::to_string);
+            current = Some(InnodbTransaction {
+                trx_id,
+                active_secs,
+                state,
+                ..InnodbTransaction::default()
+            });
+        } else if let Some(trx) = current.as_mut() {
+            if line.contains("lock struct(s)") {
+                trx.lock_structs = line
+                    .split_whitespace()
+                    .find(|it| it.parse::<u64>().is_ok())
+                    .and_then(|it| it.parse().ok());
+                trx.row_locks = line
+                    .split(", ")
+                    .find(|it| it.ends_with("row lock(s)"))
+                    .and_then(|it| it.split_whitespace().next())
+                    .and_then(|it| it.parse().ok());
+                trx.undo_log_entries = number_after(line, "undo log entries");
+            } else if let Some(rest) = line.strip_prefix("MySQL thread id ") {
+                trx.thread_id = rest.split(',').next().and_then(|it| it.trim().parse().ok());
+                expect_statement = true;
+            } else if line.starts_with("------- TRX HAS BEEN WAITING") {
+                trx.lock_wait_secs = number_after(line, "WAITING");
+                expect_statement = false;
+            } else if ["Trx read view", "TABLE LOCK", "RECORD LOCKS", "---"]
+                .iter()
+                .any(|it| line.starts_with(it))
+            {
+                // Lock details and the read view follow the statement.
+                expect_statement = false;
+            } else if expect_statement && !line.trim().is_empty() {
+                let statement = trx.statement.get_or_insert_with(String::new);
+                if !statement.is_empty() {
+                    statement.push(' ');
+                }
+                statement.push_str(line.trim());
+            }
+        }
+    }
+    finish(current.take(), &mut result);
+
+    result.max_active_secs = result.active.iter().map(|it| it.active_secs).max();
+    result.active.sort_by(|a, b| {
+        b.active_secs
+            .cmp(&a.active_secs)
+            .then_with(|| a.trx_id.cmp(&b.trx_id))
+    });
+    result.active.truncate(MAX_REPORTED_TRANSACTIONS);
+    result
+}
+
+fn parse_file_io(body: &[&str]) -> InnodbFileIo {
+    let mut result = InnodbFileIo::default();
+    let sum_list = |line: &str, key: &str| -> Option<u64> {
+        let idx = line.find(key)?;
+        let rest = &line[(idx + key.len())..];
+        let list = rest.strip_prefix(" [")?.split(']').next()?;
+        Some(
+            list.split(',')
+                .filter_map(|it| it.trim().parse::<u64>().ok())
+                .sum(),
+        )
+    };
+    for line in body {
+        if line.starts_with("Pending normal aio reads:") {
+            result.pending_aio_reads =
+                sum_list(line, "aio reads:").or_else(|| number_after(line, "aio reads:"));
+            result.pending_aio_writes =
+                sum_list(line, "aio writes:").or_else(|| number_after(line, "aio writes:"));
+        } else if line.starts_with("Pending flushes (fsync)") {
+            result.pending_log_fsyncs = number_after(line, "log:");
+            result.pending_buffer_pool_fsyncs = number_after(line, "buffer pool:");
+        } else if line.contains("OS file reads") {
+            result.os_file_reads = number_before(line, "OS file reads").map(|it| it as u64);
+            result.os_file_writes = number_before(line, "OS file writes").map(|it| it as u64);
+            result.os_fsyncs = number_before(line, "OS fsyncs").map(|it| it as u64);
+        } else if line.contains("reads/s") && line.contains("fsyncs/s") {
+            result.reads_per_sec = number_before(line, "reads/s");
+            result.writes_per_sec = number_before(line, "writes/s");
+            result.fsyncs_per_sec = number_before(line, "fsyncs/s");
+        }
+    }
+    result
+}
+
+fn parse_insert_buffer(body: &[&str]) -> InnodbInsertBuffer {
+    let mut result = InnodbInsertBuffer::default();
+    for line in body {
+        if line.starts_with("Ibuf: size") {
+            result.size = number_after(line, "size");
+            result.free_list_len = number_after(line, "free list len");
+            result.seg_size = number_after(line, "seg size");
+            result.merges = number_before(line, "merges").map(|it| it as u64);
+        } else if line.contains("hash searches/s") {
+            result.hash_searches_per_sec = number_before(line, " hash searches/s");
+            result.non_hash_searches_per_sec = number_before(line, "non-hash searches/s");
+        }
+    }
+    result
+}
+
+fn parse_log(body: &[&str]) -> InnodbLog {
+    let mut result = InnodbLog::default();
+    for line in body {
+        if line.starts_with("Log sequence number") {
+            result.log_sequence_number = number_after(line, "Log sequence number");
+        } else if line.starts_with("Log flushed up to") {
+            result.log_flushed_up_to = number_after(line, "Log flushed up to");
+        } else if line.starts_with("Pages flushed up to") {
+            result.pages_flushed_up_to = number_after(line, "Pages flushed up to");
+        } else if line.starts_with("Last checkpoint at") {
+            result.last_checkpoint_at = number_after(line, "Last checkpoint at");
+        }
+    }
+    if let (Some(lsn), Some(checkpoint)) = (result.log_sequence_number, result.last_checkpoint_at) {
+        result.checkpoint_age = Some(lsn.saturating_sub(checkpoint));
+    }
+    result
+}
+
+fn parse_buffer_pool(body: &[&str]) -> InnodbBufferPool {
+    let mut result = InnodbBufferPool::default();
+    for line in body {
+        // Per-instance sections repeat the totals; keep the first (global) values.
+        if line.starts_with("---BUFFER POOL") {
+            break;
+        }
+        if line.starts_with("Total large memory allocated") {
+            result.total_large_memory_allocated = number_after(line, "allocated");
+        } else if line.starts_with("Buffer pool size") {
+            result.pool_size_pages = number_after(line, "Buffer pool size");
+        } else if line.starts_with("Free buffers") {
+            result.free_buffers = number_after(line, "Free buffers");
+        } else if line.starts_with("Database pages") {
+            result.database_pages = number_after(line, "Database pages");
+        } else if line.starts_with("Modified db pages") {
+            result.modified_db_pages = number_after(line, "Modified db pages");
+        } else if line.starts_with("Pending reads") {
+            result.pending_reads = number_after(line, "Pending reads");
+        } else if line.starts_with("Pending writes:") {
+            result.pending_writes_lru = number_after(line, "LRU");
+            result.pending_writes_flush_list = number_after(line, "flush list");
+        } else if line.starts_with("Pages read ") {
+            result.pages_read = number_after(line, "Pages read");
+            result.pages_created = number_after(line, "created");
+            result.pages_written = number_after(line, "written");
+        } else if line.starts_with("Buffer pool hit rate") {
+            result.hit_rate_per_mille = number_after(line, "hit rate");
+        }
+    }
+    result
+}
+
+fn parse_row_operations(body: &[&str]) -> InnodbRowOperations {
+    let mut result = InnodbRowOperations::default();
+    let mut rates_seen = false;
+    for line in body {
+        if line.contains("queries inside InnoDB") {
+            result.queries_inside = number_before(line, "queries inside").map(|it| it as u64);
+            result.queries_in_queue = number_before(line, "queries in queue").map(|it| it as u64);
+        } else if line.contains("read views open") {
+            result.read_views_open = number_before(line, "read views open").map(|it| it as u64);
+        } else if line.starts_with("Number of rows inserted") {
+            result.rows_inserted = number_after(line, "inserted");
+            result.rows_updated = number_after(line, "updated");
+            result.rows_deleted = number_after(line, "deleted");
+            result.rows_read = number_after(line, "read");
+        } else if line.contains("inserts/s") && !rates_seen {
+            // The second rate line belongs to system rows.
+            rates_seen = true;
+            result.inserts_per_sec = number_before(line, "inserts/s");
+            result.updates_per_sec = number_before(line, "updates/s");
+            result.deletes_per_sec = number_before(line, "deletes/s");
+            result.reads_per_sec = number_before(line, "reads/s");
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_status() -> String {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/logs/innodb_status.txt");
+        std::fs::read_to_string(path).expect("failed reading innodb status fixture")
+    }
+
+    #[test]
+    fn should_parse_innodb_status_sections() {
+        let status = parse_innodb_status(&read_status());
+        assert_eq!(status.captured_at.as_deref(), Some("2026-02-07 12:00:05"));
+
+        assert_eq!(status.semaphores.reservation_count, Some(412));
+        assert_eq!(status.semaphores.signal_count, Some(390));
+        assert_eq!(status.semaphores.wait_count, 1);
+        assert_eq!(status.semaphores.waits[0].location, "btr0sea.ic line 90");
+        assert_eq!(status.semaphores.waits[0].waited_secs, 241.0);
+
+        assert!(
+            status.latest_foreign_key_error.as_ref().expect("fk error")[1]
+                .starts_with("TRANSACTION 1790")
+        );
+        let deadlock = status.latest_deadlock.as_ref().expect("deadlock");
+        assert_eq!(deadlock.victim.as_deref(), Some("2"));
+
+        let trx = &status.transactions;
+        assert_eq!(trx.trx_id_counter, Some(1860));
+        assert_eq!(trx.history_list_length, Some(1204));
+        assert_eq!(trx.active_count, 2);
+        assert_eq!(trx.lock_wait_count, 1);
+        assert_eq!(trx.max_active_secs, Some(42));
+        assert_eq!(trx.active[0].trx_id, "1858");
+        assert_eq!(trx.active[0].undo_log_entries, Some(1));
+        assert_eq!(trx.active[0].statement, None);
+        assert_eq!(trx.active[1].lock_wait_secs, Some(3));
+        assert_eq!(trx.active[1].row_locks, Some(1));
+        assert_eq!(
+            trx.active[1].statement.as_deref(),
+            Some("UPDATE accounts SET balance = 0 WHERE id = 3")
+        );
+
+        assert_eq!(status.file_io.pending_aio_reads, Some(0));
+        assert_eq!(status.file_io.pending_log_fsyncs, Some(1));
+        assert_eq!(status.file_io.os_file_writes, Some(3400));
+        assert_eq!(status.file_io.writes_per_sec, Some(5.2));
+        assert_eq!(status.insert_buffer.seg_size, Some(2));
+
+        assert_eq!(status.log.log_sequence_number, Some(45_000_000));
+        assert_eq!(status.log.checkpoint_age, Some(3_000_000));
+
+        assert_eq!(status.buffer_pool.pool_size_pages, Some(8192));
+        assert_eq!(status.buffer_pool.modified_db_pages, Some(320));
+        assert_eq!(status.buffer_pool.pending_reads, Some(2));
+        assert_eq!(status.buffer_pool.pending_writes_flush_list, Some(4));
+        assert_eq!(status.buffer_pool.hit_rate_per_mille, Some(998));
+
+        assert_eq!(status.row_operations.rows_read, Some(50_000));
+        assert_eq!(status.row_operations.reads_per_sec, Some(40.0));
+    }
+
+    #[test]
+    fn should_tolerate_truncated_status() {
+        let status = parse_innodb_status(
+            "----------\nSEMAPHORES\n----------\nOS WAIT ARRAY INFO: reservation count 7\n",
+        );
+        assert_eq!(status.semaphores.reservation_count, Some(7));
+        assert!(status.latest_deadlock.is_none());
+        assert_eq!(status.log.checkpoint_age, None);
+    }
+}
@@ -0,0 +1,299 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use mysql::Pool;
+use serde::Serialize;
+
+use crate::collection::CollectionPolicy;
+use crate::innodb_status::{InnodbStatus, fetch_innodb_status_text, parse_innodb_status};
+use crate::secrets::DatabaseUrl;
+
+/// Hard caps applied regardless of configuration; `SHOW ENGINE INNODB STATUS`
+/// takes the lock/trx system mutexes, so sampling must stay short.
+pub const HF_MIN_INTERVAL_MS: u64 = 100;
+pub const HF_MAX_DURATION_MS: u64 = 10_000;
+pub const HF_MAX_SAMPLES: usize = 100;
+
+#[derive(Debug, Clone)]
+pub struct InnodbStatusHfConfig {
+    pub mysql_url: Option<DatabaseUrl>,
+    pub interval_ms: u64,
+    pub duration_ms: u64,
+    pub max_samples: usize,
+}
+
+impl Default for InnodbStatusHfConfig {
+    fn default() -> Self {
+        Self {
+            mysql_url: None,
+            interval_ms: 250,
+            duration_ms: 5_000,
+            max_samples: 20,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct InnodbStatusHfReport {
+    pub enabled: bool,
+    pub skipped_reason: Option<String>,
+    pub interval_ms: u64,
+    pub duration_ms: u64,
+    pub max_samples: usize,
+    pub elapsed_ms: u64,
+    pub samples: Vec<InnodbStatusSample>,
+    pub summary: Vec<InnodbMetricSummary>,
+    pub warnings: Vec<String>,
+}
+
+/// One point of the time series; `offset_ms` is relative to the first sample.
+#[derive(Debug, Clone, Serialize, Default, PartialEq)]
+pub struct InnodbStatusSample {
+    pub offset_ms: u64,
+    pub checkpoint_age: Option<u64>,
+    pub history_list_length: Option<u64>,
+    pub pending_reads: Option<u64>,
+    pub pending_writes: Option<u64>,
+    pub semaphore_waits: u64,
+    pub active_transactions: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Default, PartialEq)]
+pub struct InnodbMetricSummary {
+    pub metric: String,
+    pub first: Option<u64>,
+    pub last: Option<u64>,
+    pub min: Option<u64>,
+    pub max: Option<u64>,
+}
+
+impl InnodbStatusSample {
+    pub fn from_status(offset_ms: u64, status: &InnodbStatus) -> Self {
+        let pool = &status.buffer_pool;
+        let pending_writes = match (pool.pending_writes_lru, pool.pending_writes_flush_list) {
+            (None, None) => None,
+            (lru, flush_list) => Some(lru.unwrap_or(0) + flush_list.unwrap_or(0)),
+        };
+        Self {
+            offset_ms,
+            checkpoint_age: status.log.checkpoint_age,
+            history_list_length: status.transactions.history_list_length,
+            pending_reads: pool.pending_reads,
+            pending_writes,
+            semaphore_waits: status.semaphores.wait_count as u64,
+            active_transactions: status.transactions.active_count as u64,
+        }
+    }
+}
+
+/// Runs the Level 3 `innodb_status_hf` task. Nothing is sampled unless the
+/// policy enables expert mode.
+pub fn collect_innodb_status_hf(
+    policy: &CollectionPolicy,
+    config: &InnodbStatusHfConfig,
+) -> InnodbStatusHfReport {
+    let mut report = capped_report(config);
+    if !policy.expert_mode_enabled {
+        report.skipped_reason = Some("expert mode is disabled by policy".to_string());
+        return report;
+    }
+    let Some(url) = config.mysql_url.as_ref() else {
+        report.skipped_reason = Some("MYSQL_URL is not set".to_string());
+        return report;
+    };
+    let opts = match url.mysql_opts() {
+        Ok(opts) => opts,
+        Err(err) => {
+            report.skipped_reason = Some(format!("invalid MySQL URL: {err}"));
+            return report;
+        }
+    };
+    let mut conn = match Pool::new(opts).and_then(|pool| pool.get_conn()) {
+        Ok(conn) => conn,
+        Err(err) => {
+            report.skipped_reason = Some(format!("failed to connect MySQL: {err}"));
+            return report;
+        }
+    };
+
+    report.enabled = true;
+    sample_innodb_status(&mut report, || {
+        fetch_innodb_status_text(&mut conn).map_err(|it| it.to_string())
+    });
+    report
+}
+
+/// Polls `fetch` every `interval_ms` until either cap is reached. A failed or
+/// empty fetch ends the window early.
+pub fn sample_innodb_status<F>(report: &mut InnodbStatusHfReport, mut fetch: F)
+where
+    F: FnMut() -> Result<Option<String>, String>,
+{
+    let interval = Duration::from_millis(report.interval_ms);
+    let deadline = Duration::from_millis(report.duration_ms);
+    let started = Instant::now();
+
+    while report.samples.len() < report.max_samples {
+        let tick = Instant::now();
+        let offset_ms = started.elapsed().as_millis() as u64;
+        match fetch() {
+            Ok(Some(text)) => {
+                let status = parse_innodb_status(&text);
+                report
+                    .samples
+                    .push(InnodbStatusSample::from_status(offset_ms, &status));
+            }
+            Ok(None) => {
+                report
+                    .warnings
+                    .push("SHOW ENGINE INNODB STATUS returned no rows".to_string());
+                break;
+            }
+            Err(err) => {
+                report
+                    .warnings
+                    .push(format!("SHOW ENGINE INNODB STATUS sampling stopped: {err}"));
+                break;
+            }
+        }
+        if started.elapsed() + interval.saturating_sub(tick.elapsed()) >= deadline {
+            break;
+        }
+        thread::sleep(interval.saturating_sub(tick.elapsed()));
+    }
+
+    report.elapsed_ms = started.elapsed().as_millis() as u64;
+    report.summary = summarize_samples(&report.samples);
+}
+
+fn capped_report(config: &InnodbStatusHfConfig) -> InnodbStatusHfReport {
+    let mut report = InnodbStatusHfReport {
+        interval_ms: config.interval_ms.max(HF_MIN_INTERVAL_MS),
+        duration_ms: config.duration_ms.min(HF_MAX_DURATION_MS),
+        max_samples: config.max_samples.clamp(1, HF_MAX_SAMPLES),
+        ..InnodbStatusHfReport::default()
+    };
+    if report.interval_ms != config.interval_ms {
+        report.warnings.push(format!(
+            "interval_ms raised from {} to {}",
+            config.interval_ms, report.interval_ms
+        ));
+    }
+    if report.duration_ms != config.duration_ms {
+        report.warnings.push(format!(
+            "duration_ms capped from {} to {}",
+            config.duration_ms, report.duration_ms
+        ));
+    }
+    if report.max_samples != config.max_samples {
+        report.warnings.push(format!(
+            "max_samples capped from {} to {}",
+            config.max_samples, report.max_samples
+        ));
+    }
+    report
+}
+
+type MetricAccessor = fn(&InnodbStatusSample) -> Option<u64>;
+
+fn summarize_samples(samples: &[InnodbStatusSample]) -> Vec<InnodbMetricSummary> {
+    let metrics: [(&str, MetricAccessor); 6] = [
+        ("checkpoint_age", |it| it.checkpoint_age),
+        ("history_list_length", |it| it.history_list_length),
+        ("pending_reads", |it| it.pending_reads),
+        ("pending_writes", |it| it.pending_writes),
+        ("semaphore_waits", |it| Some(it.semaphore_waits)),
+        ("active_transactions", |it| Some(it.active_transactions)),
+    ];
+    if samples.is_empty() {
+        return Vec::new();
+    }
+    metrics
+        .iter()
+        .map(|(metric, value)| {
+            let values = samples.iter().filter_map(value).collect::<Vec<_>>();
+            InnodbMetricSummary {
+                metric: metric.to_string(),
+                first: values.first().copied(),
+                last: values.last().copied(),
+                min: values.iter().min().copied(),
+                max: values.iter().max().copied(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_status() -> String {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/logs/innodb_status.txt");
+        std::fs::read_to_string(path).expect("failed reading innodb status fixture")
+    }
+
+    #[test]
+    fn should_skip_sampling_without_expert_mode() {
+        let config = InnodbStatusHfConfig {
+            mysql_url: Some(DatabaseUrl::new("mysql://root@127.0.0.1:1/none")),
+            ..InnodbStatusHfConfig::default()
+        };
+        let report = collect_innodb_status_hf(&CollectionPolicy::default(), &config);
+        assert!(!report.enabled);
+        assert_eq!(
+            report.skipped_reason.as_deref(),
+            Some("expert mode is disabled by policy")
+        );
+        assert!(report.samples.is_empty());
+    }
+
+    #[test]
+    fn should_cap_samples_and_build_series() {
+        let text = read_status();
+        let mut report = capped_report(&InnodbStatusHfConfig {
+            interval_ms: 0,
+            duration_ms: 60_000,
+            max_samples: 3,
+            ..InnodbStatusHfConfig::default()
+        });
+        assert_eq!(report.interval_ms, HF_MIN_INTERVAL_MS);
+        assert_eq!(report.duration_ms, HF_MAX_DURATION_MS);
+        assert_eq!(report.warnings.len(), 2);
+
+        let mut calls = 0;
+        sample_innodb_status(&mut report, || {
+            calls += 1;
+            Ok(Some(text.replace(
+                "History list length 1204",
+                &format!("History list length {}", 1200 + calls * 10),
+            )))
+        });
+
+        assert_eq!(calls, 3);
+        assert_eq!(report.samples.len(), 3);
+        assert!(report.samples[2].offset_ms >= 2 * HF_MIN_INTERVAL_MS);
+        assert_eq!(report.samples[0].checkpoint_age, Some(3_000_000));
+        assert_eq!(report.samples[0].pending_reads, Some(2));
+        assert_eq!(report.samples[0].pending_writes, Some(4));
+        assert_eq!(report.samples[0].semaphore_waits, 1);
+        assert_eq!(report.samples[0].active_transactions, 2);
+
+        let history = &report.summary[1];
+        assert_eq!(history.metric, "history_list_length");
+        assert_eq!((history.first, history.last), (Some(1210), Some(1230)));
+        assert_eq!((history.min, history.max), (Some(1210), Some(1230)));
+    }
+
+    #[test]
+    fn should_stop_sampling_on_fetch_error() {
+        let mut report = capped_report(&InnodbStatusHfConfig::default());
+        sample_innodb_status(&mut report, || Err("Access denied".to_string()));
+        assert!(report.samples.is_empty());
+        assert!(report.summary.is_empty());
+        assert_eq!(
+            report.warnings,
+            vec!["SHOW ENGINE INNODB STATUS sampling stopped: Access denied"]
+        );
+    }
+}
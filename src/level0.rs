@@ -1,21 +1,25 @@
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs;
 use std::io;
 use std::process::Command;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use mysql::prelude::Queryable;
-use mysql::{Opts, Pool, PooledConn, Row, Value};
+use mysql::{Pool, PooledConn, Row, Value};
 use serde::Serialize;
 
+use crate::secrets::DatabaseUrl;
+
 const SYSTEM_SCHEMAS: [&str; 4] = ["information_schema", "mysql", "performance_schema", "sys"];
 
 #[derive(Debug, Clone)]
 pub struct Level0CollectorConfig {
-    pub mysql_url: Option<String>,
+    pub mysql_url: Option<DatabaseUrl>,
     pub table_limit: usize,
     pub index_limit: usize,
     pub suppress_mysql_missing_warning: bool,
+    /// Tasks whose sources are not queried, e.g. from `--exclude-tasks`.
+    pub skipped_tasks: BTreeSet<&'static str>,
 }
 
 impl Default for Level0CollectorConfig {
@@ -25,10 +29,24 @@ impl Default for Level0CollectorConfig {
             table_limit: 200,
             index_limit: 500,
             suppress_mysql_missing_warning: false,
+            skipped_tasks: BTreeSet::new(),
         }
     }
 }
 
+impl Level0CollectorConfig {
+    fn runs(&self, task: &str) -> bool {
+        !self.skipped_tasks.contains(task)
+    }
+}
+
+const MYSQL_TASKS: [&str; 4] = [
+    "global_status",
+    "global_variables",
+    "schema_storage",
+    "replication_status",
+];
+
 #[derive(Debug, Clone, Serialize, Default)]
 pub struct Level0Capability {
     pub mysql_connected: bool,
@@ -146,7 +164,9 @@ pub fn collect_level0(config: &Level0CollectorConfig) -> Level0CollectionReport
         ..Level0CollectionReport::default()
     };
 
-    if let Some(mysql_url) = config.mysql_url.as_deref() {
+    if !MYSQL_TASKS.iter().any(|it| config.runs(it)) {
+        // Nothing to ask the server for.
+    } else if let Some(mysql_url) = config.mysql_url.as_ref() {
         let mysql_result = collect_mysql(mysql_url, config);
         report.capability.mysql_connected = mysql_result.connected;
         report.capability.mysql_status_access = mysql_result.status_ok;
@@ -161,10 +181,12 @@ pub fn collect_level0(config: &Level0CollectorConfig) -> Level0CollectionReport
             .push("MYSQL_URL not provided; skip MySQL Level 0 collection".to_string());
     }
 
-    let os_result = collect_os();
-    report.capability.os_metrics_access = os_result.snapshot.has_any_metric();
-    report.os = os_result.snapshot;
-    report.warnings.extend(os_result.warnings);
+    if config.runs("os_basic_metrics") {
+        let os_result = collect_os();
+        report.capability.os_metrics_access = os_result.snapshot.has_any_metric();
+        report.os = os_result.snapshot;
+        report.warnings.extend(os_result.warnings);
+    }
 
     report
 }
@@ -180,9 +202,9 @@ struct MysqlCollectResult {
     warnings: Vec<String>,
 }
 
-fn collect_mysql(mysql_url: &str, config: &Level0CollectorConfig) -> MysqlCollectResult {
+fn collect_mysql(mysql_url: &DatabaseUrl, config: &Level0CollectorConfig) -> MysqlCollectResult {
     let mut result = MysqlCollectResult::default();
-    let opts = match Opts::from_url(mysql_url) {
+    let opts = match mysql_url.mysql_opts() {
         Ok(it) => it,
         Err(err) => {
             result
@@ -213,28 +235,46 @@ fn collect_mysql(mysql_url: &str, config: &Level0CollectorConfig) -> MysqlCollec
     };
     result.connected = true;
 
-    match fetch_show_pairs(&mut conn, "SHOW GLOBAL STATUS") {
-        Ok(map) => {
-            result.status_ok = true;
-            result.snapshot.global_status = map;
+    if config.runs("global_status") {
+        match fetch_show_pairs(&mut conn, "SHOW GLOBAL STATUS") {
+            Ok(map) => {
+                result.status_ok = true;
+                result.snapshot.global_status = map;
+            }
+            Err(err) => result
+                .warnings
+                .push(format!("SHOW GLOBAL STATUS failed: {err}")),
         }
-        Err(err) => result
-            .warnings
-            .push(format!("SHOW GLOBAL STATUS failed: {err}")),
     }
 
-    match fetch_show_pairs(&mut conn, "SHOW VARIABLES") {
-        Ok(map) => {
-            result.variables_ok = true;
-            result.snapshot.global_variables = map;
+    if config.runs("global_variables") {
+        match fetch_show_pairs(&mut conn, "SHOW VARIABLES") {
+            Ok(map) => {
+                result.variables_ok = true;
+                result.snapshot.global_variables = map;
+            }
+            Err(err) => result
+                .warnings
+                .push(format!("SHOW VARIABLES failed: {err}")),
         }
-        Err(err) => result
-            .warnings
-            .push(format!("SHOW VARIABLES failed: {err}")),
     }
 
-    let table_sizes_result = fetch_table_sizes(&mut conn, config.table_limit);
-    let index_result = fetch_indexes(&mut conn, config.index_limit);
+    if config.runs("schema_storage") {
+        collect_mysql_schema_storage(&mut conn, config, &mut result);
+    }
+    if config.runs("replication_status") {
+        collect_mysql_replication_status(&mut conn, &mut result);
+    }
+    result
+}
+
+fn collect_mysql_schema_storage(
+    conn: &mut PooledConn,
+    config: &Level0CollectorConfig,
+    result: &mut MysqlCollectResult,
+) {
+    let table_sizes_result = fetch_table_sizes(conn, config.table_limit);
+    let index_result = fetch_indexes(conn, config.index_limit);
     let mut table_query_ok = false;
     let mut index_query_ok = false;
     if let Ok(rows) = table_sizes_result {
@@ -254,14 +294,16 @@ fn collect_mysql(mysql_url: &str, config: &Level0CollectorConfig) -> MysqlCollec
             .push(format!("information_schema STATISTICS query failed: {err}"));
     }
     result.info_schema_ok = table_query_ok && index_query_ok;
+}
 
-    match fetch_optional_row_map(&mut conn, "SHOW REPLICA STATUS") {
+fn collect_mysql_replication_status(conn: &mut PooledConn, result: &mut MysqlCollectResult) {
+    match fetch_optional_row_map(conn, "SHOW REPLICA STATUS") {
         Ok(replica_status) => {
             result.replication_ok = true;
             result.snapshot.replication_status = replica_status;
             result.snapshot.replication_status_source = Some("SHOW REPLICA STATUS".to_string());
         }
-        Err(replica_err) => match fetch_optional_row_map(&mut conn, "SHOW SLAVE STATUS") {
+        Err(replica_err) => match fetch_optional_row_map(conn, "SHOW SLAVE STATUS") {
             Ok(slave_status) => {
                 result.replication_ok = true;
                 result.snapshot.replication_status = slave_status;
@@ -272,8 +314,6 @@ fn collect_mysql(mysql_url: &str, config: &Level0CollectorConfig) -> MysqlCollec
             )),
         },
     }
-
-    result
 }
 
 fn fetch_show_pairs(
@@ -437,12 +477,11 @@ fn collect_os() -> OsCollectResult {
 }
 
 fn append_command_warning(warnings: &mut Vec<String>, sample: Option<&CommandSample>) {
-    if let Some(sample) = sample {
-        if let Some(err) = &sample.error {
-            if sample.available {
-                warnings.push(format!("{} failed: {}", sample.command, err));
-            }
-        }
+    if let Some(sample) = sample
+        && let Some(err) = &sample.error
+        && sample.available
+    {
+        warnings.push(format!("{} failed: {}", sample.command, err));
     }
 }
 
@@ -542,12 +581,11 @@ fn parse_proc_cpu_stat(content: &str) -> Option<ProcCpuStat> {
 fn parse_proc_meminfo(content: &str) -> Option<ProcMemInfo> {
     let mut values: HashMap<String, u64> = HashMap::new();
     for line in content.lines() {
-        if let Some((key, rest)) = line.split_once(':') {
-            if let Some(first_value) = rest.split_whitespace().next() {
-                if let Ok(parsed) = first_value.parse::<u64>() {
-                    values.insert(key.trim().to_string(), parsed);
-                }
-            }
+        if let Some((key, rest)) = line.split_once(':')
+            && let Some(first_value) = rest.split_whitespace().next()
+            && let Ok(parsed) = first_value.parse::<u64>()
+        {
+            values.insert(key.trim().to_string(), parsed);
         }
     }
 
@@ -596,6 +634,21 @@ fn now_unix_ms() -> u128 {
 mod tests {
     use super::*;
 
+    #[test]
+    fn should_not_connect_when_every_mysql_task_is_skipped() {
+        let report = collect_level0(&Level0CollectorConfig {
+            mysql_url: Some(DatabaseUrl::new("mysql://monitor@127.0.0.1:1/app")),
+            skipped_tasks: MYSQL_TASKS
+                .into_iter()
+                .chain(["os_basic_metrics"])
+                .collect(),
+            ..Level0CollectorConfig::default()
+        });
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+        assert!(!report.capability.mysql_connected);
+        assert!(!report.capability.os_metrics_access);
+    }
+
     #[test]
     fn should_parse_proc_cpu_stat() {
         let fixture = "cpu  101 202 303 404 11 12 13 14 0 0\ncpu0 1 2 3 4 5 6 7 8 9 10";
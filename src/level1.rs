@@ -1,15 +1,32 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
 use std::fs::{self, File};
-use std::io::{self, Read, Seek, SeekFrom};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use mysql::prelude::Queryable;
-use mysql::{Opts, Pool, PooledConn};
+use mysql::{Pool, PooledConn};
 use postgres::{Client, NoTls};
-use serde::Serialize;
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+use serde::{Deserialize, Serialize};
+
+use crate::deadlocks::{
+    DeadlockReport, INNODB_ERROR_LOG_SOURCE, parse_innodb_deadlocks, parse_postgres_deadlocks,
+};
+use crate::error_log::{ErrorAlertRules, ErrorSeverity, parse_error_line};
+use crate::fingerprint::{
+    Fingerprinter, SqlDialect, StatementKind, classify_statement, fingerprinter_for,
+    referenced_tables,
+};
+use crate::innodb_status::{InnodbStatus, fetch_innodb_status_text, parse_innodb_status};
+use crate::query_tags::extract_query_tags;
+use crate::secrets::DatabaseUrl;
+use crate::transactions::{
+    SessionStatement, TransactionBoundary, TransactionReport, TransactionThresholds,
+    TransactionTracker, transaction_boundary,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Level1Engine {
     Mysql,
     Postgres,
@@ -18,17 +35,26 @@ pub enum Level1Engine {
 #[derive(Debug, Clone)]
 pub struct Level1CollectorConfig {
     pub engine: Level1Engine,
-    pub mysql_url: Option<String>,
-    pub postgres_url: Option<String>,
+    pub mysql_url: Option<DatabaseUrl>,
+    pub postgres_url: Option<DatabaseUrl>,
     pub slow_log_window_secs: u64,
     pub slow_log_long_query_time_secs: f64,
     pub enable_slow_log_hot_switch: bool,
     pub restore_slow_log_settings: bool,
     pub slow_log_path: Option<String>,
     pub error_log_path: Option<String>,
+    pub slow_log_archive: Option<String>,
     pub max_slow_log_bytes: usize,
     pub max_error_log_bytes: usize,
     pub max_error_log_lines: usize,
+    pub long_transaction_secs: f64,
+    pub idle_in_transaction_secs: f64,
+    pub error_alert_rules: ErrorAlertRules,
+    /// Tasks whose sources are left alone, e.g. from `--exclude-tasks`:
+    /// `slow_log_window` owns the hot switch and the capture window,
+    /// `slow_log_digest` reading the slow log, `error_log_alerts` the error
+    /// log and InnoDB deadlock status.
+    pub skipped_tasks: BTreeSet<&'static str>,
 }
 
 impl Default for Level1CollectorConfig {
@@ -43,13 +69,32 @@ impl Default for Level1CollectorConfig {
             restore_slow_log_settings: true,
             slow_log_path: None,
             error_log_path: None,
+            slow_log_archive: None,
             max_slow_log_bytes: 2_000_000,
             max_error_log_bytes: 2_000_000,
             max_error_log_lines: 2_000,
+            long_transaction_secs: 1.0,
+            idle_in_transaction_secs: 1.0,
+            error_alert_rules: ErrorAlertRules::builtin(),
+            skipped_tasks: BTreeSet::new(),
         }
     }
 }
 
+impl Level1CollectorConfig {
+    fn runs(&self, task: &str) -> bool {
+        !self.skipped_tasks.contains(task)
+    }
+
+    fn runs_slow_log(&self) -> bool {
+        self.runs("slow_log_window") || self.runs("slow_log_digest")
+    }
+
+    fn hot_switch(&self) -> bool {
+        self.enable_slow_log_hot_switch && self.runs("slow_log_window")
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Default)]
 pub struct Level1Capability {
     pub mysql_connected: bool,
@@ -57,6 +102,7 @@ pub struct Level1Capability {
     pub can_enable_slow_log_hot_switch: bool,
     pub can_read_slow_log: bool,
     pub can_read_error_log: bool,
+    pub can_read_innodb_status: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Default)]
@@ -64,7 +110,10 @@ pub struct Level1CollectionReport {
     pub collected_at_unix_ms: u128,
     pub capability: Level1Capability,
     pub slow_log: SlowLogSnapshot,
+    pub transactions: TransactionReport,
     pub error_log: ErrorLogSnapshot,
+    pub deadlocks: DeadlockReport,
+    pub innodb_status: Option<InnodbStatus>,
     pub warnings: Vec<String>,
 }
 
@@ -78,43 +127,137 @@ pub struct SlowLogSnapshot {
     pub previous_long_query_time: Option<String>,
     pub collected_bytes: usize,
     pub parsed_entries: usize,
+    pub truncated: bool,
+    pub skipped_bytes: u64,
+    pub dropped_entries: usize,
+    pub rotated_during_window: bool,
+    pub source_files: Vec<String>,
+    pub native_digest_source: Option<String>,
     pub digest_count: usize,
     pub digests: Vec<SlowSqlDigest>,
+    pub tag_rollups: Vec<SlowSqlTagRollup>,
+    pub kind_breakdown: Vec<StatementKindSummary>,
+    pub ddl_digests: Vec<DdlDigestHighlight>,
 }
 
 #[derive(Debug, Clone, Serialize, Default)]
 pub struct SlowSqlDigest {
     pub fingerprint: String,
+    pub digest_id: String,
     pub sample_sql: String,
+    pub tables: Vec<String>,
+    /// Default database of the first statement that recorded one; unqualified
+    /// tables are looked up there.
+    pub schema: Option<String>,
+    pub statement_kind: StatementKind,
     pub count: u64,
     pub total_query_time_secs: f64,
     pub avg_query_time_secs: f64,
     pub total_lock_time_secs: f64,
     pub total_rows_sent: u64,
     pub total_rows_examined: u64,
+    pub tags: Vec<DigestTag>,
+    pub trace_samples: Vec<String>,
+    pub native_digest: Option<NativeDigestRef>,
+}
+
+/// One comment tag value seen on a digest's statements.
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct DigestTag {
+    pub key: String,
+    pub value: String,
+    pub count: u64,
+    pub total_query_time_secs: f64,
+}
+
+/// Slow time per statement kind; `time_share_pct` is relative to all digests.
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct StatementKindSummary {
+    pub kind: StatementKind,
+    pub digest_count: usize,
+    pub count: u64,
+    pub total_query_time_secs: f64,
+    pub time_share_pct: f64,
+}
+
+/// DDL seen in the slow log; called out because it holds metadata locks and
+/// replays serially on replicas.
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct DdlDigestHighlight {
+    pub digest_id: String,
+    pub fingerprint: String,
+    pub tables: Vec<String>,
+    pub count: u64,
+    pub total_query_time_secs: f64,
+}
+
+/// Slow SQL cost attributed to a tag value (e.g. `app=checkout`) across digests.
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct SlowSqlTagRollup {
+    pub key: String,
+    pub value: String,
+    pub count: u64,
+    pub total_query_time_secs: f64,
+    pub digest_ids: Vec<String>,
+}
+
+/// Server-side identity of a digest: performance_schema `DIGEST` or
+/// `pg_stat_statements.queryid`.
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct NativeDigestRef {
+    pub source: String,
+    pub native_id: String,
 }
 
 #[derive(Debug, Clone, Serialize, Default)]
 pub struct ErrorLogSnapshot {
     pub error_log_path: Option<String>,
     pub sampled_lines: usize,
+    /// Lines with a recognized severity header; continuation lines are not.
+    pub parsed_lines: usize,
     pub alert_count: usize,
     pub alerts: Vec<ErrorAlert>,
+    pub severity_counts: Vec<ErrorSeverityCount>,
+    pub code_counts: Vec<ErrorCodeCount>,
 }
 
 #[derive(Debug, Clone, Serialize, Default)]
 pub struct ErrorAlert {
     pub category: String,
     pub count: u64,
+    pub codes: Vec<String>,
     pub sample_lines: Vec<String>,
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorSeverityCount {
+    pub severity: ErrorSeverity,
+    pub count: u64,
+}
+
+/// Occurrences of one MySQL `MY-` code or PostgreSQL SQLSTATE.
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorCodeCount {
+    pub code: String,
+    pub severity: ErrorSeverity,
+    pub subsystem: Option<String>,
+    pub count: u64,
+    pub first_seen: Option<String>,
+    pub last_seen: Option<String>,
+    pub sample_line: String,
+}
+
 pub fn collect_level1(config: &Level1CollectorConfig) -> Level1CollectionReport {
     let mut report = Level1CollectionReport {
         collected_at_unix_ms: now_unix_ms(),
         ..Level1CollectionReport::default()
     };
 
+    if let Some(archive) = config.slow_log_archive.as_deref() {
+        collect_archived_level1(archive, config, &mut report);
+        return report;
+    }
+
     match config.engine {
         Level1Engine::Mysql => collect_mysql_level1(config, &mut report),
         Level1Engine::Postgres => collect_postgres_level1(config, &mut report),
@@ -123,15 +266,75 @@ pub fn collect_level1(config: &Level1CollectorConfig) -> Level1CollectionReport
     report
 }
 
+/// Offline analysis of existing (possibly compressed or rotated) logs; no
+/// database connection and no capture window are involved.
+fn collect_archived_level1(
+    archive: &str,
+    config: &Level1CollectorConfig,
+    report: &mut Level1CollectionReport,
+) {
+    report.slow_log.long_query_time_secs = config.slow_log_long_query_time_secs;
+    report.slow_log.slow_log_path = Some(archive.to_string());
+    let format = match config.engine {
+        Level1Engine::Mysql => SlowLogFormat::MysqlSlowLog,
+        Level1Engine::Postgres => SlowLogFormat::PostgresStatementLog,
+    };
+
+    if config.runs("slow_log_digest") {
+        // `--slow-log-path` names the log to pick from an archive directory.
+        let base = config
+            .slow_log_path
+            .as_deref()
+            .and_then(|it| Path::new(it).file_name()?.to_str());
+        match read_slow_log_archive(
+            Path::new(archive),
+            base,
+            format,
+            config.max_slow_log_bytes,
+            transaction_thresholds(config),
+        ) {
+            Ok(summary) => {
+                report.capability.can_read_slow_log = true;
+                apply_slow_log_summary(report, summary, config.max_slow_log_bytes);
+            }
+            Err(err) => report
+                .warnings
+                .push(format!("failed reading slow log archive {archive}: {err}")),
+        }
+    }
+
+    let Some(path) = config
+        .error_log_path
+        .clone()
+        .filter(|_| config.runs("error_log_alerts"))
+    else {
+        return;
+    };
+    report.error_log.error_log_path = Some(path.clone());
+    match read_file_tail_lines(
+        &path,
+        config.max_error_log_bytes,
+        config.max_error_log_lines,
+    ) {
+        Ok(lines) => {
+            report.capability.can_read_error_log = true;
+            apply_error_log_lines(report, config, &lines);
+        }
+        Err(err) => report
+            .warnings
+            .push(format!("failed reading error log archive {path}: {err}")),
+    }
+}
+
 fn collect_mysql_level1(config: &Level1CollectorConfig, report: &mut Level1CollectionReport) {
-    let Some(mysql_url) = config.mysql_url.as_deref() else {
+    let Some(mysql_url) = config.mysql_url.as_ref() else {
         report
             .warnings
             .push("MYSQL_URL not provided; skip Level 1 collection".to_string());
         return;
     };
 
-    let opts = match Opts::from_url(mysql_url) {
+    let opts = match mysql_url.mysql_opts() {
         Ok(it) => it,
         Err(err) => {
             report
@@ -162,19 +365,47 @@ fn collect_mysql_level1(config: &Level1CollectorConfig, report: &mut Level1Colle
     };
     report.capability.mysql_connected = true;
 
-    collect_mysql_slow_log(&mut conn, config, report);
-    collect_mysql_error_log(&mut conn, config, report);
+    if config.runs_slow_log() {
+        collect_mysql_slow_log(&mut conn, config, report);
+    }
+    if config.runs("error_log_alerts") {
+        collect_mysql_error_log(&mut conn, config, report);
+        collect_mysql_innodb_status(&mut conn, report);
+    }
+}
+
+/// `LATEST DETECTED DEADLOCK` still shows the last deadlock when
+/// `innodb_print_all_deadlocks` is off. Needs the `PROCESS` privilege.
+fn collect_mysql_innodb_status(conn: &mut PooledConn, report: &mut Level1CollectionReport) {
+    let status = match fetch_innodb_status_text(conn) {
+        Ok(Some(status)) => parse_innodb_status(&status),
+        Ok(None) => return,
+        Err(err) => {
+            report
+                .warnings
+                .push(format!("SHOW ENGINE INNODB STATUS unavailable: {err}"));
+            return;
+        }
+    };
+    report.capability.can_read_innodb_status = true;
+    report
+        .deadlocks
+        .add(status.latest_deadlock.iter().cloned().collect());
+    report.innodb_status = Some(status);
 }
 
 fn collect_postgres_level1(config: &Level1CollectorConfig, report: &mut Level1CollectionReport) {
-    let Some(postgres_url) = config.postgres_url.as_deref() else {
+    let Some(postgres_url) = config.postgres_url.as_ref() else {
         report
             .warnings
             .push("POSTGRES_URL not provided; skip Level 1 collection".to_string());
         return;
     };
 
-    let mut client = match Client::connect(postgres_url, NoTls) {
+    let mut client = match postgres_url
+        .postgres_config()
+        .and_then(|it| it.connect(NoTls))
+    {
         Ok(it) => it,
         Err(err) => {
             report
@@ -185,8 +416,12 @@ fn collect_postgres_level1(config: &Level1CollectorConfig, report: &mut Level1Co
     };
     report.capability.postgres_connected = true;
 
-    collect_postgres_statement_log(&mut client, config, report);
-    collect_postgres_error_log(&mut client, config, report);
+    if config.runs_slow_log() {
+        collect_postgres_statement_log(&mut client, config, report);
+    }
+    if config.runs("error_log_alerts") {
+        collect_postgres_error_log(&mut client, config, report);
+    }
 }
 
 fn collect_mysql_slow_log(
@@ -224,9 +459,9 @@ fn collect_mysql_slow_log(
         return;
     };
 
-    let initial_offset = file_len(&slow_log_path).unwrap_or(0);
+    let cursor = LogCursor::open(&slow_log_path);
 
-    if config.enable_slow_log_hot_switch {
+    if config.hot_switch() {
         let set_long = conn.query_drop(format!(
             "SET GLOBAL long_query_time = {:.6}",
             config.slow_log_long_query_time_secs
@@ -249,25 +484,56 @@ fn collect_mysql_slow_log(
         }
     }
 
-    if config.slow_log_window_secs > 0 {
+    if config.slow_log_window_secs > 0 && config.runs("slow_log_window") {
         std::thread::sleep(Duration::from_secs(config.slow_log_window_secs));
     }
 
-    match read_file_appended_segment(&slow_log_path, initial_offset, config.max_slow_log_bytes) {
-        Ok(segment) => {
-            report.capability.can_read_slow_log = true;
-            report.slow_log.collected_bytes = segment.len();
-            let parsed_entries = parse_mysql_slow_log_entries(&segment);
-            report.slow_log.parsed_entries = parsed_entries.len();
-            report.slow_log.digests = aggregate_slow_sql_digests(parsed_entries);
-            report.slow_log.digest_count = report.slow_log.digests.len();
+    if config.runs("slow_log_digest") {
+        match read_slow_log_window(
+            &cursor,
+            Path::new(&slow_log_path),
+            config.max_slow_log_bytes,
+            SlowLogFormat::MysqlSlowLog,
+            transaction_thresholds(config),
+        ) {
+            Ok(summary) => {
+                report.capability.can_read_slow_log = true;
+                apply_slow_log_summary(report, summary, config.max_slow_log_bytes);
+                if let Ok(natives) = fetch_mysql_native_digests(conn) {
+                    attach_native_digests(
+                        &mut report.slow_log,
+                        SqlDialect::Mysql,
+                        MYSQL_NATIVE_DIGEST_SOURCE,
+                        natives,
+                    );
+                }
+                // Above threshold 0 the slow log rarely contains BEGIN/COMMIT.
+                if report.transactions.reconstructed == 0
+                    && let Ok(history) =
+                        fetch_mysql_transaction_history(conn, config.slow_log_window_secs)
+                    && !history.is_empty()
+                {
+                    report.transactions = reconstruct_mysql_transaction_history(
+                        history,
+                        transaction_thresholds(config),
+                    );
+                } else if report.transactions.reconstructed > 0
+                    && let Some(threshold) = effective_long_query_time(&report.slow_log)
+                    && threshold > 0.0
+                {
+                    report.warnings.push(format!(
+                        "transactions reconstructed from the slow log are partial: \
+                         statements faster than long_query_time={threshold} are missing"
+                    ));
+                }
+            }
+            Err(err) => report.warnings.push(format!(
+                "failed reading slow log file {slow_log_path}: {err}"
+            )),
         }
-        Err(err) => report.warnings.push(format!(
-            "failed reading slow log file {slow_log_path}: {err}"
-        )),
     }
 
-    if config.enable_slow_log_hot_switch && config.restore_slow_log_settings {
+    if config.hot_switch() && config.restore_slow_log_settings {
         restore_mysql_slow_log_settings(conn, report);
     }
 }
@@ -302,9 +568,9 @@ fn collect_postgres_statement_log(
         return;
     };
 
-    let initial_offset = file_len(&slow_log_path).unwrap_or(0);
+    let cursor = LogCursor::open(&slow_log_path);
 
-    if config.enable_slow_log_hot_switch {
+    if config.hot_switch() {
         let threshold_ms = (config.slow_log_long_query_time_secs * 1000.0)
             .max(1.0)
             .round() as u64;
@@ -328,25 +594,48 @@ fn collect_postgres_statement_log(
         }
     }
 
-    if config.slow_log_window_secs > 0 {
+    if config.slow_log_window_secs > 0 && config.runs("slow_log_window") {
         std::thread::sleep(Duration::from_secs(config.slow_log_window_secs));
     }
 
-    match read_file_appended_segment(&slow_log_path, initial_offset, config.max_slow_log_bytes) {
-        Ok(segment) => {
-            report.capability.can_read_slow_log = true;
-            report.slow_log.collected_bytes = segment.len();
-            let parsed_entries = parse_postgres_statement_log_entries(&segment);
-            report.slow_log.parsed_entries = parsed_entries.len();
-            report.slow_log.digests = aggregate_slow_sql_digests(parsed_entries);
-            report.slow_log.digest_count = report.slow_log.digests.len();
+    // With a `%`-pattern log_filename the collector may have switched files
+    // during the window, so the current file is discovered again.
+    let current_log_path = if config.slow_log_path.is_some() {
+        slow_log_path.clone()
+    } else {
+        discover_postgres_log_path(client)
+            .ok()
+            .flatten()
+            .unwrap_or_else(|| slow_log_path.clone())
+    };
+
+    if config.runs("slow_log_digest") {
+        match read_slow_log_window(
+            &cursor,
+            Path::new(&current_log_path),
+            config.max_slow_log_bytes,
+            SlowLogFormat::PostgresStatementLog,
+            transaction_thresholds(config),
+        ) {
+            Ok(summary) => {
+                report.capability.can_read_slow_log = true;
+                apply_slow_log_summary(report, summary, config.max_slow_log_bytes);
+                if let Ok(natives) = fetch_postgres_native_digests(client) {
+                    attach_native_digests(
+                        &mut report.slow_log,
+                        SqlDialect::Postgres,
+                        POSTGRES_NATIVE_DIGEST_SOURCE,
+                        natives,
+                    );
+                }
+            }
+            Err(err) => report.warnings.push(format!(
+                "failed reading postgres statement log file {slow_log_path}: {err}"
+            )),
         }
-        Err(err) => report.warnings.push(format!(
-            "failed reading postgres statement log file {slow_log_path}: {err}"
-        )),
     }
 
-    if config.enable_slow_log_hot_switch && config.restore_slow_log_settings {
+    if config.hot_switch() && config.restore_slow_log_settings {
         restore_postgres_statement_settings(client, report);
     }
 }
@@ -375,13 +664,14 @@ fn collect_mysql_error_log(
         return;
     };
 
-    match read_file_tail(&path, config.max_error_log_bytes) {
-        Ok(raw) => {
+    match read_file_tail_lines(
+        &path,
+        config.max_error_log_bytes,
+        config.max_error_log_lines,
+    ) {
+        Ok(lines) => {
             report.capability.can_read_error_log = true;
-            let lines = take_last_lines(&raw, config.max_error_log_lines);
-            report.error_log.sampled_lines = lines.len();
-            report.error_log.alerts = extract_error_log_alerts(&lines);
-            report.error_log.alert_count = report.error_log.alerts.len();
+            apply_error_log_lines(report, config, &lines);
         }
         Err(err) => report
             .warnings
@@ -409,13 +699,14 @@ fn collect_postgres_error_log(
         return;
     };
 
-    match read_file_tail(&path, config.max_error_log_bytes) {
-        Ok(raw) => {
+    match read_file_tail_lines(
+        &path,
+        config.max_error_log_bytes,
+        config.max_error_log_lines,
+    ) {
+        Ok(lines) => {
             report.capability.can_read_error_log = true;
-            let lines = take_last_lines(&raw, config.max_error_log_lines);
-            report.error_log.sampled_lines = lines.len();
-            report.error_log.alerts = extract_error_log_alerts(&lines);
-            report.error_log.alert_count = report.error_log.alerts.len();
+            apply_error_log_lines(report, config, &lines);
         }
         Err(err) => report.warnings.push(format!(
             "failed reading postgres error log file {path}: {err}"
@@ -473,13 +764,187 @@ fn restore_postgres_statement_settings(client: &mut Client, report: &mut Level1C
     }
 }
 
-fn fetch_mysql_variable(conn: &mut PooledConn, name: &str) -> Result<Option<String>, mysql::Error> {
+pub(crate) fn fetch_mysql_variable(
+    conn: &mut PooledConn,
+    name: &str,
+) -> Result<Option<String>, mysql::Error> {
     let sql = format!("SHOW VARIABLES LIKE '{name}'");
     let row: Option<(String, String)> = conn.query_first(sql)?;
     Ok(row.map(|(_, value)| value))
 }
 
-fn fetch_postgres_setting(
+const MYSQL_NATIVE_DIGEST_SOURCE: &str = "performance_schema.events_statements_summary_by_digest";
+const POSTGRES_NATIVE_DIGEST_SOURCE: &str = "pg_stat_statements";
+
+/// Native digests as `(id, normalized text)`. Failures mean the Level 2 source
+/// is unavailable and are not reported; digests then carry no native mapping.
+fn fetch_mysql_native_digests(
+    conn: &mut PooledConn,
+) -> Result<Vec<(String, String)>, mysql::Error> {
+    conn.query(
+        "SELECT DIGEST, DIGEST_TEXT \
+         FROM performance_schema.events_statements_summary_by_digest \
+         WHERE DIGEST IS NOT NULL AND DIGEST_TEXT IS NOT NULL",
+    )
+}
+
+fn fetch_postgres_native_digests(
+    client: &mut Client,
+) -> Result<Vec<(String, String)>, postgres::Error> {
+    let rows = client.query(
+        "SELECT queryid::text, query FROM pg_stat_statements \
+         WHERE queryid IS NOT NULL AND query IS NOT NULL",
+        &[],
+    )?;
+    Ok(rows
+        .into_iter()
+        .map(|it| (it.get::<_, String>(0), it.get::<_, String>(1)))
+        .collect())
+}
+
+const MYSQL_TRANSACTION_HISTORY_SOURCE: &str =
+    "performance_schema.events_transactions_history_long";
+
+/// The `long_query_time` the slow log was written with: ours while the hot
+/// switch is on, otherwise the server's own.
+fn effective_long_query_time(slow_log: &SlowLogSnapshot) -> Option<f64> {
+    if slow_log.enabled_for_window {
+        return Some(slow_log.long_query_time_secs);
+    }
+    slow_log
+        .previous_long_query_time
+        .as_deref()
+        .and_then(|it| it.trim().parse::<f64>().ok())
+}
+
+/// One row per statement (or per empty transaction when no statement matched):
+/// thread, transaction event id and state, then statement timer start, timer
+/// wait, lock time (picoseconds) and digest text.
+type TransactionHistoryRow = (
+    u64,
+    u64,
+    String,
+    u64,
+    u64,
+    Option<u64>,
+    Option<u64>,
+    Option<u64>,
+    Option<String>,
+);
+
+/// Requires the `events_transactions_history_long` and
+/// `events_statements_history_long` consumers; an empty result is normal when
+/// they are disabled. Timers count picoseconds since server start, so only
+/// transactions that ended within the last `window_secs` of `Uptime` (plus one
+/// second for its resolution) are read.
+fn fetch_mysql_transaction_history(
+    conn: &mut PooledConn,
+    window_secs: u64,
+) -> Result<Vec<TransactionHistoryRow>, mysql::Error> {
+    conn.query(format!(
+        "SELECT t.THREAD_ID, t.EVENT_ID, t.STATE, t.TIMER_START, t.TIMER_END, \
+         s.TIMER_START, s.TIMER_WAIT, s.LOCK_TIME, s.DIGEST_TEXT \
+         FROM performance_schema.events_transactions_history_long t \
+         LEFT JOIN performance_schema.events_statements_history_long s \
+           ON s.THREAD_ID = t.THREAD_ID AND s.NESTING_EVENT_ID = t.EVENT_ID \
+          AND s.NESTING_EVENT_TYPE = 'TRANSACTION' \
+         WHERE t.TIMER_START IS NOT NULL AND t.TIMER_END IS NOT NULL \
+           AND t.TIMER_END >= ( \
+             SELECT (CAST(VARIABLE_VALUE AS SIGNED) - {}) * 1000000000000 \
+             FROM performance_schema.global_status WHERE VARIABLE_NAME = 'Uptime') \
+         ORDER BY t.THREAD_ID, t.EVENT_ID, s.EVENT_ID",
+        window_secs + 1
+    ))
+}
+
+/// Replays performance_schema transactions through the same tracker as the
+/// slow log by synthesizing the BEGIN and COMMIT/ROLLBACK boundaries.
+fn reconstruct_mysql_transaction_history(
+    rows: Vec<TransactionHistoryRow>,
+    thresholds: TransactionThresholds,
+) -> TransactionReport {
+    const PICOS_PER_SEC: f64 = 1e12;
+    let fingerprinter = fingerprinter_for(SqlDialect::Mysql);
+    let mut tracker = TransactionTracker::new(thresholds);
+    let mut current: Option<(u64, u64, String, f64)> = None;
+    let marker = |thread_id: u64, at_secs: f64, boundary: TransactionBoundary| SessionStatement {
+        thread_id,
+        start_secs: at_secs,
+        query_time_secs: 0.0,
+        lock_time_secs: 0.0,
+        fingerprint: String::new(),
+        boundary: Some(boundary),
+    };
+    let close = |tracker: &mut TransactionTracker,
+                 (thread_id, _, state, end_secs): (u64, u64, String, f64)| {
+        // Only ended transactions are fetched, so every state is terminal.
+        let kind = if state.eq_ignore_ascii_case("ROLLED BACK") {
+            TransactionBoundary::Rollback
+        } else {
+            TransactionBoundary::Commit
+        };
+        tracker.push(marker(thread_id, end_secs, kind));
+    };
+
+    for (thread_id, event_id, state, start, end, s_start, s_wait, s_lock, digest_text) in rows {
+        if current.as_ref().map(|it| (it.0, it.1)) != Some((thread_id, event_id)) {
+            if let Some(previous) = current.take() {
+                close(&mut tracker, previous);
+            }
+            tracker.push(marker(
+                thread_id,
+                start as f64 / PICOS_PER_SEC,
+                TransactionBoundary::Begin,
+            ));
+            current = Some((thread_id, event_id, state, end as f64 / PICOS_PER_SEC));
+        }
+        if let (Some(s_start), Some(text)) = (s_start, digest_text) {
+            tracker.push(SessionStatement {
+                thread_id,
+                start_secs: s_start as f64 / PICOS_PER_SEC,
+                query_time_secs: s_wait.unwrap_or(0) as f64 / PICOS_PER_SEC,
+                lock_time_secs: s_lock.unwrap_or(0) as f64 / PICOS_PER_SEC,
+                fingerprint: fingerprinter.fingerprint(&text).text,
+                boundary: None,
+            });
+        }
+    }
+    if let Some(previous) = current.take() {
+        close(&mut tracker, previous);
+    }
+    tracker.finish(MYSQL_TRANSACTION_HISTORY_SOURCE)
+}
+
+/// Links digests to native ids by fingerprinting the server's normalized text
+/// and matching on `digest_id`.
+fn attach_native_digests(
+    snapshot: &mut SlowLogSnapshot,
+    dialect: SqlDialect,
+    source: &str,
+    natives: Vec<(String, String)>,
+) {
+    let fingerprinter = fingerprinter_for(dialect);
+    let native_ids = natives
+        .into_iter()
+        .map(|(native_id, text)| (fingerprinter.fingerprint(&text).hash, native_id))
+        .collect::<HashMap<_, _>>();
+
+    let mut matched = false;
+    for digest in &mut snapshot.digests {
+        if let Some(native_id) = native_ids.get(&digest.digest_id) {
+            digest.native_digest = Some(NativeDigestRef {
+                source: source.to_string(),
+                native_id: native_id.clone(),
+            });
+            matched = true;
+        }
+    }
+    if matched {
+        snapshot.native_digest_source = Some(source.to_string());
+    }
+}
+
+pub(crate) fn fetch_postgres_setting(
     client: &mut Client,
     name: &str,
 ) -> Result<Option<String>, postgres::Error> {
@@ -488,7 +953,9 @@ fn fetch_postgres_setting(
     Ok(row.map(|it| it.get::<_, String>(0)))
 }
 
-fn discover_postgres_log_path(client: &mut Client) -> Result<Option<String>, postgres::Error> {
+pub(crate) fn discover_postgres_log_path(
+    client: &mut Client,
+) -> Result<Option<String>, postgres::Error> {
     let logging_collector = fetch_postgres_setting(client, "logging_collector")?
         .unwrap_or_default()
         .to_ascii_lowercase();
@@ -543,16 +1010,26 @@ fn escape_sql_literal(value: &str) -> String {
 }
 
 #[derive(Debug, Clone)]
-struct SlowLogEntry {
-    sql: String,
-    query_time_secs: f64,
-    lock_time_secs: f64,
-    rows_sent: u64,
-    rows_examined: u64,
+pub(crate) struct SlowLogEntry {
+    pub(crate) offset: u64,
+    pub(crate) thread_id: Option<u64>,
+    pub(crate) start_time_secs: Option<f64>,
+    pub(crate) sql: String,
+    /// Default database the statement ran in, when the log records it.
+    pub(crate) schema: Option<String>,
+    pub(crate) query_time_secs: f64,
+    pub(crate) lock_time_secs: f64,
+    pub(crate) rows_sent: u64,
+    pub(crate) rows_examined: u64,
 }
 
 #[derive(Debug, Default)]
 struct SlowLogBuilder {
+    offset: u64,
+    thread_id: Option<u64>,
+    logged_at_secs: Option<f64>,
+    set_timestamp_secs: Option<f64>,
+    schema: Option<String>,
     sql_lines: Vec<String>,
     query_time_secs: f64,
     lock_time_secs: f64,
@@ -560,79 +1037,457 @@ struct SlowLogBuilder {
     rows_examined: u64,
 }
 
-fn parse_mysql_slow_log_entries(content: &str) -> Vec<SlowLogEntry> {
-    let mut entries = Vec::new();
-    let mut current: Option<SlowLogBuilder> = None;
+impl SlowLogBuilder {
+    fn finish(self) -> Option<SlowLogEntry> {
+        let sql = self.sql_lines.join("\n").trim().to_string();
+        if sql.is_empty() {
+            return None;
+        }
+        // `# Time` is written when the statement ends; `SET timestamp` is its
+        // start but only has second precision.
+        let start_time_secs = self
+            .logged_at_secs
+            .map(|it| it - self.query_time_secs)
+            .or(self.set_timestamp_secs);
+        Some(SlowLogEntry {
+            offset: self.offset,
+            thread_id: self.thread_id,
+            start_time_secs,
+            sql,
+            schema: self.schema,
+            query_time_secs: self.query_time_secs,
+            lock_time_secs: self.lock_time_secs,
+            rows_sent: self.rows_sent,
+            rows_examined: self.rows_examined,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SlowLogFormat {
+    MysqlSlowLog,
+    PostgresStatementLog,
+}
 
-    for line in content.lines() {
-        let trimmed = line.trim();
-        if line.starts_with("# Time:") {
-            finalize_entry(&mut entries, current.take());
-            current = Some(SlowLogBuilder::default());
-            continue;
+impl SlowLogFormat {
+    fn dialect(self) -> SqlDialect {
+        match self {
+            SlowLogFormat::MysqlSlowLog => SqlDialect::Mysql,
+            SlowLogFormat::PostgresStatementLog => SqlDialect::Postgres,
         }
+    }
+}
 
-        let Some(ref mut item) = current else {
-            continue;
-        };
+/// Line iterator over a `BufRead` that tolerates invalid UTF-8 and tracks the
+/// byte offset of every line relative to the start of the stream.
+struct LogLines<R> {
+    reader: R,
+    offset: u64,
+    buffer: Vec<u8>,
+}
 
-        if line.contains("Query_time:") {
-            item.query_time_secs = extract_f64_metric(line, "Query_time").unwrap_or_default();
-            item.lock_time_secs = extract_f64_metric(line, "Lock_time").unwrap_or_default();
-            item.rows_sent = extract_u64_metric(line, "Rows_sent").unwrap_or_default();
-            item.rows_examined = extract_u64_metric(line, "Rows_examined").unwrap_or_default();
-            continue;
+impl<R: BufRead> LogLines<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            reader,
+            offset: 0,
+            buffer: Vec::new(),
         }
-        if trimmed.starts_with('#') || trimmed.starts_with("SET timestamp=") || trimmed.is_empty() {
-            continue;
+    }
+}
+
+impl<R: BufRead> Iterator for LogLines<R> {
+    type Item = io::Result<(u64, String)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.buffer.clear();
+        match self.reader.read_until(b'\n', &mut self.buffer) {
+            Ok(0) => None,
+            Ok(read) => {
+                let offset = self.offset;
+                self.offset += read as u64;
+                let line = String::from_utf8_lossy(&self.buffer);
+                Some(Ok((
+                    offset,
+                    line.trim_end_matches(['\n', '\r']).to_string(),
+                )))
+            }
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+struct MysqlSlowLogEntries<R> {
+    lines: LogLines<R>,
+    current: Option<SlowLogBuilder>,
+    /// The server writes `use db;` only when the database differs from the
+    /// one of the previously logged statement, so it carries over.
+    schema: Option<String>,
+}
+
+impl<R: BufRead> Iterator for MysqlSlowLogEntries<R> {
+    type Item = io::Result<SlowLogEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (offset, line) = match self.lines.next() {
+                Some(Ok(it)) => it,
+                Some(Err(err)) => return Some(Err(err)),
+                None => return self.current.take().and_then(SlowLogBuilder::finish).map(Ok),
+            };
+
+            let trimmed = line.trim();
+            if let Some(logged_at) = line.strip_prefix("# Time:") {
+                let finished = self.current.replace(SlowLogBuilder {
+                    offset,
+                    logged_at_secs: parse_log_timestamp(logged_at.trim()),
+                    schema: self.schema.clone(),
+                    ..SlowLogBuilder::default()
+                });
+                if let Some(entry) = finished.and_then(SlowLogBuilder::finish) {
+                    return Some(Ok(entry));
+                }
+                continue;
+            }
+
+            let Some(ref mut item) = self.current else {
+                continue;
+            };
+
+            if line.contains("Query_time:") {
+                item.query_time_secs = extract_f64_metric(&line, "Query_time").unwrap_or_default();
+                item.lock_time_secs = extract_f64_metric(&line, "Lock_time").unwrap_or_default();
+                item.rows_sent = extract_u64_metric(&line, "Rows_sent").unwrap_or_default();
+                item.rows_examined = extract_u64_metric(&line, "Rows_examined").unwrap_or_default();
+                continue;
+            }
+            if line.starts_with("# User@Host:") {
+                item.thread_id = extract_u64_metric(&line, "Id");
+                continue;
+            }
+            if let Some(value) = trimmed.strip_prefix("SET timestamp=") {
+                item.set_timestamp_secs = value.trim_end_matches(';').parse::<f64>().ok();
+                continue;
+            }
+            // Percona Server and MariaDB name the database in a header line.
+            if trimmed.starts_with('#')
+                && let Some(schema) = extract_header_schema(trimmed)
+            {
+                item.schema = Some(schema.to_string());
+                continue;
+            }
+            if trimmed.starts_with('#') || trimmed.is_empty() {
+                continue;
+            }
+            // The server logs `use db;` ahead of a statement when the schema
+            // changed; it is not part of the statement.
+            if item.sql_lines.is_empty()
+                && let Some(schema) = use_database(trimmed)
+            {
+                self.schema = Some(schema.to_string());
+                item.schema = self.schema.clone();
+                continue;
+            }
+
+            item.sql_lines.push(trimmed.to_string());
         }
+    }
+}
 
-        item.sql_lines.push(trimmed.to_string());
+/// The database of a `use db;` line.
+fn use_database(line: &str) -> Option<&str> {
+    let mut words = line.strip_suffix(';')?.split_whitespace();
+    if !words.next()?.eq_ignore_ascii_case("use") {
+        return None;
     }
-    finalize_entry(&mut entries, current);
-    entries
+    let schema = words.next()?.trim_matches('`');
+    (words.next().is_none() && !schema.is_empty()).then_some(schema)
 }
 
-fn parse_postgres_statement_log_entries(content: &str) -> Vec<SlowLogEntry> {
-    let mut entries = Vec::new();
-    let mut pending_duration_secs: Option<f64> = None;
+/// `shop` from `# Schema: shop  Last_errno: 0` or
+/// `# Thread_id: 5  Schema: shop  QC_hit: No`.
+fn extract_header_schema(line: &str) -> Option<&str> {
+    let idx = line.find("Schema:")?;
+    line[(idx + "Schema:".len())..]
+        .split_whitespace()
+        .next()
+        .filter(|it| !it.contains(':'))
+}
 
-    for line in content.lines() {
-        let trimmed = line.trim();
-        if trimmed.is_empty() {
-            continue;
+struct PostgresStatementLogEntries<R> {
+    lines: LogLines<R>,
+    pending_duration: Option<SlowLogEntry>,
+}
+
+impl<R: BufRead> Iterator for PostgresStatementLogEntries<R> {
+    type Item = io::Result<SlowLogEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (offset, line) = match self.lines.next()? {
+                Ok(it) => it,
+                Err(err) => return Some(Err(err)),
+            };
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+
+            if let Some(duration_secs) = extract_postgres_duration_secs(trimmed) {
+                let entry = postgres_entry(offset, trimmed, duration_secs);
+                if let Some(sql) = extract_postgres_statement_sql(trimmed) {
+                    self.pending_duration = None;
+                    return Some(Ok(SlowLogEntry { sql, ..entry }));
+                }
+                self.pending_duration = Some(entry);
+                continue;
+            }
+
+            if let Some(entry) = self.pending_duration.take()
+                && let Some(sql) = extract_postgres_statement_sql(trimmed)
+            {
+                return Some(Ok(SlowLogEntry { sql, ..entry }));
+            }
+        }
+    }
+}
+
+/// Builds an entry from the `duration:` line; the statement text is filled in
+/// by the caller. Session fields come from the default `%m [%p]` prefix.
+fn postgres_entry(offset: u64, line: &str, query_time_secs: f64) -> SlowLogEntry {
+    let logged_at_secs = line.get(..23).and_then(parse_log_timestamp);
+    SlowLogEntry {
+        offset,
+        thread_id: extract_postgres_pid(line),
+        start_time_secs: logged_at_secs.map(|it| it - query_time_secs),
+        sql: String::new(),
+        schema: None,
+        query_time_secs,
+        lock_time_secs: 0.0,
+        rows_sent: 0,
+        rows_examined: 0,
+    }
+}
+
+/// Entry iterator that knows how many bytes of its input it has consumed, so
+/// digests can be stitched across segments of unknown (decompressed) size.
+trait SlowLogParser: Iterator<Item = io::Result<SlowLogEntry>> {
+    fn bytes_read(&self) -> u64;
+}
+
+impl<R: BufRead> SlowLogParser for MysqlSlowLogEntries<R> {
+    fn bytes_read(&self) -> u64 {
+        self.lines.offset
+    }
+}
+
+impl<R: BufRead> SlowLogParser for PostgresStatementLogEntries<R> {
+    fn bytes_read(&self) -> u64 {
+        self.lines.offset
+    }
+}
+
+fn parse_mysql_slow_log_entries<R: BufRead>(reader: R) -> MysqlSlowLogEntries<R> {
+    MysqlSlowLogEntries {
+        lines: LogLines::new(reader),
+        current: None,
+        schema: None,
+    }
+}
+
+fn parse_postgres_statement_log_entries<R: BufRead>(reader: R) -> PostgresStatementLogEntries<R> {
+    PostgresStatementLogEntries {
+        lines: LogLines::new(reader),
+        pending_duration: None,
+    }
+}
+
+#[derive(Debug, Default)]
+struct SlowLogDigestSummary {
+    collected_bytes: u64,
+    skipped_bytes: u64,
+    parsed_entries: usize,
+    dropped_entries: usize,
+    rotated: bool,
+    lost_rotated_segment: bool,
+    source_files: Vec<String>,
+    digests: Vec<SlowSqlDigest>,
+    transactions: TransactionReport,
+}
+
+/// Streams entries from one or more stitched log segments into digests,
+/// keeping only entries that start within the first `max_bytes` of the
+/// window. An entry that begins before the limit is kept whole; later
+/// entries are counted as dropped instead of being cut.
+struct SlowLogDigester {
+    format: SlowLogFormat,
+    max_bytes: u64,
+    base_offset: u64,
+    cutoff: Option<u64>,
+    aggregator: DigestAggregator,
+    transactions: TransactionTracker,
+    summary: SlowLogDigestSummary,
+}
+
+impl SlowLogDigester {
+    fn new(format: SlowLogFormat, max_bytes: usize, thresholds: TransactionThresholds) -> Self {
+        Self {
+            format,
+            max_bytes: max_bytes as u64,
+            base_offset: 0,
+            cutoff: None,
+            aggregator: DigestAggregator::new(fingerprinter_for(format.dialect())),
+            transactions: TransactionTracker::new(thresholds),
+            summary: SlowLogDigestSummary::default(),
         }
+    }
 
-        if let Some(duration_secs) = extract_postgres_duration_secs(trimmed) {
-            if let Some(sql) = extract_postgres_statement_sql(trimmed) {
-                entries.push(SlowLogEntry {
-                    sql,
-                    query_time_secs: duration_secs,
-                    lock_time_secs: 0.0,
-                    rows_sent: 0,
-                    rows_examined: 0,
+    fn consume<P: SlowLogParser>(&mut self, mut parser: P) -> io::Result<()> {
+        for entry in parser.by_ref() {
+            let entry = entry?;
+            let offset = self.base_offset + entry.offset;
+            if offset >= self.max_bytes {
+                self.cutoff.get_or_insert(offset);
+                self.summary.dropped_entries += 1;
+                continue;
+            }
+            self.summary.parsed_entries += 1;
+            let session = entry.thread_id.zip(entry.start_time_secs);
+            let (query_time_secs, lock_time_secs) = (entry.query_time_secs, entry.lock_time_secs);
+            let fingerprint = self.aggregator.push(entry);
+            if let Some((thread_id, start_secs)) = session {
+                self.transactions.push(SessionStatement {
+                    thread_id,
+                    start_secs,
+                    query_time_secs,
+                    lock_time_secs,
+                    boundary: transaction_boundary(&fingerprint),
+                    fingerprint,
                 });
-                pending_duration_secs = None;
-            } else {
-                pending_duration_secs = Some(duration_secs);
             }
-            continue;
         }
+        self.base_offset += parser.bytes_read();
+        Ok(())
+    }
 
-        if let Some(duration_secs) = pending_duration_secs.take()
-            && let Some(sql) = extract_postgres_statement_sql(trimmed)
-        {
-            entries.push(SlowLogEntry {
-                sql,
-                query_time_secs: duration_secs,
-                lock_time_secs: 0.0,
-                rows_sent: 0,
-                rows_examined: 0,
-            });
+    fn consume_stream<R: BufRead>(&mut self, reader: R) -> io::Result<()> {
+        match self.format {
+            SlowLogFormat::MysqlSlowLog => self.consume(parse_mysql_slow_log_entries(reader)),
+            SlowLogFormat::PostgresStatementLog => {
+                self.consume(parse_postgres_statement_log_entries(reader))
+            }
         }
     }
 
-    entries
+    fn finish(mut self) -> SlowLogDigestSummary {
+        let total_bytes = self.base_offset;
+        let accepted = self.cutoff.unwrap_or(total_bytes).min(total_bytes);
+        self.summary.collected_bytes = accepted;
+        self.summary.skipped_bytes = total_bytes - accepted;
+        self.summary.digests = self.aggregator.finish();
+        self.summary.transactions = self.transactions.finish("slow_log");
+        self.summary
+    }
+}
+
+fn transaction_thresholds(config: &Level1CollectorConfig) -> TransactionThresholds {
+    TransactionThresholds {
+        long_secs: config.long_transaction_secs,
+        idle_secs: config.idle_in_transaction_secs,
+    }
+}
+
+fn apply_slow_log_summary(
+    report: &mut Level1CollectionReport,
+    summary: SlowLogDigestSummary,
+    max_bytes: usize,
+) {
+    report.slow_log.collected_bytes = summary.collected_bytes as usize;
+    report.slow_log.parsed_entries = summary.parsed_entries;
+    report.slow_log.truncated = summary.dropped_entries > 0 || summary.skipped_bytes > 0;
+    report.slow_log.skipped_bytes = summary.skipped_bytes;
+    report.slow_log.dropped_entries = summary.dropped_entries;
+    report.slow_log.rotated_during_window = summary.rotated;
+    report.slow_log.source_files = summary.source_files;
+    report.slow_log.digests = summary.digests;
+    report.transactions = summary.transactions;
+    report.slow_log.digest_count = report.slow_log.digests.len();
+    report.slow_log.tag_rollups = rollup_digest_tags(&report.slow_log.digests);
+    report.slow_log.kind_breakdown = summarize_statement_kinds(&report.slow_log.digests);
+    report.slow_log.ddl_digests = report
+        .slow_log
+        .digests
+        .iter()
+        .filter(|it| it.statement_kind == StatementKind::Ddl)
+        .map(|it| DdlDigestHighlight {
+            digest_id: it.digest_id.clone(),
+            fingerprint: it.fingerprint.clone(),
+            tables: it.tables.clone(),
+            count: it.count,
+            total_query_time_secs: it.total_query_time_secs,
+        })
+        .collect();
+    if !report.slow_log.ddl_digests.is_empty() {
+        let statements = report
+            .slow_log
+            .ddl_digests
+            .iter()
+            .map(|it| it.count)
+            .sum::<u64>();
+        report.warnings.push(format!(
+            "slow log window contains {statements} DDL statement(s); DDL holds metadata locks and can delay replicas"
+        ));
+    }
+    if report.slow_log.truncated {
+        report.warnings.push(format!(
+            "slow log window exceeded {max_bytes} bytes; skipped {} bytes ({} entries)",
+            summary.skipped_bytes, summary.dropped_entries
+        ));
+    }
+    if summary.lost_rotated_segment {
+        report.warnings.push(
+            "slow log was rotated during the window and the previous file could not be located; entries written before rotation are missing"
+                .to_string(),
+        );
+    }
+}
+
+fn extract_postgres_pid(line: &str) -> Option<u64> {
+    let start = line.find('[')?;
+    let end = start + line[start..].find(']')?;
+    line[(start + 1)..end].parse::<u64>().ok()
+}
+
+/// Parses `YYYY-MM-DD[T ]HH:MM:SS[.frac]` into unix seconds, ignoring any zone
+/// suffix; only differences between timestamps of one log are used.
+fn parse_log_timestamp(text: &str) -> Option<f64> {
+    let bytes = text.as_bytes();
+    if bytes.len() < 19 || bytes[4] != b'-' || bytes[7] != b'-' || !matches!(bytes[10], b'T' | b' ')
+    {
+        return None;
+    }
+    let field = |range: std::ops::Range<usize>| text.get(range)?.parse::<i64>().ok();
+    let (year, month, day) = (field(0..4)?, field(5..7)?, field(8..10)?);
+    let (hour, minute, second) = (field(11..13)?, field(14..16)?, field(17..19)?);
+    let fraction = text[19..]
+        .strip_prefix('.')
+        .map(|rest| {
+            let digits = rest
+                .find(|it: char| !it.is_ascii_digit())
+                .map_or(rest, |end| &rest[..end]);
+            format!("0.{digits}").parse::<f64>().unwrap_or(0.0)
+        })
+        .unwrap_or(0.0);
+
+    // Days from civil date (Howard Hinnant's algorithm).
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    Some((days * 86_400 + hour * 3_600 + minute * 60 + second) as f64 + fraction)
 }
 
 fn extract_postgres_duration_secs(line: &str) -> Option<f64> {
@@ -673,134 +1528,218 @@ fn extract_postgres_statement_sql(line: &str) -> Option<String> {
     None
 }
 
-fn finalize_entry(entries: &mut Vec<SlowLogEntry>, item: Option<SlowLogBuilder>) {
-    let Some(item) = item else {
-        return;
-    };
-    let sql = item.sql_lines.join("\n").trim().to_string();
-    if sql.is_empty() {
-        return;
-    }
-    entries.push(SlowLogEntry {
-        sql,
-        query_time_secs: item.query_time_secs,
-        lock_time_secs: item.lock_time_secs,
-        rows_sent: item.rows_sent,
-        rows_examined: item.rows_examined,
-    });
+#[derive(Debug, Default)]
+struct DigestAgg {
+    fingerprint: String,
+    sample_sql: String,
+    tables: Vec<String>,
+    schema: Option<String>,
+    statement_kind: StatementKind,
+    count: u64,
+    total_query_time_secs: f64,
+    total_lock_time_secs: f64,
+    total_rows_sent: u64,
+    total_rows_examined: u64,
+    /// Count and query time per tag key and value.
+    tags: BTreeMap<String, BTreeMap<String, (u64, f64)>>,
+    trace_samples: Vec<String>,
 }
 
-fn aggregate_slow_sql_digests(entries: Vec<SlowLogEntry>) -> Vec<SlowSqlDigest> {
-    #[derive(Debug, Default)]
-    struct Agg {
-        sample_sql: String,
-        count: u64,
-        total_query_time_secs: f64,
-        total_lock_time_secs: f64,
-        total_rows_sent: u64,
-        total_rows_examined: u64,
+const MAX_TRACE_SAMPLES: usize = 5;
+const MAX_TAG_KEYS: usize = 16;
+/// Values beyond the first ones of a key (request ids, spans) are counted
+/// under [`OTHER_TAG_VALUE`].
+const MAX_TAG_VALUES_PER_KEY: usize = 20;
+const OTHER_TAG_VALUE: &str = "(other)";
+
+/// Incremental digest builder; memory grows with distinct fingerprints, not
+/// with the number of entries pushed.
+pub(crate) struct DigestAggregator {
+    fingerprinter: &'static dyn Fingerprinter,
+    grouped: HashMap<String, DigestAgg>,
+}
+
+impl DigestAggregator {
+    pub(crate) fn new(fingerprinter: &'static dyn Fingerprinter) -> Self {
+        Self {
+            fingerprinter,
+            grouped: HashMap::new(),
+        }
     }
 
-    let mut grouped: HashMap<String, Agg> = HashMap::new();
-    for entry in entries {
-        let fingerprint = normalize_sql_fingerprint(&entry.sql);
-        let agg = grouped.entry(fingerprint).or_default();
+    /// Returns the entry's fingerprint text. Entries are grouped by the
+    /// fingerprint hash, so statements that differ only in quoting, spacing or
+    /// a trailing `;` land in one digest.
+    pub(crate) fn push(&mut self, entry: SlowLogEntry) -> String {
+        let fingerprint = self.fingerprinter.fingerprint(&entry.sql);
+        let tags = extract_query_tags(&entry.sql);
+        let agg = self.grouped.entry(fingerprint.hash).or_default();
+        if agg.fingerprint.is_empty() {
+            agg.fingerprint = fingerprint.text.clone();
+        }
         if agg.sample_sql.is_empty() {
-            agg.sample_sql = entry.sql.clone();
+            let dialect = self.fingerprinter.dialect();
+            agg.tables = referenced_tables(&entry.sql, dialect);
+            agg.statement_kind = classify_statement(&entry.sql, dialect);
+            agg.sample_sql = entry.sql;
+        }
+        if agg.schema.is_none() {
+            agg.schema = entry.schema;
         }
         agg.count += 1;
         agg.total_query_time_secs += entry.query_time_secs;
         agg.total_lock_time_secs += entry.lock_time_secs;
         agg.total_rows_sent += entry.rows_sent;
         agg.total_rows_examined += entry.rows_examined;
-    }
-
-    let mut digests = grouped
-        .into_iter()
-        .map(|(fingerprint, agg)| SlowSqlDigest {
-            fingerprint,
-            sample_sql: agg.sample_sql,
-            count: agg.count,
-            total_query_time_secs: agg.total_query_time_secs,
-            avg_query_time_secs: if agg.count == 0 {
-                0.0
-            } else {
-                agg.total_query_time_secs / agg.count as f64
-            },
-            total_lock_time_secs: agg.total_lock_time_secs,
-            total_rows_sent: agg.total_rows_sent,
-            total_rows_examined: agg.total_rows_examined,
-        })
-        .collect::<Vec<_>>();
 
-    digests.sort_by(|a, b| {
-        let a_time = (a.total_query_time_secs * 1_000_000.0) as u64;
-        let b_time = (b.total_query_time_secs * 1_000_000.0) as u64;
-        b_time
-            .cmp(&a_time)
-            .then_with(|| b.count.cmp(&a.count))
-            .then_with(|| a.fingerprint.cmp(&b.fingerprint))
-    });
-    digests
-}
-
-fn normalize_sql_fingerprint(sql: &str) -> String {
-    let mut out = String::with_capacity(sql.len());
-    let mut chars = sql.chars().peekable();
-    let mut in_single_quote = false;
-    let mut in_double_quote = false;
-    let mut prev_space = false;
-
-    while let Some(ch) = chars.next() {
-        if in_single_quote {
-            if ch == '\'' {
-                in_single_quote = false;
-                out.push('?');
-                prev_space = false;
+        for (key, value) in tags.tags {
+            if !agg.tags.contains_key(&key) && agg.tags.len() >= MAX_TAG_KEYS {
+                continue;
             }
-            continue;
+            let values = agg.tags.entry(key).or_default();
+            let value = if values.contains_key(&value) || values.len() < MAX_TAG_VALUES_PER_KEY {
+                value
+            } else {
+                OTHER_TAG_VALUE.to_string()
+            };
+            let tag = values.entry(value).or_default();
+            tag.0 += 1;
+            tag.1 += entry.query_time_secs;
         }
-        if in_double_quote {
-            if ch == '"' {
-                in_double_quote = false;
-                out.push('?');
-                prev_space = false;
-            }
-            continue;
+        if let Some(trace_id) = tags.trace_id
+            && agg.trace_samples.len() < MAX_TRACE_SAMPLES
+            && !agg.trace_samples.contains(&trace_id)
+        {
+            agg.trace_samples.push(trace_id);
         }
+        fingerprint.text
+    }
 
-        if ch == '\'' {
-            in_single_quote = true;
-            continue;
-        }
-        if ch == '"' {
-            in_double_quote = true;
-            continue;
-        }
-        if ch.is_ascii_digit() {
-            out.push('?');
-            while let Some(next) = chars.peek() {
-                if next.is_ascii_digit() || *next == '.' {
-                    chars.next();
+    pub(crate) fn finish(self) -> Vec<SlowSqlDigest> {
+        let mut digests = self
+            .grouped
+            .into_iter()
+            .map(|(digest_id, agg)| SlowSqlDigest {
+                fingerprint: agg.fingerprint,
+                digest_id,
+                sample_sql: agg.sample_sql,
+                tables: agg.tables,
+                schema: agg.schema,
+                statement_kind: agg.statement_kind,
+                count: agg.count,
+                total_query_time_secs: agg.total_query_time_secs,
+                avg_query_time_secs: if agg.count == 0 {
+                    0.0
                 } else {
-                    break;
-                }
-            }
-            prev_space = false;
-            continue;
+                    agg.total_query_time_secs / agg.count as f64
+                },
+                total_lock_time_secs: agg.total_lock_time_secs,
+                total_rows_sent: agg.total_rows_sent,
+                total_rows_examined: agg.total_rows_examined,
+                tags: agg
+                    .tags
+                    .into_iter()
+                    .flat_map(|(key, values)| {
+                        values
+                            .into_iter()
+                            .map(move |(value, (count, total))| DigestTag {
+                                key: key.clone(),
+                                value,
+                                count,
+                                total_query_time_secs: total,
+                            })
+                    })
+                    .collect(),
+                trace_samples: agg.trace_samples,
+                native_digest: None,
+            })
+            .collect::<Vec<_>>();
+
+        digests.sort_by(|a, b| {
+            let a_time = (a.total_query_time_secs * 1_000_000.0) as u64;
+            let b_time = (b.total_query_time_secs * 1_000_000.0) as u64;
+            b_time
+                .cmp(&a_time)
+                .then_with(|| b.count.cmp(&a.count))
+                .then_with(|| a.fingerprint.cmp(&b.fingerprint))
+        });
+        digests
+    }
+}
+
+fn summarize_statement_kinds(digests: &[SlowSqlDigest]) -> Vec<StatementKindSummary> {
+    let mut grouped: BTreeMap<StatementKind, StatementKindSummary> = BTreeMap::new();
+    for digest in digests {
+        let summary =
+            grouped
+                .entry(digest.statement_kind)
+                .or_insert_with(|| StatementKindSummary {
+                    kind: digest.statement_kind,
+                    ..StatementKindSummary::default()
+                });
+        summary.digest_count += 1;
+        summary.count += digest.count;
+        summary.total_query_time_secs += digest.total_query_time_secs;
+    }
+
+    let total_secs = digests
+        .iter()
+        .map(|it| it.total_query_time_secs)
+        .sum::<f64>();
+    let mut summaries = grouped.into_values().collect::<Vec<_>>();
+    for summary in &mut summaries {
+        if total_secs > 0.0 {
+            summary.time_share_pct = summary.total_query_time_secs / total_secs * 100.0;
         }
-        if ch.is_whitespace() {
-            if !prev_space {
-                out.push(' ');
-                prev_space = true;
-            }
-            continue;
+    }
+    summaries.sort_by(|a, b| {
+        let a_time = (a.total_query_time_secs * 1_000_000.0) as u64;
+        let b_time = (b.total_query_time_secs * 1_000_000.0) as u64;
+        b_time.cmp(&a_time).then_with(|| a.kind.cmp(&b.kind))
+    });
+    summaries
+}
+
+fn rollup_digest_tags(digests: &[SlowSqlDigest]) -> Vec<SlowSqlTagRollup> {
+    let mut grouped: BTreeMap<(&str, &str), SlowSqlTagRollup> = BTreeMap::new();
+    for digest in digests {
+        for tag in &digest.tags {
+            let rollup =
+                grouped
+                    .entry((&tag.key, &tag.value))
+                    .or_insert_with(|| SlowSqlTagRollup {
+                        key: tag.key.clone(),
+                        value: tag.value.clone(),
+                        ..SlowSqlTagRollup::default()
+                    });
+            rollup.count += tag.count;
+            rollup.total_query_time_secs += tag.total_query_time_secs;
+            rollup.digest_ids.push(digest.digest_id.clone());
         }
-        out.push(ch.to_ascii_lowercase());
-        prev_space = false;
     }
 
-    out.split_whitespace().collect::<Vec<_>>().join(" ")
+    let mut rollups = grouped.into_values().collect::<Vec<_>>();
+    rollups.sort_by(|a, b| {
+        let a_time = (a.total_query_time_secs * 1_000_000.0) as u64;
+        let b_time = (b.total_query_time_secs * 1_000_000.0) as u64;
+        b_time
+            .cmp(&a_time)
+            .then_with(|| a.key.cmp(&b.key))
+            .then_with(|| a.value.cmp(&b.value))
+    });
+    rollups
+}
+
+#[cfg(test)]
+pub(crate) fn aggregate_slow_sql_digests<I>(dialect: SqlDialect, entries: I) -> Vec<SlowSqlDigest>
+where
+    I: IntoIterator<Item = SlowLogEntry>,
+{
+    let mut aggregator = DigestAggregator::new(fingerprinter_for(dialect));
+    for entry in entries {
+        aggregator.push(entry);
+    }
+    aggregator.finish()
 }
 
 fn extract_f64_metric(line: &str, key: &str) -> Option<f64> {
@@ -817,23 +1756,78 @@ fn extract_u64_metric(line: &str, key: &str) -> Option<u64> {
     value.parse::<u64>().ok()
 }
 
-fn extract_error_log_alerts(lines: &[String]) -> Vec<ErrorAlert> {
+fn apply_error_log_lines(
+    report: &mut Level1CollectionReport,
+    config: &Level1CollectorConfig,
+    lines: &[String],
+) {
+    let summary = summarize_error_log(config.engine, &config.error_alert_rules, lines);
+    report.error_log.sampled_lines = summary.sampled_lines;
+    report.error_log.parsed_lines = summary.parsed_lines;
+    report.error_log.alert_count = summary.alert_count;
+    report.error_log.alerts = summary.alerts;
+    report.error_log.severity_counts = summary.severity_counts;
+    report.error_log.code_counts = summary.code_counts;
+    report.deadlocks.add(match config.engine {
+        Level1Engine::Mysql => parse_innodb_deadlocks(lines, INNODB_ERROR_LOG_SOURCE),
+        Level1Engine::Postgres => parse_postgres_deadlocks(lines),
+    });
+}
+
+/// Parses each line and categorizes it by code, subsystem and severity. Lines
+/// without a severity header never raise alerts.
+fn summarize_error_log(
+    engine: Level1Engine,
+    rules: &ErrorAlertRules,
+    lines: &[String],
+) -> ErrorLogSnapshot {
     #[derive(Default)]
     struct Agg {
         count: u64,
+        codes: BTreeSet<String>,
         sample_lines: Vec<String>,
     }
 
-    let mut grouped: HashMap<&'static str, Agg> = HashMap::new();
+    let mut snapshot = ErrorLogSnapshot {
+        sampled_lines: lines.len(),
+        ..ErrorLogSnapshot::default()
+    };
+    let mut grouped: HashMap<&str, Agg> = HashMap::new();
+    let mut severities: BTreeMap<ErrorSeverity, u64> = BTreeMap::new();
+    let mut codes: BTreeMap<String, ErrorCodeCount> = BTreeMap::new();
     for line in lines {
-        let lower = line.to_ascii_lowercase();
-        for category in classify_error_line(&lower) {
-            let agg = grouped.entry(category).or_default();
+        let Some(record) = parse_error_line(engine, line) else {
+            continue;
+        };
+        snapshot.parsed_lines += 1;
+        *severities.entry(record.severity).or_default() += 1;
+
+        for rule in rules.categorize(engine, &record) {
+            let agg = grouped.entry(&rule.category).or_default();
             agg.count += 1;
-            if agg.sample_lines.len() < 3 {
+            if let Some(code) = &record.code {
+                agg.codes.insert(code.clone());
+            }
+            if agg.sample_lines.len() < rule.max_samples {
                 agg.sample_lines.push(line.clone());
             }
         }
+
+        let Some(code) = record.code.clone() else {
+            continue;
+        };
+        let entry = codes.entry(code).or_insert_with_key(|code| ErrorCodeCount {
+            code: code.clone(),
+            severity: record.severity,
+            subsystem: record.subsystem.clone(),
+            count: 0,
+            first_seen: record.timestamp.clone(),
+            last_seen: None,
+            sample_line: line.clone(),
+        });
+        entry.count += 1;
+        entry.severity = entry.severity.max(record.severity);
+        entry.last_seen = record.timestamp;
     }
 
     let mut alerts = grouped
@@ -841,6 +1835,7 @@ fn extract_error_log_alerts(lines: &[String]) -> Vec<ErrorAlert> {
         .map(|(category, agg)| ErrorAlert {
             category: category.to_string(),
             count: agg.count,
+            codes: agg.codes.into_iter().collect(),
             sample_lines: agg.sample_lines,
         })
         .collect::<Vec<_>>();
@@ -849,85 +1844,433 @@ fn extract_error_log_alerts(lines: &[String]) -> Vec<ErrorAlert> {
             .cmp(&a.count)
             .then_with(|| a.category.cmp(&b.category))
     });
-    alerts
+    snapshot.alert_count = alerts.len();
+    snapshot.alerts = alerts;
+
+    snapshot.severity_counts = severities
+        .into_iter()
+        .rev()
+        .map(|(severity, count)| ErrorSeverityCount { severity, count })
+        .collect();
+    snapshot.code_counts = codes.into_values().collect();
+    snapshot.code_counts.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| b.severity.cmp(&a.severity))
+            .then_with(|| a.code.cmp(&b.code))
+    });
+    snapshot
 }
 
-fn classify_error_line(lower: &str) -> Vec<&'static str> {
-    let mut categories = Vec::new();
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileIdentity {
+    device: u64,
+    inode: u64,
+}
 
-    if lower.contains("deadlock") {
-        categories.push("deadlock");
+#[cfg(unix)]
+fn file_identity(metadata: &fs::Metadata) -> Option<FileIdentity> {
+    use std::os::unix::fs::MetadataExt;
+    Some(FileIdentity {
+        device: metadata.dev(),
+        inode: metadata.ino(),
+    })
+}
+
+#[cfg(not(unix))]
+fn file_identity(_metadata: &fs::Metadata) -> Option<FileIdentity> {
+    None
+}
+
+/// Read position captured when a window starts, pinned to the identity of
+/// the file so a rotation can be detected when the window ends.
+#[derive(Debug, Clone)]
+struct LogCursor {
+    path: PathBuf,
+    identity: Option<FileIdentity>,
+    offset: u64,
+}
+
+impl LogCursor {
+    fn open(path: &str) -> Self {
+        let metadata = fs::metadata(path).ok();
+        Self {
+            path: PathBuf::from(path),
+            identity: metadata.as_ref().and_then(file_identity),
+            offset: metadata.map(|it| it.len()).unwrap_or(0),
+        }
     }
-    if lower.contains("crash recovery")
-        || lower.contains("starting crash recovery")
-        || lower.contains("recovery completed")
-        || lower.contains("database system is starting up")
-        || lower.contains("redo starts at")
-        || lower.contains("redo done at")
-    {
-        categories.push("crash_recovery");
-    }
-    if lower.contains("purge") || lower.contains("vacuum") {
-        categories.push("purge");
-    }
-    if lower.contains("replication")
-        || lower.contains("replica")
-        || lower.contains("slave")
-        || lower.contains("relay log")
-        || lower.contains("group replication")
-        || lower.contains("binlog")
-        || lower.contains("wal sender")
-        || lower.contains("wal receiver")
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct LogSegment {
+    path: PathBuf,
+    start: u64,
+    end: u64,
+}
+
+#[derive(Debug, Default)]
+struct WindowSegments {
+    segments: Vec<LogSegment>,
+    rotated: bool,
+    lost_rotated_segment: bool,
+}
+
+/// Maps a window onto the byte ranges that hold its entries. When the file
+/// behind the cursor was replaced, the old file is followed by identity (at
+/// its original path or a renamed sibling such as `.1` or a timestamped name)
+/// and read from the cursor to its end. Several rotations within one window
+/// leave newer rotations between it and the current file; those are read
+/// whole, oldest first, before the new file is read from the beginning.
+fn resolve_window_segments(cursor: &LogCursor, current_path: &Path) -> io::Result<WindowSegments> {
+    let current = fs::metadata(current_path)?;
+    let current_identity = file_identity(&current);
+    let mut resolved = WindowSegments::default();
+
+    if cursor.identity.is_none() || cursor.identity == current_identity {
+        // A shrunken file was truncated in place; its content starts over at 0.
+        let start = if current.len() < cursor.offset {
+            0
+        } else {
+            cursor.offset
+        };
+        resolved.segments.push(LogSegment {
+            path: current_path.to_path_buf(),
+            start,
+            end: current.len(),
+        });
+        return Ok(resolved);
+    }
+
+    resolved.rotated = true;
+    let identity_of = |path: &Path| fs::metadata(path).ok().and_then(|it| file_identity(&it));
+    let chain = rotation_chain(&cursor.path);
+    let mut older = Vec::new();
+    match chain
+        .iter()
+        .position(|it| identity_of(it) == cursor.identity)
     {
-        categories.push("replication");
+        Some(index) => {
+            older.push((chain[index].clone(), cursor.offset));
+            older.extend(
+                chain[index + 1..]
+                    .iter()
+                    .filter(|it| identity_of(it) != current_identity)
+                    .map(|it| (it.clone(), 0)),
+            );
+        }
+        // Renamed outside the rotation family, so nothing in between is known.
+        None => match locate_file_by_identity(&cursor.path, cursor.identity) {
+            Some(path) => older.push((path, cursor.offset)),
+            None => resolved.lost_rotated_segment = true,
+        },
     }
-    categories
+    for (path, start) in older {
+        let end = segment_end(&path)?;
+        if end > start {
+            resolved.segments.push(LogSegment { path, start, end });
+        }
+    }
+    resolved.segments.push(LogSegment {
+        path: current_path.to_path_buf(),
+        start: 0,
+        end: current.len(),
+    });
+    Ok(resolved)
 }
 
-fn read_file_appended_segment(path: &str, offset: u64, max_bytes: usize) -> io::Result<String> {
-    let len = file_len(path)?;
-    let mut start = offset.min(len);
-    if len < offset {
-        start = len.saturating_sub(max_bytes as u64);
+/// The rotations of the log at `path`, oldest first; see [`list_log_inputs`].
+fn rotation_chain(path: &Path) -> Vec<PathBuf> {
+    let dir = path
+        .parent()
+        .filter(|it| !it.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let base = path.file_name().and_then(|it| it.to_str());
+    list_log_inputs(dir, base).unwrap_or_default()
+}
+
+/// Plain files end at their length; a compressed rotation is read to the end
+/// of its decompressed stream.
+fn segment_end(path: &Path) -> io::Result<u64> {
+    if detect_log_compression(path)? == LogCompression::None {
+        Ok(fs::metadata(path)?.len())
+    } else {
+        Ok(u64::MAX)
+    }
+}
+
+fn locate_file_by_identity(original: &Path, identity: Option<FileIdentity>) -> Option<PathBuf> {
+    let matches = |path: &Path| {
+        fs::metadata(path).is_ok_and(|it| it.is_file() && file_identity(&it) == identity)
+    };
+    if matches(original) {
+        return Some(original.to_path_buf());
+    }
+
+    let dir = original.parent().filter(|it| !it.as_os_str().is_empty())?;
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|it| it.path()))
+        .find(|path| matches(path))
+}
+
+fn open_segment(segment: &LogSegment) -> io::Result<Box<dyn BufRead>> {
+    if detect_log_compression(&segment.path)? == LogCompression::None {
+        return Ok(Box::new(open_file_range(
+            &segment.path,
+            segment.start,
+            segment.end,
+        )?));
+    }
+    // The cursor offset counts decompressed bytes of the file it was taken on.
+    let mut stream = open_log_stream(&segment.path)?;
+    io::copy(&mut (&mut stream).take(segment.start), &mut io::sink())?;
+    Ok(Box::new(stream.take(segment.end - segment.start)))
+}
+
+fn read_slow_log_window(
+    cursor: &LogCursor,
+    current_path: &Path,
+    max_bytes: usize,
+    format: SlowLogFormat,
+    thresholds: TransactionThresholds,
+) -> io::Result<SlowLogDigestSummary> {
+    let resolved = resolve_window_segments(cursor, current_path)?;
+    let mut digester = SlowLogDigester::new(format, max_bytes, thresholds);
+    // Segments are parsed separately so an entry never spans a rotation.
+    for segment in &resolved.segments {
+        digester.consume_stream(open_segment(segment)?)?;
     }
-    if len.saturating_sub(start) > max_bytes as u64 {
-        start = len.saturating_sub(max_bytes as u64);
+
+    let mut summary = digester.finish();
+    summary.rotated = resolved.rotated;
+    summary.lost_rotated_segment = resolved.lost_rotated_segment;
+    summary.source_files = resolved
+        .segments
+        .iter()
+        .map(|it| it.path.to_string_lossy().to_string())
+        .collect();
+    Ok(summary)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LogCompression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Detects compression from the file header rather than the extension, so
+/// logrotate suffixes like `.1.gz` or `-20260207.zst` need no special casing.
+fn detect_log_compression(path: &Path) -> io::Result<LogCompression> {
+    let mut header = [0u8; 4];
+    let mut file = File::open(path)?;
+    let mut read = 0;
+    while read < header.len() {
+        match file.read(&mut header[read..])? {
+            0 => break,
+            n => read += n,
+        }
+    }
+    if read >= ZSTD_MAGIC.len() && header == ZSTD_MAGIC {
+        Ok(LogCompression::Zstd)
+    } else if read >= GZIP_MAGIC.len() && header[..2] == GZIP_MAGIC {
+        Ok(LogCompression::Gzip)
+    } else {
+        Ok(LogCompression::None)
     }
-    read_file_range(path, start, len)
 }
 
-fn read_file_tail(path: &str, max_bytes: usize) -> io::Result<String> {
+fn open_log_stream(path: &Path) -> io::Result<Box<dyn BufRead>> {
+    let file = File::open(path)?;
+    Ok(match detect_log_compression(path)? {
+        LogCompression::None => Box::new(BufReader::new(file)),
+        LogCompression::Gzip => Box::new(BufReader::new(flate2::read::MultiGzDecoder::new(file))),
+        LogCompression::Zstd => Box::new(BufReader::new(zstd::stream::read::Decoder::new(file)?)),
+    })
+}
+
+/// Files of an archive directory that belong to one log, oldest first:
+/// numbered rotations (`slow.log.2.gz` before `slow.log.1`) by suffix, then
+/// the rest (the live file, date-stamped rotations) by modification time.
+/// The log is the one named by `base`, or else the largest family of files
+/// (see [`log_family`]), so `error.log` or `mysqld.pid` next to it are skipped.
+fn list_log_inputs(path: &Path, base: Option<&str>) -> io::Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut files = Vec::new();
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() {
+            continue;
+        }
+        let path = entry.path();
+        let Some(family) = path.file_name().and_then(|it| it.to_str()).map(log_family) else {
+            continue;
+        };
+        files.push((family, metadata.modified().unwrap_or(UNIX_EPOCH), path));
+    }
+
+    let family = match base {
+        Some(base) => log_family(base),
+        None => {
+            let mut sizes = BTreeMap::<&str, usize>::new();
+            for (family, _, _) in &files {
+                *sizes.entry(family).or_default() += 1;
+            }
+            let Some(family) = sizes
+                .into_iter()
+                .max_by_key(|(family, size)| (*size, family.contains("slow")))
+                .map(|(family, _)| family.to_string())
+            else {
+                return Ok(Vec::new());
+            };
+            family
+        }
+    };
+    let mut inputs = files
+        .into_iter()
+        .filter(|(it, _, _)| *it == family)
+        .map(|(_, modified, path)| {
+            let rotation = rotation_suffix(&path).unwrap_or(0);
+            (std::cmp::Reverse(rotation), modified, path)
+        })
+        .collect::<Vec<_>>();
+    inputs.sort();
+    Ok(inputs.into_iter().map(|(_, _, path)| path).collect())
+}
+
+/// The name without compression extension, digits and trailing separators,
+/// shared by a log and its rotations: `slow.log` for `slow.log.2.gz` and
+/// `slow.log-20260207`, `postgresql-_.log` for `postgresql-2026-02-07_0000.log`.
+fn log_family(name: &str) -> String {
+    let name = strip_compression_extension(name);
+    let mut family = String::with_capacity(name.len());
+    for ch in name.chars().filter(|it| !it.is_ascii_digit()) {
+        // Collapse the separators left between removed digits.
+        if matches!(ch, '.' | '-' | '_') && family.ends_with(ch) {
+            continue;
+        }
+        family.push(ch);
+    }
+    family.trim_end_matches(['.', '-', '_']).to_string()
+}
+
+fn strip_compression_extension(name: &str) -> &str {
+    [".gz", ".zst", ".zstd"]
+        .iter()
+        .find_map(|it| name.strip_suffix(it))
+        .unwrap_or(name)
+}
+
+/// `2` for `slow.log.2.gz` or `slow.log.2`; `None` for `slow.log` and for
+/// date stamps such as `slow.log.20260207`, which logrotate never counts to.
+fn rotation_suffix(path: &Path) -> Option<u32> {
+    let name = strip_compression_extension(path.file_name()?.to_str()?);
+    let suffix = name.rsplit_once('.')?.1;
+    if suffix.len() > 3 {
+        return None;
+    }
+    suffix.parse().ok()
+}
+
+fn is_archived_log(path: &Path) -> io::Result<bool> {
+    Ok(path.is_dir() || detect_log_compression(path)? != LogCompression::None)
+}
+
+fn read_slow_log_archive(
+    path: &Path,
+    base: Option<&str>,
+    format: SlowLogFormat,
+    max_bytes: usize,
+    thresholds: TransactionThresholds,
+) -> io::Result<SlowLogDigestSummary> {
+    let inputs = list_log_inputs(path, base)?;
+    let mut digester = SlowLogDigester::new(format, max_bytes, thresholds);
+    for input in &inputs {
+        digester.consume_stream(open_log_stream(input)?)?;
+    }
+    let mut summary = digester.finish();
+    summary.source_files = inputs
+        .iter()
+        .map(|it| it.to_string_lossy().to_string())
+        .collect();
+    Ok(summary)
+}
+
+/// Reads at most `max_lines` non-empty lines from the last `max_bytes` of the
+/// file, dropping a leading partial line when the tail starts mid-line.
+fn read_file_tail_lines(path: &str, max_bytes: usize, max_lines: usize) -> io::Result<Vec<String>> {
+    // Compressed files cannot be seeked, so archives are streamed whole and
+    // only the last `max_lines` are retained.
+    if is_archived_log(Path::new(path))? {
+        let mut lines = Vec::new();
+        for input in list_log_inputs(Path::new(path), None)? {
+            lines.extend(take_last_lines(open_log_stream(&input)?, max_lines, false)?);
+            let excess = lines.len().saturating_sub(max_lines);
+            lines.drain(..excess);
+        }
+        return Ok(lines);
+    }
+
     let len = file_len(path)?;
     let start = len.saturating_sub(max_bytes as u64);
-    read_file_range(path, start, len)
+    let starts_mid_line = start > 0 && !preceded_by_newline(path, start)?;
+    let reader = open_file_range(path, start, len)?;
+    take_last_lines(reader, max_lines, starts_mid_line)
 }
 
-fn read_file_range(path: &str, start: u64, end: u64) -> io::Result<String> {
+fn preceded_by_newline(path: &str, offset: u64) -> io::Result<bool> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(offset - 1))?;
+    let mut byte = [0u8; 1];
+    file.read_exact(&mut byte)?;
+    Ok(byte[0] == b'\n')
+}
+
+fn open_file_range<P: AsRef<Path>>(
+    path: P,
+    start: u64,
+    end: u64,
+) -> io::Result<BufReader<io::Take<File>>> {
     let mut file = File::open(path)?;
     file.seek(SeekFrom::Start(start))?;
-    let mut buffer = Vec::with_capacity(end.saturating_sub(start) as usize);
-    file.take(end.saturating_sub(start))
-        .read_to_end(&mut buffer)?;
-    Ok(String::from_utf8_lossy(&buffer).to_string())
+    Ok(BufReader::new(file.take(end.saturating_sub(start))))
 }
 
 fn file_len(path: &str) -> io::Result<u64> {
     Ok(std::fs::metadata(path)?.len())
 }
 
-fn take_last_lines(content: &str, max_lines: usize) -> Vec<String> {
-    let mut lines = content
-        .lines()
-        .map(|it| it.trim().to_string())
-        .filter(|it| !it.is_empty())
-        .collect::<Vec<_>>();
-    if lines.len() > max_lines {
-        lines = lines.split_off(lines.len() - max_lines);
+fn take_last_lines<R: BufRead>(
+    reader: R,
+    max_lines: usize,
+    skip_first: bool,
+) -> io::Result<Vec<String>> {
+    let mut lines = VecDeque::with_capacity(max_lines.min(4_096));
+    for (index, line) in LogLines::new(reader).enumerate() {
+        let (_, line) = line?;
+        if index == 0 && skip_first {
+            continue;
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if lines.len() == max_lines {
+            lines.pop_front();
+        }
+        lines.push_back(line.to_string());
     }
-    lines
+    Ok(lines.into())
 }
 
-fn is_truthy(value: &str) -> bool {
+pub(crate) fn is_truthy(value: &str) -> bool {
     matches!(
         value.trim().to_ascii_lowercase().as_str(),
         "on" | "1" | "true" | "yes"
@@ -945,7 +2288,7 @@ fn now_unix_ms() -> u128 {
 mod tests {
     use super::*;
     use std::fs;
-    use std::path::Path;
+    use std::path::{Path, PathBuf};
 
     fn fixture_path(relative: &str) -> String {
         Path::new(env!("CARGO_MANIFEST_DIR"))
@@ -963,6 +2306,33 @@ mod tests {
         serde_json::from_str(&text).expect("failed parsing fixture json")
     }
 
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sql-insight-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("failed creating scratch dir");
+        dir
+    }
+
+    fn append(path: &Path, text: &str) {
+        use std::io::Write;
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .expect("failed opening scratch log");
+        file.write_all(text.as_bytes())
+            .expect("failed writing scratch log");
+    }
+
+    fn collect_entries<I>(entries: I) -> Vec<SlowLogEntry>
+    where
+        I: Iterator<Item = io::Result<SlowLogEntry>>,
+    {
+        entries
+            .collect::<io::Result<Vec<_>>>()
+            .expect("in-memory log should parse")
+    }
+
     #[test]
     fn should_aggregate_slow_log_by_fingerprint() {
         let fixture = r#"
@@ -976,13 +2346,42 @@ SET timestamp=1770430002;
 SELECT * FROM orders WHERE id = 101;
 "#;
 
-        let entries = parse_mysql_slow_log_entries(fixture);
-        let digests = aggregate_slow_sql_digests(entries);
+        let entries = collect_entries(parse_mysql_slow_log_entries(fixture.as_bytes()));
+        let digests = aggregate_slow_sql_digests(SqlDialect::Mysql, entries);
         assert_eq!(digests.len(), 1);
         assert_eq!(digests[0].count, 2);
         assert!(digests[0].fingerprint.contains("where id = ?"));
     }
 
+    #[test]
+    fn should_drop_leading_use_database_lines() {
+        let fixture = read_text("tests/fixtures/logs/mysql_slow.log");
+        let entries = collect_entries(parse_mysql_slow_log_entries(fixture.as_bytes()));
+        let update = entries
+            .iter()
+            .find(|it| it.sql.starts_with("UPDATE"))
+            .expect("fixture update entry");
+        assert_eq!(
+            update.sql,
+            "UPDATE orders SET state = 'paid' WHERE id = 102;"
+        );
+
+        let digests = aggregate_slow_sql_digests(SqlDialect::Mysql, entries);
+        let update = digests
+            .iter()
+            .find(|it| it.fingerprint.starts_with("update"))
+            .expect("update digest");
+        assert_eq!(update.statement_kind, StatementKind::Dml);
+        assert_eq!(update.schema.as_deref(), Some("shop"));
+        assert_eq!(use_database("USE `shop`;"), Some("shop"));
+        assert_eq!(use_database("use_count = 1;"), None);
+        assert_eq!(
+            extract_header_schema("# Thread_id: 5  Schema: shop  QC_hit: No"),
+            Some("shop")
+        );
+        assert_eq!(extract_header_schema("# Schema:  Last_errno: 0"), None);
+    }
+
     #[test]
     fn should_parse_postgres_statement_logs() {
         let fixture = r#"
@@ -991,44 +2390,697 @@ SELECT * FROM orders WHERE id = 101;
 2026-02-07 16:00:01.456 UTC [77] postgres@app STATEMENT:  SELECT * FROM orders WHERE user_id = 2;
 "#;
 
-        let entries = parse_postgres_statement_log_entries(fixture);
-        let digests = aggregate_slow_sql_digests(entries);
+        let entries = collect_entries(parse_postgres_statement_log_entries(fixture.as_bytes()));
+        let digests = aggregate_slow_sql_digests(SqlDialect::Postgres, entries);
         assert_eq!(digests.len(), 1);
         assert_eq!(digests[0].count, 2);
         assert!(digests[0].fingerprint.contains("where user_id = ?"));
     }
 
     #[test]
-    fn should_extract_error_alerts() {
-        let lines = vec![
-            "InnoDB: Deadlock found when trying to get lock".to_string(),
-            "InnoDB: Starting crash recovery from checkpoint".to_string(),
-            "replication applier thread stopped with error".to_string(),
-            "InnoDB: purge lag increased".to_string(),
-            "ERROR: deadlock detected".to_string(),
-            "LOG: wal receiver process terminated".to_string(),
-        ];
-        let alerts = extract_error_log_alerts(&lines);
-        assert!(alerts.iter().any(|it| it.category == "deadlock"));
-        assert!(alerts.iter().any(|it| it.category == "crash_recovery"));
-        assert!(alerts.iter().any(|it| it.category == "replication"));
-        assert!(alerts.iter().any(|it| it.category == "purge"));
+    fn should_report_dropped_entries_instead_of_cutting_them() {
+        let fixture = read_text("tests/fixtures/logs/mysql_slow.log");
+        let second_entry = fixture
+            .match_indices("# Time:")
+            .nth(1)
+            .map(|(idx, _)| idx)
+            .expect("fixture should have several entries");
+
+        let mut digester = SlowLogDigester::new(
+            SlowLogFormat::MysqlSlowLog,
+            second_entry,
+            TransactionThresholds::default(),
+        );
+        digester
+            .consume(parse_mysql_slow_log_entries(fixture.as_bytes()))
+            .expect("in-memory log should parse");
+        let summary = digester.finish();
+        assert_eq!(summary.parsed_entries, 1);
+        assert_eq!(summary.dropped_entries, 3);
+        assert_eq!(summary.collected_bytes, second_entry as u64);
+        assert_eq!(summary.skipped_bytes, (fixture.len() - second_entry) as u64);
+        assert_eq!(
+            summary.digests[0].sample_sql,
+            "SELECT * FROM orders WHERE id = 100;"
+        );
+    }
+
+    #[test]
+    fn should_stitch_renamed_slow_log_after_rotation() {
+        let dir = scratch_dir("rotate-rename");
+        let path = dir.join("slow.log");
+        append(
+            &path,
+            "# Time: 2026-02-07T11:59:59.000000Z\n# Query_time: 9.0 Lock_time: 0.0 Rows_sent: 0 Rows_examined: 0\nSELECT 'before window';\n",
+        );
+        let cursor = LogCursor::open(path.to_str().expect("utf-8 path"));
+
+        append(
+            &path,
+            "# Time: 2026-02-07T12:00:00.000000Z\n# Query_time: 1.0 Lock_time: 0.0 Rows_sent: 1 Rows_examined: 1\nSELECT * FROM a WHERE id = 1;\n",
+        );
+        fs::rename(&path, dir.join("slow.log.1")).expect("rotate slow log");
+        append(
+            &path,
+            "/usr/sbin/mysqld, Version: 8.0.36. started with:\nTime                 Id Command    Argument\n# Time: 2026-02-07T12:00:01.000000Z\n# Query_time: 2.0 Lock_time: 0.0 Rows_sent: 1 Rows_examined: 1\nSELECT * FROM b WHERE id = 2;\n",
+        );
+
+        let summary = read_slow_log_window(
+            &cursor,
+            &path,
+            2_000_000,
+            SlowLogFormat::MysqlSlowLog,
+            TransactionThresholds::default(),
+        )
+        .expect("rotated window should read");
+        let _ = fs::remove_dir_all(&dir);
+
+        assert!(summary.rotated);
+        assert!(!summary.lost_rotated_segment);
+        assert_eq!(summary.source_files.len(), 2);
+        assert!(summary.source_files[0].ends_with("slow.log.1"));
+        assert_eq!(summary.parsed_entries, 2);
+        let fingerprints = summary
+            .digests
+            .iter()
+            .map(|it| it.fingerprint.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fingerprints,
+            vec![
+                "select * from b where id = ?;",
+                "select * from a where id = ?;"
+            ]
+        );
+    }
+
+    #[test]
+    fn should_walk_every_rotation_within_the_window() {
+        let dir = scratch_dir("rotate-twice");
+        let path = dir.join("slow.log");
+        let entry = |second: u32, table: &str| {
+            format!(
+                "# Time: 2026-02-07T12:00:0{second}.000000Z\n# Query_time: 1.0 Lock_time: 0.0 Rows_sent: 1 Rows_examined: 1\nSELECT * FROM {table} WHERE id = 1;\n"
+            )
+        };
+        append(&path, &entry(0, "before"));
+        let cursor = LogCursor::open(path.to_str().expect("utf-8 path"));
+
+        append(&path, &entry(1, "a"));
+        fs::rename(&path, dir.join("slow.log.1")).expect("rotate slow log");
+        append(&path, &entry(2, "b"));
+        fs::rename(dir.join("slow.log.1"), dir.join("slow.log.2")).expect("shift rotation");
+        fs::rename(&path, dir.join("slow.log.1")).expect("rotate slow log again");
+        append(&path, &entry(3, "c"));
+        append(
+            &dir.join("error.log"),
+            "2026-02-07T12:00:00Z 0 [Note] [MY-000000] [Server] x\n",
+        );
+
+        let summary = read_slow_log_window(
+            &cursor,
+            &path,
+            2_000_000,
+            SlowLogFormat::MysqlSlowLog,
+            TransactionThresholds::default(),
+        )
+        .expect("rotated window should read");
+        let _ = fs::remove_dir_all(&dir);
+
+        assert!(summary.rotated);
+        assert!(!summary.lost_rotated_segment);
+        let names = summary
+            .source_files
+            .iter()
+            .map(|it| {
+                Path::new(it)
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["slow.log.2", "slow.log.1", "slow.log"]);
+        assert_eq!(summary.parsed_entries, 3);
+        assert!(
+            summary
+                .digests
+                .iter()
+                .all(|it| !it.fingerprint.contains("before"))
+        );
+    }
+
+    #[test]
+    fn should_follow_postgres_log_filename_switch() {
+        let dir = scratch_dir("rotate-switch");
+        let first = dir.join("postgresql-15.log");
+        let second = dir.join("postgresql-16.log");
+        append(
+            &first,
+            "2026-02-07 15:59:59 UTC [77] LOG:  duration: 900.0 ms  statement: SELECT 'before window';\n",
+        );
+        let cursor = LogCursor::open(first.to_str().expect("utf-8 path"));
+        append(
+            &first,
+            "2026-02-07 15:59:59.900 UTC [77] LOG:  duration: 10.0 ms  statement: SELECT 1;\n",
+        );
+        append(
+            &second,
+            "2026-02-07 16:00:00.100 UTC [77] LOG:  duration: 20.0 ms  statement: SELECT 2;\n",
+        );
+
+        let summary = read_slow_log_window(
+            &cursor,
+            &second,
+            2_000_000,
+            SlowLogFormat::PostgresStatementLog,
+            TransactionThresholds::default(),
+        )
+        .expect("switched window should read");
+        let _ = fs::remove_dir_all(&dir);
+
+        assert!(summary.rotated);
+        assert_eq!(summary.parsed_entries, 2);
+        assert_eq!(summary.digests.len(), 1);
+        assert_eq!(summary.digests[0].count, 2);
+    }
+
+    #[test]
+    fn should_digest_directory_of_compressed_rotated_logs() {
+        use std::io::Write;
+
+        let dir = scratch_dir("archive");
+        let entry = |id: u32| {
+            format!(
+                "# Time: 2026-02-07T12:00:0{id}.000000Z\n# Query_time: 1.0 Lock_time: 0.0 Rows_sent: 1 Rows_examined: 1\nSELECT * FROM orders WHERE id = {id};\n"
+            )
+        };
+        let write_at = |name: &str, bytes: &[u8], age_secs: u64| {
+            let path = dir.join(name);
+            fs::write(&path, bytes).expect("failed writing archive file");
+            File::options()
+                .write(true)
+                .open(&path)
+                .and_then(|it| it.set_modified(SystemTime::now() - Duration::from_secs(age_secs)))
+                .expect("failed setting mtime");
+        };
+
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(entry(1).as_bytes()).expect("gzip write");
+        // Copied rotations can have newer mtimes; the suffix decides.
+        write_at("slow.log.2.gz", &gzip.finish().expect("gzip finish"), 100);
+        let zstd_bytes = zstd::encode_all(entry(2).as_bytes(), 0).expect("zstd encode");
+        write_at("slow.log.1.zst", &zstd_bytes, 300);
+        write_at("slow.log", entry(4).as_bytes(), 200);
+        // Date stamps are not rotation numbers; they sort with the live log.
+        write_at("slow.log.20260207", entry(3).as_bytes(), 250);
+        write_at(
+            "error.log",
+            b"2026-02-07T12:00:00.000000Z 0 [Warning] x\n",
+            50,
+        );
+        write_at("mysqld.pid", b"4242\n", 50);
+
+        let summary = read_slow_log_archive(
+            &dir,
+            None,
+            SlowLogFormat::MysqlSlowLog,
+            usize::MAX,
+            TransactionThresholds::default(),
+        )
+        .expect("archive should read");
+        let capped = read_slow_log_archive(
+            &dir,
+            Some("slow.log"),
+            SlowLogFormat::MysqlSlowLog,
+            entry(1).len(),
+            TransactionThresholds::default(),
+        )
+        .expect("archive should read");
+        let error_lines = read_file_tail_lines(
+            dir.join("slow.log.2.gz").to_str().expect("utf-8 path"),
+            10,
+            1,
+        )
+        .expect("compressed log tail should read");
+        let _ = fs::remove_dir_all(&dir);
+
+        let names = summary
+            .source_files
+            .iter()
+            .filter_map(|it| Path::new(it).file_name()?.to_str().map(str::to_string))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                "slow.log.2.gz",
+                "slow.log.1.zst",
+                "slow.log.20260207",
+                "slow.log"
+            ]
+        );
+        assert_eq!(summary.parsed_entries, 4);
+        assert_eq!(summary.dropped_entries, 0);
+        assert_eq!(summary.digests.len(), 1);
+        assert_eq!(summary.digests[0].count, 4);
+        assert_eq!(
+            summary.digests[0].sample_sql,
+            "SELECT * FROM orders WHERE id = 1;"
+        );
+        assert_eq!(error_lines, vec!["SELECT * FROM orders WHERE id = 1;"]);
+        assert_eq!(capped.parsed_entries, 1);
+        assert_eq!(capped.dropped_entries, 3);
+    }
+
+    #[test]
+    fn should_keep_last_error_lines_without_partial_head() {
+        let content = "partial tail of an older line\nfirst\n\nsecond\nthird\n";
+        let lines = take_last_lines(content.as_bytes(), 2, true).expect("lines should read");
+        assert_eq!(lines, vec!["second".to_string(), "third".to_string()]);
+    }
+
+    #[test]
+    fn should_roll_up_digests_by_comment_tags() {
+        let log = "\
+# Time: 2026-02-07T12:00:00.000000Z
+# Query_time: 1.000 Lock_time: 0.000 Rows_sent: 1 Rows_examined: 1
+SELECT * FROM carts WHERE id = 1 /*app='checkout',route='%2Fpay',traceparent='00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01'*/;
+# Time: 2026-02-07T12:00:00.000000Z
+# Query_time: 0.500 Lock_time: 0.000 Rows_sent: 1 Rows_examined: 1
+SELECT * FROM carts WHERE id = 2 /*app='checkout',route='%2Fcart'*/;
+# Time: 2026-02-07T12:00:00.000000Z
+# Query_time: 0.250 Lock_time: 0.000 Rows_sent: 1 Rows_examined: 1
+/*app:search*/ SELECT * FROM items WHERE name LIKE 'a%';
+";
+        let digests = aggregate_slow_sql_digests(
+            SqlDialect::Mysql,
+            collect_entries(parse_mysql_slow_log_entries(log.as_bytes())),
+        );
+        assert_eq!(digests.len(), 2);
+        assert_eq!(digests[0].fingerprint, "select * from carts where id = ?;");
+        assert_eq!(
+            digests[0].trace_samples,
+            vec!["0af7651916cd43dd8448eb211c80319c".to_string()]
+        );
+        assert_eq!(digests[0].tags.len(), 3);
+
+        let rollups = rollup_digest_tags(&digests);
+        let summary = rollups
+            .iter()
+            .map(|it| (it.key.as_str(), it.value.as_str(), it.count))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                ("app", "checkout", 2),
+                ("route", "/pay", 1),
+                ("route", "/cart", 1),
+                ("app", "search", 1),
+            ]
+        );
+        assert_eq!(rollups[0].digest_ids, vec![digests[0].digest_id.clone()]);
+    }
+
+    #[test]
+    fn should_bound_tag_values_per_digest() {
+        let log = (0..MAX_TAG_VALUES_PER_KEY + 10)
+            .map(|id| {
+                format!(
+                    "# Time: 2026-02-07T12:00:00.000000Z\n\
+                     # Query_time: 1.000 Lock_time: 0.000 Rows_sent: 1 Rows_examined: 1\n\
+                     SELECT * FROM carts WHERE id = 1 /*app='checkout',request_id='r{id}'*/;\n"
+                )
+            })
+            .collect::<String>();
+        let digests = aggregate_slow_sql_digests(
+            SqlDialect::Mysql,
+            collect_entries(parse_mysql_slow_log_entries(log.as_bytes())),
+        );
+
+        let request_ids = digests[0]
+            .tags
+            .iter()
+            .filter(|it| it.key == "request_id")
+            .collect::<Vec<_>>();
+        assert_eq!(request_ids.len(), MAX_TAG_VALUES_PER_KEY + 1);
+        let other = request_ids
+            .iter()
+            .find(|it| it.value == OTHER_TAG_VALUE)
+            .expect("other bucket");
+        assert_eq!(other.count, 10);
+        assert!(
+            digests[0]
+                .tags
+                .iter()
+                .any(|it| it.key == "app" && it.count == 30)
+        );
+    }
+
+    #[test]
+    fn should_leave_sources_of_skipped_tasks_alone() {
+        let logs = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/logs");
+        let config = Level1CollectorConfig {
+            slow_log_archive: Some(logs.join("mysql_slow.log").display().to_string()),
+            error_log_path: Some(logs.join("mysql_error.log").display().to_string()),
+            ..Level1CollectorConfig::default()
+        };
+        let report = collect_level1(&config);
+        assert!(report.capability.can_read_slow_log);
+        assert!(report.capability.can_read_error_log);
+
+        let report = collect_level1(&Level1CollectorConfig {
+            skipped_tasks: BTreeSet::from(["slow_log_digest", "error_log_alerts"]),
+            ..config
+        });
+        assert!(!report.capability.can_read_slow_log);
+        assert!(!report.capability.can_read_error_log);
+        assert_eq!(report.slow_log.parsed_entries, 0);
+        assert_eq!(report.error_log.error_log_path, None);
+    }
+
+    #[test]
+    fn should_merge_spelling_variants_into_one_digest() {
+        let log = [
+            "SELECT * FROM t WHERE id = 1",
+            "SELECT * FROM t WHERE id = 1;",
+            "SELECT * FROM `t` WHERE id = 1",
+            "SELECT * FROM t WHERE id=1",
+        ]
+        .iter()
+        .map(|sql| {
+            format!(
+                "# Time: 2026-02-07T12:00:00.000000Z\n\
+                 # Query_time: 1.000 Lock_time: 0.000 Rows_sent: 1 Rows_examined: 1\n{sql}\n"
+            )
+        })
+        .collect::<String>();
+        let digests = aggregate_slow_sql_digests(
+            SqlDialect::Mysql,
+            collect_entries(parse_mysql_slow_log_entries(log.as_bytes())),
+        );
+        assert_eq!(digests.len(), 1, "{digests:?}");
+        assert_eq!(digests[0].count, 4);
+        assert_eq!(digests[0].tables, vec!["t".to_string()]);
+    }
+
+    #[test]
+    fn should_break_down_slow_time_by_statement_kind() {
+        let log = "\
+# Time: 2026-02-07T12:00:00.000000Z
+# Query_time: 3.000 Lock_time: 2.500 Rows_sent: 0 Rows_examined: 0
+ALTER TABLE orders ADD INDEX idx_user (user_id);
+# Time: 2026-02-07T12:00:01.000000Z
+# Query_time: 0.500 Lock_time: 0.000 Rows_sent: 0 Rows_examined: 1
+UPDATE orders SET state = 'paid' WHERE id = 1;
+# Time: 2026-02-07T12:00:02.000000Z
+# Query_time: 0.500 Lock_time: 0.000 Rows_sent: 1 Rows_examined: 1
+SELECT * FROM orders WHERE id = 1;
+";
+        let summary = SlowLogDigestSummary {
+            digests: aggregate_slow_sql_digests(
+                SqlDialect::Mysql,
+                collect_entries(parse_mysql_slow_log_entries(log.as_bytes())),
+            ),
+            ..SlowLogDigestSummary::default()
+        };
+        let mut report = Level1CollectionReport::default();
+        apply_slow_log_summary(&mut report, summary, usize::MAX);
+
+        let breakdown = report
+            .slow_log
+            .kind_breakdown
+            .iter()
+            .map(|it| (it.kind, it.count, it.time_share_pct))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            breakdown,
+            vec![
+                (StatementKind::Ddl, 1, 75.0),
+                (StatementKind::Select, 1, 12.5),
+                (StatementKind::Dml, 1, 12.5),
+            ]
+        );
+        assert_eq!(report.slow_log.ddl_digests.len(), 1);
+        assert_eq!(report.slow_log.ddl_digests[0].tables, vec!["orders"]);
+        assert!(
+            report
+                .warnings
+                .iter()
+                .any(|it| it.contains("1 DDL statement"))
+        );
+    }
+
+    #[test]
+    fn should_reconstruct_transactions_from_slow_log_sessions() {
+        let log = "\
+# Time: 2026-02-07T12:00:00.000000Z
+# User@Host: app[app] @ localhost []  Id:    12
+# Query_time: 0.000 Lock_time: 0.000 Rows_sent: 0 Rows_examined: 0
+BEGIN;
+# Time: 2026-02-07T12:00:00.200000Z
+# User@Host: app[app] @ localhost []  Id:    12
+# Query_time: 0.200 Lock_time: 0.050 Rows_sent: 1 Rows_examined: 1
+SELECT * FROM carts WHERE id = 1 FOR UPDATE;
+# Time: 2026-02-07T12:00:00.300000Z
+# User@Host: app[app] @ localhost []  Id:    13
+# Query_time: 0.100 Lock_time: 0.000 Rows_sent: 1 Rows_examined: 1
+SELECT 1;
+# Time: 2026-02-07T12:00:03.300000Z
+# User@Host: app[app] @ localhost []  Id:    12
+# Query_time: 0.100 Lock_time: 0.000 Rows_sent: 0 Rows_examined: 1
+UPDATE carts SET state = 'paid' WHERE id = 1;
+# Time: 2026-02-07T12:00:03.300000Z
+# User@Host: app[app] @ localhost []  Id:    12
+# Query_time: 0.000 Lock_time: 0.000 Rows_sent: 0 Rows_examined: 0
+COMMIT;
+";
+        let mut digester = SlowLogDigester::new(
+            SlowLogFormat::MysqlSlowLog,
+            usize::MAX,
+            TransactionThresholds::default(),
+        );
+        digester
+            .consume_stream(log.as_bytes())
+            .expect("in-memory log should parse");
+        let report = digester.finish().transactions;
+
+        assert_eq!(report.source.as_deref(), Some("slow_log"));
+        assert_eq!(report.reconstructed, 1);
+        let transaction = &report.transactions[0];
+        assert_eq!(transaction.thread_id, 12);
+        assert_eq!(transaction.statement_count, 2);
+        assert!((transaction.duration_secs - 3.3).abs() < 1e-6);
+        assert!((transaction.total_lock_time_secs - 0.05).abs() < 1e-9);
+        assert!(transaction.long_running && transaction.idle_in_transaction);
+        assert_eq!(
+            transaction.fingerprints,
+            vec![
+                "select * from carts where id = ? for update;",
+                "update carts set state = ? where id = ?;"
+            ]
+        );
+    }
+
+    #[test]
+    fn should_read_postgres_session_fields_from_log_prefix() {
+        let log = "2026-02-07 16:00:01.500 UTC [77] app@shop LOG:  duration: 500.000 ms  statement: SELECT 1\n";
+        let entries = collect_entries(parse_postgres_statement_log_entries(log.as_bytes()));
+        assert_eq!(entries[0].thread_id, Some(77));
+        assert_eq!(
+            entries[0].start_time_secs,
+            parse_log_timestamp("2026-02-07T16:00:01Z")
+        );
+        assert_eq!(
+            parse_log_timestamp("1970-01-02 00:00:01.25"),
+            Some(86_401.25)
+        );
+    }
+
+    #[test]
+    fn should_replay_performance_schema_transaction_history() {
+        const SEC: u64 = 1_000_000_000_000;
+        let row = |event_id, state: &str, statement: Option<(u64, &str)>| {
+            (
+                7,
+                event_id,
+                state.to_string(),
+                10 * SEC,
+                13 * SEC,
+                statement.map(|it| it.0 * SEC),
+                statement.map(|_| SEC / 10),
+                statement.map(|_| 0),
+                statement.map(|it| it.1.to_string()),
+            )
+        };
+        let report = reconstruct_mysql_transaction_history(
+            vec![
+                row(1, "ROLLED BACK", Some((10, "UPDATE `t` SET `a` = ?"))),
+                row(
+                    1,
+                    "ROLLED BACK",
+                    Some((12, "DELETE FROM `t` WHERE `id` = ?")),
+                ),
+            ],
+            TransactionThresholds::default(),
+        );
+
+        assert_eq!(
+            report.source.as_deref(),
+            Some(MYSQL_TRANSACTION_HISTORY_SOURCE)
+        );
+        let transaction = &report.transactions[0];
+        assert_eq!(
+            transaction.outcome,
+            crate::transactions::TransactionOutcome::RolledBack
+        );
+        assert_eq!(transaction.statement_count, 2);
+        assert!((transaction.duration_secs - 3.0).abs() < 1e-9);
+        assert!((transaction.max_idle_gap_secs - 1.9).abs() < 1e-9);
     }
 
     #[test]
-    fn should_normalize_sql_fingerprint() {
-        let sql = r#"SELECT * FROM t WHERE user_id = 123 AND name = "alice" AND score > 1.5"#;
-        let fp = normalize_sql_fingerprint(sql);
+    fn should_use_the_threshold_the_slow_log_was_written_with() {
+        let mut snapshot = SlowLogSnapshot {
+            long_query_time_secs: 0.2,
+            previous_long_query_time: Some("10.000000".to_string()),
+            ..SlowLogSnapshot::default()
+        };
+        assert_eq!(effective_long_query_time(&snapshot), Some(10.0));
+        snapshot.enabled_for_window = true;
+        assert_eq!(effective_long_query_time(&snapshot), Some(0.2));
+        snapshot.enabled_for_window = false;
+        snapshot.previous_long_query_time = None;
+        assert_eq!(effective_long_query_time(&snapshot), None);
+    }
+
+    #[test]
+    fn should_map_digests_to_native_ids_by_normalized_text() {
+        let fixture = read_text("tests/fixtures/logs/mysql_slow.log");
+        let mut snapshot = SlowLogSnapshot {
+            digests: aggregate_slow_sql_digests(
+                SqlDialect::Mysql,
+                collect_entries(parse_mysql_slow_log_entries(fixture.as_bytes())),
+            ),
+            ..SlowLogSnapshot::default()
+        };
+        attach_native_digests(
+            &mut snapshot,
+            SqlDialect::Mysql,
+            MYSQL_NATIVE_DIGEST_SOURCE,
+            vec![
+                (
+                    "5a1f".to_string(),
+                    "SELECT COUNT ( * ) FROM `orders` WHERE `user_id` = ?".to_string(),
+                ),
+                (
+                    "77c0".to_string(),
+                    "SELECT * FROM `users` WHERE `id` = ?".to_string(),
+                ),
+            ],
+        );
+
+        let mapped = snapshot
+            .digests
+            .iter()
+            .filter_map(|it| Some((it.fingerprint.as_str(), it.native_digest.as_ref()?)))
+            .collect::<Vec<_>>();
+        assert_eq!(mapped.len(), 1);
+        assert_eq!(
+            mapped[0].0,
+            "select count(*) from orders where user_id = ?;"
+        );
+        assert_eq!(mapped[0].1.native_id, "5a1f");
         assert_eq!(
-            fp,
-            "select * from t where user_id = ? and name = ? and score > ?"
+            snapshot.native_digest_source.as_deref(),
+            Some(MYSQL_NATIVE_DIGEST_SOURCE)
         );
     }
 
+    #[test]
+    fn should_extract_error_alerts() {
+        let lines = [
+            "2026-02-07T12:00:00.000000Z 9 [Note] [MY-012468] [InnoDB] Transactions deadlock detected, dumping detailed information.",
+            "2026-02-07T12:00:01.000000Z 0 [System] [MY-013576] [InnoDB] InnoDB initialization has started; starting crash recovery.",
+            "2026-02-07T12:00:02.000000Z 12 [ERROR] [MY-010584] [Repl] Replica SQL for channel '': Worker 1 failed executing transaction",
+            "2026-02-07T12:00:03.000000Z 12 [ERROR] [MY-010584] [Repl] Replica SQL for channel '': Worker 2 failed executing transaction",
+            "2026-02-07T12:00:04.000000Z 0 [Warning] [MY-011825] [InnoDB] purge lag increased",
+            "2026-02-07T12:00:05.000000Z 0 [Note] [MY-011825] [InnoDB] purge coordinator finished a batch",
+            "2026-02-07T12:00:06.000000Z 8 [Note] [MY-010914] [Server] Aborted connection 8 to db: 'app'",
+            "*** (1) TRANSACTION:",
+        ]
+        .map(str::to_string);
+        let summary = summarize_error_log(Level1Engine::Mysql, &ErrorAlertRules::builtin(), &lines);
+        assert_eq!(summary.sampled_lines, 8);
+        assert_eq!(summary.parsed_lines, 7);
+
+        let alerts = summary
+            .alerts
+            .iter()
+            .map(|it| (it.category.as_str(), it.count))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            alerts,
+            vec![
+                ("replication", 2),
+                ("aborted_connection", 1),
+                ("crash_recovery", 1),
+                ("deadlock", 1),
+                ("purge", 1)
+            ]
+        );
+        assert_eq!(summary.alerts[0].codes, vec!["MY-010584"]);
+
+        let codes = summary
+            .code_counts
+            .iter()
+            .map(|it| (it.code.as_str(), it.count, it.severity))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            codes[..2],
+            [
+                ("MY-010584", 2, ErrorSeverity::Error),
+                ("MY-011825", 2, ErrorSeverity::Warning)
+            ]
+        );
+        assert_eq!(
+            summary.code_counts[0].last_seen.as_deref(),
+            Some("2026-02-07T12:00:03.000000Z")
+        );
+        assert_eq!(summary.severity_counts[0].severity, ErrorSeverity::Error);
+    }
+
+    #[test]
+    fn should_match_error_alert_catalog_golden_fixtures() {
+        for (engine, fixture, golden) in [
+            (
+                Level1Engine::Mysql,
+                "tests/fixtures/logs/mysql_error_catalog.log",
+                "tests/golden/log_parsing/mysql_catalog_alerts.json",
+            ),
+            (
+                Level1Engine::Postgres,
+                "tests/fixtures/logs/postgres_error_catalog.log",
+                "tests/golden/log_parsing/postgres_catalog_alerts.json",
+            ),
+        ] {
+            let lines = read_text(fixture)
+                .lines()
+                .map(|it| it.trim().to_string())
+                .filter(|it| !it.is_empty())
+                .collect::<Vec<_>>();
+            let alerts = summarize_error_log(engine, &ErrorAlertRules::builtin(), &lines).alerts;
+            let alerts_value = serde_json::to_value(alerts).expect("serialize catalog alerts");
+            assert_eq!(alerts_value, read_json(golden), "{fixture}");
+        }
+    }
+
     #[test]
     fn should_match_log_parsing_golden_fixtures() {
         let mysql_slow = read_text("tests/fixtures/logs/mysql_slow.log");
-        let mysql_digests = aggregate_slow_sql_digests(parse_mysql_slow_log_entries(&mysql_slow));
+        let mysql_digests = aggregate_slow_sql_digests(
+            SqlDialect::Mysql,
+            collect_entries(parse_mysql_slow_log_entries(mysql_slow.as_bytes())),
+        );
         let mysql_digests_value =
             serde_json::to_value(mysql_digests).expect("serialize mysql digests");
         assert_eq!(
@@ -1042,7 +3094,12 @@ SELECT * FROM orders WHERE id = 101;
             .map(|it| it.trim().to_string())
             .filter(|it| !it.is_empty())
             .collect::<Vec<_>>();
-        let mysql_alerts = extract_error_log_alerts(&mysql_error_lines);
+        let mysql_alerts = summarize_error_log(
+            Level1Engine::Mysql,
+            &ErrorAlertRules::builtin(),
+            &mysql_error_lines,
+        )
+        .alerts;
         let mysql_alerts_value =
             serde_json::to_value(mysql_alerts).expect("serialize mysql alerts");
         assert_eq!(
@@ -1051,8 +3108,12 @@ SELECT * FROM orders WHERE id = 101;
         );
 
         let pg_statement = read_text("tests/fixtures/logs/postgres_statement.log");
-        let pg_digests =
-            aggregate_slow_sql_digests(parse_postgres_statement_log_entries(&pg_statement));
+        let pg_digests = aggregate_slow_sql_digests(
+            SqlDialect::Postgres,
+            collect_entries(parse_postgres_statement_log_entries(
+                pg_statement.as_bytes(),
+            )),
+        );
         let pg_digests_value = serde_json::to_value(pg_digests).expect("serialize pg digests");
         assert_eq!(
             pg_digests_value,
@@ -1065,7 +3126,12 @@ SELECT * FROM orders WHERE id = 101;
             .map(|it| it.trim().to_string())
             .filter(|it| !it.is_empty())
             .collect::<Vec<_>>();
-        let pg_alerts = extract_error_log_alerts(&pg_error_lines);
+        let pg_alerts = summarize_error_log(
+            Level1Engine::Postgres,
+            &ErrorAlertRules::builtin(),
+            &pg_error_lines,
+        )
+        .alerts;
         let pg_alerts_value = serde_json::to_value(pg_alerts).expect("serialize pg alerts");
         assert_eq!(
             pg_alerts_value,
@@ -1,5 +1,21 @@
 pub mod collection;
+pub mod config;
+pub mod deadlocks;
+pub mod error_log;
+pub mod executor;
+pub mod fingerprint;
+pub mod innodb_status;
+pub mod innodb_status_hf;
 pub mod level0;
 pub mod level1;
+pub mod perf_profile;
 pub mod pipeline;
 pub mod postgres_level0;
+pub mod probe;
+pub mod query_tags;
+pub mod secrets;
+pub mod strace_summary;
+pub mod table_attribution;
+pub mod targets;
+pub mod transactions;
+pub mod wire_capture;
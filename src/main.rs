@@ -1,23 +1,50 @@
+use std::collections::BTreeSet;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::thread;
 use std::time::{Duration, Instant};
 
-use clap::{ArgAction, Parser, ValueEnum};
+use clap::parser::ValueSource;
+use clap::{ArgAction, ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
 use serde::Serialize;
 use sql_insight::collection::{
-    CapabilityProbe, CollectionLevel, CollectionPolicy, negotiate_collection_level,
+    CollectionLevel, CollectionPolicy, DowngradeReason, NegotiationResult, all_tasks,
+    negotiate_collection_level,
+};
+use sql_insight::config::{
+    CollectionSection, ConfigFile, ConfigLayer, ConnectionSection, Level1Section, LimitsSection,
+    OutputFormat, OutputSection, RulesSection, SchedulerSection,
+};
+use sql_insight::error_log::ErrorAlertRules;
+use sql_insight::executor::{
+    Collector, CollectorOutcome, CollectorRegistry, TaskExecution, TaskFilter,
+};
+use sql_insight::innodb_status_hf::{
+    InnodbStatusHfConfig, InnodbStatusHfReport, collect_innodb_status_hf,
 };
 use sql_insight::level0::{Level0CollectionReport, Level0CollectorConfig, collect_level0};
 use sql_insight::level1::{
     Level1CollectionReport, Level1CollectorConfig, Level1Engine, collect_level1,
 };
+use sql_insight::perf_profile::{PerfProfileConfig, PerfProfileReport, collect_perf_profile};
 use sql_insight::pipeline::{
-    AttemptTrace, RunMode, SchedulerConfig, SourceStatus, UnifiedCollectionRecord,
-    jittered_interval, new_run_id, now_unix_ms, run_with_timeout,
+    AttemptSlots, AttemptTrace, CollectionTarget, RunMode, SchedulerConfig, SourceStatus,
+    UnifiedCollectionRecord, jittered_interval, new_run_id, now_unix_ms, run_with_timeout,
 };
 use sql_insight::postgres_level0::{
     PostgresLevel0CollectionReport, PostgresLevel0CollectorConfig, collect_postgres_level0,
 };
-use tracing::{debug, info, warn};
+use sql_insight::probe::{ProbeConfig, ProbeReport, run_probe};
+use sql_insight::secrets::{CredentialSources, DatabaseUrl, redact, redact_json};
+use sql_insight::strace_summary::{StraceConfig, StraceSummaryReport, collect_strace_summary};
+use sql_insight::table_attribution::{
+    TableSlowSqlRollup, attribute_slow_sql_to_tables, mysql_table_sizes, postgres_table_sizes,
+};
+use sql_insight::targets::{TargetConfig, load_targets, run_fleet};
+use sql_insight::wire_capture::{WireCaptureConfig, WireCaptureReport, collect_wire_capture};
+use tracing::{Span, debug, info, info_span, warn};
 use tracing_subscriber::filter::LevelFilter;
 
 #[derive(Debug, Clone, Parser)]
@@ -27,6 +54,12 @@ use tracing_subscriber::filter::LevelFilter;
     about = "SQL Insight collector with unified data contract and scheduler"
 )]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<CliCommand>,
+    #[arg(long, env = "CONFIG_FILE", global = true)]
+    config: Option<String>,
+    #[arg(long, env = "CONFIG_PROFILE", global = true)]
+    profile: Option<String>,
     #[arg(long, value_enum, env = "DB_ENGINE", default_value_t = DatabaseEngine::Mysql)]
     engine: DatabaseEngine,
     #[arg(long, value_enum, default_value_t = CollectLevel::Level1)]
@@ -45,10 +78,12 @@ struct Cli {
     retry_backoff_ms: u64,
     #[arg(long, env = "RUN_MAX_CYCLES")]
     max_cycles: Option<u32>,
-    #[arg(long, env = "MYSQL_URL")]
-    mysql_url: Option<String>,
-    #[arg(long, env = "POSTGRES_URL")]
-    postgres_url: Option<String>,
+    #[arg(long, env = "MYSQL_URL", hide_env_values = true)]
+    mysql_url: Option<DatabaseUrl>,
+    #[arg(long, env = "POSTGRES_URL", hide_env_values = true)]
+    postgres_url: Option<DatabaseUrl>,
+    #[arg(long, env = "DB_PASSWORD_FILE")]
+    password_file: Option<String>,
     #[arg(long, env = "LEVEL0_TABLE_LIMIT", default_value_t = 200)]
     table_limit: usize,
     #[arg(long, env = "LEVEL0_INDEX_LIMIT", default_value_t = 500)]
@@ -61,26 +96,93 @@ struct Cli {
     slow_log_path: Option<String>,
     #[arg(long, env = "LEVEL1_ERROR_LOG_PATH")]
     error_log_path: Option<String>,
+    #[arg(long, env = "LEVEL1_SLOW_LOG_ARCHIVE")]
+    slow_log_archive: Option<String>,
     #[arg(long, env = "LEVEL1_MAX_SLOW_LOG_BYTES", default_value_t = 2_000_000)]
     max_slow_log_bytes: usize,
     #[arg(long, env = "LEVEL1_MAX_ERROR_LOG_BYTES", default_value_t = 2_000_000)]
     max_error_log_bytes: usize,
     #[arg(long, env = "LEVEL1_MAX_ERROR_LOG_LINES", default_value_t = 2_000)]
     max_error_log_lines: usize,
+    #[arg(long, env = "LEVEL1_ERROR_ALERT_RULES")]
+    error_alert_rules: Option<String>,
+    #[arg(long, env = "LEVEL1_LONG_TRANSACTION_SECS", default_value_t = 1.0)]
+    long_transaction_secs: f64,
+    #[arg(long, env = "LEVEL1_IDLE_IN_TRANSACTION_SECS", default_value_t = 1.0)]
+    idle_in_transaction_secs: f64,
+    #[arg(long, env = "EXPERT_MODE_ENABLED", default_value_t = false)]
+    expert_mode: bool,
+    #[arg(long, env = "INNODB_STATUS_HF_INTERVAL_MS", default_value_t = 250)]
+    innodb_status_hf_interval_ms: u64,
+    #[arg(long, env = "INNODB_STATUS_HF_DURATION_MS", default_value_t = 5_000)]
+    innodb_status_hf_duration_ms: u64,
+    #[arg(long, env = "INNODB_STATUS_HF_MAX_SAMPLES", default_value_t = 20)]
+    innodb_status_hf_max_samples: usize,
+    #[arg(long, env = "PERF_DURATION_SECS", default_value_t = 5)]
+    perf_duration_secs: u64,
+    #[arg(long, env = "PERF_FREQUENCY_HZ", default_value_t = 99)]
+    perf_frequency_hz: u32,
+    #[arg(long, env = "STRACE_DURATION_SECS", default_value_t = 5)]
+    strace_duration_secs: u64,
+    #[arg(long, env = "PERF_OUTPUT_DIR")]
+    perf_output_dir: Option<String>,
+    #[arg(long, env = "DB_PID")]
+    db_pid: Option<u32>,
+    #[arg(long, env = "DB_PID_FILE")]
+    db_pid_file: Option<String>,
+    #[arg(long, env = "PCAP_FILE")]
+    pcap_file: Option<String>,
+    #[arg(long, env = "CAPTURE_PORT")]
+    capture_port: Option<u16>,
+    #[arg(long, env = "CAPTURE_INTERFACE", default_value = "any")]
+    capture_interface: String,
+    #[arg(long, env = "TCPDUMP_DURATION_SECS", default_value_t = 5)]
+    tcpdump_duration_secs: u64,
+    #[arg(long, env = "CAPTURE_OUTPUT_DIR")]
+    capture_output_dir: Option<String>,
+    #[arg(long, env = "INCLUDE_TASKS", value_delimiter = ',')]
+    include_tasks: Vec<String>,
+    #[arg(long, env = "EXCLUDE_TASKS", value_delimiter = ',')]
+    exclude_tasks: Vec<String>,
+    #[arg(long, env = "TARGETS_FILE")]
+    targets_file: Option<String>,
+    #[arg(long, env = "MAX_WORKERS", default_value_t = 4)]
+    max_workers: usize,
     #[arg(long, default_value_t = false)]
     no_slow_log_hot_switch: bool,
     #[arg(long, default_value_t = false)]
     no_restore_slow_log_settings: bool,
     #[arg(long, value_enum, default_value_t = ReportFormat::PrettyJson)]
     output: ReportFormat,
+    #[arg(long, env = "OUTPUT_FILE")]
+    output_file: Option<String>,
     #[arg(short, long, action = ArgAction::Count, global = true)]
     verbose: u8,
 }
 
+#[derive(Debug, Clone, Subcommand)]
+enum CliCommand {
+    /// Probe capabilities, negotiate the collection level and print the result
+    Probe,
+    /// Inspect the configuration file and profiles
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+}
+
+#[derive(Debug, Clone, Subcommand)]
+enum ConfigCommand {
+    /// Print the effective configuration with secrets masked
+    Check,
+}
+
 #[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq)]
 enum CollectLevel {
     Level0,
     Level1,
+    Level2,
+    Level3,
 }
 
 #[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq)]
@@ -100,15 +202,70 @@ struct CliOutput {
     engine: String,
     requested_level: String,
     selected_level: String,
-    downgrade_reasons: Vec<String>,
+    downgrade_reasons: Vec<DowngradeReason>,
+    probe: ProbeReport,
+    tasks: Vec<TaskExecution>,
     level0: Level0CollectionReport,
     postgres_level0: Option<PostgresLevel0CollectionReport>,
     level1: Option<Level1CollectionReport>,
+    table_attribution: Option<Vec<TableSlowSqlRollup>>,
+    innodb_status_hf: Option<InnodbStatusHfReport>,
+    perf_profile: Option<PerfProfileReport>,
+    strace_summary: Option<StraceSummaryReport>,
+    wire_capture: Option<WireCaptureReport>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ProbeOutput {
+    engine: String,
+    requested_level: String,
+    selected_level: String,
+    downgrade_reasons: Vec<DowngradeReason>,
+    tasks: Vec<String>,
+    probe: ProbeReport,
 }
 
 fn main() -> anyhow::Result<()> {
-    let cli = Cli::parse();
+    run().map_err(|err| anyhow::anyhow!(redact(&format!("{err:#}")).into_owned()))
+}
+
+fn run() -> anyhow::Result<()> {
+    let matches = Cli::command().get_matches();
+    let mut cli = Cli::from_arg_matches(&matches)?;
     init_tracing(cli.verbose);
+    if let Some(path) = cli.config.clone() {
+        let file = ConfigFile::load(Path::new(&path))?;
+        let layer = file.resolve(cli.profile.as_deref())?;
+        if cli.profile.is_none() {
+            cli.profile = file.default_profile;
+        }
+        apply_config(&mut cli, &matches, layer);
+        info!(
+            path = path,
+            profile = cli.profile.as_deref(),
+            "config file applied"
+        );
+    } else if cli.profile.is_some() {
+        anyhow::bail!("--profile requires --config");
+    }
+    if let Some(CliCommand::Config {
+        command: ConfigCommand::Check,
+    }) = &cli.command
+    {
+        return run_config_check(&cli);
+    }
+    resolve_credentials(&mut cli)?;
+    if let Some(CliCommand::Probe) = cli.command {
+        return run_probe_command(&cli);
+    }
+    let alert_rules = match cli.error_alert_rules.as_deref() {
+        Some(path) => {
+            let rules = ErrorAlertRules::load(Path::new(path))?;
+            info!(path = path, rules = rules.len(), "error alert rules loaded");
+            rules
+        }
+        None => ErrorAlertRules::builtin(),
+    };
 
     let scheduler = SchedulerConfig {
         mode: cli.run_mode,
@@ -130,199 +287,1169 @@ fn main() -> anyhow::Result<()> {
         "scheduler initialized"
     );
 
+    if let Some(path) = cli.targets_file.as_deref() {
+        return run_targets(&cli, Path::new(path), &alert_rules, &scheduler, &run_id);
+    }
+
+    let task_filter = TaskFilter::new(
+        level1_engine(cli.engine),
+        &cli.include_tasks,
+        &cli.exclude_tasks,
+    )
+    .map_err(anyhow::Error::msg)?;
+
+    // One attempt at a time, even when an earlier one timed out and is still
+    // running detached.
+    let slots = AttemptSlots::new(1);
     let mut cycle: u32 = 0;
     loop {
         cycle += 1;
-        let cycle_start_ms = now_unix_ms();
-        let mut attempts = Vec::new();
-        let mut payload = None;
-        let total_attempts = scheduler.retry_times.saturating_add(1);
+        let record = collect_cycle(
+            &cli,
+            &alert_rules,
+            &task_filter,
+            &scheduler,
+            CycleId {
+                run_id: &run_id,
+                cycle,
+                target: None,
+            },
+            &[&slots],
+        );
+        emit_record(&record, &cli)?;
 
+        if scheduler.mode == RunMode::Once {
+            break;
+        }
+        if let Some(max_cycles) = scheduler.max_cycles
+            && cycle >= max_cycles
+        {
+            info!(
+                run_id = run_id,
+                cycle = cycle,
+                "scheduler reached max cycles"
+            );
+            break;
+        }
+
+        let sleep_for = jittered_interval(scheduler.interval_secs, scheduler.jitter_pct);
         info!(
             run_id = run_id,
             cycle = cycle,
-            engine = ?cli.engine,
-            collect_level = ?cli.collect_level,
-            "collection cycle started"
+            sleep_ms = sleep_for.as_millis() as u64,
+            "scheduler sleeping before next cycle"
         );
+        thread::sleep(sleep_for);
+    }
 
-        for attempt in 1..=total_attempts {
-            let attempt_start = Instant::now();
-            let attempt_cli = cli.clone();
-            let timeout = Duration::from_secs(scheduler.timeout_secs);
-            let result = run_with_timeout(timeout, move || {
-                collect_once(&attempt_cli).map_err(|it| it.to_string())
-            });
-            let duration_ms = attempt_start.elapsed().as_millis();
-
-            match result {
-                Ok(output) => {
-                    attempts.push(AttemptTrace {
-                        attempt,
-                        duration_ms,
-                        status: "ok".to_string(),
-                        error: None,
-                    });
-                    payload = Some(output);
-                    info!(
-                        run_id = run_id,
-                        cycle = cycle,
-                        attempt = attempt,
-                        duration_ms = duration_ms,
-                        "collection attempt succeeded"
-                    );
-                    break;
-                }
-                Err(err) => {
-                    attempts.push(AttemptTrace {
-                        attempt,
-                        duration_ms,
-                        status: "failed".to_string(),
+    Ok(())
+}
+
+/// Collects every target in the file on a bounded worker pool, each on its
+/// own interval. Records are emitted as targets finish.
+fn run_targets(
+    cli: &Cli,
+    path: &Path,
+    alert_rules: &ErrorAlertRules,
+    scheduler: &SchedulerConfig,
+    run_id: &str,
+) -> anyhow::Result<()> {
+    let targets = load_targets(path)?;
+    let max_workers = normalize_limit("max_workers", cli.max_workers, 4);
+    info!(
+        path = %path.display(),
+        targets = targets.len(),
+        max_workers = max_workers,
+        "targets loaded"
+    );
+
+    let mut jobs = Vec::with_capacity(targets.len());
+    for target in &targets {
+        let mut target_cli = target_cli(cli, target);
+        let prepared = {
+            let _span = info_span!("target", name = %target.name).entered();
+            resolve_credentials(&mut target_cli)
+        }
+        .and_then(|()| {
+            TaskFilter::new(
+                target.engine,
+                &target_cli.include_tasks,
+                &target_cli.exclude_tasks,
+            )
+            .map_err(anyhow::Error::msg)
+        })
+        .map_err(|err| {
+            let err = format!("target setup failed: {err:#}");
+            warn!(target = %target.name, error = %err, "target will report failed records");
+            err
+        });
+        jobs.push(TargetJob {
+            scheduler: SchedulerConfig {
+                interval_secs: target.interval_secs.unwrap_or(scheduler.interval_secs),
+                ..scheduler.clone()
+            },
+            target: CollectionTarget {
+                name: target.name.clone(),
+                labels: target.labels.clone(),
+            },
+            slots: AttemptSlots::new(1),
+            cli: target_cli,
+            prepared,
+        });
+    }
+    let intervals = jobs
+        .iter()
+        .map(|it| it.scheduler.interval_secs)
+        .collect::<Vec<_>>();
+    let jobs = Arc::new(jobs);
+    let alert_rules = alert_rules.clone();
+    let job_run_id = run_id.to_string();
+    // Timed-out attempts keep running detached; they count against the same
+    // budget so the fleet never runs more than `max_workers` collections.
+    let fleet_slots = AttemptSlots::new(max_workers);
+
+    run_fleet(
+        &intervals,
+        scheduler,
+        max_workers,
+        move |index, cycle| {
+            let job = &jobs[index];
+            let _span = info_span!("target", name = %job.target.name).entered();
+            match &job.prepared {
+                Ok(task_filter) => collect_cycle(
+                    &job.cli,
+                    &alert_rules,
+                    task_filter,
+                    &job.scheduler,
+                    job.cycle_id(&job_run_id, cycle),
+                    &[&job.slots, &fleet_slots],
+                ),
+                Err(err) => cycle_record(
+                    &job.cli,
+                    &job.scheduler,
+                    job.cycle_id(&job_run_id, cycle),
+                    CycleOutcome {
+                        start_ms: now_unix_ms(),
+                        attempts: Vec::new(),
                         error: Some(err.clone()),
-                    });
-                    warn!(
-                        run_id = run_id,
-                        cycle = cycle,
-                        attempt = attempt,
-                        duration_ms = duration_ms,
-                        error = err,
-                        "collection attempt failed"
-                    );
-                    if attempt < total_attempts {
-                        thread::sleep(Duration::from_millis(scheduler.retry_backoff_ms));
-                    }
-                }
+                        payload: None,
+                    },
+                ),
             }
-        }
+        },
+        |_, _, record| {
+            if let Err(err) = emit_record(&record, cli) {
+                warn!(
+                    target = record.target.as_ref().map(|it| it.name.as_str()),
+                    error = %err,
+                    "failed to emit record"
+                );
+            }
+        },
+    );
+    Ok(())
+}
 
-        let cycle_end_ms = now_unix_ms();
-        let last_error = attempts.iter().rev().find_map(|it| it.error.clone());
-        let source_status = payload
-            .as_ref()
-            .map(derive_source_status)
-            .unwrap_or_default();
-        let warnings = payload.as_ref().map(collect_warnings).unwrap_or_default();
-        let selected_level = payload.as_ref().map(|it| it.selected_level.clone());
-        let record = UnifiedCollectionRecord {
-            contract_version: "v1".to_string(),
-            run_id: run_id.clone(),
+/// One entry of a targets file, resolved once before the fleet starts. A
+/// target whose credentials or task filter cannot be set up keeps its slot
+/// in the schedule and reports a failed record every cycle.
+struct TargetJob {
+    cli: Cli,
+    prepared: Result<TaskFilter, String>,
+    scheduler: SchedulerConfig,
+    target: CollectionTarget,
+    /// Keeps a timed-out attempt from overlapping the target's next one.
+    slots: AttemptSlots,
+}
+
+impl TargetJob {
+    fn cycle_id<'a>(&self, run_id: &'a str, cycle: u32) -> CycleId<'a> {
+        CycleId {
+            run_id,
             cycle,
-            engine: engine_label(cli.engine).to_string(),
-            requested_level: format!("{}", requested_to_collection_level(cli.collect_level)),
-            selected_level,
-            schedule: scheduler.clone(),
-            window: sql_insight::pipeline::ScheduleWindow {
-                start_unix_ms: cycle_start_ms,
-                end_unix_ms: cycle_end_ms,
-                duration_ms: cycle_end_ms.saturating_sub(cycle_start_ms),
-            },
-            attempts,
-            source_status,
-            warnings,
-            status: if payload.is_some() {
-                "ok".to_string()
-            } else {
-                "failed".to_string()
-            },
-            error: last_error,
-            payload,
-        };
-        emit_record(&record, cli.output)?;
+            target: Some(self.target.clone()),
+        }
+    }
+}
 
-        if scheduler.mode == RunMode::Once {
-            break;
+/// Applies a target's engine, URL, log paths and limits over the CLI options.
+fn target_cli(cli: &Cli, target: &TargetConfig) -> Cli {
+    let mut target_cli = cli.clone();
+    target_cli.engine = database_engine(target.engine);
+    match target.engine {
+        Level1Engine::Mysql => {
+            target_cli.mysql_url = Some(DatabaseUrl::new(&target.url));
+            target_cli.postgres_url = None;
         }
-        if let Some(max_cycles) = scheduler.max_cycles {
-            if cycle >= max_cycles {
+        Level1Engine::Postgres => {
+            target_cli.postgres_url = Some(DatabaseUrl::new(&target.url));
+            target_cli.mysql_url = None;
+        }
+    }
+    if let Some(path) = &target.password_file {
+        target_cli.password_file = Some(path.clone());
+    }
+    if let Some(level) = target.collect_level {
+        target_cli.collect_level = collection_to_requested_level(level);
+    }
+    if let Some(path) = &target.slow_log_path {
+        target_cli.slow_log_path = Some(path.clone());
+    }
+    if let Some(path) = &target.error_log_path {
+        target_cli.error_log_path = Some(path.clone());
+    }
+    target_cli.table_limit = target.table_limit.unwrap_or(cli.table_limit);
+    target_cli.index_limit = target.index_limit.unwrap_or(cli.index_limit);
+    target_cli
+}
+
+/// Adds passwords from `--password-file`, `MYSQL_PWD` / `PGPASSWORD`,
+/// `~/.my.cnf` or `.pgpass` to URLs that carry none.
+fn resolve_credentials(cli: &mut Cli) -> anyhow::Result<()> {
+    let password_file = cli.password_file.as_ref().map(PathBuf::from);
+    for (engine, slot) in [
+        (Level1Engine::Mysql, &mut cli.mysql_url),
+        (Level1Engine::Postgres, &mut cli.postgres_url),
+    ] {
+        let Some(url) = slot.take() else {
+            continue;
+        };
+        let sources = CredentialSources::from_env(engine, password_file.clone());
+        let (url, warnings) = url.with_credentials(engine, &sources)?;
+        log_report_warnings("credentials", &warnings);
+        debug!(
+            engine = ?engine,
+            password_source = %url.password_source(),
+            "database credentials resolved"
+        );
+        *slot = Some(url);
+    }
+    Ok(())
+}
+
+/// Runs one scheduled cycle with retries and wraps the result in a record;
+/// failures are recorded rather than returned.
+fn collect_cycle(
+    cli: &Cli,
+    alert_rules: &ErrorAlertRules,
+    task_filter: &TaskFilter,
+    scheduler: &SchedulerConfig,
+    id: CycleId,
+    slots: &[&AttemptSlots],
+) -> UnifiedCollectionRecord<CliOutput> {
+    let CycleId { run_id, cycle, .. } = id;
+    let cycle_start_ms = now_unix_ms();
+    let mut attempts = Vec::new();
+    let mut payload = None;
+    let total_attempts = scheduler.retry_times.saturating_add(1);
+
+    info!(
+        run_id = run_id,
+        cycle = cycle,
+        engine = ?cli.engine,
+        collect_level = ?cli.collect_level,
+        "collection cycle started"
+    );
+
+    for attempt in 1..=total_attempts {
+        let attempt_start = Instant::now();
+        let attempt_cli = cli.clone();
+        let attempt_rules = alert_rules.clone();
+        let attempt_filter = task_filter.clone();
+        let span = Span::current();
+        let timeout = Duration::from_secs(scheduler.timeout_secs);
+        let result = run_with_timeout(timeout, slots, move || {
+            let _span = span.entered();
+            collect_once(&attempt_cli, &attempt_rules, &attempt_filter).map_err(|it| it.to_string())
+        });
+        let duration_ms = attempt_start.elapsed().as_millis();
+
+        match result {
+            Ok(output) => {
+                attempts.push(AttemptTrace {
+                    attempt,
+                    duration_ms,
+                    status: "ok".to_string(),
+                    error: None,
+                });
+                payload = Some(output);
                 info!(
                     run_id = run_id,
                     cycle = cycle,
-                    "scheduler reached max cycles"
+                    attempt = attempt,
+                    duration_ms = duration_ms,
+                    "collection attempt succeeded"
                 );
                 break;
             }
+            Err(err) => {
+                attempts.push(AttemptTrace {
+                    attempt,
+                    duration_ms,
+                    status: "failed".to_string(),
+                    error: Some(err.clone()),
+                });
+                warn!(
+                    run_id = run_id,
+                    cycle = cycle,
+                    attempt = attempt,
+                    duration_ms = duration_ms,
+                    error = err,
+                    "collection attempt failed"
+                );
+                if attempt < total_attempts {
+                    thread::sleep(Duration::from_millis(scheduler.retry_backoff_ms));
+                }
+            }
         }
+    }
 
-        let sleep_for = jittered_interval(scheduler.interval_secs, scheduler.jitter_pct);
-        info!(
-            run_id = run_id,
-            cycle = cycle,
-            sleep_ms = sleep_for.as_millis() as u64,
-            "scheduler sleeping before next cycle"
-        );
-        thread::sleep(sleep_for);
+    let error = attempts.iter().rev().find_map(|it| it.error.clone());
+    cycle_record(
+        cli,
+        scheduler,
+        id,
+        CycleOutcome {
+            start_ms: cycle_start_ms,
+            attempts,
+            error,
+            payload,
+        },
+    )
+}
+
+/// Identifies one collection cycle in the emitted record.
+struct CycleId<'a> {
+    run_id: &'a str,
+    cycle: u32,
+    target: Option<CollectionTarget>,
+}
+
+struct CycleOutcome {
+    start_ms: u128,
+    attempts: Vec<AttemptTrace>,
+    error: Option<String>,
+    payload: Option<CliOutput>,
+}
+
+fn cycle_record(
+    cli: &Cli,
+    scheduler: &SchedulerConfig,
+    id: CycleId,
+    outcome: CycleOutcome,
+) -> UnifiedCollectionRecord<CliOutput> {
+    let CycleId {
+        run_id,
+        cycle,
+        target,
+    } = id;
+    let CycleOutcome {
+        start_ms,
+        attempts,
+        error,
+        payload,
+    } = outcome;
+    let end_ms = now_unix_ms();
+    let source_status = payload
+        .as_ref()
+        .map(derive_source_status)
+        .unwrap_or_default();
+    let warnings = payload.as_ref().map(collect_warnings).unwrap_or_default();
+    let selected_level = payload.as_ref().map(|it| it.selected_level.clone());
+    UnifiedCollectionRecord {
+        contract_version: "v2".to_string(),
+        run_id: run_id.to_string(),
+        cycle,
+        engine: engine_label(cli.engine).to_string(),
+        target,
+        requested_level: format!("{}", requested_to_collection_level(cli.collect_level)),
+        selected_level,
+        schedule: scheduler.clone(),
+        window: sql_insight::pipeline::ScheduleWindow {
+            start_unix_ms: start_ms,
+            end_unix_ms: end_ms,
+            duration_ms: end_ms.saturating_sub(start_ms),
+        },
+        attempts,
+        source_status,
+        warnings,
+        status: if payload.is_some() {
+            "ok".to_string()
+        } else {
+            "failed".to_string()
+        },
+        error,
+        payload,
     }
+}
 
+fn run_config_check(cli: &Cli) -> anyhow::Result<()> {
+    let header = format!(
+        "# config: {}\n# profile: {}\n",
+        cli.config.as_deref().unwrap_or("(none)"),
+        cli.profile.as_deref().unwrap_or("(none)")
+    );
+    println!("{}", redact(&header));
+    print!("{}", effective_config(cli).to_masked_toml()?);
     Ok(())
 }
 
-fn collect_once(cli: &Cli) -> anyhow::Result<CliOutput> {
-    let table_limit = normalize_limit("table_limit", cli.table_limit, 200);
-    let index_limit = normalize_limit("index_limit", cli.index_limit, 500);
-    let level0_config = Level0CollectorConfig {
-        mysql_url: if cli.engine == DatabaseEngine::Mysql {
-            cli.mysql_url.clone()
-        } else {
-            None
+/// Fills options still at their clap defaults from the config file, so
+/// environment variables and flags keep precedence over it.
+fn apply_config(cli: &mut Cli, matches: &ArgMatches, layer: ConfigLayer) {
+    let ConfigLayer {
+        connection,
+        collection,
+        scheduler,
+        limits,
+        level1,
+        rules,
+        output,
+    } = layer;
+    let m = matches;
+    layer_value(
+        m,
+        "engine",
+        &mut cli.engine,
+        connection.engine.map(database_engine),
+    );
+    layer_value(
+        m,
+        "mysql_url",
+        &mut cli.mysql_url,
+        connection.mysql_url.map(|it| Some(DatabaseUrl::new(it))),
+    );
+    layer_value(
+        m,
+        "postgres_url",
+        &mut cli.postgres_url,
+        connection.postgres_url.map(|it| Some(DatabaseUrl::new(it))),
+    );
+    layer_value(
+        m,
+        "password_file",
+        &mut cli.password_file,
+        connection.password_file.map(Some),
+    );
+    layer_value(
+        m,
+        "targets_file",
+        &mut cli.targets_file,
+        connection.targets_file.map(Some),
+    );
+
+    layer_value(
+        m,
+        "collect_level",
+        &mut cli.collect_level,
+        collection.collect_level.map(collection_to_requested_level),
+    );
+    layer_value(
+        m,
+        "expert_mode",
+        &mut cli.expert_mode,
+        collection.expert_mode,
+    );
+    layer_value(
+        m,
+        "include_tasks",
+        &mut cli.include_tasks,
+        collection.include_tasks,
+    );
+    layer_value(
+        m,
+        "exclude_tasks",
+        &mut cli.exclude_tasks,
+        collection.exclude_tasks,
+    );
+
+    layer_value(m, "run_mode", &mut cli.run_mode, scheduler.run_mode);
+    layer_value(
+        m,
+        "interval_secs",
+        &mut cli.interval_secs,
+        scheduler.interval_secs,
+    );
+    layer_value(m, "jitter_pct", &mut cli.jitter_pct, scheduler.jitter_pct);
+    layer_value(
+        m,
+        "timeout_secs",
+        &mut cli.timeout_secs,
+        scheduler.timeout_secs,
+    );
+    layer_value(
+        m,
+        "retry_times",
+        &mut cli.retry_times,
+        scheduler.retry_times,
+    );
+    layer_value(
+        m,
+        "retry_backoff_ms",
+        &mut cli.retry_backoff_ms,
+        scheduler.retry_backoff_ms,
+    );
+    layer_value(
+        m,
+        "max_cycles",
+        &mut cli.max_cycles,
+        scheduler.max_cycles.map(Some),
+    );
+    layer_value(
+        m,
+        "max_workers",
+        &mut cli.max_workers,
+        scheduler.max_workers,
+    );
+
+    layer_value(m, "table_limit", &mut cli.table_limit, limits.table_limit);
+    layer_value(m, "index_limit", &mut cli.index_limit, limits.index_limit);
+    layer_value(
+        m,
+        "max_slow_log_bytes",
+        &mut cli.max_slow_log_bytes,
+        limits.max_slow_log_bytes,
+    );
+    layer_value(
+        m,
+        "max_error_log_bytes",
+        &mut cli.max_error_log_bytes,
+        limits.max_error_log_bytes,
+    );
+    layer_value(
+        m,
+        "max_error_log_lines",
+        &mut cli.max_error_log_lines,
+        limits.max_error_log_lines,
+    );
+
+    layer_value(
+        m,
+        "slow_log_window_secs",
+        &mut cli.slow_log_window_secs,
+        level1.slow_log_window_secs,
+    );
+    layer_value(
+        m,
+        "slow_log_long_query_time_secs",
+        &mut cli.slow_log_long_query_time_secs,
+        level1.slow_log_long_query_time_secs,
+    );
+    layer_value(
+        m,
+        "slow_log_path",
+        &mut cli.slow_log_path,
+        level1.slow_log_path.map(Some),
+    );
+    layer_value(
+        m,
+        "error_log_path",
+        &mut cli.error_log_path,
+        level1.error_log_path.map(Some),
+    );
+    layer_value(
+        m,
+        "no_slow_log_hot_switch",
+        &mut cli.no_slow_log_hot_switch,
+        level1.slow_log_hot_switch.map(|it| !it),
+    );
+    layer_value(
+        m,
+        "no_restore_slow_log_settings",
+        &mut cli.no_restore_slow_log_settings,
+        level1.restore_slow_log_settings.map(|it| !it),
+    );
+
+    layer_value(
+        m,
+        "error_alert_rules",
+        &mut cli.error_alert_rules,
+        rules.error_alert_rules.map(Some),
+    );
+    layer_value(
+        m,
+        "long_transaction_secs",
+        &mut cli.long_transaction_secs,
+        rules.long_transaction_secs,
+    );
+    layer_value(
+        m,
+        "idle_in_transaction_secs",
+        &mut cli.idle_in_transaction_secs,
+        rules.idle_in_transaction_secs,
+    );
+
+    layer_value(
+        m,
+        "output",
+        &mut cli.output,
+        output.format.map(report_format),
+    );
+    layer_value(
+        m,
+        "output_file",
+        &mut cli.output_file,
+        output.file.map(Some),
+    );
+}
+
+fn layer_value<T>(matches: &ArgMatches, id: &str, slot: &mut T, value: Option<T>) {
+    if let Some(value) = value
+        && matches!(
+            matches.value_source(id),
+            None | Some(ValueSource::DefaultValue)
+        )
+    {
+        *slot = value;
+    }
+}
+
+/// The settings a run would use, in config file layout.
+fn effective_config(cli: &Cli) -> ConfigLayer {
+    ConfigLayer {
+        connection: ConnectionSection {
+            engine: Some(level1_engine(cli.engine)),
+            mysql_url: cli.mysql_url.as_ref().map(DatabaseUrl::masked),
+            postgres_url: cli.postgres_url.as_ref().map(DatabaseUrl::masked),
+            password_file: cli.password_file.clone(),
+            targets_file: cli.targets_file.clone(),
+        },
+        collection: CollectionSection {
+            collect_level: Some(requested_to_collection_level(cli.collect_level)),
+            expert_mode: Some(cli.expert_mode),
+            include_tasks: Some(cli.include_tasks.clone()),
+            exclude_tasks: Some(cli.exclude_tasks.clone()),
         },
-        table_limit,
-        index_limit,
-        suppress_mysql_missing_warning: cli.engine == DatabaseEngine::Postgres,
+        scheduler: SchedulerSection {
+            run_mode: Some(cli.run_mode),
+            interval_secs: Some(cli.interval_secs),
+            jitter_pct: Some(cli.jitter_pct),
+            timeout_secs: Some(cli.timeout_secs),
+            retry_times: Some(cli.retry_times),
+            retry_backoff_ms: Some(cli.retry_backoff_ms),
+            max_cycles: cli.max_cycles,
+            max_workers: Some(cli.max_workers),
+        },
+        limits: LimitsSection {
+            table_limit: Some(cli.table_limit),
+            index_limit: Some(cli.index_limit),
+            max_slow_log_bytes: Some(cli.max_slow_log_bytes),
+            max_error_log_bytes: Some(cli.max_error_log_bytes),
+            max_error_log_lines: Some(cli.max_error_log_lines),
+        },
+        level1: Level1Section {
+            slow_log_window_secs: Some(cli.slow_log_window_secs),
+            slow_log_long_query_time_secs: Some(cli.slow_log_long_query_time_secs),
+            slow_log_path: cli.slow_log_path.clone(),
+            error_log_path: cli.error_log_path.clone(),
+            slow_log_hot_switch: Some(!cli.no_slow_log_hot_switch),
+            restore_slow_log_settings: Some(!cli.no_restore_slow_log_settings),
+        },
+        rules: RulesSection {
+            error_alert_rules: cli.error_alert_rules.clone(),
+            long_transaction_secs: Some(cli.long_transaction_secs),
+            idle_in_transaction_secs: Some(cli.idle_in_transaction_secs),
+        },
+        output: OutputSection {
+            format: Some(match cli.output {
+                ReportFormat::Json => OutputFormat::Json,
+                ReportFormat::PrettyJson => OutputFormat::PrettyJson,
+            }),
+            file: cli.output_file.clone(),
+        },
+    }
+}
+
+fn run_probe_command(cli: &Cli) -> anyhow::Result<()> {
+    let policy = collection_policy(cli);
+    let probe = run_probe(&probe_config(cli));
+    log_report_warnings("probe", &probe.warnings);
+    let negotiation =
+        negotiate_collection_level(level1_engine(cli.engine), &policy, &probe.capability);
+    log_negotiation(&negotiation);
+
+    let output = ProbeOutput {
+        engine: engine_label(cli.engine).to_string(),
+        requested_level: format!("{}", policy.preferred_level),
+        selected_level: format!("{}", negotiation.selected_level),
+        downgrade_reasons: negotiation
+            .downgrade_reasons(probe.grantee.as_deref(), probe.server_version_num),
+        tasks: negotiation
+            .tasks
+            .iter()
+            .map(|it| it.name.to_string())
+            .collect(),
+        probe,
     };
+    let mut output = serde_json::to_value(&output)?;
+    redact_json(&mut output);
+    let output_text = match cli.output {
+        ReportFormat::Json => serde_json::to_string(&output)?,
+        ReportFormat::PrettyJson => serde_json::to_string_pretty(&output)?,
+    };
+    println!("{output_text}");
+    Ok(())
+}
+
+const MYSQL_LEVEL0_TASKS: &[&str] = &[
+    "global_status",
+    "global_variables",
+    "schema_storage",
+    "replication_status",
+    "os_basic_metrics",
+];
+const POSTGRES_LEVEL0_TASKS: &[&str] = &[
+    "global_status",
+    "global_variables",
+    "schema_storage",
+    "replication_status",
+];
+const OS_LEVEL0_TASKS: &[&str] = &["os_basic_metrics"];
+const LEVEL1_TASKS: &[&str] = &["slow_log_window", "slow_log_digest", "error_log_alerts"];
+
+/// Reports written by the collectors of one run.
+#[derive(Debug, Default)]
+struct CollectionState {
+    level0: Option<Level0CollectionReport>,
+    postgres_level0: Option<PostgresLevel0CollectionReport>,
+    level1: Option<Level1CollectionReport>,
+    innodb_status_hf: Option<InnodbStatusHfReport>,
+    perf_profile: Option<PerfProfileReport>,
+    strace_summary: Option<StraceSummaryReport>,
+    wire_capture: Option<WireCaptureReport>,
+}
+
+struct Level0Collector {
+    config: Level0CollectorConfig,
+    tasks: &'static [&'static str],
+}
+
+impl Collector<CollectionState> for Level0Collector {
+    fn name(&self) -> &'static str {
+        "level0"
+    }
+
+    fn tasks(&self) -> &[&'static str] {
+        self.tasks
+    }
+
+    fn collect(
+        &self,
+        tasks: &[&'static str],
+        state: &mut CollectionState,
+    ) -> Result<CollectorOutcome, String> {
+        let config = Level0CollectorConfig {
+            skipped_tasks: skipped_tasks(self.tasks, tasks),
+            ..self.config.clone()
+        };
+        info!(
+            table_limit = config.table_limit,
+            index_limit = config.index_limit,
+            "running level 0 collector"
+        );
+        let report = collect_level0(&config);
+        log_report_warnings("level0", &report.warnings);
+        let outcome = CollectorOutcome::from_report(None, &report.warnings);
+        state.level0 = Some(report);
+        Ok(outcome)
+    }
+}
+
+struct PostgresLevel0Collector {
+    config: PostgresLevel0CollectorConfig,
+}
+
+impl Collector<CollectionState> for PostgresLevel0Collector {
+    fn name(&self) -> &'static str {
+        "postgres_level0"
+    }
+
+    fn tasks(&self) -> &[&'static str] {
+        POSTGRES_LEVEL0_TASKS
+    }
+
+    fn collect(
+        &self,
+        tasks: &[&'static str],
+        state: &mut CollectionState,
+    ) -> Result<CollectorOutcome, String> {
+        let config = PostgresLevel0CollectorConfig {
+            skipped_tasks: skipped_tasks(POSTGRES_LEVEL0_TASKS, tasks),
+            ..self.config.clone()
+        };
+        info!(
+            table_limit = config.table_limit,
+            index_limit = config.index_limit,
+            "running postgres level 0 collector"
+        );
+        let report = collect_postgres_level0(&config);
+        log_report_warnings("postgres_level0", &report.warnings);
+        let outcome = CollectorOutcome::from_report(None, &report.warnings);
+        state.postgres_level0 = Some(report);
+        Ok(outcome)
+    }
+}
+
+struct Level1Collector {
+    config: Level1CollectorConfig,
+}
+
+impl Collector<CollectionState> for Level1Collector {
+    fn name(&self) -> &'static str {
+        "level1"
+    }
+
+    fn tasks(&self) -> &[&'static str] {
+        LEVEL1_TASKS
+    }
+
+    fn collect(
+        &self,
+        tasks: &[&'static str],
+        state: &mut CollectionState,
+    ) -> Result<CollectorOutcome, String> {
+        let config = Level1CollectorConfig {
+            skipped_tasks: skipped_tasks(LEVEL1_TASKS, tasks),
+            ..self.config.clone()
+        };
+        info!(
+            engine = ?config.engine,
+            slow_log_window_secs = config.slow_log_window_secs,
+            slow_log_long_query_time_secs = config.slow_log_long_query_time_secs,
+            enable_slow_log_hot_switch = config.enable_slow_log_hot_switch,
+            restore_slow_log_settings = config.restore_slow_log_settings,
+            skipped_tasks = ?config.skipped_tasks,
+            "running level 1 collector"
+        );
+        let report = collect_level1(&config);
+        log_report_warnings("level1", &report.warnings);
+        let outcome = CollectorOutcome::from_report(None, &report.warnings);
+        state.level1 = Some(report);
+        Ok(outcome)
+    }
+}
+
+struct InnodbStatusHfCollector {
+    policy: CollectionPolicy,
+    config: InnodbStatusHfConfig,
+}
+
+impl Collector<CollectionState> for InnodbStatusHfCollector {
+    fn name(&self) -> &'static str {
+        "innodb_status_hf"
+    }
+
+    fn tasks(&self) -> &[&'static str] {
+        &["innodb_status_hf"]
+    }
+
+    fn collect(
+        &self,
+        _tasks: &[&'static str],
+        state: &mut CollectionState,
+    ) -> Result<CollectorOutcome, String> {
+        info!(
+            interval_ms = self.config.interval_ms,
+            duration_ms = self.config.duration_ms,
+            max_samples = self.config.max_samples,
+            "running innodb status high-frequency sampler"
+        );
+        let report = collect_innodb_status_hf(&self.policy, &self.config);
+        log_report_warnings("innodb_status_hf", &report.warnings);
+        let outcome =
+            CollectorOutcome::from_report(report.skipped_reason.as_deref(), &report.warnings);
+        state.innodb_status_hf = Some(report);
+        Ok(outcome)
+    }
+}
+
+struct PerfProfileCollector {
+    policy: CollectionPolicy,
+    config: PerfProfileConfig,
+}
+
+impl Collector<CollectionState> for PerfProfileCollector {
+    fn name(&self) -> &'static str {
+        "perf_profile"
+    }
+
+    fn tasks(&self) -> &[&'static str] {
+        &["perf_or_strace_short_window"]
+    }
+
+    fn collect(
+        &self,
+        _tasks: &[&'static str],
+        state: &mut CollectionState,
+    ) -> Result<CollectorOutcome, String> {
+        let mut config = self.config.clone();
+        config.pid_file = config.pid_file.or_else(|| database_pid_file(state));
+        info!(
+            duration_secs = config.duration_secs,
+            frequency_hz = config.frequency_hz,
+            "running perf short-window profiler"
+        );
+        let report = collect_perf_profile(&self.policy, &config);
+        if let Some(reason) = &report.skipped_reason {
+            warn!(reason = %reason, "perf profiling skipped");
+        }
+        log_report_warnings("perf_profile", &report.warnings);
+        let outcome =
+            CollectorOutcome::from_report(report.skipped_reason.as_deref(), &report.warnings);
+        state.perf_profile = Some(report);
+        Ok(outcome)
+    }
+}
+
+struct StraceSummaryCollector {
+    policy: CollectionPolicy,
+    config: StraceConfig,
+}
+
+impl Collector<CollectionState> for StraceSummaryCollector {
+    fn name(&self) -> &'static str {
+        "strace_summary"
+    }
 
+    fn tasks(&self) -> &[&'static str] {
+        &["perf_or_strace_short_window"]
+    }
+
+    fn collect(
+        &self,
+        _tasks: &[&'static str],
+        state: &mut CollectionState,
+    ) -> Result<CollectorOutcome, String> {
+        let mut config = self.config.clone();
+        config.pid_file = config.pid_file.or_else(|| database_pid_file(state));
+        info!(
+            duration_secs = config.duration_secs,
+            "running strace short-window syscall summary"
+        );
+        let report = collect_strace_summary(&self.policy, &config);
+        if let Some(reason) = &report.skipped_reason {
+            warn!(reason = %reason, "strace summary skipped");
+        }
+        log_report_warnings("strace_summary", &report.warnings);
+        let outcome =
+            CollectorOutcome::from_report(report.skipped_reason.as_deref(), &report.warnings);
+        state.strace_summary = Some(report);
+        Ok(outcome)
+    }
+}
+
+struct WireCaptureCollector {
+    policy: CollectionPolicy,
+    config: WireCaptureConfig,
+}
+
+impl Collector<CollectionState> for WireCaptureCollector {
+    fn name(&self) -> &'static str {
+        "wire_capture"
+    }
+
+    fn tasks(&self) -> &[&'static str] {
+        &["tcpdump_short_window"]
+    }
+
+    fn collect(
+        &self,
+        _tasks: &[&'static str],
+        state: &mut CollectionState,
+    ) -> Result<CollectorOutcome, String> {
+        info!(
+            pcap_file = ?self.config.pcap_file,
+            duration_secs = self.config.duration_secs,
+            "decoding wire protocol capture"
+        );
+        let report = collect_wire_capture(&self.policy, &self.config);
+        if let Some(reason) = &report.skipped_reason {
+            warn!(reason = %reason, "wire capture skipped");
+        }
+        log_report_warnings("wire_capture", &report.warnings);
+        let outcome =
+            CollectorOutcome::from_report(report.skipped_reason.as_deref(), &report.warnings);
+        state.wire_capture = Some(report);
+        Ok(outcome)
+    }
+}
+
+/// The collector's own tasks that this run does not allow.
+fn skipped_tasks(own: &[&'static str], allowed: &[&'static str]) -> BTreeSet<&'static str> {
+    own.iter()
+        .copied()
+        .filter(|it| !allowed.contains(it))
+        .collect()
+}
+
+fn collect_once(
+    cli: &Cli,
+    alert_rules: &ErrorAlertRules,
+    task_filter: &TaskFilter,
+) -> anyhow::Result<CliOutput> {
     info!(
         engine = ?cli.engine,
         collect_level = ?cli.collect_level,
-        mysql_url_set = level0_config.mysql_url.is_some(),
+        mysql_url_set = cli.mysql_url.is_some(),
         postgres_url_set = cli.postgres_url.is_some(),
         "starting collection run"
     );
-    info!(
-        table_limit = level0_config.table_limit,
-        index_limit = level0_config.index_limit,
-        "running level 0 collector"
-    );
-    let level0_report = collect_level0(&level0_config);
-    log_report_warnings("level0", &level0_report.warnings);
-    let postgres_level0_report = if cli.engine == DatabaseEngine::Postgres {
-        info!(
-            table_limit = table_limit,
-            index_limit = index_limit,
-            "running postgres level 0 collector"
+    let policy = collection_policy(cli);
+    let probe = run_probe(&probe_config(cli));
+    for check in probe.checks.iter().filter(|it| !it.ok) {
+        debug!(
+            check = %check.name,
+            detail = check.detail.as_deref().unwrap_or_default(),
+            "capability check failed"
         );
-        let report = collect_postgres_level0(&PostgresLevel0CollectorConfig {
-            postgres_url: cli.postgres_url.clone(),
-            table_limit,
-            index_limit,
-        });
-        log_report_warnings("postgres_level0", &report.warnings);
-        Some(report)
-    } else {
-        None
-    };
+    }
+    log_report_warnings("probe", &probe.warnings);
+    let negotiation =
+        negotiate_collection_level(level1_engine(cli.engine), &policy, &probe.capability);
+    log_negotiation(&negotiation);
 
-    let level1_report = if cli.collect_level == CollectLevel::Level1 {
-        let slow_log_window_secs =
-            normalize_u64_limit("slow_log_window_secs", cli.slow_log_window_secs, 30);
-        let slow_log_long_query_time_secs = normalize_f64_limit(
-            "slow_log_long_query_time_secs",
-            cli.slow_log_long_query_time_secs,
-            0.2,
-        );
-        let level1_config = Level1CollectorConfig {
-            engine: if cli.engine == DatabaseEngine::Mysql {
-                Level1Engine::Mysql
-            } else {
-                Level1Engine::Postgres
-            },
+    let mut tasks = negotiation.tasks.clone();
+    // Offline inputs need no database privileges, so they bypass negotiation.
+    let offline_tasks = [
+        (cli.slow_log_archive.is_some(), "slow_log_digest"),
+        (cli.pcap_file.is_some(), "tcpdump_short_window"),
+    ];
+    for (_, name) in offline_tasks.iter().filter(|(enabled, _)| *enabled) {
+        if !tasks.iter().any(|it| it.name == *name) {
+            tasks.extend(
+                all_tasks(level1_engine(cli.engine))
+                    .into_iter()
+                    .filter(|it| it.name == *name),
+            );
+        }
+    }
+    tasks.sort_by_key(|it| it.level);
+
+    let registry = collector_registry(cli, &policy, alert_rules);
+    let mut state = CollectionState::default();
+    let executions = registry.execute(&tasks, task_filter, &mut state);
+    for execution in &executions {
+        if execution.status == "failed" {
+            warn!(
+                task = %execution.task,
+                duration_ms = execution.duration_ms as u64,
+                errors = %execution.errors.join("; "),
+                "collection task failed"
+            );
+        } else {
+            info!(
+                task = %execution.task,
+                status = %execution.status,
+                duration_ms = execution.duration_ms as u64,
+                "collection task finished"
+            );
+        }
+    }
+
+    let level0_report = state.level0.unwrap_or_default();
+    let table_attribution = state.level1.as_ref().map(|level1| {
+        let (sizes, default_schema) = match &state.postgres_level0 {
+            Some(pg) => (
+                postgres_table_sizes(&pg.postgres.table_sizes, &pg.postgres.indexes),
+                Some("public"),
+            ),
+            None => (
+                mysql_table_sizes(
+                    &level0_report.mysql.table_sizes,
+                    &level0_report.mysql.indexes,
+                ),
+                None,
+            ),
+        };
+        attribute_slow_sql_to_tables(&level1.slow_log.digests, &sizes, default_schema)
+    });
+
+    Ok(CliOutput {
+        engine: engine_label(cli.engine).to_string(),
+        requested_level: format!("{}", policy.preferred_level),
+        selected_level: format!("{}", negotiation.selected_level),
+        downgrade_reasons: negotiation
+            .downgrade_reasons(probe.grantee.as_deref(), probe.server_version_num),
+        probe,
+        tasks: executions,
+        level0: level0_report,
+        postgres_level0: state.postgres_level0,
+        level1: state.level1,
+        table_attribution,
+        innodb_status_hf: state.innodb_status_hf,
+        perf_profile: state.perf_profile,
+        strace_summary: state.strace_summary,
+        wire_capture: state.wire_capture,
+    })
+}
+
+/// Registers the collectors available for the engine; tasks without a
+/// collector are reported as skipped.
+fn collector_registry(
+    cli: &Cli,
+    policy: &CollectionPolicy,
+    alert_rules: &ErrorAlertRules,
+) -> CollectorRegistry<CollectionState> {
+    let table_limit = normalize_limit("table_limit", cli.table_limit, 200);
+    let index_limit = normalize_limit("index_limit", cli.index_limit, 500);
+    let mut registry = CollectorRegistry::default();
+
+    match cli.engine {
+        DatabaseEngine::Mysql => {
+            registry.register(Level0Collector {
+                config: Level0CollectorConfig {
+                    mysql_url: cli.mysql_url.clone(),
+                    table_limit,
+                    index_limit,
+                    suppress_mysql_missing_warning: false,
+                    skipped_tasks: BTreeSet::new(),
+                },
+                tasks: MYSQL_LEVEL0_TASKS,
+            });
+        }
+        DatabaseEngine::Postgres => {
+            registry
+                .register(Level0Collector {
+                    config: Level0CollectorConfig {
+                        mysql_url: None,
+                        table_limit,
+                        index_limit,
+                        suppress_mysql_missing_warning: true,
+                        skipped_tasks: BTreeSet::new(),
+                    },
+                    tasks: OS_LEVEL0_TASKS,
+                })
+                .register(PostgresLevel0Collector {
+                    config: PostgresLevel0CollectorConfig {
+                        postgres_url: cli.postgres_url.clone(),
+                        table_limit,
+                        index_limit,
+                        skipped_tasks: BTreeSet::new(),
+                    },
+                });
+        }
+    }
+
+    registry.register(Level1Collector {
+        config: Level1CollectorConfig {
+            engine: level1_engine(cli.engine),
             mysql_url: cli.mysql_url.clone(),
             postgres_url: cli.postgres_url.clone(),
-            slow_log_window_secs,
-            slow_log_long_query_time_secs,
+            slow_log_window_secs: normalize_u64_limit(
+                "slow_log_window_secs",
+                cli.slow_log_window_secs,
+                30,
+            ),
+            slow_log_long_query_time_secs: normalize_f64_limit(
+                "slow_log_long_query_time_secs",
+                cli.slow_log_long_query_time_secs,
+                0.2,
+            ),
             enable_slow_log_hot_switch: !cli.no_slow_log_hot_switch,
             restore_slow_log_settings: !cli.no_restore_slow_log_settings,
             slow_log_path: cli.slow_log_path.clone(),
             error_log_path: cli.error_log_path.clone(),
+            slow_log_archive: cli.slow_log_archive.clone(),
             max_slow_log_bytes: normalize_limit(
                 "max_slow_log_bytes",
                 cli.max_slow_log_bytes,
@@ -338,42 +1465,111 @@ fn collect_once(cli: &Cli) -> anyhow::Result<CliOutput> {
                 cli.max_error_log_lines,
                 2_000,
             ),
-        };
-        info!(
-            engine = ?cli.engine,
-            slow_log_window_secs = level1_config.slow_log_window_secs,
-            slow_log_long_query_time_secs = level1_config.slow_log_long_query_time_secs,
-            enable_slow_log_hot_switch = level1_config.enable_slow_log_hot_switch,
-            restore_slow_log_settings = level1_config.restore_slow_log_settings,
-            "running level 1 collector"
-        );
-        let report = collect_level1(&level1_config);
-        log_report_warnings("level1", &report.warnings);
-        Some(report)
-    } else {
-        None
-    };
+            long_transaction_secs: normalize_f64_limit(
+                "long_transaction_secs",
+                cli.long_transaction_secs,
+                1.0,
+            ),
+            idle_in_transaction_secs: normalize_f64_limit(
+                "idle_in_transaction_secs",
+                cli.idle_in_transaction_secs,
+                1.0,
+            ),
+            error_alert_rules: alert_rules.clone(),
+            skipped_tasks: BTreeSet::new(),
+        },
+    });
 
-    let probe = build_capability_probe(
-        cli.engine,
-        &level0_report,
-        postgres_level0_report.as_ref(),
-        level1_report.as_ref(),
-    );
+    if cli.engine == DatabaseEngine::Mysql {
+        registry.register(InnodbStatusHfCollector {
+            policy: policy.clone(),
+            config: InnodbStatusHfConfig {
+                mysql_url: cli.mysql_url.clone(),
+                interval_ms: cli.innodb_status_hf_interval_ms,
+                duration_ms: cli.innodb_status_hf_duration_ms,
+                max_samples: cli.innodb_status_hf_max_samples,
+            },
+        });
+    }
+
+    let pid_file = cli.db_pid_file.as_deref().map(PathBuf::from);
+    registry
+        .register(PerfProfileCollector {
+            policy: policy.clone(),
+            config: PerfProfileConfig {
+                engine: level1_engine(cli.engine),
+                pid: cli.db_pid,
+                pid_file: pid_file.clone(),
+                duration_secs: cli.perf_duration_secs,
+                frequency_hz: cli.perf_frequency_hz,
+                output_dir: cli
+                    .perf_output_dir
+                    .as_deref()
+                    .map(PathBuf::from)
+                    .unwrap_or_else(std::env::temp_dir),
+                ..PerfProfileConfig::default()
+            },
+        })
+        .register(StraceSummaryCollector {
+            policy: policy.clone(),
+            config: StraceConfig {
+                engine: level1_engine(cli.engine),
+                pid: cli.db_pid,
+                pid_file,
+                duration_secs: cli.strace_duration_secs,
+                ..StraceConfig::default()
+            },
+        })
+        .register(WireCaptureCollector {
+            policy: policy.clone(),
+            config: WireCaptureConfig {
+                engine: level1_engine(cli.engine),
+                pcap_file: cli.pcap_file.as_deref().map(PathBuf::from),
+                port: cli.capture_port,
+                interface: cli.capture_interface.clone(),
+                duration_secs: cli.tcpdump_duration_secs,
+                output_dir: cli
+                    .capture_output_dir
+                    .as_deref()
+                    .map(PathBuf::from)
+                    .unwrap_or_else(std::env::temp_dir),
+                ..WireCaptureConfig::default()
+            },
+        });
+    registry
+}
+
+fn collection_policy(cli: &Cli) -> CollectionPolicy {
     let target_level = requested_to_collection_level(cli.collect_level);
-    let policy = CollectionPolicy {
+    CollectionPolicy {
         preferred_level: target_level,
         max_accepted_level: target_level,
-        expert_mode_enabled: false,
-    };
-    let negotiation = negotiate_collection_level(&policy, &probe);
+        expert_mode_enabled: cli.expert_mode,
+    }
+}
+
+fn probe_config(cli: &Cli) -> ProbeConfig {
+    ProbeConfig {
+        engine: level1_engine(cli.engine),
+        mysql_url: cli.mysql_url.clone(),
+        postgres_url: cli.postgres_url.clone(),
+        slow_log_path: cli.slow_log_path.clone(),
+        error_log_path: cli.error_log_path.clone(),
+        enable_slow_log_hot_switch: !cli.no_slow_log_hot_switch,
+        db_pid: cli.db_pid,
+        db_pid_file: cli.db_pid_file.as_deref().map(PathBuf::from),
+        capture_interface: cli.capture_interface.clone(),
+        ..ProbeConfig::default()
+    }
+}
 
+fn log_negotiation(negotiation: &NegotiationResult) {
     info!(level = %negotiation.selected_level, "collection level selected");
     for evaluation in &negotiation.evaluations {
         let reasons = evaluation
             .reasons
             .iter()
-            .map(|it| map_reason_for_engine(cli.engine, it))
+            .map(|it| it.message)
             .collect::<Vec<_>>()
             .join("; ");
         if evaluation.ok {
@@ -395,29 +1591,6 @@ fn collect_once(cli: &Cli) -> anyhow::Result<CliOutput> {
             "enabled collection task"
         );
     }
-
-    Ok(CliOutput {
-        engine: engine_label(cli.engine).to_string(),
-        requested_level: format!("{}", target_level),
-        selected_level: format!("{}", negotiation.selected_level),
-        downgrade_reasons: negotiation
-            .evaluations
-            .iter()
-            .filter(|it| !it.ok)
-            .map(|it| {
-                let reasons = it
-                    .reasons
-                    .iter()
-                    .map(|reason| map_reason_for_engine(cli.engine, reason))
-                    .collect::<Vec<_>>()
-                    .join("; ");
-                format!("[{}] {}", it.level, reasons)
-            })
-            .collect(),
-        level0: level0_report,
-        postgres_level0: postgres_level0_report,
-        level1: level1_report,
-    })
 }
 
 fn init_tracing(verbose: u8) {
@@ -429,19 +1602,39 @@ fn init_tracing(verbose: u8) {
     tracing_subscriber::fmt()
         .with_max_level(level)
         .with_target(false)
-        .with_writer(std::io::stderr)
+        .with_writer(|| RedactingStderr)
         .init();
 }
 
-fn emit_record(
-    record: &UnifiedCollectionRecord<CliOutput>,
-    format: ReportFormat,
-) -> anyhow::Result<()> {
-    let output_text = match format {
-        ReportFormat::Json => serde_json::to_string(record)?,
-        ReportFormat::PrettyJson => serde_json::to_string_pretty(record)?,
+/// Log writer that strips database URLs and passwords from every line.
+struct RedactingStderr;
+
+impl Write for RedactingStderr {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let text = String::from_utf8_lossy(buf);
+        std::io::stderr().write_all(redact(&text).as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        std::io::stderr().flush()
+    }
+}
+
+fn emit_record(record: &UnifiedCollectionRecord<CliOutput>, cli: &Cli) -> anyhow::Result<()> {
+    let mut record = serde_json::to_value(record)?;
+    redact_json(&mut record);
+    let output_text = match cli.output {
+        ReportFormat::Json => serde_json::to_string(&record)?,
+        ReportFormat::PrettyJson => serde_json::to_string_pretty(&record)?,
     };
-    println!("{output_text}");
+    match cli.output_file.as_deref() {
+        Some(path) => {
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            writeln!(file, "{output_text}")?;
+        }
+        None => println!("{output_text}"),
+    }
     Ok(())
 }
 
@@ -530,6 +1723,18 @@ fn collect_warnings(output: &CliOutput) -> Vec<String> {
     if let Some(pg) = &output.postgres_level0 {
         warnings.extend(pg.warnings.clone());
     }
+    if let Some(hf) = &output.innodb_status_hf {
+        warnings.extend(hf.warnings.clone());
+    }
+    if let Some(perf) = &output.perf_profile {
+        warnings.extend(perf.warnings.clone());
+    }
+    if let Some(strace) = &output.strace_summary {
+        warnings.extend(strace.warnings.clone());
+    }
+    if let Some(capture) = &output.wire_capture {
+        warnings.extend(capture.warnings.clone());
+    }
     warnings
 }
 
@@ -570,7 +1775,7 @@ fn normalize_f64_limit(name: &str, value: f64, fallback: f64) -> f64 {
 }
 
 fn normalize_jitter(name: &str, value: f64, fallback: f64) -> f64 {
-    if value < 0.0 || value >= 1.0 {
+    if !(0.0..1.0).contains(&value) {
         warn!(name, value, fallback, "invalid jitter, fallback applied");
         fallback
     } else {
@@ -582,77 +1787,55 @@ fn requested_to_collection_level(level: CollectLevel) -> CollectionLevel {
     match level {
         CollectLevel::Level0 => CollectionLevel::Level0,
         CollectLevel::Level1 => CollectionLevel::Level1,
+        CollectLevel::Level2 => CollectionLevel::Level2,
+        CollectLevel::Level3 => CollectionLevel::Level3,
     }
 }
 
-fn build_capability_probe(
-    engine: DatabaseEngine,
-    mysql_level0: &Level0CollectionReport,
-    postgres_level0: Option<&PostgresLevel0CollectionReport>,
-    mysql_level1: Option<&Level1CollectionReport>,
-) -> CapabilityProbe {
+fn collection_to_requested_level(level: CollectionLevel) -> CollectLevel {
+    match level {
+        CollectionLevel::Level0 => CollectLevel::Level0,
+        CollectionLevel::Level1 => CollectLevel::Level1,
+        CollectionLevel::Level2 => CollectLevel::Level2,
+        CollectionLevel::Level3 => CollectLevel::Level3,
+    }
+}
+
+fn database_engine(engine: Level1Engine) -> DatabaseEngine {
     match engine {
-        DatabaseEngine::Mysql => CapabilityProbe {
-            has_mysql_status_access: mysql_level0.capability.mysql_status_access,
-            has_mysql_variables_access: mysql_level0.capability.mysql_variables_access,
-            has_information_schema_access: mysql_level0.capability.information_schema_access,
-            has_replication_status_access: mysql_level0.capability.replication_status_access,
-            has_os_metrics_access: mysql_level0.capability.os_metrics_access,
-            can_enable_slow_log_hot_switch: mysql_level1
-                .map(|it| it.capability.can_enable_slow_log_hot_switch)
-                .unwrap_or(false),
-            can_read_slow_log: mysql_level1
-                .map(|it| it.capability.can_read_slow_log)
-                .unwrap_or(false),
-            can_read_error_log: mysql_level1
-                .map(|it| it.capability.can_read_error_log)
-                .unwrap_or(false),
-            ..CapabilityProbe::default()
-        },
-        DatabaseEngine::Postgres => {
-            let pg = postgres_level0.cloned().unwrap_or_default();
-            CapabilityProbe {
-                has_mysql_status_access: pg.capability.has_status_access,
-                has_mysql_variables_access: pg.capability.has_settings_access,
-                has_information_schema_access: pg.capability.has_storage_access,
-                has_replication_status_access: pg.capability.has_replication_status_access,
-                has_os_metrics_access: mysql_level0.capability.os_metrics_access,
-                can_enable_slow_log_hot_switch: mysql_level1
-                    .map(|it| it.capability.can_enable_slow_log_hot_switch)
-                    .unwrap_or(false),
-                can_read_slow_log: mysql_level1
-                    .map(|it| it.capability.can_read_slow_log)
-                    .unwrap_or(false),
-                can_read_error_log: mysql_level1
-                    .map(|it| it.capability.can_read_error_log)
-                    .unwrap_or(false),
-                ..CapabilityProbe::default()
-            }
-        }
+        Level1Engine::Mysql => DatabaseEngine::Mysql,
+        Level1Engine::Postgres => DatabaseEngine::Postgres,
     }
 }
 
-fn map_reason_for_engine(engine: DatabaseEngine, reason: &str) -> String {
+fn report_format(format: OutputFormat) -> ReportFormat {
+    match format {
+        OutputFormat::Json => ReportFormat::Json,
+        OutputFormat::PrettyJson => ReportFormat::PrettyJson,
+    }
+}
+
+fn level1_engine(engine: DatabaseEngine) -> Level1Engine {
     match engine {
-        DatabaseEngine::Mysql => reason.to_string(),
-        DatabaseEngine::Postgres => match reason {
-            "missing SHOW GLOBAL STATUS access" => "missing pg_stat_database access".to_string(),
-            "missing SHOW VARIABLES access" => "missing pg_settings access".to_string(),
-            "missing INFORMATION_SCHEMA access" => {
-                "missing relation/index metadata access".to_string()
-            }
-            "missing replication status access" => {
-                "missing pg_stat_replication/pg_stat_wal_receiver access".to_string()
-            }
-            "cannot hot-enable slow log" => {
-                "cannot hot-enable log_min_duration_statement (ALTER SYSTEM + reload)".to_string()
-            }
-            "cannot collect slow log for digest aggregation" => {
-                "cannot collect statement log for digest aggregation".to_string()
-            }
-            "cannot collect error log" => "cannot collect PostgreSQL log alerts".to_string(),
-            other => other.to_string(),
-        },
+        DatabaseEngine::Mysql => Level1Engine::Mysql,
+        DatabaseEngine::Postgres => Level1Engine::Postgres,
+    }
+}
+
+/// MySQL reports `pid_file` directly; PostgreSQL keeps `postmaster.pid` in
+/// `data_directory`.
+fn database_pid_file(state: &CollectionState) -> Option<PathBuf> {
+    match &state.postgres_level0 {
+        Some(pg) => pg
+            .postgres
+            .global_variables
+            .get("data_directory")
+            .map(|dir| Path::new(dir).join("postmaster.pid")),
+        None => state
+            .level0
+            .as_ref()
+            .and_then(|it| it.mysql.global_variables.get("pid_file"))
+            .map(PathBuf::from),
     }
 }
 
@@ -0,0 +1,672 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::collection::CollectionPolicy;
+use crate::level1::Level1Engine;
+
+/// Hard caps so a misconfigured run cannot profile for long or at high rates.
+pub const PERF_MAX_DURATION_SECS: u64 = 30;
+pub const PERF_MAX_FREQUENCY_HZ: u32 = 999;
+const MAX_TOP_FUNCTIONS: usize = 30;
+
+#[derive(Debug, Clone)]
+pub struct PerfProfileConfig {
+    pub engine: Level1Engine,
+    /// Explicit PID; skips pid file and `/proc` discovery.
+    pub pid: Option<u32>,
+    /// MySQL `pid_file` or PostgreSQL `postmaster.pid`.
+    pub pid_file: Option<PathBuf>,
+    pub duration_secs: u64,
+    pub frequency_hz: u32,
+    pub output_dir: PathBuf,
+    pub perf_binary: String,
+    pub proc_root: PathBuf,
+}
+
+impl Default for PerfProfileConfig {
+    fn default() -> Self {
+        Self {
+            engine: Level1Engine::Mysql,
+            pid: None,
+            pid_file: None,
+            duration_secs: 5,
+            frequency_hz: 99,
+            output_dir: std::env::temp_dir(),
+            perf_binary: "perf".to_string(),
+            proc_root: PathBuf::from("/proc"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct PerfProfileReport {
+    pub enabled: bool,
+    pub skipped_reason: Option<String>,
+    pub pid: Option<u32>,
+    pub pid_source: Option<String>,
+    pub duration_secs: u64,
+    pub frequency_hz: u32,
+    pub total_samples: u64,
+    pub stack_count: usize,
+    /// Brendan Gregg collapsed format (`a;b;c count`), root frame first.
+    pub collapsed_stacks_path: Option<String>,
+    pub top_functions: Vec<PerfFunction>,
+    pub warnings: Vec<String>,
+}
+
+/// `self_samples` counts the function as the leaf frame; `total_samples`
+/// counts any stack containing it.
+#[derive(Debug, Clone, Serialize, Default, PartialEq)]
+pub struct PerfFunction {
+    pub function: String,
+    pub self_samples: u64,
+    pub self_pct: f64,
+    pub total_samples: u64,
+    pub total_pct: f64,
+}
+
+/// Runs the Level 3 `perf_or_strace_short_window` task with `perf record -g`.
+/// Nothing runs unless the policy enables expert mode.
+pub fn collect_perf_profile(
+    policy: &CollectionPolicy,
+    config: &PerfProfileConfig,
+) -> PerfProfileReport {
+    let mut report = PerfProfileReport {
+        duration_secs: config.duration_secs.clamp(1, PERF_MAX_DURATION_SECS),
+        frequency_hz: config.frequency_hz.clamp(1, PERF_MAX_FREQUENCY_HZ),
+        ..PerfProfileReport::default()
+    };
+    if report.duration_secs != config.duration_secs {
+        report.warnings.push(format!(
+            "perf duration_secs capped from {} to {}",
+            config.duration_secs, report.duration_secs
+        ));
+    }
+    if report.frequency_hz != config.frequency_hz {
+        report.warnings.push(format!(
+            "perf frequency_hz capped from {} to {}",
+            config.frequency_hz, report.frequency_hz
+        ));
+    }
+    if !policy.expert_mode_enabled {
+        report.skipped_reason = Some("expert mode is disabled by policy".to_string());
+        return report;
+    }
+
+    let (pid, source) = match config.pid {
+        Some(pid) => (pid, "config".to_string()),
+        None => {
+            match locate_database_pid(config.engine, config.pid_file.as_deref(), &config.proc_root)
+            {
+                Ok(found) => found,
+                Err(err) => {
+                    report.skipped_reason = Some(err);
+                    return report;
+                }
+            }
+        }
+    };
+    report.pid = Some(pid);
+    report.pid_source = Some(source);
+
+    if let Err(err) = probe_perf(&config.perf_binary, &config.proc_root) {
+        report.skipped_reason = Some(err);
+        return report;
+    }
+
+    let scratch = match create_private_dir(&config.output_dir, "perf") {
+        Ok(dir) => dir,
+        Err(err) => {
+            report.skipped_reason = Some(format!(
+                "cannot create a private directory in {}: {err}",
+                config.output_dir.display()
+            ));
+            return report;
+        }
+    };
+    report.enabled = true;
+    let data_path = scratch.join("perf.data");
+    let script = record_and_script(config, &report, pid, &data_path);
+    if let Err(err) = fs::remove_file(&data_path)
+        && err.kind() != io::ErrorKind::NotFound
+    {
+        report
+            .warnings
+            .push(format!("failed removing {}: {err}", data_path.display()));
+    }
+    let script = match script {
+        Ok(script) => script,
+        Err(err) => {
+            let _ = fs::remove_dir(&scratch);
+            report.enabled = false;
+            report.skipped_reason = Some(err);
+            return report;
+        }
+    };
+
+    let folded = fold_perf_script(&script);
+    let collapsed_path = scratch.join(format!("perf-{pid}.folded"));
+    let written = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&collapsed_path)
+        .and_then(|mut file| file.write_all(render_collapsed(&folded).as_bytes()));
+    match written {
+        Ok(()) => report.collapsed_stacks_path = Some(collapsed_path.display().to_string()),
+        Err(err) => {
+            report.warnings.push(format!(
+                "failed writing {}: {err}",
+                collapsed_path.display()
+            ));
+            let _ = fs::remove_file(&collapsed_path);
+            let _ = fs::remove_dir(&scratch);
+        }
+    }
+    report.total_samples = folded.values().sum();
+    report.stack_count = folded.len();
+    report.top_functions = top_functions(&folded, MAX_TOP_FUNCTIONS);
+    report
+}
+
+/// Prefers the pid file, then scans `<proc_root>/*/comm`. For PostgreSQL the
+/// postmaster is the matching process whose parent is not also postgres.
+pub fn locate_database_pid(
+    engine: Level1Engine,
+    pid_file: Option<&Path>,
+    proc_root: &Path,
+) -> Result<(u32, String), String> {
+    if let Some(path) = pid_file {
+        match fs::read_to_string(path) {
+            Ok(content) => {
+                let pid = content
+                    .lines()
+                    .next()
+                    .and_then(|it| it.trim().parse::<u32>().ok())
+                    .ok_or_else(|| format!("no PID in {}", path.display()))?;
+                if proc_root.join(pid.to_string()).exists() {
+                    return Ok((pid, format!("pid_file:{}", path.display())));
+                }
+                return Err(format!("PID {pid} from {} is not running", path.display()));
+            }
+            Err(err) if err.kind() == io::ErrorKind::PermissionDenied => {
+                return Err(format!("cannot read {}: {err}", path.display()));
+            }
+            Err(_) => {}
+        }
+    }
+
+    let names: &[&str] = match engine {
+        Level1Engine::Mysql => &["mysqld", "mariadbd"],
+        Level1Engine::Postgres => &["postgres", "postmaster"],
+    };
+    let entries = fs::read_dir(proc_root)
+        .map_err(|err| format!("cannot scan {}: {err}", proc_root.display()))?;
+    let mut matched = BTreeMap::new();
+    for entry in entries.flatten() {
+        let Some(pid) = entry
+            .file_name()
+            .to_str()
+            .and_then(|it| it.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        let Ok(comm) = fs::read_to_string(entry.path().join("comm")) else {
+            continue;
+        };
+        if names.contains(&comm.trim()) {
+            let ppid = fs::read_to_string(entry.path().join("stat"))
+                .ok()
+                .and_then(|it| parse_stat_ppid(&it));
+            matched.insert(pid, ppid);
+        }
+    }
+    matched
+        .iter()
+        .find(|(_, ppid)| ppid.is_none_or(|it| !matched.contains_key(&it)))
+        .map(|(pid, _)| (*pid, "proc_scan".to_string()))
+        .ok_or_else(|| {
+            format!(
+                "no {} process found under {}",
+                names[0],
+                proc_root.display()
+            )
+        })
+}
+
+/// `pid (comm) state ppid ...`; comm may contain spaces or parentheses.
+fn parse_stat_ppid(stat: &str) -> Option<u32> {
+    let rest = &stat[stat.rfind(')')? + 1..];
+    rest.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// Checks that `perf` runs and that `perf_event_paranoid` allows profiling
+/// another user's process; `Err` carries the reason.
+pub fn probe_perf(perf_binary: &str, proc_root: &Path) -> Result<(), String> {
+    check_perf_available(perf_binary)?;
+    match perf_privilege_problem(proc_root) {
+        Some(reason) => Err(reason),
+        None => Ok(()),
+    }
+}
+
+fn check_perf_available(perf_binary: &str) -> Result<(), String> {
+    match Command::new(perf_binary).arg("--version").output() {
+        Ok(output) if output.status.success() => Ok(()),
+        Ok(output) => Err(format!(
+            "perf is not usable: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            Err(format!("perf not found ({perf_binary})"))
+        }
+        Err(err) => Err(format!("failed running {perf_binary}: {err}")),
+    }
+}
+
+/// Non-root users need `perf_event_paranoid <= 1` to profile another user's
+/// process with kernel stacks.
+fn perf_privilege_problem(proc_root: &Path) -> Option<String> {
+    let paranoid = fs::read_to_string(proc_root.join("sys/kernel/perf_event_paranoid"))
+        .ok()
+        .and_then(|it| it.trim().parse::<i32>().ok())?;
+    if paranoid > 1 && !effective_uid_is_root(proc_root) {
+        return Some(format!(
+            "perf_event_paranoid={paranoid} requires root or CAP_PERFMON"
+        ));
+    }
+    None
+}
+
+/// Reads the effective UID from `<proc_root>/self/status`.
+pub(crate) fn effective_uid_is_root(proc_root: &Path) -> bool {
+    process_uids(proc_root, "self").is_some_and(|it| it[1] == 0)
+}
+
+/// Real, effective, saved and filesystem UIDs of `<proc_root>/<process>`.
+pub(crate) fn process_uids(proc_root: &Path, process: &str) -> Option<[u32; 4]> {
+    let status = fs::read_to_string(proc_root.join(process).join("status")).ok()?;
+    let mut uids = status
+        .lines()
+        .find_map(|it| it.strip_prefix("Uid:"))?
+        .split_whitespace()
+        .map(|it| it.parse::<u32>().ok());
+    Some([uids.next()??, uids.next()??, uids.next()??, uids.next()??])
+}
+
+/// Whether capability number `cap` is in our effective set (`CapEff`).
+pub(crate) fn has_effective_capability(proc_root: &Path, cap: u32) -> bool {
+    fs::read_to_string(proc_root.join("self/status"))
+        .ok()
+        .and_then(|status| {
+            status
+                .lines()
+                .find_map(|it| it.strip_prefix("CapEff:"))
+                .and_then(|it| u64::from_str_radix(it.trim(), 16).ok())
+        })
+        .is_some_and(|it| it & (1 << cap) != 0)
+}
+
+/// Creates a new `sql-insight-<label>-<pid>-<n>` directory under `parent`
+/// that only this user can enter. `create_dir` fails on an existing path, so
+/// a planted file or symlink is never reused.
+pub(crate) fn create_private_dir(parent: &Path, label: &str) -> io::Result<PathBuf> {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|it| it.subsec_nanos())
+        .unwrap_or_default();
+    let mut attempts = 0;
+    loop {
+        let path = parent.join(format!(
+            "sql-insight-{label}-{}-{seed:x}{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let mut builder = fs::DirBuilder::new();
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        match builder.create(&path) {
+            Ok(()) => return Ok(path),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists && attempts < 16 => {
+                attempts += 1;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+fn record_and_script(
+    config: &PerfProfileConfig,
+    report: &PerfProfileReport,
+    pid: u32,
+    data_path: &Path,
+) -> Result<String, String> {
+    let record = Command::new(&config.perf_binary)
+        .args(["record", "-g", "-F"])
+        .arg(report.frequency_hz.to_string())
+        .arg("-p")
+        .arg(pid.to_string())
+        .arg("-o")
+        .arg(data_path)
+        .args(["--", "sleep"])
+        .arg(report.duration_secs.to_string())
+        .output()
+        .map_err(|err| format!("failed running perf record: {err}"))?;
+    if !record.status.success() {
+        return Err(describe_perf_failure("perf record", &record.stderr));
+    }
+
+    let script = Command::new(&config.perf_binary)
+        .args(["script", "-i"])
+        .arg(data_path)
+        .output()
+        .map_err(|err| format!("failed running perf script: {err}"))?;
+    if !script.status.success() {
+        return Err(describe_perf_failure("perf script", &script.stderr));
+    }
+    Ok(String::from_utf8_lossy(&script.stdout).into_owned())
+}
+
+fn describe_perf_failure(step: &str, stderr: &[u8]) -> String {
+    let stderr = String::from_utf8_lossy(stderr);
+    let detail = stderr
+        .lines()
+        .map(str::trim)
+        .find(|it| !it.is_empty())
+        .unwrap_or("no output");
+    let lowered = stderr.to_ascii_lowercase();
+    if lowered.contains("permission denied")
+        || lowered.contains("perf_event_paranoid")
+        || lowered.contains("not permitted")
+    {
+        format!("{step} lacks privileges: {detail}")
+    } else {
+        format!("{step} failed: {detail}")
+    }
+}
+
+/// Folds `perf script` output into `comm;root;...;leaf -> samples`.
+pub fn fold_perf_script(script: &str) -> BTreeMap<String, u64> {
+    let mut folded = BTreeMap::new();
+    let mut comm: Option<String> = None;
+    let mut frames: Vec<String> = Vec::new();
+
+    let mut flush = |comm: &mut Option<String>, frames: &mut Vec<String>| {
+        if let Some(name) = comm.take()
+            && !frames.is_empty()
+        {
+            let mut stack = vec![name];
+            stack.extend(frames.drain(..).rev());
+            *folded.entry(stack.join(";")).or_insert(0) += 1;
+        }
+        frames.clear();
+    };
+
+    for line in script.lines() {
+        if line.trim().is_empty() {
+            flush(&mut comm, &mut frames);
+        } else if line.starts_with(char::is_whitespace) {
+            if comm.is_some()
+                && let Some(symbol) = parse_frame_symbol(line)
+            {
+                frames.push(symbol);
+            }
+        } else if !line.starts_with('#') {
+            flush(&mut comm, &mut frames);
+            comm = line.split_whitespace().next().map(str::to_string);
+        }
+    }
+    flush(&mut comm, &mut frames);
+    folded
+}
+
+/// `\t 55d0c0a1b2c3 row_search_mvcc+0x213 (/usr/sbin/mysqld)` -> `row_search_mvcc`.
+fn parse_frame_symbol(line: &str) -> Option<String> {
+    let (_, rest) = line.trim().split_once(char::is_whitespace)?;
+    let symbol = match rest.rfind(" (") {
+        Some(idx) => &rest[..idx],
+        None => rest,
+    };
+    let symbol = match symbol.rfind("+0x") {
+        Some(idx) => &symbol[..idx],
+        None => symbol,
+    };
+    let symbol = symbol.trim().replace(';', ":");
+    (!symbol.is_empty()).then_some(symbol)
+}
+
+pub fn render_collapsed(folded: &BTreeMap<String, u64>) -> String {
+    folded
+        .iter()
+        .map(|(stack, count)| format!("{stack} {count}\n"))
+        .collect()
+}
+
+pub fn top_functions(folded: &BTreeMap<String, u64>, limit: usize) -> Vec<PerfFunction> {
+    let total: u64 = folded.values().sum();
+    let mut by_function: BTreeMap<&str, (u64, u64)> = BTreeMap::new();
+    for (stack, count) in folded {
+        // The first element is the command name, not a frame.
+        let frames = stack.split(';').skip(1).collect::<Vec<_>>();
+        if let Some(leaf) = frames.last() {
+            by_function.entry(leaf).or_default().0 += count;
+        }
+        for frame in frames.iter().collect::<BTreeSet<_>>() {
+            by_function.entry(frame).or_default().1 += count;
+        }
+    }
+
+    let pct = |value: u64| {
+        if total == 0 {
+            0.0
+        } else {
+            (value as f64 * 10_000.0 / total as f64).round() / 100.0
+        }
+    };
+    let mut functions = by_function
+        .into_iter()
+        .map(|(function, (self_samples, total_samples))| PerfFunction {
+            function: function.to_string(),
+            self_samples,
+            self_pct: pct(self_samples),
+            total_samples,
+            total_pct: pct(total_samples),
+        })
+        .collect::<Vec<_>>();
+    functions.sort_by(|a, b| {
+        b.self_samples
+            .cmp(&a.self_samples)
+            .then_with(|| b.total_samples.cmp(&a.total_samples))
+            .then_with(|| a.function.cmp(&b.function))
+    });
+    functions.truncate(limit);
+    functions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sql-insight-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("failed creating scratch dir");
+        dir
+    }
+
+    fn fake_process(proc_root: &Path, pid: u32, comm: &str, ppid: u32) {
+        let dir = proc_root.join(pid.to_string());
+        fs::create_dir_all(&dir).expect("failed creating fake proc entry");
+        fs::write(dir.join("comm"), format!("{comm}\n")).expect("failed writing comm");
+        fs::write(dir.join("stat"), format!("{pid} ({comm}) S {ppid} 1 1 0"))
+            .expect("failed writing stat");
+    }
+
+    #[test]
+    fn should_fold_perf_script_and_rank_functions() {
+        let path =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/perf/perf_script.txt");
+        let script = fs::read_to_string(path).expect("failed reading perf fixture");
+        let folded = fold_perf_script(&script);
+
+        assert_eq!(folded.len(), 3);
+        assert_eq!(
+            folded.get(
+                "mysqld;do_command;handler::ha_index_next;ha_innobase::index_next;row_search_mvcc"
+            ),
+            Some(&2)
+        );
+        assert!(render_collapsed(&folded).contains("mysqld;log_writer;[unknown] 1\n"));
+
+        let top = top_functions(&folded, 10);
+        assert_eq!(top[0].function, "row_search_mvcc");
+        assert_eq!((top[0].self_samples, top[0].total_samples), (2, 3));
+        assert_eq!(top[0].self_pct, 50.0);
+        let do_command = top.iter().find(|it| it.function == "do_command").unwrap();
+        assert_eq!((do_command.self_samples, do_command.total_pct), (0, 75.0));
+    }
+
+    #[test]
+    fn should_locate_pid_from_pid_file_or_proc_scan() {
+        let dir = scratch_dir("perf-pid");
+        let proc_root = dir.join("proc");
+        fake_process(&proc_root, 700, "postgres", 1);
+        fake_process(&proc_root, 701, "postgres", 700);
+        fake_process(&proc_root, 900, "mysqld", 1);
+
+        let pid_file = dir.join("postmaster.pid");
+        fs::write(&pid_file, "700\n/var/lib/postgresql/data\n").unwrap();
+        let (pid, source) =
+            locate_database_pid(Level1Engine::Postgres, Some(&pid_file), &proc_root).unwrap();
+        assert_eq!(pid, 700);
+        assert!(source.starts_with("pid_file:"));
+
+        let missing = dir.join("missing.pid");
+        assert_eq!(
+            locate_database_pid(Level1Engine::Postgres, Some(&missing), &proc_root).unwrap(),
+            (700, "proc_scan".to_string())
+        );
+        assert_eq!(
+            locate_database_pid(Level1Engine::Mysql, None, &proc_root)
+                .unwrap()
+                .0,
+            900
+        );
+
+        fs::write(&pid_file, "4242\n").unwrap();
+        let err =
+            locate_database_pid(Level1Engine::Postgres, Some(&pid_file), &proc_root).unwrap_err();
+        assert!(err.contains("is not running"));
+    }
+
+    #[test]
+    fn should_skip_without_expert_mode_or_perf() {
+        let config = PerfProfileConfig {
+            pid: Some(std::process::id()),
+            duration_secs: 120,
+            perf_binary: "sql-insight-missing-perf".to_string(),
+            ..PerfProfileConfig::default()
+        };
+        let report = collect_perf_profile(&CollectionPolicy::default(), &config);
+        assert_eq!(
+            report.skipped_reason.as_deref(),
+            Some("expert mode is disabled by policy")
+        );
+        assert_eq!(report.duration_secs, PERF_MAX_DURATION_SECS);
+
+        let policy = CollectionPolicy {
+            expert_mode_enabled: true,
+            ..CollectionPolicy::default()
+        };
+        let report = collect_perf_profile(&policy, &config);
+        assert!(!report.enabled);
+        assert_eq!(
+            report.skipped_reason.as_deref(),
+            Some("perf not found (sql-insight-missing-perf)")
+        );
+    }
+
+    #[test]
+    fn should_flag_privilege_failures() {
+        assert_eq!(
+            describe_perf_failure(
+                "perf record",
+                b"Error:\nNo permission to enable cycles event.\nperf_event_paranoid setting is 3"
+            ),
+            "perf record lacks privileges: Error:"
+        );
+
+        let dir = scratch_dir("perf-paranoid");
+        fs::create_dir_all(dir.join("sys/kernel")).unwrap();
+        fs::create_dir_all(dir.join("self")).unwrap();
+        fs::write(dir.join("sys/kernel/perf_event_paranoid"), "2\n").unwrap();
+        fs::write(
+            dir.join("self/status"),
+            "Name:\tx\nUid:\t1000\t1000\t1000\t1000\n",
+        )
+        .unwrap();
+        assert_eq!(
+            perf_privilege_problem(&dir).as_deref(),
+            Some("perf_event_paranoid=2 requires root or CAP_PERFMON")
+        );
+        fs::write(dir.join("self/status"), "Uid:\t0\t0\t0\t0\n").unwrap();
+        assert_eq!(perf_privilege_problem(&dir), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn should_write_into_a_private_directory_and_drop_failed_data() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = scratch_dir("perf-private");
+        fs::create_dir_all(root.join("proc/self")).unwrap();
+        fs::write(root.join("proc/self/status"), "Uid:\t0\t0\t0\t0\n").unwrap();
+        let output_dir = root.join("out");
+        fs::create_dir_all(&output_dir).unwrap();
+        let first = create_private_dir(&output_dir, "perf").unwrap();
+        let second = create_private_dir(&output_dir, "perf").unwrap();
+        assert_ne!(first, second);
+        let mode = fs::metadata(&first).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+        fs::remove_dir(&first).unwrap();
+        fs::remove_dir(&second).unwrap();
+
+        // Writes the `-o` file, then fails like a denied `perf record`.
+        let perf = root.join("perf");
+        fs::write(
+            &perf,
+            "#!/bin/sh\n[ \"$1\" = --version ] && exit 0\n\
+             while [ $# -gt 0 ]; do [ \"$1\" = -o ] && echo data > \"$2\"; shift; done\n\
+             echo 'Permission denied' >&2\nexit 1\n",
+        )
+        .unwrap();
+        fs::set_permissions(&perf, fs::Permissions::from_mode(0o755)).unwrap();
+        let config = PerfProfileConfig {
+            pid: Some(std::process::id()),
+            duration_secs: 1,
+            output_dir: output_dir.clone(),
+            perf_binary: perf.display().to_string(),
+            proc_root: root.join("proc"),
+            ..PerfProfileConfig::default()
+        };
+        let policy = CollectionPolicy {
+            expert_mode_enabled: true,
+            ..CollectionPolicy::default()
+        };
+        let report = collect_perf_profile(&policy, &config);
+        assert!(!report.enabled);
+        assert_eq!(
+            report.skipped_reason.as_deref(),
+            Some("perf record lacks privileges: Permission denied")
+        );
+        assert_eq!(fs::read_dir(&output_dir).unwrap().count(), 0);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
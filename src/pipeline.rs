@@ -1,11 +1,12 @@
-use std::sync::mpsc;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Condvar, Mutex, PoisonError, mpsc};
 use std::thread;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use clap::ValueEnum;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, Serialize, ValueEnum, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ValueEnum, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum RunMode {
     Once,
@@ -52,6 +53,13 @@ pub struct AttemptTrace {
     pub error: Option<String>,
 }
 
+/// Instance a record was collected from when running a targets file.
+#[derive(Debug, Clone, Serialize)]
+pub struct CollectionTarget {
+    pub name: String,
+    pub labels: BTreeMap<String, String>,
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct SourceStatus {
     pub source: String,
@@ -67,6 +75,7 @@ where
     pub run_id: String,
     pub cycle: u32,
     pub engine: String,
+    pub target: Option<CollectionTarget>,
     pub requested_level: String,
     pub selected_level: Option<String>,
     pub schedule: SchedulerConfig,
@@ -79,18 +88,93 @@ where
     pub payload: Option<T>,
 }
 
-pub fn run_with_timeout<T, F>(timeout: Duration, job: F) -> Result<T, String>
+/// Counts running collection attempts, including ones that outlived their
+/// timeout and keep running detached, so timed-out work cannot pile up past
+/// `limit` threads.
+#[derive(Debug, Clone)]
+pub struct AttemptSlots {
+    limit: usize,
+    running: Arc<(Mutex<usize>, Condvar)>,
+}
+
+impl AttemptSlots {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit: limit.max(1),
+            running: Arc::new((Mutex::new(0), Condvar::new())),
+        }
+    }
+
+    pub fn running(&self) -> usize {
+        *self
+            .running
+            .0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Takes a slot, waiting until `deadline` at most.
+    fn acquire(&self, deadline: Instant) -> Option<AttemptSlot> {
+        let (count, freed) = &*self.running;
+        let mut running = count.lock().unwrap_or_else(PoisonError::into_inner);
+        while *running >= self.limit {
+            let wait = deadline
+                .checked_duration_since(Instant::now())
+                .filter(|it| !it.is_zero())?;
+            running = freed
+                .wait_timeout(running, wait)
+                .unwrap_or_else(PoisonError::into_inner)
+                .0;
+        }
+        *running += 1;
+        Some(AttemptSlot(self.clone()))
+    }
+}
+
+/// Held by the attempt thread and released when the job really returns.
+struct AttemptSlot(AttemptSlots);
+
+impl Drop for AttemptSlot {
+    fn drop(&mut self) {
+        let (count, freed) = &*self.0.running;
+        *count.lock().unwrap_or_else(PoisonError::into_inner) -= 1;
+        freed.notify_all();
+    }
+}
+
+/// Runs `job` on its own thread once a slot in each of `slots` is free.
+/// Waiting for the slots counts against `timeout`; a job that times out is
+/// detached but keeps its slots until it returns.
+pub fn run_with_timeout<T, F>(
+    timeout: Duration,
+    slots: &[&AttemptSlots],
+    job: F,
+) -> Result<T, String>
 where
     T: Send + 'static,
     F: FnOnce() -> Result<T, String> + Send + 'static,
 {
+    let deadline = Instant::now() + timeout;
+    let mut held = Vec::with_capacity(slots.len());
+    for slots in slots {
+        let Some(slot) = slots.acquire(deadline) else {
+            return Err(format!(
+                "no collection slot freed within {}ms; {} earlier attempt(s) still running",
+                timeout.as_millis(),
+                slots.running()
+            ));
+        };
+        held.push(slot);
+    }
+
     let (tx, rx) = mpsc::channel();
     thread::spawn(move || {
         let result = job();
+        drop(held);
         let _ = tx.send(result);
     });
 
-    match rx.recv_timeout(timeout) {
+    match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
         Ok(result) => result,
         Err(mpsc::RecvTimeoutError::Timeout) => Err(format!(
             "collection timed out after {}ms",
@@ -136,13 +220,37 @@ mod tests {
 
     #[test]
     fn timeout_should_fail() {
-        let result = run_with_timeout(Duration::from_millis(30), || {
+        let slots = AttemptSlots::new(1);
+        let result = run_with_timeout(Duration::from_millis(30), &[&slots], || {
             thread::sleep(Duration::from_millis(100));
             Ok::<_, String>(42)
         });
         assert!(result.is_err());
     }
 
+    #[test]
+    fn timed_out_attempts_should_keep_their_slot() {
+        let slots = AttemptSlots::new(1);
+        let slow = run_with_timeout(Duration::from_millis(30), &[&slots], || {
+            thread::sleep(Duration::from_millis(300));
+            Ok::<_, String>(1)
+        });
+        assert_eq!(slow.unwrap_err(), "collection timed out after 30ms");
+        assert_eq!(slots.running(), 1);
+
+        let blocked = run_with_timeout(Duration::from_millis(30), &[&slots], || Ok(2));
+        assert!(
+            blocked
+                .unwrap_err()
+                .contains("1 earlier attempt(s) still running"),
+            "the detached attempt still holds the only slot"
+        );
+
+        let waited = run_with_timeout(Duration::from_secs(5), &[&slots], || Ok(3));
+        assert_eq!(waited, Ok(3));
+        assert_eq!(slots.running(), 0);
+    }
+
     #[test]
     fn jitter_should_stay_in_bounds() {
         let base_secs = 10;
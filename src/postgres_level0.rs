@@ -1,14 +1,18 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use postgres::{Client, NoTls, Row};
 use serde::Serialize;
 
+use crate::secrets::DatabaseUrl;
+
 #[derive(Debug, Clone)]
 pub struct PostgresLevel0CollectorConfig {
-    pub postgres_url: Option<String>,
+    pub postgres_url: Option<DatabaseUrl>,
     pub table_limit: usize,
     pub index_limit: usize,
+    /// Tasks whose sources are not queried, e.g. from `--exclude-tasks`.
+    pub skipped_tasks: BTreeSet<&'static str>,
 }
 
 impl Default for PostgresLevel0CollectorConfig {
@@ -17,10 +21,17 @@ impl Default for PostgresLevel0CollectorConfig {
             postgres_url: None,
             table_limit: 200,
             index_limit: 500,
+            skipped_tasks: BTreeSet::new(),
         }
     }
 }
 
+impl PostgresLevel0CollectorConfig {
+    fn runs(&self, task: &str) -> bool {
+        !self.skipped_tasks.contains(task)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Default)]
 pub struct PostgresLevel0Capability {
     pub postgres_connected: bool,
@@ -73,14 +84,14 @@ pub fn collect_postgres_level0(
         ..PostgresLevel0CollectionReport::default()
     };
 
-    let Some(url) = config.postgres_url.as_deref() else {
+    let Some(url) = config.postgres_url.as_ref() else {
         report
             .warnings
             .push("POSTGRES_URL not provided; skip PostgreSQL Level 0 collection".to_string());
         return report;
     };
 
-    let mut client = match Client::connect(url, NoTls) {
+    let mut client = match url.postgres_config().and_then(|it| it.connect(NoTls)) {
         Ok(it) => it,
         Err(err) => {
             report
@@ -91,28 +102,56 @@ pub fn collect_postgres_level0(
     };
     report.capability.postgres_connected = true;
 
-    match fetch_status_pairs(&mut client) {
-        Ok(map) => {
-            report.capability.has_status_access = true;
-            report.postgres.global_status = map;
+    if config.runs("global_status") {
+        match fetch_status_pairs(&mut client) {
+            Ok(map) => {
+                report.capability.has_status_access = true;
+                report.postgres.global_status = map;
+            }
+            Err(err) => report
+                .warnings
+                .push(format!("failed querying pg_stat_database: {err}")),
         }
-        Err(err) => report
-            .warnings
-            .push(format!("failed querying pg_stat_database: {err}")),
     }
 
-    match fetch_settings_pairs(&mut client) {
-        Ok(map) => {
-            report.capability.has_settings_access = true;
-            report.postgres.global_variables = map;
+    if config.runs("global_variables") {
+        match fetch_settings_pairs(&mut client) {
+            Ok(map) => {
+                report.capability.has_settings_access = true;
+                report.postgres.global_variables = map;
+            }
+            Err(err) => report
+                .warnings
+                .push(format!("failed querying pg_settings: {err}")),
         }
-        Err(err) => report
-            .warnings
-            .push(format!("failed querying pg_settings: {err}")),
     }
 
-    let table_result = fetch_table_sizes(&mut client, config.table_limit);
-    let index_result = fetch_indexes(&mut client, config.index_limit);
+    if config.runs("schema_storage") {
+        collect_storage(&mut client, config, &mut report);
+    }
+
+    if config.runs("replication_status") {
+        match fetch_replication_status(&mut client) {
+            Ok(map) => {
+                report.capability.has_replication_status_access = true;
+                report.postgres.replication_status = map;
+            }
+            Err(err) => report
+                .warnings
+                .push(format!("failed querying replication status: {err}")),
+        }
+    }
+
+    report
+}
+
+fn collect_storage(
+    client: &mut Client,
+    config: &PostgresLevel0CollectorConfig,
+    report: &mut PostgresLevel0CollectionReport,
+) {
+    let table_result = fetch_table_sizes(client, config.table_limit);
+    let index_result = fetch_indexes(client, config.index_limit);
     let mut table_ok = false;
     let mut index_ok = false;
     if let Ok(rows) = table_result {
@@ -132,18 +171,6 @@ pub fn collect_postgres_level0(
             .push(format!("failed querying pg_indexes: {err}"));
     }
     report.capability.has_storage_access = table_ok && index_ok;
-
-    match fetch_replication_status(&mut client) {
-        Ok(map) => {
-            report.capability.has_replication_status_access = true;
-            report.postgres.replication_status = map;
-        }
-        Err(err) => report
-            .warnings
-            .push(format!("failed querying replication status: {err}")),
-    }
-
-    report
 }
 
 fn fetch_settings_pairs(client: &mut Client) -> Result<BTreeMap<String, String>, postgres::Error> {
@@ -0,0 +1,725 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use mysql::prelude::Queryable;
+use mysql::{Pool, PooledConn};
+use postgres::{Client, NoTls};
+use serde::Serialize;
+
+use crate::collection::{Capability, CapabilityProbe};
+use crate::innodb_status::fetch_innodb_status_text;
+use crate::level1::{
+    Level1Engine, discover_postgres_log_path, fetch_mysql_variable, fetch_postgres_setting,
+    is_truthy,
+};
+use crate::perf_profile::{locate_database_pid, probe_perf};
+use crate::secrets::DatabaseUrl;
+use crate::strace_summary::probe_strace;
+use crate::wire_capture::probe_tcpdump;
+
+const HOT_SWITCH_DISABLED: &str = "hot switch disabled by --no-slow-log-hot-switch";
+
+#[derive(Debug, Clone)]
+pub struct ProbeConfig {
+    pub engine: Level1Engine,
+    pub mysql_url: Option<DatabaseUrl>,
+    pub postgres_url: Option<DatabaseUrl>,
+    pub slow_log_path: Option<String>,
+    pub error_log_path: Option<String>,
+    pub enable_slow_log_hot_switch: bool,
+    pub perf_binary: String,
+    pub strace_binary: String,
+    /// Database process for the ptrace check; located like Level 3 when unset.
+    pub db_pid: Option<u32>,
+    pub db_pid_file: Option<PathBuf>,
+    pub tcpdump_binary: String,
+    pub capture_interface: String,
+    pub proc_root: PathBuf,
+}
+
+impl Default for ProbeConfig {
+    fn default() -> Self {
+        Self {
+            engine: Level1Engine::Mysql,
+            mysql_url: None,
+            postgres_url: None,
+            slow_log_path: None,
+            error_log_path: None,
+            enable_slow_log_hot_switch: true,
+            perf_binary: "perf".to_string(),
+            strace_binary: "strace".to_string(),
+            db_pid: None,
+            db_pid_file: None,
+            tcpdump_binary: "tcpdump".to_string(),
+            capture_interface: "any".to_string(),
+            proc_root: PathBuf::from("/proc"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct ProbeReport {
+    pub engine: String,
+    pub connected: bool,
+    /// Account as written in `GRANT ... TO`, used in remediation hints.
+    pub grantee: Option<String>,
+    /// `major * 10000 + minor * 100 + patch`, as PostgreSQL's
+    /// `server_version_num`; picks version-specific remediation hints.
+    pub server_version_num: Option<u32>,
+    /// `SHOW GRANTS` lines for MySQL; `SUPERUSER` plus role memberships for
+    /// PostgreSQL.
+    pub grants: Vec<String>,
+    pub checks: Vec<ProbeCheck>,
+    pub capability: CapabilityProbe,
+    pub warnings: Vec<String>,
+}
+
+/// One active check; `detail` explains a failure or qualifies a pass.
+#[derive(Debug, Clone, Serialize, Default, PartialEq)]
+pub struct ProbeCheck {
+    pub name: String,
+    pub ok: bool,
+    pub detail: Option<String>,
+}
+
+impl ProbeReport {
+    fn record(&mut self, name: &str, ok: bool, detail: Option<String>) -> bool {
+        self.checks.push(ProbeCheck {
+            name: name.to_string(),
+            ok,
+            detail,
+        });
+        ok
+    }
+
+    fn check(&mut self, name: &str, result: Result<(), String>) -> bool {
+        let ok = result.is_ok();
+        self.record(name, ok, result.err())
+    }
+}
+
+/// Privileges from `SHOW GRANTS`, split into `*.*` and per-schema grants.
+/// Table- and column-level grants are ignored.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MysqlGrants {
+    pub global: BTreeSet<String>,
+    pub schemas: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl MysqlGrants {
+    pub fn has_global(&self, privilege: &str) -> bool {
+        self.global.contains("ALL PRIVILEGES") || self.global.contains(privilege)
+    }
+
+    pub fn can_select(&self, schema: &str) -> bool {
+        self.has_global("SELECT")
+            || self
+                .schemas
+                .get(schema)
+                .is_some_and(|it| it.contains("SELECT") || it.contains("ALL PRIVILEGES"))
+    }
+}
+
+pub fn parse_mysql_grants(lines: &[String]) -> MysqlGrants {
+    let mut grants = MysqlGrants::default();
+    for line in lines {
+        let Some(rest) = line.trim().strip_prefix("GRANT ") else {
+            continue;
+        };
+        // Role grants (`GRANT role TO user`) have no ON clause.
+        let Some((privileges, target)) = rest.split_once(" ON ") else {
+            continue;
+        };
+        let object = target.split(" TO ").next().unwrap_or_default().trim();
+        let privileges = privileges
+            .split(',')
+            .map(|it| {
+                let name = it.split('(').next().unwrap_or_default();
+                name.trim().to_ascii_uppercase()
+            })
+            .filter(|it| !it.is_empty());
+        if object == "*.*" {
+            grants.global.extend(privileges);
+        } else if let Some(schema) = object.strip_suffix(".*") {
+            grants
+                .schemas
+                .entry(schema.trim_matches('`').to_string())
+                .or_default()
+                .extend(privileges);
+        }
+    }
+    grants
+}
+
+/// `5.7.44-log` or `8.0.36-0ubuntu0.22.04.1` as `50744` or `80036`.
+fn parse_mysql_version_num(version: &str) -> Option<u32> {
+    let mut parts = version.split(['.', '-']).map(|it| it.parse::<u32>().ok());
+    let (major, minor, patch) = (parts.next()??, parts.next()??, parts.next()??);
+    Some(major * 10000 + minor * 100 + patch)
+}
+
+/// `user@host` from `CURRENT_USER()` as `'user'@'host'`.
+fn mysql_grantee(current_user: &str) -> Option<String> {
+    let (user, host) = current_user.rsplit_once('@')?;
+    Some(format!("'{user}'@'{host}'"))
+}
+
+/// Actively checks what this process may collect, before any collector runs.
+pub fn run_probe(config: &ProbeConfig) -> ProbeReport {
+    let mut report = ProbeReport::default();
+    let mut capability = CapabilityProbe::default();
+    match config.engine {
+        Level1Engine::Mysql => {
+            report.engine = "mysql".to_string();
+            probe_mysql(config, &mut report, &mut capability);
+        }
+        Level1Engine::Postgres => {
+            report.engine = "postgres".to_string();
+            probe_postgres(config, &mut report, &mut capability);
+        }
+    }
+
+    let proc_stat = config.proc_root.join("stat");
+    capability.set(
+        Capability::OsMetrics,
+        report.check(
+            "os.basic_metrics",
+            fs::read_to_string(&proc_stat)
+                .map(|_| ())
+                .map_err(|err| format!("cannot read {}: {err}", proc_stat.display())),
+        ),
+    );
+    capability.set(
+        Capability::PerfShortWindow,
+        report.check(
+            "binary.perf",
+            probe_perf(&config.perf_binary, &config.proc_root),
+        ),
+    );
+    capability.set(
+        Capability::StraceShortWindow,
+        report.check(
+            "binary.strace",
+            probe_strace(
+                &config.strace_binary,
+                config.db_pid.or_else(|| {
+                    locate_database_pid(
+                        config.engine,
+                        config.db_pid_file.as_deref(),
+                        &config.proc_root,
+                    )
+                    .ok()
+                    .map(|it| it.0)
+                }),
+                &config.proc_root,
+            ),
+        ),
+    );
+    capability.set(
+        Capability::TcpdumpShortWindow,
+        report.check(
+            "binary.tcpdump",
+            probe_tcpdump(
+                &config.tcpdump_binary,
+                &config.capture_interface,
+                &config.proc_root,
+            ),
+        ),
+    );
+    report.capability = capability;
+    report
+}
+
+fn check_readable(path: Option<String>, missing: &str) -> Result<(), String> {
+    let path = path.ok_or_else(|| missing.to_string())?;
+    fs::File::open(Path::new(&path))
+        .map(|_| ())
+        .map_err(|err| format!("cannot read {path}: {err}"))
+}
+
+fn connect_mysql(url: Option<&DatabaseUrl>) -> Result<PooledConn, String> {
+    let url = url.ok_or("MYSQL_URL not provided")?;
+    let opts = url
+        .mysql_opts()
+        .map_err(|err| format!("invalid MYSQL_URL format: {err}"))?;
+    Pool::new(opts)
+        .and_then(|pool| pool.get_conn())
+        .map_err(|err| format!("failed to connect MySQL: {err}"))
+}
+
+fn probe_mysql(config: &ProbeConfig, report: &mut ProbeReport, capability: &mut CapabilityProbe) {
+    let mut conn = match connect_mysql(config.mysql_url.as_ref()) {
+        Ok(conn) => conn,
+        Err(err) => {
+            report.check("mysql.connection", Err(err));
+            return;
+        }
+    };
+    report.connected = report.check("mysql.connection", Ok(()));
+    report.grantee = conn
+        .query_first::<String, _>("SELECT CURRENT_USER()")
+        .ok()
+        .flatten()
+        .and_then(|it| mysql_grantee(&it));
+    report.server_version_num = conn
+        .query_first::<String, _>("SELECT VERSION()")
+        .ok()
+        .flatten()
+        .and_then(|it| parse_mysql_version_num(&it));
+
+    match conn.query::<String, _>("SHOW GRANTS") {
+        Ok(lines) => report.grants = lines,
+        Err(err) => report.warnings.push(format!("SHOW GRANTS failed: {err}")),
+    }
+    let grants = parse_mysql_grants(&report.grants);
+
+    capability.set(
+        Capability::StatusCounters,
+        report.check(
+            "mysql.global_status",
+            conn.query_drop("SHOW GLOBAL STATUS LIKE 'Uptime'")
+                .map_err(|it| it.to_string()),
+        ),
+    );
+    let variables = [
+        "performance_schema",
+        "slow_query_log",
+        "slow_query_log_file",
+        "log_error",
+    ]
+    .into_iter()
+    .map(|name| {
+        fetch_mysql_variable(&mut conn, name)
+            .map(|value| (name, value.filter(|it| !it.trim().is_empty())))
+    })
+    .collect::<Result<BTreeMap<_, _>, _>>();
+    capability.set(
+        Capability::Settings,
+        report.check(
+            "mysql.variables",
+            variables.as_ref().map(|_| ()).map_err(|it| it.to_string()),
+        ),
+    );
+    let variables = variables.unwrap_or_default();
+    let variable = |name: &str| variables.get(name).cloned().flatten();
+
+    capability.set(
+        Capability::SchemaMetadata,
+        report.check(
+            "mysql.information_schema",
+            conn.query_drop("SELECT 1 FROM information_schema.TABLES LIMIT 1")
+                .and_then(|_| {
+                    conn.query_drop("SELECT 1 FROM information_schema.STATISTICS LIMIT 1")
+                })
+                .map_err(|it| it.to_string()),
+        ),
+    );
+    capability.set(
+        Capability::ReplicationStatus,
+        report.check(
+            "mysql.replication_status",
+            conn.query_drop("SHOW REPLICA STATUS")
+                .or_else(|_| conn.query_drop("SHOW SLAVE STATUS"))
+                .map_err(|it| it.to_string()),
+        ),
+    );
+
+    capability.set(
+        Capability::StatementLogHotSwitch,
+        if !config.enable_slow_log_hot_switch {
+            report.check(
+                "mysql.slow_log_hot_switch",
+                Err(HOT_SWITCH_DISABLED.to_string()),
+            )
+        } else if grants.has_global("SUPER") || grants.has_global("SYSTEM_VARIABLES_ADMIN") {
+            report.check("mysql.slow_log_hot_switch", Ok(()))
+        } else if variable("slow_query_log").is_some_and(|it| is_truthy(&it)) {
+            report.record(
+                "mysql.slow_log_hot_switch",
+                true,
+                Some("slow_query_log is already ON; long_query_time cannot be changed".to_string()),
+            )
+        } else {
+            report.check(
+                "mysql.slow_log_hot_switch",
+                Err(
+                    "SET GLOBAL slow_query_log requires SUPER or SYSTEM_VARIABLES_ADMIN"
+                        .to_string(),
+                ),
+            )
+        },
+    );
+    capability.set(
+        Capability::StatementLog,
+        report.check(
+            "mysql.slow_log",
+            check_readable(
+                config
+                    .slow_log_path
+                    .clone()
+                    .or_else(|| variable("slow_query_log_file")),
+                "slow log path unavailable (provide --slow-log-path or MySQL slow_query_log_file)",
+            ),
+        ),
+    );
+    capability.set(
+        Capability::ErrorLog,
+        report.check(
+            "mysql.error_log",
+            check_readable(
+                config
+                    .error_log_path
+                    .clone()
+                    .or_else(|| variable("log_error").filter(|it| it != "stderr")),
+                "error log path unavailable (provide --error-log-path or MySQL log_error)",
+            ),
+        ),
+    );
+
+    capability.set(
+        Capability::StatementStatsEnabled,
+        report.check(
+            "mysql.performance_schema_enabled",
+            match variable("performance_schema") {
+                Some(value) if is_truthy(&value) => Ok(()),
+                Some(value) => Err(format!("performance_schema = {value}")),
+                None => Err("performance_schema variable unavailable".to_string()),
+            },
+        ),
+    );
+    capability.set(
+        Capability::StatementStatsAccess,
+        report.check(
+            "mysql.performance_schema",
+            conn.query_drop(
+                "SELECT 1 FROM performance_schema.events_statements_summary_by_digest LIMIT 1",
+            )
+            .map_err(|it| it.to_string()),
+        ),
+    );
+    capability.set(
+        Capability::DiagnosticViews,
+        report.check(
+            "mysql.sys_schema",
+            conn.query_drop("SELECT 1 FROM sys.version")
+                .map_err(|it| it.to_string()),
+        ),
+    );
+    capability.set(
+        Capability::EngineStatusSampling,
+        report.check(
+            "mysql.innodb_status",
+            match fetch_innodb_status_text(&mut conn) {
+                Ok(Some(_)) => Ok(()),
+                Ok(None) => Err("SHOW ENGINE INNODB STATUS returned no rows".to_string()),
+                Err(err) => Err(err.to_string()),
+            },
+        ),
+    );
+}
+
+fn pg_flag(client: &mut Client, sql: &str, object: &str) -> Result<bool, String> {
+    client
+        .query_one(sql, &[&object])
+        .map(|row| row.get::<_, bool>(0))
+        .map_err(|it| it.to_string())
+}
+
+fn pg_select_privilege(client: &mut Client, objects: &[&str]) -> Result<(), String> {
+    for object in objects {
+        if !pg_flag(client, "SELECT has_table_privilege($1, 'SELECT')", object)? {
+            return Err(format!("missing SELECT on {object}"));
+        }
+    }
+    Ok(())
+}
+
+fn probe_postgres(
+    config: &ProbeConfig,
+    report: &mut ProbeReport,
+    capability: &mut CapabilityProbe,
+) {
+    let connected = config
+        .postgres_url
+        .as_ref()
+        .ok_or_else(|| "POSTGRES_URL not provided".to_string())
+        .and_then(|url| {
+            url.postgres_config()
+                .and_then(|it| it.connect(NoTls))
+                .map_err(|err| format!("failed to connect PostgreSQL: {err}"))
+        });
+    let mut client = match connected {
+        Ok(client) => client,
+        Err(err) => {
+            report.check("postgres.connection", Err(err));
+            return;
+        }
+    };
+    report.connected = report.check("postgres.connection", Ok(()));
+    report.grantee = client
+        .query_one("SELECT quote_ident(current_user)", &[])
+        .map(|row| row.get::<_, String>(0))
+        .ok();
+    report.server_version_num = fetch_postgres_setting(&mut client, "server_version_num")
+        .ok()
+        .flatten()
+        .and_then(|it| it.parse::<u32>().ok());
+
+    let superuser = client
+        .query_one(
+            "SELECT rolsuper FROM pg_roles WHERE rolname = current_user",
+            &[],
+        )
+        .map(|row| row.get::<_, bool>(0))
+        .unwrap_or(false);
+    if superuser {
+        report.grants.push("SUPERUSER".to_string());
+    }
+    match client.query(
+        "SELECT rolname FROM pg_roles \
+         WHERE rolname <> current_user AND pg_has_role(current_user, oid, 'MEMBER') \
+         ORDER BY rolname",
+        &[],
+    ) {
+        Ok(rows) => report
+            .grants
+            .extend(rows.iter().map(|it| it.get::<_, String>(0))),
+        Err(err) => report
+            .warnings
+            .push(format!("failed listing role memberships: {err}")),
+    }
+    let reads_all_stats = superuser
+        || report
+            .grants
+            .iter()
+            .any(|it| it == "pg_read_all_stats" || it == "pg_monitor");
+
+    capability.set(
+        Capability::StatusCounters,
+        report.check(
+            "postgres.global_status",
+            pg_select_privilege(&mut client, &["pg_catalog.pg_stat_database"]),
+        ),
+    );
+    capability.set(
+        Capability::Settings,
+        report.check(
+            "postgres.settings",
+            pg_select_privilege(&mut client, &["pg_catalog.pg_settings"]),
+        ),
+    );
+    capability.set(
+        Capability::SchemaMetadata,
+        report.check(
+            "postgres.storage",
+            pg_select_privilege(
+                &mut client,
+                &["pg_catalog.pg_class", "pg_catalog.pg_indexes"],
+            ),
+        ),
+    );
+    capability.set(
+        Capability::ReplicationStatus,
+        match pg_select_privilege(&mut client, &["pg_catalog.pg_stat_replication"]) {
+            Ok(()) if !reads_all_stats => report.record(
+                "postgres.replication_status",
+                true,
+                Some("replication details are hidden without pg_read_all_stats".to_string()),
+            ),
+            result => report.check("postgres.replication_status", result),
+        },
+    );
+
+    let hot_switch = if !config.enable_slow_log_hot_switch {
+        Err(HOT_SWITCH_DISABLED.to_string())
+    } else if superuser {
+        Ok(())
+    } else if report.server_version_num.unwrap_or(0) < 150_000 {
+        Err("ALTER SYSTEM requires superuser before PostgreSQL 15".to_string())
+    } else {
+        let alter = pg_flag(
+            &mut client,
+            "SELECT has_parameter_privilege($1, 'ALTER SYSTEM')",
+            "log_min_duration_statement",
+        );
+        let reload = pg_flag(
+            &mut client,
+            "SELECT has_function_privilege($1, 'EXECUTE')",
+            "pg_reload_conf()",
+        );
+        match (alter, reload) {
+            (Ok(true), Ok(true)) => Ok(()),
+            (Err(err), _) | (_, Err(err)) => Err(err),
+            _ => Err(
+                "requires ALTER SYSTEM on log_min_duration_statement and EXECUTE on pg_reload_conf()"
+                    .to_string(),
+            ),
+        }
+    };
+    capability.set(
+        Capability::StatementLogHotSwitch,
+        report.check("postgres.statement_log_hot_switch", hot_switch),
+    );
+
+    let statement_log = config
+        .slow_log_path
+        .clone()
+        .or_else(|| discover_postgres_log_path(&mut client).ok().flatten());
+    capability.set(Capability::StatementLog, report.check(
+        "postgres.statement_log",
+        check_readable(
+            statement_log.clone(),
+            "statement log path unavailable (enable logging_collector or provide --slow-log-path)",
+        ),
+    ));
+    capability.set(
+        Capability::ErrorLog,
+        report.check(
+            "postgres.error_log",
+            check_readable(
+                config.error_log_path.clone().or(statement_log),
+                "postgres error log path unavailable (provide --error-log-path or --slow-log-path)",
+            ),
+        ),
+    );
+
+    let installed = client
+        .query_one(
+            "SELECT EXISTS (SELECT 1 FROM pg_extension WHERE extname = 'pg_stat_statements')",
+            &[],
+        )
+        .map(|row| row.get::<_, bool>(0))
+        .map_err(|it| it.to_string());
+    capability.set(
+        Capability::StatementStatsEnabled,
+        report.check(
+            "postgres.pg_stat_statements_installed",
+            match installed {
+                Ok(true) => Ok(()),
+                Ok(false) => {
+                    Err("CREATE EXTENSION pg_stat_statements has not been run".to_string())
+                }
+                Err(err) => Err(err),
+            },
+        ),
+    );
+    capability.set(
+        Capability::StatementStatsAccess,
+        report.check(
+            "postgres.pg_stat_statements",
+            if capability.has(Capability::StatementStatsEnabled) {
+                client
+                    .simple_query("SELECT 1 FROM pg_stat_statements LIMIT 1")
+                    .map(|_| ())
+                    .map_err(|it| it.to_string())
+            } else {
+                Err("pg_stat_statements is not installed".to_string())
+            },
+        ),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_global_and_schema_grants() {
+        let lines = [
+            "GRANT SELECT, PROCESS, REPLICATION CLIENT ON *.* TO `monitor`@`%`",
+            "GRANT SYSTEM_VARIABLES_ADMIN ON *.* TO `monitor`@`%`",
+            "GRANT SELECT, UPDATE (`note`) ON `performance_schema`.* TO `monitor`@`%`",
+            "GRANT SELECT ON `shop`.`orders` TO `monitor`@`%`",
+            "GRANT `dba_role`@`%` TO `monitor`@`%`",
+        ]
+        .map(String::from);
+        let grants = parse_mysql_grants(&lines);
+
+        assert_eq!(
+            grants.global.iter().map(String::as_str).collect::<Vec<_>>(),
+            vec![
+                "PROCESS",
+                "REPLICATION CLIENT",
+                "SELECT",
+                "SYSTEM_VARIABLES_ADMIN"
+            ]
+        );
+        assert!(grants.has_global("SYSTEM_VARIABLES_ADMIN"));
+        assert!(!grants.has_global("SUPER"));
+        assert_eq!(
+            grants.schemas["performance_schema"]
+                .iter()
+                .map(String::as_str)
+                .collect::<Vec<_>>(),
+            vec!["SELECT", "UPDATE"]
+        );
+        assert!(!grants.schemas.contains_key("shop"));
+        assert!(grants.can_select("sys"));
+
+        assert_eq!(
+            mysql_grantee("monitor@10.0.%").as_deref(),
+            Some("'monitor'@'10.0.%'")
+        );
+        assert_eq!(parse_mysql_version_num("5.7.44-log"), Some(50744));
+        assert_eq!(
+            parse_mysql_version_num("8.0.36-0ubuntu0.22.04.1"),
+            Some(80036)
+        );
+
+        let root =
+            parse_mysql_grants(&["GRANT ALL PRIVILEGES ON *.* TO `root`@`localhost`".to_string()]);
+        assert!(root.has_global("SUPER"));
+        assert!(root.can_select("performance_schema"));
+    }
+
+    #[test]
+    fn should_report_checks_without_a_database() {
+        let proc_root =
+            std::env::temp_dir().join(format!("sql-insight-probe-{}", std::process::id()));
+        fs::create_dir_all(&proc_root).unwrap();
+        fs::write(proc_root.join("stat"), "cpu  1 2 3 4\n").unwrap();
+        let config = ProbeConfig {
+            perf_binary: "sql-insight-missing-perf".to_string(),
+            strace_binary: "sql-insight-missing-strace".to_string(),
+            tcpdump_binary: "sql-insight-missing-tcpdump".to_string(),
+            proc_root: proc_root.clone(),
+            ..ProbeConfig::default()
+        };
+        let report = run_probe(&config);
+        fs::remove_dir_all(&proc_root).unwrap();
+
+        assert!(!report.connected);
+        let checks = report
+            .checks
+            .iter()
+            .map(|it| (it.name.as_str(), it.ok, it.detail.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            checks,
+            vec![
+                ("mysql.connection", false, Some("MYSQL_URL not provided")),
+                ("os.basic_metrics", true, None),
+                (
+                    "binary.perf",
+                    false,
+                    Some("perf not found (sql-insight-missing-perf)")
+                ),
+                (
+                    "binary.strace",
+                    false,
+                    Some("strace not found (sql-insight-missing-strace)")
+                ),
+                (
+                    "binary.tcpdump",
+                    false,
+                    Some("tcpdump not found (sql-insight-missing-tcpdump)")
+                ),
+            ]
+        );
+        assert_eq!(
+            report.capability,
+            CapabilityProbe::from_iter([Capability::OsMetrics])
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::BTreeMap;
+
+/// Key/value tags carried in leading or trailing block comments, as written by
+/// sqlcommenter (`key='value'`, URL-encoded) or Rails marginalia (`key:value`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryTags {
+    pub tags: BTreeMap<String, String>,
+    pub trace_id: Option<String>,
+}
+
+/// Tags that identify a single request; they become trace samples instead of
+/// aggregation keys.
+const TRACE_KEYS: &[&str] = &["traceparent", "tracestate", "trace_id", "traceid"];
+
+pub fn extract_query_tags(sql: &str) -> QueryTags {
+    let mut result = QueryTags::default();
+    for body in edge_comments(sql) {
+        let Some(pairs) = parse_tag_pairs(body) else {
+            continue;
+        };
+        for (key, value) in pairs {
+            if TRACE_KEYS.contains(&key.as_str()) {
+                if result.trace_id.is_none() {
+                    result.trace_id = trace_id_from(&key, &value);
+                }
+                continue;
+            }
+            result.tags.entry(key).or_insert(value);
+        }
+    }
+    result
+}
+
+/// Bodies of the `/* ... */` comments before the first token and after the last
+/// one. Comments inside the statement are never tags.
+fn edge_comments(sql: &str) -> Vec<&str> {
+    let mut bodies = Vec::new();
+
+    let mut rest = sql.trim_start();
+    while let Some(stripped) = rest.strip_prefix("/*") {
+        let Some(end) = stripped.find("*/") else {
+            break;
+        };
+        bodies.push(&stripped[..end]);
+        rest = stripped[(end + 2)..].trim_start();
+    }
+
+    let mut tail = rest.trim_end().trim_end_matches(';').trim_end();
+    while let Some(stripped) = tail.strip_suffix("*/") {
+        let Some(start) = stripped.rfind("/*") else {
+            break;
+        };
+        bodies.push(&stripped[(start + 2)..]);
+        tail = stripped[..start].trim_end();
+    }
+    bodies
+}
+
+/// Returns `None` when any comma-separated item is not a `key=value` or
+/// `key:value` pair, so free-form comments are not mistaken for tags.
+fn parse_tag_pairs(body: &str) -> Option<Vec<(String, String)>> {
+    let body = body.trim();
+    if body.is_empty() {
+        return None;
+    }
+
+    let mut pairs = Vec::new();
+    for item in split_unquoted(body) {
+        let item = item.trim();
+        let idx = item.find(['=', ':'])?;
+        let key = item[..idx].trim();
+        if key.is_empty()
+            || !key
+                .chars()
+                .all(|it| it.is_ascii_alphanumeric() || matches!(it, '_' | '-' | '.'))
+        {
+            return None;
+        }
+        let raw = item[(idx + 1)..].trim();
+        let value = match raw.strip_prefix('\'').and_then(|it| it.strip_suffix('\'')) {
+            Some(quoted) => percent_decode(&quoted.replace("\\'", "'")),
+            None => raw.to_string(),
+        };
+        pairs.push((key.to_ascii_lowercase(), value));
+    }
+    Some(pairs)
+}
+
+fn split_unquoted(body: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let mut in_quote = false;
+    let mut escaped = false;
+    let mut start = 0;
+    for (idx, ch) in body.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match ch {
+            '\\' if in_quote => escaped = true,
+            '\'' => in_quote = !in_quote,
+            ',' if !in_quote => {
+                items.push(&body[start..idx]);
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&body[start..]);
+    items
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        if bytes[idx] == b'%'
+            && let Some(hex) = value.get((idx + 1)..(idx + 3))
+            && let Ok(byte) = u8::from_str_radix(hex, 16)
+        {
+            out.push(byte);
+            idx += 3;
+            continue;
+        }
+        out.push(bytes[idx]);
+        idx += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// W3C `traceparent` is `version-traceid-spanid-flags`; other trace keys carry
+/// the id directly.
+fn trace_id_from(key: &str, value: &str) -> Option<String> {
+    match key {
+        "tracestate" => None,
+        "traceparent" => {
+            let trace_id = value.split('-').nth(1)?;
+            (trace_id.len() == 32 && trace_id.chars().all(|it| it.is_ascii_hexdigit()))
+                .then(|| trace_id.to_ascii_lowercase())
+        }
+        _ => (!value.is_empty()).then(|| value.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_extract_sqlcommenter_tags_and_trace_id() {
+        let tags = extract_query_tags(
+            "SELECT * FROM orders WHERE id = 7 /*app='checkout',route='%2Fpay%2Fconfirm',\
+             traceparent='00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01'*/;",
+        );
+        assert_eq!(tags.tags.get("app").map(String::as_str), Some("checkout"));
+        assert_eq!(
+            tags.tags.get("route").map(String::as_str),
+            Some("/pay/confirm")
+        );
+        assert!(!tags.tags.contains_key("traceparent"));
+        assert_eq!(
+            tags.trace_id.as_deref(),
+            Some("4bf92f3577b34da6a3ce929d0e0e4736")
+        );
+    }
+
+    #[test]
+    fn should_extract_leading_marginalia_tags() {
+        let tags =
+            extract_query_tags("/*application:Shop,controller:orders,action:index*/ SELECT 1");
+        assert_eq!(tags.tags.len(), 3);
+        assert_eq!(
+            tags.tags.get("controller").map(String::as_str),
+            Some("orders")
+        );
+        assert_eq!(tags.trace_id, None);
+    }
+
+    #[test]
+    fn should_ignore_free_form_and_inner_comments() {
+        assert_eq!(
+            extract_query_tags("/* nightly report */ SELECT 1"),
+            QueryTags::default()
+        );
+        assert_eq!(
+            extract_query_tags("SELECT /* app='x' */ 1 FROM t"),
+            QueryTags::default()
+        );
+    }
+}
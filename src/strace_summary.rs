@@ -0,0 +1,436 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Output, Stdio};
+use std::thread;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::collection::CollectionPolicy;
+use crate::level1::Level1Engine;
+use crate::perf_profile::{
+    effective_uid_is_root, has_effective_capability, locate_database_pid, process_uids,
+};
+
+pub const STRACE_MAX_DURATION_SECS: u64 = 30;
+
+/// Capability number of `CAP_SYS_PTRACE` in the `CapEff` mask.
+const CAP_SYS_PTRACE: u32 = 19;
+
+/// Syscalls on the write/flush path whose latency is reported separately.
+const IO_SYNC_SYSCALLS: [&str; 10] = [
+    "fsync",
+    "fdatasync",
+    "sync_file_range",
+    "pwrite",
+    "pwrite64",
+    "pwritev",
+    "pwritev2",
+    "write",
+    "io_submit",
+    "io_getevents",
+];
+
+#[derive(Debug, Clone)]
+pub struct StraceConfig {
+    pub engine: Level1Engine,
+    pub pid: Option<u32>,
+    pub pid_file: Option<PathBuf>,
+    pub duration_secs: u64,
+    pub strace_binary: String,
+    pub proc_root: PathBuf,
+}
+
+impl Default for StraceConfig {
+    fn default() -> Self {
+        Self {
+            engine: Level1Engine::Mysql,
+            pid: None,
+            pid_file: None,
+            duration_secs: 5,
+            strace_binary: "strace".to_string(),
+            proc_root: PathBuf::from("/proc"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct StraceSummaryReport {
+    pub enabled: bool,
+    pub skipped_reason: Option<String>,
+    pub pid: Option<u32>,
+    pub pid_source: Option<String>,
+    pub duration_secs: u64,
+    pub total_calls: u64,
+    pub total_errors: u64,
+    pub total_seconds: f64,
+    /// Sorted by time spent, highest first.
+    pub syscalls: Vec<SyscallStat>,
+    /// fsync/fdatasync/pwrite and friends, for IO stall triage.
+    pub io_latency: Vec<SyscallStat>,
+    pub warnings: Vec<String>,
+}
+
+/// One row of `strace -c -w`; `seconds` and `usecs_per_call` are wall-clock.
+#[derive(Debug, Clone, Serialize, Default, PartialEq)]
+pub struct SyscallStat {
+    pub syscall: String,
+    pub calls: u64,
+    pub errors: u64,
+    pub seconds: f64,
+    pub usecs_per_call: u64,
+    pub time_pct: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StraceSummary {
+    pub syscalls: Vec<SyscallStat>,
+    pub total_calls: u64,
+    pub total_errors: u64,
+    pub total_seconds: f64,
+}
+
+/// Checks that `strace` runs and that ptrace attach to the database process
+/// (`target_pid`) is allowed; `Err` carries the reason.
+pub fn probe_strace(
+    strace_binary: &str,
+    target_pid: Option<u32>,
+    proc_root: &Path,
+) -> Result<(), String> {
+    match Command::new(strace_binary).arg("-V").output() {
+        Ok(output) if output.status.success() => {}
+        Ok(output) => {
+            return Err(format!(
+                "strace is not usable: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            return Err(format!("strace not found ({strace_binary})"));
+        }
+        Err(err) => return Err(format!("failed running {strace_binary}: {err}")),
+    }
+    if effective_uid_is_root(proc_root) || has_effective_capability(proc_root, CAP_SYS_PTRACE) {
+        return Ok(());
+    }
+    let scope = fs::read_to_string(proc_root.join("sys/kernel/yama/ptrace_scope"))
+        .ok()
+        .and_then(|it| it.trim().parse::<u8>().ok());
+    match scope {
+        Some(3) => return Err("ptrace is disabled (kernel.yama.ptrace_scope=3)".to_string()),
+        // Scope 1 only allows descendants, which a running server never is.
+        Some(scope @ 1..=2) => {
+            return Err(format!(
+                "kernel.yama.ptrace_scope={scope} requires root or CAP_SYS_PTRACE"
+            ));
+        }
+        _ => {}
+    }
+    // Classic ptrace rules: every UID of the target must be our effective UID.
+    let Some(pid) = target_pid else {
+        return Err(
+            "database process not found; attaching requires its uid, root or CAP_SYS_PTRACE"
+                .to_string(),
+        );
+    };
+    let ours = process_uids(proc_root, "self").map(|it| it[1]);
+    match process_uids(proc_root, &pid.to_string()) {
+        Some(target) if ours.is_some_and(|uid| target[..3].iter().all(|it| *it == uid)) => Ok(()),
+        Some(target) => Err(format!(
+            "database process {pid} runs as uid {}; attaching requires the same uid, root or CAP_SYS_PTRACE",
+            target[1]
+        )),
+        None => Err(format!("cannot read the owner of database process {pid}")),
+    }
+}
+
+/// Runs the strace half of the Level 3 `perf_or_strace_short_window` task.
+/// Nothing runs unless the policy enables expert mode.
+pub fn collect_strace_summary(
+    policy: &CollectionPolicy,
+    config: &StraceConfig,
+) -> StraceSummaryReport {
+    let mut report = StraceSummaryReport {
+        duration_secs: config.duration_secs.clamp(1, STRACE_MAX_DURATION_SECS),
+        ..StraceSummaryReport::default()
+    };
+    if report.duration_secs != config.duration_secs {
+        report.warnings.push(format!(
+            "strace duration_secs capped from {} to {}",
+            config.duration_secs, report.duration_secs
+        ));
+    }
+    if !policy.expert_mode_enabled {
+        report.skipped_reason = Some("expert mode is disabled by policy".to_string());
+        return report;
+    }
+    let located = match config.pid {
+        Some(pid) => Ok((pid, "config".to_string())),
+        None => locate_database_pid(config.engine, config.pid_file.as_deref(), &config.proc_root),
+    };
+    let target_pid = located.as_ref().ok().map(|it| it.0);
+    if let Err(err) = probe_strace(&config.strace_binary, target_pid, &config.proc_root) {
+        report.skipped_reason = Some(err);
+        return report;
+    }
+    let (pid, source) = match located {
+        Ok(found) => found,
+        Err(err) => {
+            report.skipped_reason = Some(err);
+            return report;
+        }
+    };
+    report.pid = Some(pid);
+    report.pid_source = Some(source);
+
+    let output = match run_strace(&config.strace_binary, pid, report.duration_secs) {
+        Ok(output) => output,
+        Err(err) => {
+            report.skipped_reason = Some(err);
+            return report;
+        }
+    };
+    let summary = parse_strace_summary(&output);
+    if summary.syscalls.is_empty() {
+        let detail = output
+            .lines()
+            .map(str::trim)
+            .find(|it| !it.is_empty())
+            .unwrap_or("no output");
+        report.skipped_reason = Some(if detail.contains("Operation not permitted") {
+            format!("strace lacks privileges: {detail}")
+        } else {
+            format!("strace produced no summary: {detail}")
+        });
+        return report;
+    }
+
+    report.enabled = true;
+    report.total_calls = summary.total_calls;
+    report.total_errors = summary.total_errors;
+    report.total_seconds = summary.total_seconds;
+    report.io_latency = summary
+        .syscalls
+        .iter()
+        .filter(|it| IO_SYNC_SYSCALLS.contains(&it.syscall.as_str()))
+        .cloned()
+        .collect();
+    report.syscalls = summary.syscalls;
+    report
+}
+
+/// `strace -c` only prints its table when interrupted, so the window ends
+/// with SIGINT rather than a kill.
+fn run_strace(strace_binary: &str, pid: u32, duration_secs: u64) -> Result<String, String> {
+    let child = Command::new(strace_binary)
+        .args(["-c", "-w", "-f", "-p"])
+        .arg(pid.to_string())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("failed running strace: {err}"))?;
+    let output = interrupt_after(child, duration_secs, "strace")?;
+    Ok(String::from_utf8_lossy(&output.stderr).into_owned())
+}
+
+/// Lets `child` run for the window, then sends SIGINT so it can flush its
+/// results. A child that already exited (attach refused, process gone) is
+/// just collected.
+pub(crate) fn interrupt_after(
+    mut child: Child,
+    duration_secs: u64,
+    name: &str,
+) -> Result<Output, String> {
+    thread::sleep(Duration::from_secs(duration_secs));
+    if child.try_wait().is_ok_and(|it| it.is_none()) {
+        let interrupted = Command::new("kill")
+            .args(["-INT", &child.id().to_string()])
+            .status();
+        if !interrupted.is_ok_and(|it| it.success()) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format!("failed to interrupt {name}"));
+        }
+    }
+    child
+        .wait_with_output()
+        .map_err(|err| format!("failed waiting for {name}: {err}"))
+}
+
+/// Parses the `% time  seconds  usecs/call  calls  errors  syscall` table.
+/// The errors column is blank for syscalls that never failed.
+pub fn parse_strace_summary(text: &str) -> StraceSummary {
+    let mut summary = StraceSummary::default();
+    let mut in_table = false;
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("% time") {
+            in_table = true;
+            continue;
+        }
+        if !in_table || trimmed.is_empty() || trimmed.starts_with("---") {
+            continue;
+        }
+        let tokens = trimmed.split_whitespace().collect::<Vec<_>>();
+        let Some(syscall) = tokens.last() else {
+            continue;
+        };
+        if *syscall == "total" {
+            break;
+        }
+        if !(5..=6).contains(&tokens.len()) {
+            continue;
+        }
+        let parse = |idx: usize| tokens.get(idx).and_then(|it| it.parse::<f64>().ok());
+        let (Some(time_pct), Some(seconds), Some(usecs), Some(calls)) =
+            (parse(0), parse(1), parse(2), parse(3))
+        else {
+            continue;
+        };
+        summary.syscalls.push(SyscallStat {
+            syscall: syscall.to_string(),
+            calls: calls as u64,
+            errors: if tokens.len() == 6 {
+                parse(4).unwrap_or(0.0) as u64
+            } else {
+                0
+            },
+            seconds,
+            usecs_per_call: usecs as u64,
+            time_pct,
+        });
+    }
+
+    // The `total` row drops columns inconsistently across versions; sum rows.
+    summary.total_calls = summary.syscalls.iter().map(|it| it.calls).sum();
+    summary.total_errors = summary.syscalls.iter().map(|it| it.errors).sum();
+    summary.total_seconds =
+        (summary.syscalls.iter().map(|it| it.seconds).sum::<f64>() * 1e6).round() / 1e6;
+    summary.syscalls.sort_by(|a, b| {
+        b.seconds
+            .total_cmp(&a.seconds)
+            .then_with(|| a.syscall.cmp(&b.syscall))
+    });
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_strace_summary_table() {
+        let path =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/strace/strace_summary.txt");
+        let text = fs::read_to_string(path).expect("failed reading strace fixture");
+        let summary = parse_strace_summary(&text);
+
+        assert_eq!(summary.syscalls.len(), 7);
+        assert_eq!(summary.total_calls, 40_444);
+        assert_eq!(summary.total_errors, 1_575);
+        assert_eq!(summary.total_seconds, 5.068772);
+
+        let fsync = &summary.syscalls[0];
+        assert_eq!(fsync.syscall, "fsync");
+        assert_eq!(
+            (fsync.calls, fsync.errors, fsync.usecs_per_call),
+            (1000, 0, 2104)
+        );
+        let futex = summary
+            .syscalls
+            .iter()
+            .find(|it| it.syscall == "futex")
+            .unwrap();
+        assert_eq!(futex.errors, 1520);
+    }
+
+    #[test]
+    fn should_report_probe_failures() {
+        let missing = probe_strace("sql-insight-missing-strace", None, Path::new("/proc"));
+        assert_eq!(
+            missing.unwrap_err(),
+            "strace not found (sql-insight-missing-strace)"
+        );
+
+        let config = StraceConfig {
+            duration_secs: 0,
+            strace_binary: "sql-insight-missing-strace".to_string(),
+            ..StraceConfig::default()
+        };
+        let report = collect_strace_summary(&CollectionPolicy::default(), &config);
+        assert_eq!(
+            report.skipped_reason.as_deref(),
+            Some("expert mode is disabled by policy")
+        );
+        assert_eq!(report.duration_secs, 1);
+
+        let policy = CollectionPolicy {
+            expert_mode_enabled: true,
+            ..CollectionPolicy::default()
+        };
+        let report = collect_strace_summary(&policy, &config);
+        assert!(!report.enabled);
+        assert_eq!(
+            report.skipped_reason.as_deref(),
+            Some("strace not found (sql-insight-missing-strace)")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn should_allow_same_uid_or_cap_sys_ptrace_attach() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root =
+            std::env::temp_dir().join(format!("sql-insight-{}-strace-probe", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let proc_root = root.join("proc");
+        fs::create_dir_all(proc_root.join("self")).unwrap();
+        fs::create_dir_all(proc_root.join("sys/kernel/yama")).unwrap();
+        fs::create_dir_all(proc_root.join("900")).unwrap();
+        let strace = root.join("strace");
+        fs::write(&strace, "#!/bin/sh\nexit 0\n").unwrap();
+        fs::set_permissions(&strace, fs::Permissions::from_mode(0o755)).unwrap();
+        let strace = strace.display().to_string();
+        let write_self = |cap_eff: &str| {
+            fs::write(
+                proc_root.join("self/status"),
+                format!("Uid:\t1000\t1000\t1000\t1000\nCapEff:\t{cap_eff}\n"),
+            )
+            .unwrap();
+        };
+        let write_target = |uid: u32| {
+            fs::write(
+                proc_root.join("900/status"),
+                format!("Uid:\t{uid}\t{uid}\t{uid}\t{uid}\n"),
+            )
+            .unwrap();
+        };
+        fs::write(proc_root.join("sys/kernel/yama/ptrace_scope"), "0\n").unwrap();
+        write_self("0000000000000000");
+
+        write_target(1000);
+        assert_eq!(probe_strace(&strace, Some(900), &proc_root), Ok(()));
+        write_target(27);
+        assert_eq!(
+            probe_strace(&strace, Some(900), &proc_root).unwrap_err(),
+            "database process 900 runs as uid 27; attaching requires the same uid, root or CAP_SYS_PTRACE"
+        );
+        assert_eq!(
+            probe_strace(&strace, None, &proc_root).unwrap_err(),
+            "database process not found; attaching requires its uid, root or CAP_SYS_PTRACE"
+        );
+
+        fs::write(proc_root.join("sys/kernel/yama/ptrace_scope"), "1\n").unwrap();
+        write_target(1000);
+        assert_eq!(
+            probe_strace(&strace, Some(900), &proc_root).unwrap_err(),
+            "kernel.yama.ptrace_scope=1 requires root or CAP_SYS_PTRACE"
+        );
+        // CAP_SYS_PTRACE is bit 19.
+        write_self("0000000000080000");
+        assert_eq!(probe_strace(&strace, Some(900), &proc_root), Ok(()));
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,297 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+
+use crate::level0::{IndexEntry, TableSizeEntry};
+use crate::level1::SlowSqlDigest;
+use crate::postgres_level0::{PostgresIndexEntry, PostgresTableSizeEntry};
+
+/// Engine-neutral table size, built from Level 0 `table_sizes` and `indexes`.
+#[derive(Debug, Clone, Default)]
+pub struct TableSizeRef {
+    pub table_schema: String,
+    pub table_name: String,
+    pub estimated_rows: u64,
+    pub data_length: u64,
+    pub index_length: u64,
+    pub total_length: u64,
+    pub index_count: usize,
+}
+
+/// Slow SQL cost per table. A digest touching several tables counts fully
+/// toward each of them.
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct TableSlowSqlRollup {
+    pub table: String,
+    pub table_schema: Option<String>,
+    pub table_name: String,
+    pub digest_count: usize,
+    pub count: u64,
+    pub total_query_time_secs: f64,
+    pub total_rows_examined: u64,
+    pub digest_ids: Vec<String>,
+    pub size: Option<TableSizeInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct TableSizeInfo {
+    pub estimated_rows: u64,
+    pub data_length: u64,
+    pub index_length: u64,
+    pub total_length: u64,
+    pub index_count: usize,
+}
+
+pub fn mysql_table_sizes(tables: &[TableSizeEntry], indexes: &[IndexEntry]) -> Vec<TableSizeRef> {
+    // `indexes` has one row per indexed column.
+    let mut index_names: BTreeMap<(&str, &str), BTreeSet<&str>> = BTreeMap::new();
+    for index in indexes {
+        index_names
+            .entry((&index.table_schema, &index.table_name))
+            .or_default()
+            .insert(&index.index_name);
+    }
+    tables
+        .iter()
+        .map(|it| TableSizeRef {
+            table_schema: it.table_schema.clone(),
+            table_name: it.table_name.clone(),
+            estimated_rows: it.table_rows,
+            data_length: it.data_length,
+            index_length: it.index_length,
+            total_length: it.total_length,
+            index_count: index_names
+                .get(&(it.table_schema.as_str(), it.table_name.as_str()))
+                .map_or(0, BTreeSet::len),
+        })
+        .collect()
+}
+
+pub fn postgres_table_sizes(
+    tables: &[PostgresTableSizeEntry],
+    indexes: &[PostgresIndexEntry],
+) -> Vec<TableSizeRef> {
+    let mut index_counts: BTreeMap<(&str, &str), usize> = BTreeMap::new();
+    for index in indexes {
+        *index_counts
+            .entry((&index.table_schema, &index.table_name))
+            .or_default() += 1;
+    }
+    tables
+        .iter()
+        .map(|it| TableSizeRef {
+            table_schema: it.table_schema.clone(),
+            table_name: it.table_name.clone(),
+            estimated_rows: it.estimated_rows.max(0) as u64,
+            data_length: it.data_length.max(0) as u64,
+            index_length: it.index_length.max(0) as u64,
+            total_length: it.total_length.max(0) as u64,
+            index_count: index_counts
+                .get(&(it.table_schema.as_str(), it.table_name.as_str()))
+                .copied()
+                .unwrap_or(0),
+        })
+        .collect()
+}
+
+/// Rolls digests up per referenced table, ordered by slow-query time. Names
+/// that resolve to a Level 0 table are keyed as `schema.table`, so `orders` and
+/// `shop.orders` land in one row. Unqualified names that exist in several
+/// schemas resolve to the digest's own schema (MySQL `use db`), else to
+/// `default_schema` (`public` on PostgreSQL).
+pub fn attribute_slow_sql_to_tables(
+    digests: &[SlowSqlDigest],
+    sizes: &[TableSizeRef],
+    default_schema: Option<&str>,
+) -> Vec<TableSlowSqlRollup> {
+    let mut grouped: BTreeMap<String, TableSlowSqlRollup> = BTreeMap::new();
+    for digest in digests {
+        let default_schema = digest.schema.as_deref().or(default_schema);
+        for table in &digest.tables {
+            let (schema, name) = match table.rsplit_once('.') {
+                Some((schema, name)) => (Some(schema), name),
+                None => (None, table.as_str()),
+            };
+            let rollup = match find_table_size(sizes, schema, name, default_schema) {
+                Some(found) => grouped
+                    .entry(format!("{}.{}", found.table_schema, found.table_name))
+                    .or_insert_with_key(|key| TableSlowSqlRollup {
+                        table: key.clone(),
+                        table_schema: Some(found.table_schema.clone()),
+                        table_name: found.table_name.clone(),
+                        size: Some(TableSizeInfo {
+                            estimated_rows: found.estimated_rows,
+                            data_length: found.data_length,
+                            index_length: found.index_length,
+                            total_length: found.total_length,
+                            index_count: found.index_count,
+                        }),
+                        ..TableSlowSqlRollup::default()
+                    }),
+                None => grouped
+                    .entry(table.clone())
+                    .or_insert_with(|| TableSlowSqlRollup {
+                        table: table.clone(),
+                        table_schema: schema.map(str::to_string),
+                        table_name: name.to_string(),
+                        ..TableSlowSqlRollup::default()
+                    }),
+            };
+            if rollup.digest_ids.contains(&digest.digest_id) {
+                continue;
+            }
+            rollup.digest_count += 1;
+            rollup.count += digest.count;
+            rollup.total_query_time_secs += digest.total_query_time_secs;
+            rollup.total_rows_examined += digest.total_rows_examined;
+            rollup.digest_ids.push(digest.digest_id.clone());
+        }
+    }
+
+    let mut rollups = grouped.into_values().collect::<Vec<_>>();
+    rollups.sort_by(|a, b| {
+        let a_time = (a.total_query_time_secs * 1_000_000.0) as u64;
+        let b_time = (b.total_query_time_secs * 1_000_000.0) as u64;
+        b_time
+            .cmp(&a_time)
+            .then_with(|| b.count.cmp(&a.count))
+            .then_with(|| a.table.cmp(&b.table))
+    });
+    rollups
+}
+
+fn find_table_size<'a>(
+    sizes: &'a [TableSizeRef],
+    schema: Option<&str>,
+    name: &str,
+    default_schema: Option<&str>,
+) -> Option<&'a TableSizeRef> {
+    let candidates = sizes
+        .iter()
+        .filter(|it| it.table_name.eq_ignore_ascii_case(name))
+        .filter(|it| schema.is_none_or(|schema| it.table_schema.eq_ignore_ascii_case(schema)))
+        .collect::<Vec<_>>();
+    match candidates.as_slice() {
+        [only] => Some(*only),
+        _ => default_schema.and_then(|schema| {
+            candidates
+                .iter()
+                .find(|it| it.table_schema.eq_ignore_ascii_case(schema))
+                .copied()
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digest(id: &str, tables: &[&str], count: u64, secs: f64, rows: u64) -> SlowSqlDigest {
+        SlowSqlDigest {
+            digest_id: id.to_string(),
+            tables: tables.iter().map(|it| it.to_string()).collect(),
+            count,
+            total_query_time_secs: secs,
+            total_rows_examined: rows,
+            ..SlowSqlDigest::default()
+        }
+    }
+
+    #[test]
+    fn should_roll_up_digests_per_table_with_sizes() {
+        let sizes = mysql_table_sizes(
+            &[
+                TableSizeEntry {
+                    table_schema: "shop".to_string(),
+                    table_name: "orders".to_string(),
+                    table_rows: 1_000,
+                    data_length: 4_096,
+                    index_length: 1_024,
+                    total_length: 5_120,
+                    ..TableSizeEntry::default()
+                },
+                TableSizeEntry {
+                    table_schema: "shop".to_string(),
+                    table_name: "users".to_string(),
+                    ..TableSizeEntry::default()
+                },
+            ],
+            &[
+                IndexEntry {
+                    table_schema: "shop".to_string(),
+                    table_name: "orders".to_string(),
+                    index_name: "PRIMARY".to_string(),
+                    ..IndexEntry::default()
+                },
+                IndexEntry {
+                    table_schema: "shop".to_string(),
+                    table_name: "orders".to_string(),
+                    index_name: "idx_user_created".to_string(),
+                    seq_in_index: 1,
+                    ..IndexEntry::default()
+                },
+                IndexEntry {
+                    table_schema: "shop".to_string(),
+                    table_name: "orders".to_string(),
+                    index_name: "idx_user_created".to_string(),
+                    seq_in_index: 2,
+                    ..IndexEntry::default()
+                },
+            ],
+        );
+        let rollups = attribute_slow_sql_to_tables(
+            &[
+                digest("a", &["orders"], 2, 2.0, 190),
+                digest("b", &["shop.orders", "users"], 1, 0.5, 40),
+                digest("c", &["audit"], 1, 0.1, 1),
+            ],
+            &sizes,
+            None,
+        );
+
+        let summary = rollups
+            .iter()
+            .map(|it| (it.table.as_str(), it.count, it.digest_ids.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![("shop.orders", 3, 2), ("shop.users", 1, 1), ("audit", 1, 1)]
+        );
+        let orders = rollups[0].size.as_ref().expect("orders size");
+        assert_eq!(orders.total_length, 5_120);
+        assert_eq!(orders.index_count, 2);
+        assert_eq!(rollups[0].total_rows_examined, 230);
+        assert!(rollups[2].size.is_none());
+        assert_eq!(rollups[2].table_schema, None);
+    }
+
+    #[test]
+    fn should_prefer_default_schema_for_ambiguous_names() {
+        let table = |schema: &str, total: i64| PostgresTableSizeEntry {
+            table_schema: schema.to_string(),
+            table_name: "events".to_string(),
+            total_length: total,
+            ..PostgresTableSizeEntry::default()
+        };
+        let sizes = postgres_table_sizes(&[table("archive", 10), table("public", 20)], &[]);
+        let digests = [digest("a", &["events"], 1, 1.0, 1)];
+
+        let ambiguous = attribute_slow_sql_to_tables(&digests, &sizes, None);
+        assert!(ambiguous[0].size.is_none());
+        let resolved = attribute_slow_sql_to_tables(&digests, &sizes, Some("public"));
+        assert_eq!(
+            resolved[0].size.as_ref().map(|it| it.total_length),
+            Some(20)
+        );
+
+        let digests = [SlowSqlDigest {
+            schema: Some("archive".to_string()),
+            ..digest("b", &["events"], 1, 1.0, 1)
+        }];
+        let resolved = attribute_slow_sql_to_tables(&digests, &sizes, Some("public"));
+        assert_eq!(
+            resolved[0].size.as_ref().map(|it| it.total_length),
+            Some(10)
+        );
+    }
+}
@@ -0,0 +1,338 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use serde::Deserialize;
+
+use crate::collection::CollectionLevel;
+use crate::level1::Level1Engine;
+use crate::pipeline::{RunMode, SchedulerConfig, jittered_interval};
+
+#[derive(Debug, thiserror::Error)]
+pub enum TargetsError {
+    #[error("failed reading targets {path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("failed parsing targets {path}: {message}")]
+    Parse { path: String, message: String },
+    #[error("invalid target #{index} ({name}): {reason}")]
+    Invalid {
+        index: usize,
+        name: String,
+        reason: String,
+    },
+}
+
+/// Targets file layout, as TOML (`[[targets]]`) or YAML (`targets:`).
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TargetsFile {
+    targets: Vec<TargetConfig>,
+}
+
+/// One database instance; unset options fall back to the CLI values.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TargetConfig {
+    pub name: String,
+    pub engine: Level1Engine,
+    pub url: String,
+    /// Password for a URL without one; see `--password-file`.
+    pub password_file: Option<String>,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    pub collect_level: Option<CollectionLevel>,
+    pub interval_secs: Option<u64>,
+    pub slow_log_path: Option<String>,
+    pub error_log_path: Option<String>,
+    pub table_limit: Option<usize>,
+    pub index_limit: Option<usize>,
+}
+
+/// Loads a `.toml`, `.yaml` or `.yml` targets file.
+pub fn load_targets(path: &Path) -> Result<Vec<TargetConfig>, TargetsError> {
+    let display = path.display().to_string();
+    let text = fs::read_to_string(path).map_err(|source| TargetsError::Io {
+        path: display.clone(),
+        source,
+    })?;
+    let is_yaml = path
+        .extension()
+        .and_then(|it| it.to_str())
+        .is_some_and(|it| it.eq_ignore_ascii_case("yaml") || it.eq_ignore_ascii_case("yml"));
+    let file: TargetsFile = if is_yaml {
+        serde_norway::from_str(&text).map_err(|err| err.to_string())
+    } else {
+        toml::from_str(&text).map_err(|err| err.to_string())
+    }
+    .map_err(|message| TargetsError::Parse {
+        path: display.clone(),
+        message,
+    })?;
+    if file.targets.is_empty() {
+        return Err(TargetsError::Parse {
+            path: display,
+            message: "no targets defined".to_string(),
+        });
+    }
+    validate_targets(&file.targets)?;
+    Ok(file.targets)
+}
+
+fn validate_targets(targets: &[TargetConfig]) -> Result<(), TargetsError> {
+    let mut names = BTreeSet::new();
+    for (index, target) in targets.iter().enumerate() {
+        let invalid = |reason: &str| TargetsError::Invalid {
+            index,
+            name: target.name.clone(),
+            reason: reason.to_string(),
+        };
+        if target.name.trim().is_empty() {
+            return Err(invalid("name must not be empty"));
+        }
+        if !names.insert(target.name.as_str()) {
+            return Err(invalid("duplicate target name"));
+        }
+        let schemes: &[&str] = match target.engine {
+            Level1Engine::Mysql => &["mysql://"],
+            Level1Engine::Postgres => &["postgres://", "postgresql://"],
+        };
+        if !schemes.iter().any(|it| target.url.starts_with(it)) {
+            return Err(invalid(&format!(
+                "url must start with {}",
+                schemes.join(" or ")
+            )));
+        }
+        if target.interval_secs == Some(0) {
+            return Err(invalid("interval_secs must be positive"));
+        }
+        if target.table_limit == Some(0) || target.index_limit == Some(0) {
+            return Err(invalid("table_limit and index_limit must be positive"));
+        }
+    }
+    Ok(())
+}
+
+/// Runs `job(target, cycle)` for every target on at most `max_workers`
+/// threads. Each target follows its own interval, measured from the end of its
+/// previous cycle, and never overlaps itself, so a slow or failing target
+/// holds one worker and does not delay the others. `schedule.max_cycles`
+/// applies per target; `emit` runs on the calling thread in completion order.
+pub fn run_fleet<R, J, E>(
+    intervals_secs: &[u64],
+    schedule: &SchedulerConfig,
+    max_workers: usize,
+    job: J,
+    mut emit: E,
+) where
+    R: Send + 'static,
+    J: Fn(usize, u32) -> R + Send + Sync + 'static,
+    E: FnMut(usize, u32, R),
+{
+    let targets = intervals_secs.len();
+    if targets == 0 {
+        return;
+    }
+    let max_cycles = match schedule.mode {
+        RunMode::Once => Some(1),
+        RunMode::Daemon => schedule.max_cycles,
+    };
+
+    let (job_tx, job_rx) = mpsc::channel::<(usize, u32)>();
+    let job_rx = Arc::new(Mutex::new(job_rx));
+    let (result_tx, result_rx) = mpsc::channel::<(usize, u32, R)>();
+    let job = Arc::new(job);
+    for _ in 0..max_workers.clamp(1, targets) {
+        let job_rx = Arc::clone(&job_rx);
+        let result_tx = result_tx.clone();
+        let job = Arc::clone(&job);
+        thread::spawn(move || {
+            loop {
+                let next = match job_rx.lock() {
+                    Ok(rx) => rx.recv(),
+                    Err(_) => break,
+                };
+                let Ok((target, cycle)) = next else {
+                    break;
+                };
+                let result = job(target, cycle);
+                if result_tx.send((target, cycle, result)).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    drop(result_tx);
+
+    let started = Instant::now();
+    // `None` once a target has finished its cycles.
+    let mut next_due = vec![Some(started); targets];
+    let mut cycles = vec![0u32; targets];
+    let mut in_flight = vec![false; targets];
+
+    loop {
+        let now = Instant::now();
+        for target in 0..targets {
+            if !in_flight[target] && next_due[target].is_some_and(|due| due <= now) {
+                in_flight[target] = true;
+                cycles[target] += 1;
+                if job_tx.send((target, cycles[target])).is_err() {
+                    return;
+                }
+            }
+        }
+        if !in_flight.contains(&true) && next_due.iter().all(Option::is_none) {
+            break;
+        }
+
+        let wait = next_due
+            .iter()
+            .zip(&in_flight)
+            .filter(|(_, busy)| !**busy)
+            .filter_map(|(due, _)| *due)
+            .min()
+            .map(|due| due.saturating_duration_since(Instant::now()));
+        let received = match wait {
+            Some(wait) => result_rx.recv_timeout(wait),
+            None => result_rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok((target, cycle, result)) => {
+                in_flight[target] = false;
+                emit(target, cycle, result);
+                next_due[target] = if max_cycles.is_some_and(|max| cycle >= max) {
+                    None
+                } else {
+                    Some(
+                        Instant::now()
+                            + jittered_interval(intervals_secs[target], schedule.jitter_pct),
+                    )
+                };
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[test]
+    fn should_load_toml_and_yaml_targets() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/targets");
+        for file in ["targets.toml", "targets.yaml"] {
+            let targets = load_targets(&root.join(file)).expect(file);
+            assert_eq!(targets.len(), 2, "{file}");
+            let orders = &targets[0];
+            assert_eq!(orders.name, "orders-primary");
+            assert_eq!(orders.engine, Level1Engine::Mysql);
+            assert_eq!(orders.labels["region"], "eu-west-1");
+            assert_eq!(orders.collect_level, Some(CollectionLevel::Level1));
+            assert_eq!(orders.interval_secs, Some(30));
+            assert_eq!(
+                orders.slow_log_path.as_deref(),
+                Some("/var/log/mysql/slow.log")
+            );
+            let billing = &targets[1];
+            assert_eq!(billing.engine, Level1Engine::Postgres);
+            assert_eq!(billing.collect_level, None);
+            assert_eq!(billing.table_limit, Some(50));
+        }
+    }
+
+    #[test]
+    fn should_reject_invalid_targets() {
+        let parse = |text: &str| {
+            let file: TargetsFile = toml::from_str(text).expect("targets toml");
+            validate_targets(&file.targets)
+                .expect_err("invalid target")
+                .to_string()
+        };
+        let target = "[[targets]]\nname = \"a\"\nengine = \"mysql\"\nurl = \"mysql://h/db\"\n";
+
+        assert!(parse(&format!("{target}{target}")).contains("duplicate target name"));
+        assert!(
+            parse("[[targets]]\nname = \"a\"\nengine = \"postgres\"\nurl = \"mysql://h/db\"")
+                .contains("url must start with postgres:// or postgresql://")
+        );
+        assert!(parse(&format!("{target}interval_secs = 0")).contains("interval_secs"));
+        assert!(
+            toml::from_str::<TargetsFile>(&format!("{target}urls = \"x\"")).is_err(),
+            "unknown fields are rejected"
+        );
+    }
+
+    #[test]
+    fn should_bound_workers_and_run_each_target_once() {
+        let active = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let schedule = SchedulerConfig {
+            mode: RunMode::Once,
+            jitter_pct: 0.0,
+            max_cycles: Some(5),
+            ..SchedulerConfig::default()
+        };
+        let (job_active, job_peak) = (Arc::clone(&active), Arc::clone(&peak));
+        let mut emitted = Vec::new();
+
+        run_fleet(
+            &[0, 0, 0, 0],
+            &schedule,
+            2,
+            move |target, _| {
+                let running = job_active.fetch_add(1, Ordering::SeqCst) + 1;
+                job_peak.fetch_max(running, Ordering::SeqCst);
+                thread::sleep(Duration::from_millis(20));
+                job_active.fetch_sub(1, Ordering::SeqCst);
+                target
+            },
+            |target, cycle, result| emitted.push((target, cycle, result)),
+        );
+
+        emitted.sort();
+        assert_eq!(emitted, vec![(0, 1, 0), (1, 1, 1), (2, 1, 2), (3, 1, 3)]);
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn should_not_delay_other_targets_behind_a_slow_one() {
+        let schedule = SchedulerConfig {
+            mode: RunMode::Daemon,
+            jitter_pct: 0.0,
+            max_cycles: Some(3),
+            ..SchedulerConfig::default()
+        };
+        let mut emitted = Vec::new();
+
+        run_fleet(
+            &[0, 0, 0],
+            &schedule,
+            2,
+            |target, _| {
+                if target == 0 {
+                    thread::sleep(Duration::from_millis(300));
+                }
+            },
+            |target, cycle, _| emitted.push((target, cycle)),
+        );
+
+        assert_eq!(emitted.len(), 9);
+        let first_slow = emitted.iter().position(|it| *it == (0, 1)).unwrap();
+        let fast_done = [(1, 3), (2, 3)]
+            .iter()
+            .map(|done| emitted.iter().position(|it| it == done).unwrap())
+            .max()
+            .unwrap();
+        assert!(fast_done < first_slow, "{emitted:?}");
+    }
+}
@@ -0,0 +1,392 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap};
+
+use serde::Serialize;
+
+/// Only the first statements of a transaction are kept in `fingerprints`.
+const MAX_FINGERPRINTS_PER_TRANSACTION: usize = 20;
+const MAX_REPORTED_TRANSACTIONS: usize = 50;
+
+#[derive(Debug, Clone, Copy)]
+pub struct TransactionThresholds {
+    pub long_secs: f64,
+    pub idle_secs: f64,
+}
+
+impl Default for TransactionThresholds {
+    fn default() -> Self {
+        Self {
+            long_secs: 1.0,
+            idle_secs: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionBoundary {
+    Begin,
+    Commit,
+    Rollback,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionOutcome {
+    #[default]
+    Committed,
+    RolledBack,
+    /// A new `BEGIN` on the same thread implicitly committed the previous one.
+    ImplicitCommit,
+    /// Still open when the window ended.
+    Open,
+}
+
+/// One statement of a session. Times are seconds on the source's clock.
+#[derive(Debug, Clone)]
+pub struct SessionStatement {
+    pub thread_id: u64,
+    pub start_secs: f64,
+    pub query_time_secs: f64,
+    pub lock_time_secs: f64,
+    pub fingerprint: String,
+    pub boundary: Option<TransactionBoundary>,
+}
+
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct TransactionSummary {
+    pub thread_id: u64,
+    pub start_secs: f64,
+    pub duration_secs: f64,
+    pub statement_count: usize,
+    pub total_query_time_secs: f64,
+    pub total_lock_time_secs: f64,
+    pub idle_secs: f64,
+    pub max_idle_gap_secs: f64,
+    pub outcome: TransactionOutcome,
+    pub fingerprints: Vec<String>,
+    pub long_running: bool,
+    pub idle_in_transaction: bool,
+}
+
+/// `start_secs` is unix time for log sources and seconds since server start
+/// for `performance_schema`.
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct TransactionReport {
+    pub source: Option<String>,
+    pub long_threshold_secs: f64,
+    pub idle_threshold_secs: f64,
+    pub reconstructed: usize,
+    pub long_running: usize,
+    pub idle_in_transaction: usize,
+    pub transactions: Vec<TransactionSummary>,
+}
+
+/// Maps a normalized statement to a transaction boundary. `ROLLBACK TO
+/// SAVEPOINT` is not a boundary, and `SET autocommit = 0` sessions are not
+/// tracked.
+pub fn transaction_boundary(fingerprint: &str) -> Option<TransactionBoundary> {
+    let text = fingerprint.trim_end_matches(';').trim();
+    let mut words = text.split_whitespace();
+    match (words.next()?, words.next()) {
+        ("begin", None | Some("work" | "transaction")) => Some(TransactionBoundary::Begin),
+        ("start", Some("transaction")) => Some(TransactionBoundary::Begin),
+        ("commit" | "end", _) => Some(TransactionBoundary::Commit),
+        ("rollback" | "abort", next) if !text.contains(" to ") && next != Some("prepared") => {
+            Some(TransactionBoundary::Rollback)
+        }
+        _ => None,
+    }
+}
+
+struct OpenTransaction {
+    start_secs: f64,
+    last_end_secs: f64,
+    summary: TransactionSummary,
+}
+
+impl OpenTransaction {
+    fn begin(statement: &SessionStatement) -> Self {
+        Self {
+            start_secs: statement.start_secs,
+            last_end_secs: statement.start_secs + statement.query_time_secs,
+            summary: TransactionSummary {
+                thread_id: statement.thread_id,
+                start_secs: statement.start_secs,
+                ..TransactionSummary::default()
+            },
+        }
+    }
+
+    fn add(&mut self, statement: SessionStatement) {
+        let gap = statement.start_secs - self.last_end_secs;
+        if gap > self.summary.max_idle_gap_secs {
+            self.summary.max_idle_gap_secs = gap;
+        }
+        self.last_end_secs = self
+            .last_end_secs
+            .max(statement.start_secs + statement.query_time_secs);
+        self.summary.statement_count += 1;
+        self.summary.total_query_time_secs += statement.query_time_secs;
+        self.summary.total_lock_time_secs += statement.lock_time_secs;
+        if self.summary.fingerprints.len() < MAX_FINGERPRINTS_PER_TRANSACTION {
+            self.summary.fingerprints.push(statement.fingerprint);
+        }
+    }
+
+    fn close(
+        mut self,
+        end: Option<&SessionStatement>,
+        outcome: TransactionOutcome,
+    ) -> TransactionSummary {
+        if let Some(end) = end {
+            let gap = end.start_secs - self.last_end_secs;
+            if gap > self.summary.max_idle_gap_secs {
+                self.summary.max_idle_gap_secs = gap;
+            }
+            self.last_end_secs = self.last_end_secs.max(end.start_secs + end.query_time_secs);
+        }
+        self.summary.duration_secs = (self.last_end_secs - self.start_secs).max(0.0);
+        self.summary.idle_secs =
+            (self.summary.duration_secs - self.summary.total_query_time_secs).max(0.0);
+        self.summary.outcome = outcome;
+        self.summary
+    }
+}
+
+/// Report order: flagged transactions first, then longest first.
+struct Ranked(TransactionSummary);
+
+impl Ranked {
+    fn flagged(&self) -> bool {
+        self.0.long_running || self.0.idle_in_transaction
+    }
+}
+
+impl Ord for Ranked {
+    /// Greater ranks higher in the report.
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.flagged()
+            .cmp(&other.flagged())
+            .then_with(|| self.0.duration_secs.total_cmp(&other.0.duration_secs))
+            .then_with(|| other.0.thread_id.cmp(&self.0.thread_id))
+            .then_with(|| other.0.start_secs.total_cmp(&self.0.start_secs))
+    }
+}
+
+impl PartialOrd for Ranked {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Ranked {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Ranked {}
+
+/// Groups statements by thread between `BEGIN` and `COMMIT`/`ROLLBACK`.
+/// Statements outside an explicit transaction (autocommit) are ignored.
+/// Statements must arrive in log order per thread. Completed transactions are
+/// counted, but only the top `MAX_REPORTED_TRANSACTIONS` are kept.
+pub struct TransactionTracker {
+    thresholds: TransactionThresholds,
+    open: HashMap<u64, OpenTransaction>,
+    /// Min-heap of the best-ranked transactions so far.
+    top: BinaryHeap<Reverse<Ranked>>,
+    reconstructed: usize,
+    long_running: usize,
+    idle_in_transaction: usize,
+}
+
+impl TransactionTracker {
+    pub fn new(thresholds: TransactionThresholds) -> Self {
+        Self {
+            thresholds,
+            open: HashMap::new(),
+            top: BinaryHeap::new(),
+            reconstructed: 0,
+            long_running: 0,
+            idle_in_transaction: 0,
+        }
+    }
+
+    pub fn push(&mut self, statement: SessionStatement) {
+        match statement.boundary {
+            Some(TransactionBoundary::Begin) => {
+                let next = OpenTransaction::begin(&statement);
+                if let Some(previous) = self.open.insert(statement.thread_id, next) {
+                    self.complete(previous.close(None, TransactionOutcome::ImplicitCommit));
+                }
+            }
+            Some(boundary) => {
+                if let Some(open) = self.open.remove(&statement.thread_id) {
+                    let outcome = match boundary {
+                        TransactionBoundary::Rollback => TransactionOutcome::RolledBack,
+                        _ => TransactionOutcome::Committed,
+                    };
+                    self.complete(open.close(Some(&statement), outcome));
+                }
+            }
+            None => {
+                if let Some(open) = self.open.get_mut(&statement.thread_id) {
+                    open.add(statement);
+                }
+            }
+        }
+    }
+
+    fn complete(&mut self, mut summary: TransactionSummary) {
+        summary.long_running = summary.duration_secs >= self.thresholds.long_secs;
+        summary.idle_in_transaction = summary.max_idle_gap_secs >= self.thresholds.idle_secs;
+        self.reconstructed += 1;
+        self.long_running += usize::from(summary.long_running);
+        self.idle_in_transaction += usize::from(summary.idle_in_transaction);
+        self.top.push(Reverse(Ranked(summary)));
+        if self.top.len() > MAX_REPORTED_TRANSACTIONS {
+            self.top.pop();
+        }
+    }
+
+    pub fn finish(mut self, source: &str) -> TransactionReport {
+        let open = self.open.drain().map(|(_, it)| it).collect::<Vec<_>>();
+        for transaction in open {
+            self.complete(transaction.close(None, TransactionOutcome::Open));
+        }
+
+        // Ascending `Reverse` order is descending rank.
+        let transactions = self
+            .top
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(Ranked(it))| it)
+            .collect();
+        TransactionReport {
+            source: Some(source.to_string()),
+            long_threshold_secs: self.thresholds.long_secs,
+            idle_threshold_secs: self.thresholds.idle_secs,
+            reconstructed: self.reconstructed,
+            long_running: self.long_running,
+            idle_in_transaction: self.idle_in_transaction,
+            transactions,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn statement(
+        thread_id: u64,
+        start_secs: f64,
+        query_time_secs: f64,
+        sql: &str,
+    ) -> SessionStatement {
+        SessionStatement {
+            thread_id,
+            start_secs,
+            query_time_secs,
+            lock_time_secs: 0.0,
+            fingerprint: sql.to_string(),
+            boundary: transaction_boundary(sql),
+        }
+    }
+
+    #[test]
+    fn should_detect_transaction_boundaries() {
+        assert_eq!(
+            transaction_boundary("begin;"),
+            Some(TransactionBoundary::Begin)
+        );
+        assert_eq!(
+            transaction_boundary("start transaction read only"),
+            Some(TransactionBoundary::Begin)
+        );
+        assert_eq!(
+            transaction_boundary("commit work"),
+            Some(TransactionBoundary::Commit)
+        );
+        assert_eq!(
+            transaction_boundary("rollback"),
+            Some(TransactionBoundary::Rollback)
+        );
+        assert_eq!(transaction_boundary("rollback to savepoint a"), None);
+        assert_eq!(transaction_boundary("select ?"), None);
+    }
+
+    #[test]
+    fn should_group_statements_by_thread_between_boundaries() {
+        let mut tracker = TransactionTracker::new(TransactionThresholds {
+            long_secs: 2.0,
+            idle_secs: 1.0,
+        });
+        for it in [
+            statement(1, 100.0, 0.0, "begin"),
+            statement(2, 100.0, 0.0, "begin"),
+            statement(1, 100.0, 0.2, "select * from carts where id = ?"),
+            statement(2, 100.1, 0.1, "update stock set qty = qty - ? where id = ?"),
+            statement(3, 100.2, 0.5, "select ?"),
+            statement(2, 100.3, 0.0, "rollback"),
+            statement(1, 102.7, 0.3, "update carts set state = ? where id = ?"),
+            statement(1, 103.0, 0.0, "commit"),
+            statement(4, 104.0, 0.0, "begin"),
+            statement(4, 104.0, 0.1, "select ?"),
+        ] {
+            tracker.push(it);
+        }
+        let report = tracker.finish("slow_log");
+
+        assert_eq!(report.reconstructed, 3);
+        assert_eq!(report.long_running, 1);
+        assert_eq!(report.idle_in_transaction, 1);
+
+        let first = &report.transactions[0];
+        assert_eq!(first.thread_id, 1);
+        assert_eq!(first.outcome, TransactionOutcome::Committed);
+        assert_eq!(first.statement_count, 2);
+        assert!((first.duration_secs - 3.0).abs() < 1e-9);
+        assert!((first.max_idle_gap_secs - 2.5).abs() < 1e-9);
+        assert!(first.long_running && first.idle_in_transaction);
+
+        let outcomes = report.transactions[1..]
+            .iter()
+            .map(|it| (it.thread_id, it.outcome))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            outcomes,
+            vec![
+                (2, TransactionOutcome::RolledBack),
+                (4, TransactionOutcome::Open)
+            ]
+        );
+    }
+
+    #[test]
+    fn should_keep_only_the_top_transactions() {
+        let mut tracker = TransactionTracker::new(TransactionThresholds {
+            long_secs: 100.0,
+            idle_secs: 100.0,
+        });
+        for thread_id in 0..(MAX_REPORTED_TRANSACTIONS as u64 * 4) {
+            let start = thread_id as f64;
+            tracker.push(statement(thread_id, start, 0.0, "begin"));
+            let duration = (thread_id % 97) as f64;
+            tracker.push(statement(thread_id, start + duration, 0.0, "commit"));
+        }
+        assert!(tracker.top.len() <= MAX_REPORTED_TRANSACTIONS);
+        let report = tracker.finish("slow_log");
+
+        assert_eq!(report.reconstructed, MAX_REPORTED_TRANSACTIONS * 4);
+        assert_eq!(report.transactions.len(), MAX_REPORTED_TRANSACTIONS);
+        assert_eq!(report.transactions[0].thread_id, 96);
+        assert!(
+            report
+                .transactions
+                .windows(2)
+                .all(|it| it[0].duration_secs >= it[1].duration_secs)
+        );
+    }
+}
@@ -0,0 +1,1547 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, Read};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use serde::Serialize;
+
+use crate::collection::CollectionPolicy;
+use crate::fingerprint::{SqlDialect, fingerprinter_for};
+use crate::level1::{DigestAggregator, Level1Engine, SlowLogEntry, SlowSqlDigest};
+use crate::perf_profile::{create_private_dir, effective_uid_is_root};
+use crate::strace_summary::interrupt_after;
+
+pub const TCPDUMP_MAX_DURATION_SECS: u64 = 30;
+const MAX_SLOWEST_QUERIES: usize = 20;
+/// How long the non-root probe waits on its one-packet test capture.
+const PROBE_CAPTURE_SECS: u64 = 1;
+/// Connections beyond this are counted but not decoded.
+const MAX_CONNECTIONS: usize = 1024;
+/// A direction whose buffer grows past this is assumed desynchronized.
+const MAX_STREAM_BUFFER_BYTES: usize = 16 * 1024 * 1024;
+
+const LINKTYPE_NULL: u32 = 0;
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_RAW_ALT: u32 = 12;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+const LINKTYPE_LINUX_SLL2: u32 = 276;
+
+#[derive(Debug, Clone)]
+pub struct WireCaptureConfig {
+    pub engine: Level1Engine,
+    /// Offline capture to decode; no live capture runs when set.
+    pub pcap_file: Option<PathBuf>,
+    /// Server port; defaults to 3306 or 5432 by engine.
+    pub port: Option<u16>,
+    pub interface: String,
+    pub duration_secs: u64,
+    pub output_dir: PathBuf,
+    pub tcpdump_binary: String,
+    pub proc_root: PathBuf,
+}
+
+impl Default for WireCaptureConfig {
+    fn default() -> Self {
+        Self {
+            engine: Level1Engine::Mysql,
+            pcap_file: None,
+            port: None,
+            interface: "any".to_string(),
+            duration_secs: 5,
+            output_dir: std::env::temp_dir(),
+            tcpdump_binary: "tcpdump".to_string(),
+            proc_root: PathBuf::from("/proc"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct WireCaptureReport {
+    pub enabled: bool,
+    pub skipped_reason: Option<String>,
+    /// `pcap_file` or `tcpdump`.
+    pub source: Option<String>,
+    /// The decoded `pcap_file`; live captures are deleted after decoding.
+    pub pcap_path: Option<String>,
+    pub port: u16,
+    pub duration_secs: u64,
+    pub packets: u64,
+    pub decoded_queries: u64,
+    pub connections: Vec<WireConnection>,
+    /// Highest response time first.
+    pub slowest_queries: Vec<WireQuery>,
+    /// Same shape as slow log digests; `total_lock_time_secs` is always 0.
+    pub digests: Vec<SlowSqlDigest>,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Default, PartialEq)]
+pub struct WireConnection {
+    pub client: String,
+    pub server: String,
+    pub queries: u64,
+    pub errors: u64,
+    pub total_response_time_secs: f64,
+    pub max_response_time_secs: f64,
+    /// TLS was negotiated, so nothing after the handshake is decodable.
+    pub encrypted: bool,
+    /// Lost or out-of-order segments; decoding restarts after each one.
+    pub stream_gaps: u64,
+}
+
+/// One request/response exchange. `response_time_secs` runs from the packet
+/// carrying the request to the packet that completed the response.
+#[derive(Debug, Clone, Serialize, Default, PartialEq)]
+pub struct WireQuery {
+    pub client: String,
+    pub sql: String,
+    pub started_at_secs: f64,
+    pub response_time_secs: f64,
+    pub rows_sent: u64,
+    pub error: Option<String>,
+}
+
+/// Decoded queries are handed to the caller as they complete, so only
+/// per-connection state is held.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WireAnalysis {
+    pub packets: u64,
+    pub connections: Vec<WireConnection>,
+    pub warnings: Vec<String>,
+}
+
+/// Checks that `tcpdump` runs and that this process may open a capture.
+pub fn probe_tcpdump(
+    tcpdump_binary: &str,
+    interface: &str,
+    proc_root: &Path,
+) -> Result<(), String> {
+    match Command::new(tcpdump_binary).arg("--version").output() {
+        Ok(output) if output.status.success() => {}
+        Ok(output) => {
+            return Err(format!(
+                "tcpdump is not usable: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            return Err(format!("tcpdump not found ({tcpdump_binary})"));
+        }
+        Err(err) => return Err(format!("failed running {tcpdump_binary}: {err}")),
+    }
+    if effective_uid_is_root(proc_root) {
+        return Ok(());
+    }
+    // File capabilities (`setcap cap_net_raw,cap_net_admin=eip`) let non-root
+    // users capture, so try a real one-packet capture instead of guessing.
+    let child = Command::new(tcpdump_binary)
+        .args([
+            "-i",
+            interface,
+            "-c",
+            "1",
+            "--immediate-mode",
+            "-n",
+            "-w",
+            "/dev/null",
+        ])
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("failed running {tcpdump_binary}: {err}"))?;
+    let output = interrupt_after(child, PROBE_CAPTURE_SECS, "tcpdump")?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if output.status.success() || stderr.contains("listening on") {
+        return Ok(());
+    }
+    let detail = stderr
+        .lines()
+        .map(str::trim)
+        .find(|it| !it.is_empty())
+        .unwrap_or("no output");
+    Err(format!(
+        "packet capture requires root or CAP_NET_RAW on {interface}: {detail}"
+    ))
+}
+
+/// Runs the Level 3 `tcpdump_short_window` task. Decoding an existing pcap is
+/// always allowed; a live capture only runs when the policy enables expert
+/// mode.
+pub fn collect_wire_capture(
+    policy: &CollectionPolicy,
+    config: &WireCaptureConfig,
+) -> WireCaptureReport {
+    let mut report = WireCaptureReport {
+        port: config.port.unwrap_or(match config.engine {
+            Level1Engine::Mysql => 3306,
+            Level1Engine::Postgres => 5432,
+        }),
+        ..WireCaptureReport::default()
+    };
+
+    let dialect = match config.engine {
+        Level1Engine::Mysql => SqlDialect::Mysql,
+        Level1Engine::Postgres => SqlDialect::Postgres,
+    };
+    let mut aggregator = DigestAggregator::new(fingerprinter_for(dialect));
+    let mut slowest = Vec::with_capacity(MAX_SLOWEST_QUERIES + 1);
+    let mut on_query = |query: WireQuery| {
+        aggregator.push(SlowLogEntry {
+            offset: report.decoded_queries,
+            thread_id: None,
+            start_time_secs: Some(query.started_at_secs),
+            sql: query.sql.clone(),
+            schema: None,
+            query_time_secs: query.response_time_secs,
+            lock_time_secs: 0.0,
+            rows_sent: query.rows_sent,
+            rows_examined: 0,
+        });
+        report.decoded_queries += 1;
+        keep_slowest(&mut slowest, query);
+    };
+
+    let analysis = match &config.pcap_file {
+        Some(path) => {
+            report.source = Some("pcap_file".to_string());
+            report.pcap_path = Some(path.display().to_string());
+            decode_pcap_file(path, config.engine, report.port, &mut on_query)
+        }
+        None => {
+            report.source = Some("tcpdump".to_string());
+            report.duration_secs = config.duration_secs.clamp(1, TCPDUMP_MAX_DURATION_SECS);
+            if report.duration_secs != config.duration_secs {
+                report.warnings.push(format!(
+                    "tcpdump duration_secs capped from {} to {}",
+                    config.duration_secs, report.duration_secs
+                ));
+            }
+            if !policy.expert_mode_enabled {
+                report.skipped_reason = Some("expert mode is disabled by policy".to_string());
+                return report;
+            }
+            if let Err(err) =
+                probe_tcpdump(&config.tcpdump_binary, &config.interface, &config.proc_root)
+            {
+                report.skipped_reason = Some(err);
+                return report;
+            }
+            capture_and_decode(config, report.port, report.duration_secs, &mut on_query)
+        }
+    };
+    let analysis = match analysis {
+        Ok(analysis) => analysis,
+        Err(err) => {
+            report.decoded_queries = 0;
+            report.skipped_reason = Some(err);
+            return report;
+        }
+    };
+
+    report.enabled = true;
+    report.packets = analysis.packets;
+    report.connections = analysis.connections;
+    report.warnings.extend(analysis.warnings);
+    report.digests = aggregator.finish();
+    slowest.sort_by(|a, b| b.response_time_secs.total_cmp(&a.response_time_secs));
+    report.slowest_queries = slowest;
+    report
+}
+
+/// Keeps the `MAX_SLOWEST_QUERIES` slowest queries seen so far.
+fn keep_slowest(slowest: &mut Vec<WireQuery>, query: WireQuery) {
+    slowest.push(query);
+    if slowest.len() > MAX_SLOWEST_QUERIES
+        && let Some(fastest) = slowest
+            .iter()
+            .enumerate()
+            .min_by(|a, b| a.1.response_time_secs.total_cmp(&b.1.response_time_secs))
+            .map(|(index, _)| index)
+    {
+        slowest.swap_remove(fastest);
+    }
+}
+
+fn decode_pcap_file<F: FnMut(WireQuery)>(
+    path: &Path,
+    engine: Level1Engine,
+    port: u16,
+    on_query: F,
+) -> Result<WireAnalysis, String> {
+    let file = File::open(path).map_err(|err| format!("cannot read {}: {err}", path.display()))?;
+    analyze_pcap(BufReader::new(file), engine, port, on_query)
+        .map_err(|err| format!("{}: {err}", path.display()))
+}
+
+/// Captures into a new file inside a fresh owner-only directory, decodes it
+/// and removes both, whatever the outcome.
+fn capture_and_decode<F: FnMut(WireQuery)>(
+    config: &WireCaptureConfig,
+    port: u16,
+    duration_secs: u64,
+    on_query: F,
+) -> Result<WireAnalysis, String> {
+    let dir = create_private_dir(&config.output_dir, "capture").map_err(|err| {
+        format!(
+            "cannot create a private directory in {}: {err}",
+            config.output_dir.display()
+        )
+    })?;
+    let path = dir.join(format!("{port}.pcap"));
+    let result = run_tcpdump(config, port, duration_secs, &path)
+        .and_then(|()| decode_pcap_file(&path, config.engine, port, on_query));
+    let _ = fs::remove_file(&path);
+    let _ = fs::remove_dir(&dir);
+    result
+}
+
+/// tcpdump writes the capture to stdout (`-w -`), redirected into a file
+/// created here with `create_new`, so it never opens a path itself. `-U`
+/// flushes per packet so the interrupted capture is complete on disk.
+fn run_tcpdump(
+    config: &WireCaptureConfig,
+    port: u16,
+    duration_secs: u64,
+    path: &Path,
+) -> Result<(), String> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let file = options
+        .open(path)
+        .map_err(|err| format!("cannot create {}: {err}", path.display()))?;
+    let child = Command::new(&config.tcpdump_binary)
+        .args(["-i", &config.interface, "-s", "0", "-U", "-n", "-w", "-"])
+        .arg(format!("tcp port {port}"))
+        .stdout(file)
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("failed running tcpdump: {err}"))?;
+    let output = interrupt_after(child, duration_secs, "tcpdump")?;
+    // Anything shorter than the pcap file header means tcpdump never started
+    // writing this capture.
+    if fs::metadata(path).is_ok_and(|it| it.len() >= PCAP_HEADER_LEN as u64) {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let detail = stderr
+        .lines()
+        .map(str::trim)
+        .find(|it| !it.is_empty() && !it.starts_with("tcpdump: listening"))
+        .unwrap_or("no output");
+    Err(format!("tcpdump wrote no capture: {detail}"))
+}
+
+/// Decodes a classic pcap capture and reconstructs the queries sent to
+/// `port`, one packet at a time, passing each to `on_query` in completion
+/// order. pcapng is rejected; convert with `tcpdump -r in.pcapng -w out.pcap`.
+pub fn analyze_pcap<R: Read, F: FnMut(WireQuery)>(
+    reader: R,
+    engine: Level1Engine,
+    port: u16,
+    mut on_query: F,
+) -> Result<WireAnalysis, String> {
+    let mut analysis = WireAnalysis::default();
+    let mut pcap = PcapReader::new(reader)?;
+    let linktype = pcap.linktype;
+    if !matches!(
+        linktype,
+        LINKTYPE_NULL
+            | LINKTYPE_ETHERNET
+            | LINKTYPE_RAW
+            | LINKTYPE_RAW_ALT
+            | LINKTYPE_LINUX_SLL
+            | LINKTYPE_LINUX_SLL2
+    ) {
+        return Err(format!("unsupported pcap link type {linktype}"));
+    }
+
+    let mut connections: BTreeMap<(SocketAddr, SocketAddr), Connection> = BTreeMap::new();
+    let mut untracked = BTreeSet::new();
+    while let Some((ts, frame)) = pcap.next_frame(&mut analysis.warnings)? {
+        analysis.packets += 1;
+        let Some(segment) = link_payload(linktype, frame).and_then(parse_tcp) else {
+            continue;
+        };
+        let to_server = segment.dst.port() == port;
+        if !to_server && segment.src.port() != port {
+            continue;
+        }
+        let key = if to_server {
+            (segment.src, segment.dst)
+        } else {
+            (segment.dst, segment.src)
+        };
+        if !connections.contains_key(&key) && connections.len() >= MAX_CONNECTIONS {
+            // Bounded too: only the count of the distinct skipped connections matters.
+            if untracked.len() < MAX_CONNECTIONS {
+                untracked.insert(key);
+            }
+            continue;
+        }
+        let connection = connections
+            .entry(key)
+            .or_insert_with(|| Connection::new(engine, key));
+        connection.accept(&segment, to_server, ts, &mut on_query);
+    }
+    if !untracked.is_empty() {
+        analysis.warnings.push(format!(
+            "more than {MAX_CONNECTIONS} connections; at least {} later ones were not decoded",
+            untracked.len()
+        ));
+    }
+
+    for connection in connections.into_values() {
+        if connection.overflowed {
+            analysis.warnings.push(format!(
+                "{} -> {}: stream buffer exceeded {} bytes and was reset",
+                connection.summary.client, connection.summary.server, MAX_STREAM_BUFFER_BYTES
+            ));
+        }
+        analysis.connections.push(connection.summary);
+    }
+    let encrypted = analysis
+        .connections
+        .iter()
+        .filter(|it| it.encrypted)
+        .count();
+    if encrypted > 0 {
+        analysis.warnings.push(format!(
+            "{encrypted} connection(s) use TLS and were not decoded"
+        ));
+    }
+    Ok(analysis)
+}
+
+const PCAP_HEADER_LEN: usize = 24;
+const PCAP_RECORD_HEADER_LEN: usize = 16;
+
+/// Streams `(timestamp, frame)` records from a classic pcap, reusing one
+/// frame buffer.
+struct PcapReader<R> {
+    reader: R,
+    big_endian: bool,
+    divisor: f64,
+    linktype: u32,
+    frame: Vec<u8>,
+    frames: u64,
+}
+
+impl<R: Read> PcapReader<R> {
+    fn new(mut reader: R) -> Result<Self, String> {
+        let mut header = [0u8; PCAP_HEADER_LEN];
+        if read_full(&mut reader, &mut header)? < PCAP_HEADER_LEN {
+            return Err("file is too short for a pcap header".to_string());
+        }
+        let (big_endian, nanos) = match [header[0], header[1], header[2], header[3]] {
+            [0xd4, 0xc3, 0xb2, 0xa1] => (false, false),
+            [0xa1, 0xb2, 0xc3, 0xd4] => (true, false),
+            [0x4d, 0x3c, 0xb2, 0xa1] => (false, true),
+            [0xa1, 0xb2, 0x3c, 0x4d] => (true, true),
+            [0x0a, 0x0d, 0x0d, 0x0a] => {
+                return Err(
+                    "pcapng is not supported; convert with `tcpdump -r in.pcapng -w out.pcap`"
+                        .to_string(),
+                );
+            }
+            _ => return Err("not a pcap file".to_string()),
+        };
+        let mut pcap = Self {
+            reader,
+            big_endian,
+            divisor: if nanos { 1e9 } else { 1e6 },
+            linktype: 0,
+            frame: Vec::new(),
+            frames: 0,
+        };
+        pcap.linktype = pcap.read_u32(&header, 20) & 0x0fff_ffff;
+        Ok(pcap)
+    }
+
+    fn read_u32(&self, bytes: &[u8], at: usize) -> u32 {
+        let raw = [bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]];
+        if self.big_endian {
+            u32::from_be_bytes(raw)
+        } else {
+            u32::from_le_bytes(raw)
+        }
+    }
+
+    /// `None` at the end of the capture. A truncated trailing record, as left
+    /// by an interrupted capture, only produces a warning.
+    fn next_frame(&mut self, warnings: &mut Vec<String>) -> Result<Option<(f64, &[u8])>, String> {
+        let mut header = [0u8; PCAP_RECORD_HEADER_LEN];
+        match read_full(&mut self.reader, &mut header)? {
+            0 => return Ok(None),
+            PCAP_RECORD_HEADER_LEN => {}
+            _ => {
+                warnings.push(format!("pcap truncated after {} packets", self.frames));
+                return Ok(None);
+            }
+        }
+        let ts = self.read_u32(&header, 0) as f64 + self.read_u32(&header, 4) as f64 / self.divisor;
+        let captured = self.read_u32(&header, 8) as u64;
+        self.frame.clear();
+        // `take` grows the buffer with the data actually present, so a corrupt
+        // length cannot force a huge allocation.
+        (&mut self.reader)
+            .take(captured)
+            .read_to_end(&mut self.frame)
+            .map_err(|err| format!("failed reading pcap: {err}"))?;
+        if (self.frame.len() as u64) < captured {
+            warnings.push(format!("pcap truncated after {} packets", self.frames));
+            return Ok(None);
+        }
+        self.frames += 1;
+        Ok(Some((ts, &self.frame)))
+    }
+}
+
+/// Fills `buf` unless the reader ends first; returns the bytes read.
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> Result<usize, String> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(read) => filled += read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(format!("failed reading pcap: {err}")),
+        }
+    }
+    Ok(filled)
+}
+
+fn be16(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn be32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+/// Strips the link-layer header, returning the IP packet.
+fn link_payload(linktype: u32, frame: &[u8]) -> Option<&[u8]> {
+    let (ethertype, offset) = match linktype {
+        LINKTYPE_RAW | LINKTYPE_RAW_ALT => return Some(frame),
+        LINKTYPE_NULL => {
+            // The family is in the capturing host's byte order.
+            let raw: [u8; 4] = frame.get(..4)?.try_into().ok()?;
+            let family = match u32::from_le_bytes(raw) {
+                family if family > 0xffff => u32::from_be_bytes(raw),
+                family => family,
+            };
+            match family {
+                2 => (0x0800, 4),
+                24 | 28 | 30 => (0x86dd, 4),
+                _ => return None,
+            }
+        }
+        LINKTYPE_ETHERNET => {
+            let mut ethertype = be16(frame, 12)?;
+            let mut offset = 14;
+            while matches!(ethertype, 0x8100 | 0x88a8) {
+                ethertype = be16(frame, offset + 2)?;
+                offset += 4;
+            }
+            (ethertype, offset)
+        }
+        LINKTYPE_LINUX_SLL => (be16(frame, 14)?, 16),
+        LINKTYPE_LINUX_SLL2 => (be16(frame, 0)?, 20),
+        _ => return None,
+    };
+    match ethertype {
+        0x0800 | 0x86dd => frame.get(offset..),
+        _ => None,
+    }
+}
+
+struct TcpSegment<'a> {
+    src: SocketAddr,
+    dst: SocketAddr,
+    seq: u32,
+    syn: bool,
+    payload: &'a [u8],
+}
+
+/// Parses IPv4/IPv6 + TCP. Fragments and IPv6 extension headers are skipped.
+fn parse_tcp(ip: &[u8]) -> Option<TcpSegment<'_>> {
+    let (src_ip, dst_ip, tcp) = match ip.first()? >> 4 {
+        4 => {
+            let header_len = ((ip[0] & 0x0f) as usize) * 4;
+            let total_len = be16(ip, 2)? as usize;
+            let fragment = be16(ip, 6)?;
+            if ip.get(9)? != &6 || fragment & 0x3fff != 0 {
+                return None;
+            }
+            let src: [u8; 4] = ip.get(12..16)?.try_into().ok()?;
+            let dst: [u8; 4] = ip.get(16..20)?.try_into().ok()?;
+            // Ethernet pads short frames past the IP total length.
+            let end = total_len.min(ip.len());
+            (
+                IpAddr::V4(Ipv4Addr::from(src)),
+                IpAddr::V4(Ipv4Addr::from(dst)),
+                ip.get(header_len..end)?,
+            )
+        }
+        6 => {
+            if ip.get(6)? != &6 {
+                return None;
+            }
+            let payload_len = be16(ip, 4)? as usize;
+            let src: [u8; 16] = ip.get(8..24)?.try_into().ok()?;
+            let dst: [u8; 16] = ip.get(24..40)?.try_into().ok()?;
+            let end = (40 + payload_len).min(ip.len());
+            (
+                IpAddr::V6(Ipv6Addr::from(src)),
+                IpAddr::V6(Ipv6Addr::from(dst)),
+                ip.get(40..end)?,
+            )
+        }
+        _ => return None,
+    };
+    let data_offset = ((tcp.get(12)? >> 4) as usize) * 4;
+    Some(TcpSegment {
+        src: SocketAddr::new(src_ip, be16(tcp, 0)?),
+        dst: SocketAddr::new(dst_ip, be16(tcp, 2)?),
+        seq: be32(tcp, 4)?,
+        syn: tcp.get(13)? & 0x02 != 0,
+        payload: tcp.get(data_offset..)?,
+    })
+}
+
+/// One direction of a TCP connection, reassembled by sequence number.
+/// Out-of-order segments are treated as loss.
+#[derive(Default)]
+struct Stream {
+    next_seq: Option<u32>,
+    buffer: Vec<u8>,
+}
+
+impl Stream {
+    /// Returns the new bytes and whether a gap preceded them.
+    fn accept<'a>(&mut self, segment: &TcpSegment<'a>) -> (bool, &'a [u8]) {
+        let payload = segment.payload;
+        if segment.syn {
+            self.next_seq = Some(segment.seq.wrapping_add(1));
+            return (false, &[]);
+        }
+        if payload.is_empty() {
+            return (false, payload);
+        }
+        let end = segment.seq.wrapping_add(payload.len() as u32);
+        let Some(next) = self.next_seq else {
+            self.next_seq = Some(end);
+            return (false, payload);
+        };
+        let delta = segment.seq.wrapping_sub(next) as i32;
+        if delta > 0 {
+            self.next_seq = Some(end);
+            return (true, payload);
+        }
+        // Retransmission, possibly overlapping new data.
+        let seen = delta.unsigned_abs() as usize;
+        if seen >= payload.len() {
+            return (false, &[]);
+        }
+        self.next_seq = Some(end);
+        (false, &payload[seen..])
+    }
+}
+
+/// A completed exchange; `sql` is `None` for commands without statement text.
+struct Exchange {
+    sql: Option<String>,
+    started: f64,
+    finished: f64,
+    rows_sent: u64,
+    error: Option<String>,
+}
+
+struct Connection {
+    to_server: Stream,
+    to_client: Stream,
+    session: Session,
+    summary: WireConnection,
+    overflowed: bool,
+}
+
+enum Session {
+    Mysql(MysqlSession),
+    Postgres(PostgresSession),
+}
+
+impl Connection {
+    fn new(engine: Level1Engine, (client, server): (SocketAddr, SocketAddr)) -> Self {
+        Self {
+            to_server: Stream::default(),
+            to_client: Stream::default(),
+            session: match engine {
+                Level1Engine::Mysql => Session::Mysql(MysqlSession::default()),
+                Level1Engine::Postgres => Session::Postgres(PostgresSession::default()),
+            },
+            summary: WireConnection {
+                client: client.to_string(),
+                server: server.to_string(),
+                ..WireConnection::default()
+            },
+            overflowed: false,
+        }
+    }
+
+    fn accept<F: FnMut(WireQuery)>(
+        &mut self,
+        segment: &TcpSegment<'_>,
+        to_server: bool,
+        ts: f64,
+        on_query: &mut F,
+    ) {
+        let stream = if to_server {
+            &mut self.to_server
+        } else {
+            &mut self.to_client
+        };
+        let (gap, data) = stream.accept(segment);
+        if self.summary.encrypted || data.is_empty() {
+            return;
+        }
+        if gap {
+            self.summary.stream_gaps += 1;
+            self.reset();
+        }
+        let stream = if to_server {
+            &mut self.to_server
+        } else {
+            &mut self.to_client
+        };
+        stream.buffer.extend_from_slice(data);
+        if stream.buffer.len() > MAX_STREAM_BUFFER_BYTES {
+            self.overflowed = true;
+            self.reset();
+            return;
+        }
+
+        let mut exchanges = Vec::new();
+        let encrypted = match &mut self.session {
+            Session::Mysql(session) => {
+                if to_server {
+                    session.on_client(&mut self.to_server.buffer, &mut self.to_client.buffer, ts);
+                } else {
+                    session.on_server(&mut self.to_client.buffer, ts, &mut exchanges);
+                }
+                session.encrypted
+            }
+            Session::Postgres(session) => {
+                if to_server {
+                    session.on_client(&mut self.to_server.buffer, &mut self.to_client.buffer, ts);
+                } else {
+                    session.on_server(&mut self.to_client.buffer, ts, &mut exchanges);
+                }
+                session.encrypted
+            }
+        };
+        if encrypted {
+            self.summary.encrypted = true;
+            self.to_server.buffer.clear();
+            self.to_client.buffer.clear();
+        }
+
+        for exchange in exchanges {
+            let Some(sql) = exchange.sql else {
+                continue;
+            };
+            let response_time_secs =
+                ((exchange.finished - exchange.started).max(0.0) * 1e6).round() / 1e6;
+            self.summary.queries += 1;
+            self.summary.errors += u64::from(exchange.error.is_some());
+            self.summary.total_response_time_secs =
+                ((self.summary.total_response_time_secs + response_time_secs) * 1e6).round() / 1e6;
+            self.summary.max_response_time_secs =
+                self.summary.max_response_time_secs.max(response_time_secs);
+            on_query(WireQuery {
+                client: self.summary.client.clone(),
+                sql,
+                started_at_secs: exchange.started,
+                response_time_secs,
+                rows_sent: exchange.rows_sent,
+                error: exchange.error,
+            });
+        }
+    }
+
+    /// Drops partial messages and in-flight requests; prepared statements
+    /// and negotiated capabilities survive.
+    fn reset(&mut self) {
+        self.to_server.buffer.clear();
+        self.to_client.buffer.clear();
+        match &mut self.session {
+            Session::Mysql(session) => session.pending = None,
+            Session::Postgres(session) => session.pending.clear(),
+        }
+    }
+}
+
+const MYSQL_CLIENT_PROTOCOL_41: u32 = 0x0000_0200;
+const MYSQL_CLIENT_SSL: u32 = 0x0000_0800;
+const MYSQL_CLIENT_DEPRECATE_EOF: u32 = 0x0100_0000;
+const MYSQL_CLIENT_QUERY_ATTRIBUTES: u32 = 0x0800_0000;
+const MYSQL_SERVER_MORE_RESULTS_EXISTS: u16 = 0x0008;
+
+#[derive(Default)]
+struct MysqlSession {
+    /// Learned from the handshake response; `None` for connections captured
+    /// mid-stream.
+    capabilities: Option<u32>,
+    statements: HashMap<u32, String>,
+    pending: Option<MysqlPending>,
+    encrypted: bool,
+}
+
+#[derive(Debug, PartialEq)]
+enum MysqlCommand {
+    Query,
+    Prepare,
+    Execute,
+}
+
+#[derive(Debug, PartialEq)]
+enum MysqlResultState {
+    Head,
+    Columns(u64),
+    ColumnsEof,
+    Rows,
+}
+
+struct MysqlPending {
+    command: MysqlCommand,
+    sql: Option<String>,
+    started: f64,
+    state: MysqlResultState,
+    rows: u64,
+}
+
+/// Splits complete `len:3 seq:1 payload` packets off the front of `buffer`.
+fn drain_mysql_packets(buffer: &mut Vec<u8>) -> Vec<(u8, Vec<u8>)> {
+    let mut packets = Vec::new();
+    let mut pos = 0;
+    while buffer.len() - pos >= 4 {
+        let len = u32::from_le_bytes([buffer[pos], buffer[pos + 1], buffer[pos + 2], 0]) as usize;
+        if buffer.len() - pos - 4 < len {
+            break;
+        }
+        packets.push((buffer[pos + 3], buffer[pos + 4..pos + 4 + len].to_vec()));
+        pos += 4 + len;
+    }
+    buffer.drain(..pos);
+    packets
+}
+
+/// MySQL length-encoded integer; returns the value and its width.
+fn mysql_lenenc(data: &[u8]) -> Option<(u64, usize)> {
+    let read = |width: usize| {
+        let mut raw = [0u8; 8];
+        raw[..width].copy_from_slice(data.get(1..1 + width)?);
+        Some((u64::from_le_bytes(raw), 1 + width))
+    };
+    match *data.first()? {
+        byte @ 0..=0xfa => Some((byte as u64, 1)),
+        0xfc => read(2),
+        0xfd => read(3),
+        0xfe => read(8),
+        _ => None,
+    }
+}
+
+fn mysql_error(payload: &[u8]) -> String {
+    let code = payload
+        .get(1..3)
+        .map(|it| u16::from_le_bytes([it[0], it[1]]))
+        .unwrap_or(0);
+    if payload.get(3) == Some(&b'#') && payload.len() >= 9 {
+        format!(
+            "ERROR {code} ({}): {}",
+            String::from_utf8_lossy(&payload[4..9]),
+            String::from_utf8_lossy(&payload[9..])
+        )
+    } else {
+        format!(
+            "ERROR {code}: {}",
+            String::from_utf8_lossy(payload.get(3..).unwrap_or_default())
+        )
+    }
+}
+
+/// Status flags of an OK packet (`0x00` or `0xFE` header) or a 5-byte EOF.
+fn mysql_status_flags(payload: &[u8]) -> u16 {
+    if payload.len() == 5 {
+        return u16::from_le_bytes([payload[3], payload[4]]);
+    }
+    let status = || {
+        let (_, affected_width) = mysql_lenenc(payload.get(1..)?)?;
+        let (_, insert_id_width) = mysql_lenenc(payload.get(1 + affected_width..)?)?;
+        let at = 1 + affected_width + insert_id_width;
+        Some(u16::from_le_bytes(
+            payload.get(at..at + 2)?.try_into().ok()?,
+        ))
+    };
+    status().unwrap_or(0)
+}
+
+impl MysqlSession {
+    fn deprecate_eof(&self) -> Option<bool> {
+        self.capabilities
+            .map(|it| it & MYSQL_CLIENT_DEPRECATE_EOF != 0)
+    }
+
+    fn on_client(&mut self, buffer: &mut Vec<u8>, server_buffer: &mut Vec<u8>, ts: f64) {
+        for (seq, payload) in drain_mysql_packets(buffer) {
+            if seq == 1 && self.pending.is_none() {
+                self.on_handshake_response(&payload);
+                if self.encrypted {
+                    return;
+                }
+                continue;
+            }
+            if seq != 0 || payload.is_empty() {
+                continue;
+            }
+            // The classic protocol never pipelines commands, so anything
+            // still pending was never answered within the capture.
+            self.pending = None;
+            server_buffer.clear();
+            let (command, sql) = match payload[0] {
+                0x03 => (MysqlCommand::Query, Some(self.query_text(&payload[1..]))),
+                0x16 => (
+                    MysqlCommand::Prepare,
+                    Some(String::from_utf8_lossy(&payload[1..]).into_owned()),
+                ),
+                0x17 => {
+                    let sql = payload
+                        .get(1..5)
+                        .map(|it| u32::from_le_bytes([it[0], it[1], it[2], it[3]]))
+                        .and_then(|id| self.statements.get(&id).cloned());
+                    (MysqlCommand::Execute, sql)
+                }
+                0x19 => {
+                    if let Some(id) = payload.get(1..5) {
+                        self.statements
+                            .remove(&u32::from_le_bytes([id[0], id[1], id[2], id[3]]));
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            self.pending = Some(MysqlPending {
+                command,
+                sql,
+                started: ts,
+                state: MysqlResultState::Head,
+                rows: 0,
+            });
+        }
+    }
+
+    fn on_handshake_response(&mut self, payload: &[u8]) {
+        let Some(raw) = payload.get(..4) else {
+            return;
+        };
+        let capabilities = u32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]);
+        if capabilities & MYSQL_CLIENT_PROTOCOL_41 == 0 {
+            return;
+        }
+        self.capabilities = Some(capabilities);
+        self.encrypted = capabilities & MYSQL_CLIENT_SSL != 0;
+    }
+
+    /// With `CLIENT_QUERY_ATTRIBUTES` the text is preceded by a parameter
+    /// count and set count; an attribute-free query starts `00 01`.
+    fn query_text(&self, body: &[u8]) -> String {
+        let attributes = self
+            .capabilities
+            .map(|it| it & MYSQL_CLIENT_QUERY_ATTRIBUTES != 0);
+        let text = match attributes {
+            Some(false) => body,
+            _ if body.starts_with(&[0x00, 0x01]) => &body[2..],
+            _ => body,
+        };
+        String::from_utf8_lossy(text).into_owned()
+    }
+
+    fn on_server(&mut self, buffer: &mut Vec<u8>, ts: f64, exchanges: &mut Vec<Exchange>) {
+        for (_, payload) in drain_mysql_packets(buffer) {
+            let deprecate_eof = self.deprecate_eof();
+            let Some(pending) = self.pending.as_mut() else {
+                continue;
+            };
+            let Some(&header) = payload.first() else {
+                continue;
+            };
+            let mut finished = false;
+            let mut error = None;
+            match pending.state {
+                MysqlResultState::Head => match header {
+                    0x00 if pending.command == MysqlCommand::Prepare => {
+                        if let (Some(id), Some(sql)) = (payload.get(1..5), pending.sql.take()) {
+                            self.statements
+                                .insert(u32::from_le_bytes([id[0], id[1], id[2], id[3]]), sql);
+                        }
+                        finished = true;
+                    }
+                    0x00 => {
+                        finished =
+                            mysql_status_flags(&payload) & MYSQL_SERVER_MORE_RESULTS_EXISTS == 0;
+                    }
+                    0xff => {
+                        error = Some(mysql_error(&payload));
+                        finished = true;
+                    }
+                    // LOCAL INFILE request; the final OK follows the upload.
+                    0xfb => {}
+                    _ => match mysql_lenenc(&payload) {
+                        Some((columns, _)) if columns > 0 => {
+                            pending.state = MysqlResultState::Columns(columns);
+                        }
+                        _ => finished = true,
+                    },
+                },
+                MysqlResultState::Columns(remaining) => {
+                    pending.state = match (remaining, deprecate_eof) {
+                        (1, Some(true)) => MysqlResultState::Rows,
+                        (1, _) => MysqlResultState::ColumnsEof,
+                        (remaining, _) => MysqlResultState::Columns(remaining - 1),
+                    };
+                }
+                MysqlResultState::ColumnsEof => {
+                    if header == 0xfe && payload.len() == 5 {
+                        pending.state = MysqlResultState::Rows;
+                    } else if header == 0xfe && payload.len() < 0xff_ffff {
+                        // No EOF after the columns: an empty result's final OK.
+                        self.capabilities =
+                            Some(self.capabilities.unwrap_or(0) | MYSQL_CLIENT_DEPRECATE_EOF);
+                        finished =
+                            mysql_status_flags(&payload) & MYSQL_SERVER_MORE_RESULTS_EXISTS == 0;
+                        pending.state = MysqlResultState::Head;
+                    } else if header == 0xff {
+                        error = Some(mysql_error(&payload));
+                        finished = true;
+                    } else {
+                        pending.rows += 1;
+                        pending.state = MysqlResultState::Rows;
+                    }
+                }
+                MysqlResultState::Rows => {
+                    let terminator = header == 0xfe
+                        && if deprecate_eof == Some(true) {
+                            payload.len() < 0xff_ffff
+                        } else {
+                            payload.len() < 9
+                        };
+                    if terminator {
+                        finished =
+                            mysql_status_flags(&payload) & MYSQL_SERVER_MORE_RESULTS_EXISTS == 0;
+                        pending.state = MysqlResultState::Head;
+                    } else if header == 0xff {
+                        error = Some(mysql_error(&payload));
+                        finished = true;
+                    } else {
+                        pending.rows += 1;
+                    }
+                }
+            }
+            if finished && let Some(pending) = self.pending.take() {
+                exchanges.push(Exchange {
+                    sql: pending.sql,
+                    started: pending.started,
+                    finished: ts,
+                    rows_sent: pending.rows,
+                    error,
+                });
+            }
+        }
+    }
+}
+
+const PG_SSL_REQUEST: u32 = 80_877_103;
+const PG_GSSENC_REQUEST: u32 = 80_877_104;
+
+#[derive(Default)]
+struct PostgresSession {
+    awaiting_ssl_reply: bool,
+    statements: HashMap<String, String>,
+    portals: HashMap<String, String>,
+    /// Requests in send order; a `Sync` bounds each extended-protocol batch.
+    pending: VecDeque<PgPending>,
+    encrypted: bool,
+}
+
+enum PgPending {
+    Simple(PgQuery),
+    Execute(PgQuery),
+    Sync,
+}
+
+struct PgQuery {
+    sql: Option<String>,
+    started: f64,
+    rows: u64,
+    error: Option<String>,
+}
+
+fn pg_cstring(data: &[u8]) -> (String, &[u8]) {
+    let end = data.iter().position(|it| *it == 0).unwrap_or(data.len());
+    (
+        String::from_utf8_lossy(&data[..end]).into_owned(),
+        data.get(end + 1..).unwrap_or_default(),
+    )
+}
+
+/// Splits complete `type:1 len:4 body` messages off the front of `buffer`.
+/// Untyped startup-phase messages start with a zero byte and are returned
+/// with type `0`.
+fn drain_pg_messages(buffer: &mut Vec<u8>) -> Option<Vec<(u8, Vec<u8>)>> {
+    let mut messages = Vec::new();
+    let mut pos = 0;
+    loop {
+        let rest = &buffer[pos..];
+        let (kind, header) = match rest.first() {
+            None => break,
+            Some(0) => (0, 0),
+            Some(kind) => (*kind, 1),
+        };
+        let Some(len) = be32(rest, header) else {
+            break;
+        };
+        if len < 4 {
+            return None;
+        }
+        let total = header + len as usize;
+        if rest.len() < total {
+            break;
+        }
+        messages.push((kind, rest[header + 4..total].to_vec()));
+        pos += total;
+    }
+    buffer.drain(..pos);
+    Some(messages)
+}
+
+impl PostgresSession {
+    fn on_client(&mut self, buffer: &mut Vec<u8>, server_buffer: &mut Vec<u8>, ts: f64) {
+        let Some(messages) = drain_pg_messages(buffer) else {
+            buffer.clear();
+            return;
+        };
+        for (kind, body) in messages {
+            if matches!(kind, b'Q' | b'E') && self.pending.is_empty() {
+                // Leftovers from before the capture started.
+                server_buffer.clear();
+            }
+            match kind {
+                0 => {
+                    let code = be32(&body, 0);
+                    self.awaiting_ssl_reply =
+                        code.is_some_and(|it| it == PG_SSL_REQUEST || it == PG_GSSENC_REQUEST);
+                }
+                b'Q' => {
+                    let (sql, _) = pg_cstring(&body);
+                    self.pending.push_back(PgPending::Simple(PgQuery {
+                        sql: Some(sql),
+                        started: ts,
+                        rows: 0,
+                        error: None,
+                    }));
+                }
+                b'P' => {
+                    let (name, rest) = pg_cstring(&body);
+                    let (sql, _) = pg_cstring(rest);
+                    self.statements.insert(name, sql);
+                }
+                b'B' => {
+                    let (portal, rest) = pg_cstring(&body);
+                    let (statement, _) = pg_cstring(rest);
+                    match self.statements.get(&statement) {
+                        Some(sql) => self.portals.insert(portal, sql.clone()),
+                        None => self.portals.remove(&portal),
+                    };
+                }
+                b'E' => {
+                    let (portal, _) = pg_cstring(&body);
+                    self.pending.push_back(PgPending::Execute(PgQuery {
+                        sql: self.portals.get(&portal).cloned(),
+                        started: ts,
+                        rows: 0,
+                        error: None,
+                    }));
+                }
+                b'S' => self.pending.push_back(PgPending::Sync),
+                b'C' => {
+                    let (name, _) = pg_cstring(body.get(1..).unwrap_or_default());
+                    match body.first() {
+                        Some(b'S') => self.statements.remove(&name),
+                        Some(b'P') => self.portals.remove(&name),
+                        _ => None,
+                    };
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn on_server(&mut self, buffer: &mut Vec<u8>, ts: f64, exchanges: &mut Vec<Exchange>) {
+        if self.awaiting_ssl_reply && !buffer.is_empty() {
+            self.awaiting_ssl_reply = false;
+            match buffer[0] {
+                b'S' | b'G' => {
+                    self.encrypted = true;
+                    return;
+                }
+                b'N' => {
+                    buffer.drain(..1);
+                }
+                _ => {}
+            }
+        }
+        let Some(messages) = drain_pg_messages(buffer) else {
+            buffer.clear();
+            return;
+        };
+        let finish = |query: PgQuery, exchanges: &mut Vec<Exchange>| {
+            exchanges.push(Exchange {
+                sql: query.sql,
+                started: query.started,
+                finished: ts,
+                rows_sent: query.rows,
+                error: query.error,
+            });
+        };
+        for (kind, body) in messages {
+            match kind {
+                b'D' => {
+                    if let Some(PgPending::Simple(query) | PgPending::Execute(query)) =
+                        self.pending.front_mut()
+                    {
+                        query.rows += 1;
+                    }
+                }
+                // CommandComplete, EmptyQueryResponse, PortalSuspended.
+                b'C' | b'I' | b's' => {
+                    if matches!(self.pending.front(), Some(PgPending::Execute(_)))
+                        && let Some(PgPending::Execute(query)) = self.pending.pop_front()
+                    {
+                        finish(query, exchanges);
+                    }
+                }
+                b'E' => {
+                    let error = pg_error(&body);
+                    match self.pending.front_mut() {
+                        Some(PgPending::Simple(query)) => {
+                            query.error.get_or_insert(error);
+                        }
+                        Some(PgPending::Execute(_)) => {
+                            if let Some(PgPending::Execute(mut query)) = self.pending.pop_front() {
+                                query.error = Some(error);
+                                finish(query, exchanges);
+                            }
+                            // The server skips the rest of the batch.
+                            while matches!(self.pending.front(), Some(PgPending::Execute(_))) {
+                                self.pending.pop_front();
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                b'Z' => {
+                    while let Some(item) = self.pending.pop_front() {
+                        match item {
+                            PgPending::Simple(query) => {
+                                finish(query, exchanges);
+                                break;
+                            }
+                            PgPending::Sync => break,
+                            PgPending::Execute(_) => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Formats an ErrorResponse as `SQLSTATE: message`.
+fn pg_error(body: &[u8]) -> String {
+    let mut code = String::new();
+    let mut message = String::new();
+    let mut rest = body;
+    while let Some((&field, tail)) = rest.split_first() {
+        if field == 0 {
+            break;
+        }
+        let (value, tail) = pg_cstring(tail);
+        match field {
+            b'C' => code = value,
+            b'M' => message = value,
+            _ => {}
+        }
+        rest = tail;
+    }
+    format!("{code}: {message}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_fixture(name: &str) -> Vec<u8> {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/pcap")
+            .join(name);
+        fs::read(path).expect("failed reading pcap fixture")
+    }
+
+    #[test]
+    fn should_decode_mysql_queries_from_pcap() {
+        let mut queries = Vec::new();
+        let analysis = analyze_pcap(
+            &read_fixture("mysql_queries.pcap")[..],
+            Level1Engine::Mysql,
+            3306,
+            |it| queries.push(it),
+        )
+        .unwrap();
+
+        assert_eq!(analysis.packets, 22);
+        assert!(analysis.warnings.is_empty());
+        let summary = queries
+            .iter()
+            .map(|it| (it.sql.as_str(), it.response_time_secs, it.rows_sent))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                ("SELECT id, name FROM users WHERE id = 42", 0.15, 1),
+                ("SELECT COUNT(*) FROM orders", 0.3, 1),
+                ("SELECT id, name FROM users WHERE id = 7", 0.05, 1),
+                (
+                    "UPDATE accounts SET balance = balance - 10 WHERE id = 3",
+                    0.4,
+                    0
+                ),
+                ("SELECT * FROM orders WHERE user_id = ?", 0.8, 1),
+                ("UPDATE accounts SET balance = 0 WHERE id = 4", 0.25, 0),
+            ]
+        );
+        assert_eq!(
+            queries[5].error.as_deref(),
+            Some(
+                "ERROR 1213 (40001): Deadlock found when trying to get lock; try restarting transaction"
+            )
+        );
+
+        assert_eq!(analysis.connections.len(), 2);
+        let first = &analysis.connections[0];
+        assert_eq!(
+            (first.client.as_str(), first.server.as_str()),
+            ("10.0.0.5:51000", "10.0.0.9:3306")
+        );
+        assert_eq!((first.queries, first.errors), (5, 1));
+        assert_eq!(first.max_response_time_secs, 0.8);
+        // The retransmitted query is decoded once.
+        assert_eq!(analysis.connections[1].queries, 1);
+        assert_eq!(analysis.connections[1].stream_gaps, 0);
+    }
+
+    #[test]
+    fn should_decode_postgres_simple_and_extended_queries() {
+        let mut queries = Vec::new();
+        let analysis = analyze_pcap(
+            &read_fixture("postgres_queries.pcap")[..],
+            Level1Engine::Postgres,
+            5432,
+            |it| queries.push(it),
+        )
+        .unwrap();
+
+        let summary = queries
+            .iter()
+            .map(|it| (it.sql.as_str(), it.response_time_secs, it.error.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                ("SELECT * FROM accounts WHERE id = 1", 0.2, None),
+                ("UPDATE accounts SET balance = $1 WHERE id = $2", 0.5, None),
+                (
+                    "INSERT INTO accounts (id) VALUES (1)",
+                    0.1,
+                    Some("23505: duplicate key value violates unique constraint \"accounts_pkey\"")
+                ),
+                ("SELECT * FROM accounts WHERE id = $1", 0.3, None),
+                ("SELECT * FROM accounts WHERE id = $1", 0.6, None),
+            ]
+        );
+        assert_eq!(analysis.connections[0].queries, 5);
+    }
+
+    #[test]
+    fn should_aggregate_pcap_queries_into_digests() {
+        let path =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/pcap/postgres_queries.pcap");
+        let config = WireCaptureConfig {
+            engine: Level1Engine::Postgres,
+            pcap_file: Some(path),
+            ..WireCaptureConfig::default()
+        };
+        let report = collect_wire_capture(&CollectionPolicy::default(), &config);
+
+        assert!(report.enabled);
+        assert_eq!(report.source.as_deref(), Some("pcap_file"));
+        assert_eq!(report.port, 5432);
+        assert_eq!(report.decoded_queries, 5);
+        assert_eq!(report.slowest_queries[0].response_time_secs, 0.6);
+        let accounts = report
+            .digests
+            .iter()
+            .find(|it| it.fingerprint == "select * from accounts where id = ?")
+            .unwrap();
+        assert_eq!(accounts.count, 3);
+        assert_eq!(accounts.total_rows_sent, 3);
+        assert!((accounts.total_query_time_secs - 1.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn should_skip_live_capture_without_expert_mode() {
+        let config = WireCaptureConfig {
+            duration_secs: 120,
+            tcpdump_binary: "sql-insight-missing-tcpdump".to_string(),
+            ..WireCaptureConfig::default()
+        };
+        let report = collect_wire_capture(&CollectionPolicy::default(), &config);
+        assert_eq!(
+            report.skipped_reason.as_deref(),
+            Some("expert mode is disabled by policy")
+        );
+        assert_eq!(report.duration_secs, TCPDUMP_MAX_DURATION_SECS);
+        assert_eq!(report.port, 3306);
+
+        let policy = CollectionPolicy {
+            expert_mode_enabled: true,
+            ..CollectionPolicy::default()
+        };
+        let report = collect_wire_capture(&policy, &config);
+        assert_eq!(
+            report.skipped_reason.as_deref(),
+            Some("tcpdump not found (sql-insight-missing-tcpdump)")
+        );
+
+        let err = analyze_pcap(
+            &[0x0a, 0x0d, 0x0d, 0x0a, 0, 0, 0, 0][..],
+            Level1Engine::Mysql,
+            3306,
+            |_| {},
+        )
+        .unwrap_err();
+        assert_eq!(err, "file is too short for a pcap header");
+        let mut pcapng = vec![0x0a, 0x0d, 0x0d, 0x0a];
+        pcapng.resize(28, 0);
+        assert!(
+            analyze_pcap(&pcapng[..], Level1Engine::Mysql, 3306, |_| {})
+                .unwrap_err()
+                .starts_with("pcapng is not supported")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn should_decode_live_capture_and_remove_it() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root =
+            std::env::temp_dir().join(format!("sql-insight-{}-live-capture", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let output_dir = root.join("out");
+        fs::create_dir_all(root.join("proc/self")).unwrap();
+        fs::create_dir_all(&output_dir).unwrap();
+        fs::write(root.join("proc/self/status"), "Uid:\t0\t0\t0\t0\n").unwrap();
+        let fixture =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/pcap/mysql_queries.pcap");
+        let tcpdump = root.join("tcpdump");
+        let write_script = |body: &str| {
+            fs::write(
+                &tcpdump,
+                format!("#!/bin/sh\n[ \"$1\" = --version ] && exit 0\n{body}\n"),
+            )
+            .unwrap();
+            fs::set_permissions(&tcpdump, fs::Permissions::from_mode(0o755)).unwrap();
+        };
+        let config = WireCaptureConfig {
+            duration_secs: 1,
+            output_dir: output_dir.clone(),
+            tcpdump_binary: tcpdump.display().to_string(),
+            proc_root: root.join("proc"),
+            ..WireCaptureConfig::default()
+        };
+        let policy = CollectionPolicy {
+            expert_mode_enabled: true,
+            ..CollectionPolicy::default()
+        };
+
+        write_script(&format!("cat '{}'", fixture.display()));
+        let report = collect_wire_capture(&policy, &config);
+        assert!(report.enabled, "{:?}", report.skipped_reason);
+        assert_eq!(report.decoded_queries, 6);
+        assert_eq!(report.pcap_path, None);
+        assert_eq!(fs::read_dir(&output_dir).unwrap().count(), 0);
+
+        write_script("echo 'tcpdump: any: You don'\\''t have permission' >&2\nexit 1");
+        let report = collect_wire_capture(&policy, &config);
+        assert_eq!(
+            report.skipped_reason.as_deref(),
+            Some("tcpdump wrote no capture: tcpdump: any: You don't have permission")
+        );
+        assert_eq!(fs::read_dir(&output_dir).unwrap().count(), 0);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn should_probe_capture_for_non_root_users() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root =
+            std::env::temp_dir().join(format!("sql-insight-{}-probe-capture", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("proc/self")).unwrap();
+        fs::write(
+            root.join("proc/self/status"),
+            "Uid:\t1000\t1000\t1000\t1000\n",
+        )
+        .unwrap();
+        let tcpdump = root.join("tcpdump");
+        let write_script = |body: &str| {
+            fs::write(
+                &tcpdump,
+                format!("#!/bin/sh\n[ \"$1\" = --version ] && exit 0\n{body}\n"),
+            )
+            .unwrap();
+            fs::set_permissions(&tcpdump, fs::Permissions::from_mode(0o755)).unwrap();
+        };
+        let binary = tcpdump.display().to_string();
+
+        // A binary with cap_net_raw file capabilities captures as non-root.
+        write_script("echo \"tcpdump: listening on $2\" >&2\nexit 0");
+        assert_eq!(probe_tcpdump(&binary, "eth0", &root.join("proc")), Ok(()));
+
+        write_script(
+            "echo 'tcpdump: eth0: You don'\\''t have permission to perform this capture' >&2\nexit 1",
+        );
+        assert_eq!(
+            probe_tcpdump(&binary, "eth0", &root.join("proc")),
+            Err("packet capture requires root or CAP_NET_RAW on eth0: tcpdump: eth0: You don't have permission to perform this capture".to_string())
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
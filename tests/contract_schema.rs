@@ -1,3 +1,5 @@
+#![recursion_limit = "256"]
+
 use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -103,10 +105,11 @@ fn summarize_record(file_name: String, record: &Value) -> Value {
 
 fn minimal_mysql_record() -> Value {
     json!({
-      "contract_version": "v1",
+      "contract_version": "v2",
       "run_id": "run-sample",
       "cycle": 1,
       "engine": "mysql",
+      "target": null,
       "requested_level": "Level 0",
       "selected_level": "Level 0",
       "schedule": {
@@ -136,7 +139,53 @@ fn minimal_mysql_record() -> Value {
         "engine": "mysql",
         "requested_level": "Level 0",
         "selected_level": "Level 0",
-        "downgrade_reasons": [],
+        "downgrade_reasons": [
+          {
+            "code": "mysql_replication_status_denied",
+            "missing_capabilities": ["replication_status"],
+            "blocked_level": "Level 0",
+            "message": "missing replication status access",
+            "remediation": "GRANT REPLICATION CLIENT ON *.* TO 'monitor'@'%';"
+          }
+        ],
+        "probe": {
+          "engine": "mysql",
+          "connected": true,
+          "grantee": "'monitor'@'%'",
+          "server_version_num": 80036,
+          "grants": ["GRANT SELECT, PROCESS ON *.* TO `monitor`@`%`"],
+          "checks": [
+            {"name": "mysql.connection", "ok": true, "detail": null},
+            {"name": "mysql.slow_log_hot_switch", "ok": false, "detail": "SET GLOBAL slow_query_log requires SUPER or SYSTEM_VARIABLES_ADMIN"}
+          ],
+          "capability": [
+            "status_counters",
+            "settings",
+            "schema_metadata",
+            "os_metrics"
+          ],
+          "warnings": []
+        },
+        "tasks": [
+          {
+            "task": "global_status",
+            "level": "Level 0",
+            "collectors": ["level0"],
+            "status": "ok",
+            "duration_ms": 12,
+            "skipped_reason": null,
+            "errors": []
+          },
+          {
+            "task": "replication_status",
+            "level": "Level 0",
+            "collectors": [],
+            "status": "skipped",
+            "duration_ms": 0,
+            "skipped_reason": "excluded by task filter",
+            "errors": []
+          }
+        ],
         "level0": {
           "collected_at_unix_ms": 1,
           "capability": {
@@ -166,17 +215,23 @@ fn minimal_mysql_record() -> Value {
           "warnings": []
         },
         "postgres_level0": null,
-        "level1": null
+        "level1": null,
+        "table_attribution": null,
+        "innodb_status_hf": null,
+        "perf_profile": null,
+        "strace_summary": null,
+        "wire_capture": null
       }
     })
 }
 
 fn minimal_postgres_level1_record() -> Value {
     json!({
-      "contract_version": "v1",
+      "contract_version": "v2",
       "run_id": "run-sample-pg",
       "cycle": 1,
       "engine": "postgres",
+      "target": null,
       "requested_level": "Level 1",
       "selected_level": "Level 1",
       "schedule": {
@@ -207,6 +262,38 @@ fn minimal_postgres_level1_record() -> Value {
         "requested_level": "Level 1",
         "selected_level": "Level 1",
         "downgrade_reasons": [],
+        "probe": {
+          "engine": "postgres",
+          "connected": true,
+          "grantee": "monitor",
+          "server_version_num": 160002,
+          "grants": ["SUPERUSER"],
+          "checks": [
+            {"name": "postgres.connection", "ok": true, "detail": null}
+          ],
+          "capability": [
+            "status_counters",
+            "settings",
+            "schema_metadata",
+            "replication_status",
+            "os_metrics",
+            "statement_log_hot_switch",
+            "statement_log",
+            "error_log"
+          ],
+          "warnings": []
+        },
+        "tasks": [
+          {
+            "task": "slow_log_window",
+            "level": "Level 1",
+            "collectors": ["level1"],
+            "status": "partial",
+            "duration_ms": 30012,
+            "skipped_reason": null,
+            "errors": ["log file not readable"]
+          }
+        ],
         "level0": {
           "collected_at_unix_ms": 1,
           "capability": {
@@ -254,7 +341,8 @@ fn minimal_postgres_level1_record() -> Value {
             "postgres_connected": true,
             "can_enable_slow_log_hot_switch": true,
             "can_read_slow_log": true,
-            "can_read_error_log": true
+            "can_read_error_log": true,
+            "can_read_innodb_status": false
           },
           "slow_log": {
             "enabled_for_window": true,
@@ -265,35 +353,133 @@ fn minimal_postgres_level1_record() -> Value {
             "previous_long_query_time": "200ms",
             "collected_bytes": 1,
             "parsed_entries": 1,
+            "truncated": false,
+            "skipped_bytes": 0,
+            "dropped_entries": 0,
+            "rotated_during_window": false,
+            "source_files": ["/var/log/postgresql/postgresql.log"],
+            "native_digest_source": null,
             "digest_count": 1,
-            "digests": []
+            "digests": [],
+            "tag_rollups": [],
+            "kind_breakdown": [],
+            "ddl_digests": []
+          },
+          "transactions": {
+            "source": "slow_log",
+            "long_threshold_secs": 1.0,
+            "idle_threshold_secs": 1.0,
+            "reconstructed": 0,
+            "long_running": 0,
+            "idle_in_transaction": 0,
+            "transactions": []
           },
           "error_log": {
             "error_log_path": "/var/log/postgresql/postgresql.log",
             "sampled_lines": 3,
+            "parsed_lines": 3,
             "alert_count": 1,
-            "alerts": []
+            "alerts": [
+              {
+                "category": "deadlock",
+                "count": 1,
+                "codes": ["40P01"],
+                "sample_lines": ["2026-02-07 16:00:03 UTC [77] ERROR:  40P01: deadlock detected"]
+              }
+            ],
+            "severity_counts": [
+              { "severity": "error", "count": 1 },
+              { "severity": "log", "count": 2 }
+            ],
+            "code_counts": [
+              {
+                "code": "40P01",
+                "severity": "error",
+                "subsystem": null,
+                "count": 1,
+                "first_seen": "2026-02-07 16:00:03 UTC",
+                "last_seen": "2026-02-07 16:00:03 UTC",
+                "sample_line": "2026-02-07 16:00:03 UTC [77] ERROR:  40P01: deadlock detected"
+              }
+            ]
+          },
+          "deadlocks": {
+            "parsed": 1,
+            "groups": [
+              {
+                "deadlock_id": "5b0c1a8e2f4d6c71",
+                "count": 1,
+                "first_detected_at": "2026-02-07 16:00:03",
+                "last_detected_at": "2026-02-07 16:00:03",
+                "tables": ["accounts"],
+                "statements": ["update accounts set balance = ? where id = ?"],
+                "latest": {
+                  "source": "postgres_log",
+                  "detected_at": "2026-02-07 16:00:03",
+                  "deadlock_id": "5b0c1a8e2f4d6c71",
+                  "participants": [
+                    {
+                      "label": "77",
+                      "transaction_id": null,
+                      "thread_id": 77,
+                      "active_secs": null,
+                      "lock_structs": null,
+                      "row_locks": null,
+                      "statement": "UPDATE accounts SET balance = 0 WHERE id = 2",
+                      "statement_fingerprint": "update accounts set balance = ? where id = ?",
+                      "holds": [],
+                      "waits_for": [
+                        {
+                          "lock_type": "transaction",
+                          "mode": "ShareLock",
+                          "table": null,
+                          "index": null,
+                          "object": "transaction 1235"
+                        }
+                      ],
+                      "rolled_back": true
+                    }
+                  ],
+                  "victim": "77",
+                  "tables": ["accounts"],
+                  "indexes": []
+                }
+              }
+            ]
           },
+          "innodb_status": null,
           "warnings": []
-        }
+        },
+        "table_attribution": [],
+        "innodb_status_hf": null,
+        "perf_profile": null,
+        "strace_summary": null,
+        "wire_capture": null
       }
     })
 }
 
 #[test]
 fn schema_accepts_minimal_samples() {
-    let schema = load_json(&repo_path("contracts/unified-record-v1.schema.json"));
+    let schema = load_json(&repo_path("contracts/unified-record-v2.schema.json"));
     validate_against_schema(&schema, &minimal_mysql_record(), "minimal mysql");
     validate_against_schema(
         &schema,
         &minimal_postgres_level1_record(),
         "minimal postgres level1",
     );
+
+    let mut targeted = minimal_postgres_level1_record();
+    targeted["target"] = json!({
+        "name": "billing-replica",
+        "labels": { "region": "us-east-1", "role": "replica" }
+    });
+    validate_against_schema(&schema, &targeted, "minimal postgres with target");
 }
 
 #[test]
 fn matrix_results_match_schema_and_golden_summary() {
-    let schema = load_json(&repo_path("contracts/unified-record-v1.schema.json"));
+    let schema = load_json(&repo_path("contracts/unified-record-v2.schema.json"));
     let default_results = repo_path("tests/artifacts/results");
     let results_dir = env::var("CONTRACT_RESULTS_DIR")
         .map(PathBuf::from)
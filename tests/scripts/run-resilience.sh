@@ -70,9 +70,9 @@ run_mysql_downgrade_case() {
 
   assert_jq "${output}" '.status == "ok"' "mysql downgrade record status"
   assert_jq "${output}" '.selected_level == "Level 0"' "mysql downgraded to level 0"
-  assert_jq "${output}" '(.payload.downgrade_reasons | join(" ") | contains("cannot hot-enable slow log")) == true' "mysql downgrade reason hot switch"
-  assert_jq "${output}" '(.payload.downgrade_reasons | join(" ") | contains("cannot collect slow log for digest aggregation")) == true' "mysql downgrade reason slow log"
-  assert_jq "${output}" '(.payload.downgrade_reasons | join(" ") | contains("cannot collect error log")) == true' "mysql downgrade reason error log"
+  assert_jq "${output}" 'any(.payload.downgrade_reasons[]; .code == "mysql_slow_log_hot_switch_denied")' "mysql downgrade reason hot switch"
+  assert_jq "${output}" 'any(.payload.downgrade_reasons[]; .code == "mysql_slow_log_unreadable")' "mysql downgrade reason slow log"
+  assert_jq "${output}" 'any(.payload.downgrade_reasons[]; .code == "mysql_error_log_unreadable")' "mysql downgrade reason error log"
 }
 
 run_postgres_downgrade_case() {
@@ -92,9 +92,9 @@ run_postgres_downgrade_case() {
 
   assert_jq "${output}" '.status == "ok"' "postgres downgrade record status"
   assert_jq "${output}" '.selected_level == "Level 0"' "postgres downgraded to level 0"
-  assert_jq "${output}" '(.payload.downgrade_reasons | join(" ") | contains("cannot hot-enable log_min_duration_statement")) == true' "postgres downgrade reason hot switch"
-  assert_jq "${output}" '(.payload.downgrade_reasons | join(" ") | contains("cannot collect statement log for digest aggregation")) == true' "postgres downgrade reason statement log"
-  assert_jq "${output}" '(.payload.downgrade_reasons | join(" ") | contains("cannot collect PostgreSQL log alerts")) == true' "postgres downgrade reason error log"
+  assert_jq "${output}" 'any(.payload.downgrade_reasons[]; .code == "postgres_statement_log_hot_switch_denied")' "postgres downgrade reason hot switch"
+  assert_jq "${output}" 'any(.payload.downgrade_reasons[]; .code == "postgres_statement_log_unreadable")' "postgres downgrade reason statement log"
+  assert_jq "${output}" 'any(.payload.downgrade_reasons[]; .code == "postgres_error_log_unreadable")' "postgres downgrade reason error log"
 }
 
 run_mysql_timeout_retry_case